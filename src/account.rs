@@ -0,0 +1,168 @@
+//! Standard BIP44, BIP49, BIP84 and BIP86 account paths.
+
+use crate::bip32::{ChildNumber, DerivationPath, KeyApplication};
+use crate::descriptor::{Descriptor, DescriptorKey};
+use crate::{Network, Result};
+#[cfg(feature = "secp256k1")]
+use crate::{
+    bip32::Xpriv,
+    descriptor::{DescriptorPublicKey, KeyOrigin},
+};
+use alloc::boxed::Box;
+use alloc::vec;
+
+/// The purpose level of a derivation path, which fixes the script type.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Purpose {
+    /// P2PKH.
+    Bip44,
+    /// P2WPKH nested in P2SH.
+    Bip49,
+    /// P2WPKH.
+    Bip84,
+    /// Single-key P2TR.
+    Bip86,
+}
+
+impl Purpose {
+    pub fn number(self) -> u32 {
+        match self {
+            Purpose::Bip44 => 44,
+            Purpose::Bip49 => 49,
+            Purpose::Bip84 => 84,
+            Purpose::Bip86 => 86,
+        }
+    }
+
+    pub fn from_number(number: u32) -> Option<Purpose> {
+        match number {
+            44 => Some(Purpose::Bip44),
+            49 => Some(Purpose::Bip49),
+            84 => Some(Purpose::Bip84),
+            86 => Some(Purpose::Bip86),
+            _ => None,
+        }
+    }
+
+    /// The SLIP-132 prefix for account keys; BIP86 defines none.
+    pub fn key_application(self) -> Option<KeyApplication> {
+        match self {
+            Purpose::Bip44 => Some(KeyApplication::Legacy),
+            Purpose::Bip49 => Some(KeyApplication::NestedSegwit),
+            Purpose::Bip84 => Some(KeyApplication::Segwit),
+            Purpose::Bip86 => None,
+        }
+    }
+
+    /// Wraps `key` in the descriptor for this purpose's script type.
+    pub fn descriptor(self, key: DescriptorKey) -> Descriptor {
+        match self {
+            Purpose::Bip44 => Descriptor::Pkh(key),
+            Purpose::Bip49 => Descriptor::Sh(Box::new(Descriptor::Wpkh(key))),
+            Purpose::Bip84 => Descriptor::Wpkh(key),
+            Purpose::Bip86 => Descriptor::Tr {
+                internal_key: key,
+                tree: None,
+            },
+        }
+    }
+}
+
+/// An account at `m/purpose'/coin_type'/account'`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Account {
+    purpose: Purpose,
+    network: Network,
+    index: u32,
+}
+
+impl Account {
+    pub fn new(purpose: Purpose, network: Network, index: u32) -> Result<Account> {
+        ChildNumber::from_hardened_idx(index)?;
+        Ok(Account {
+            purpose,
+            network,
+            index,
+        })
+    }
+
+    pub fn bip44(network: Network, index: u32) -> Result<Account> {
+        Account::new(Purpose::Bip44, network, index)
+    }
+
+    pub fn bip49(network: Network, index: u32) -> Result<Account> {
+        Account::new(Purpose::Bip49, network, index)
+    }
+
+    pub fn bip84(network: Network, index: u32) -> Result<Account> {
+        Account::new(Purpose::Bip84, network, index)
+    }
+
+    pub fn bip86(network: Network, index: u32) -> Result<Account> {
+        Account::new(Purpose::Bip86, network, index)
+    }
+
+    pub fn purpose(&self) -> Purpose {
+        self.purpose
+    }
+
+    pub fn network(&self) -> Network {
+        self.network
+    }
+
+    pub fn index(&self) -> u32 {
+        self.index
+    }
+
+    /// SLIP-44 coin type: 0 for mainnet, 1 for every test network.
+    pub fn coin_type(&self) -> u32 {
+        match self.network {
+            Network::Mainnet => 0,
+            _ => 1,
+        }
+    }
+
+    pub fn path(&self) -> DerivationPath {
+        DerivationPath::from(vec![
+            ChildNumber::Hardened {
+                index: self.purpose.number(),
+            },
+            ChildNumber::Hardened {
+                index: self.coin_type(),
+            },
+            ChildNumber::Hardened { index: self.index },
+        ])
+    }
+
+    /// The path of address `index` on the receive or change chain.
+    pub fn address_path(&self, change: bool, index: u32) -> Result<DerivationPath> {
+        Ok(self.path().extend([
+            ChildNumber::Normal {
+                index: u32::from(change),
+            },
+            ChildNumber::from_normal_idx(index)?,
+        ]))
+    }
+
+    /// The ranged descriptor for the receive or change chain of this
+    /// account under `master`, with the key origin filled in.
+    #[cfg(feature = "secp256k1")]
+    pub fn descriptor(&self, master: &Xpriv, change: bool) -> Result<Descriptor> {
+        let path = self.path();
+        let xpub = master.derive_priv(&path)?.to_xpub();
+        let key = DescriptorKey {
+            origin: Some(KeyOrigin {
+                fingerprint: master.fingerprint(),
+                path,
+            }),
+            key: DescriptorPublicKey::Extended {
+                xpub,
+                path: DerivationPath::from(vec![ChildNumber::Normal {
+                    index: u32::from(change),
+                }]),
+                wildcard: true,
+            },
+        };
+        Ok(self.purpose.descriptor(key))
+    }
+}
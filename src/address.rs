@@ -0,0 +1,238 @@
+use crate::{BitcoinError, Network, Result, Script, ScriptHash, ScriptType, base58, bech32};
+use alloc::vec::Vec;
+use core::fmt;
+use core::str::FromStr;
+
+/// A standard output type encoded for display, along with the network it
+/// is meant for.
+///
+/// Testnet, testnet4 and signet share their encodings, so parsing one of
+/// their addresses gives `Network::Testnet`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Address {
+    P2pkh {
+        network: Network,
+        pubkey_hash: [u8; 20],
+    },
+    P2sh {
+        network: Network,
+        script_hash: [u8; 20],
+    },
+    P2wpkh {
+        network: Network,
+        pubkey_hash: [u8; 20],
+    },
+    P2wsh {
+        network: Network,
+        script_hash: [u8; 32],
+    },
+    P2tr {
+        network: Network,
+        output_key: [u8; 32],
+    },
+}
+
+fn p2pkh_prefix(network: Network) -> u8 {
+    match network {
+        Network::Mainnet => 0x00,
+        _ => 0x6f,
+    }
+}
+
+fn p2sh_prefix(network: Network) -> u8 {
+    match network {
+        Network::Mainnet => 0x05,
+        _ => 0xc4,
+    }
+}
+
+fn hrp(network: Network) -> &'static str {
+    match network {
+        Network::Mainnet => "bc",
+        Network::Regtest => "bcrt",
+        _ => "tb",
+    }
+}
+
+impl Address {
+    pub fn network(&self) -> Network {
+        match *self {
+            Address::P2pkh { network, .. }
+            | Address::P2sh { network, .. }
+            | Address::P2wpkh { network, .. }
+            | Address::P2wsh { network, .. }
+            | Address::P2tr { network, .. } => network,
+        }
+    }
+
+    /// The witness version and program of a segwit address.
+    pub fn witness_program(&self) -> Option<(u8, &[u8])> {
+        match self {
+            Address::P2wpkh { pubkey_hash, .. } => Some((0, pubkey_hash)),
+            Address::P2wsh { script_hash, .. } => Some((0, script_hash)),
+            Address::P2tr { output_key, .. } => Some((1, output_key)),
+            _ => None,
+        }
+    }
+
+    /// Parses `s` and checks it is an address for `expected`, returning it
+    /// with that network. Networks sharing an encoding (testnet, testnet4
+    /// and signet everywhere, plus regtest for base58) all accept it.
+    pub fn parse_checked(s: &str, expected: Network) -> Result<Address> {
+        let address: Address = s.parse()?;
+        if !address.is_valid_for_network(expected) {
+            return Err(BitcoinError::WrongNetwork {
+                expected,
+                found: address.network(),
+            });
+        }
+        Ok(address.with_network(expected))
+    }
+
+    /// Whether this address is encoded the same way on `network`.
+    pub fn is_valid_for_network(&self, network: Network) -> bool {
+        match self {
+            Address::P2pkh { .. } | Address::P2sh { .. } => {
+                p2pkh_prefix(self.network()) == p2pkh_prefix(network)
+            }
+            _ => hrp(self.network()) == hrp(network),
+        }
+    }
+
+    fn with_network(mut self, new: Network) -> Address {
+        match &mut self {
+            Address::P2pkh { network, .. }
+            | Address::P2sh { network, .. }
+            | Address::P2wpkh { network, .. }
+            | Address::P2wsh { network, .. }
+            | Address::P2tr { network, .. } => *network = new,
+        }
+        self
+    }
+
+    pub fn script_pubkey(&self) -> Script {
+        match self {
+            Address::P2pkh { pubkey_hash, .. } => Script::new_p2pkh(pubkey_hash),
+            Address::P2sh { script_hash, .. } => Script::new_p2sh(script_hash),
+            Address::P2wpkh { pubkey_hash, .. } => Script::new_p2wpkh(pubkey_hash),
+            Address::P2wsh { script_hash, .. } => Script::new_p2wsh(&ScriptHash(*script_hash)),
+            Address::P2tr { output_key, .. } => Script::new_p2tr(output_key),
+        }
+    }
+
+    /// The address of an output script, for the output types that have one.
+    pub fn from_script(script: &Script, network: Network) -> Result<Address> {
+        let bytes = script.bytes.as_slice();
+        let hash20 = |range: core::ops::Range<usize>| bytes[range].try_into().unwrap();
+        let program = || script.witness_program().unwrap().1;
+        Ok(match script.classify() {
+            ScriptType::P2pkh => Address::P2pkh {
+                network,
+                pubkey_hash: hash20(3..23),
+            },
+            ScriptType::P2sh => Address::P2sh {
+                network,
+                script_hash: hash20(2..22),
+            },
+            ScriptType::P2wpkh => Address::P2wpkh {
+                network,
+                pubkey_hash: program().try_into().unwrap(),
+            },
+            ScriptType::P2wsh => Address::P2wsh {
+                network,
+                script_hash: program().try_into().unwrap(),
+            },
+            ScriptType::P2tr => Address::P2tr {
+                network,
+                output_key: program().try_into().unwrap(),
+            },
+            _ => return Err(BitcoinError::InvalidAddress("script has no address form")),
+        })
+    }
+
+    fn from_base58(s: &str) -> Result<Address> {
+        let data = base58::decode_check(s)?;
+        let (&prefix, hash) = data
+            .split_first()
+            .ok_or(BitcoinError::InvalidAddress("empty payload"))?;
+        let hash: [u8; 20] = hash
+            .try_into()
+            .map_err(|_| BitcoinError::InvalidAddress("invalid payload length"))?;
+        Ok(match prefix {
+            0x00 => Address::P2pkh {
+                network: Network::Mainnet,
+                pubkey_hash: hash,
+            },
+            0x05 => Address::P2sh {
+                network: Network::Mainnet,
+                script_hash: hash,
+            },
+            0x6f => Address::P2pkh {
+                network: Network::Testnet,
+                pubkey_hash: hash,
+            },
+            0xc4 => Address::P2sh {
+                network: Network::Testnet,
+                script_hash: hash,
+            },
+            _ => return Err(BitcoinError::InvalidAddress("unknown version byte")),
+        })
+    }
+
+    fn from_bech32(s: &str) -> Result<Address> {
+        let (hrp, version, program) = bech32::decode_segwit(s)?;
+        let network = match hrp.as_str() {
+            "bc" => Network::Mainnet,
+            "tb" => Network::Testnet,
+            "bcrt" => Network::Regtest,
+            _ => return Err(BitcoinError::InvalidAddress("unknown human-readable part")),
+        };
+        Ok(match (version, program.len()) {
+            (0, 20) => Address::P2wpkh {
+                network,
+                pubkey_hash: program.try_into().unwrap(),
+            },
+            (0, 32) => Address::P2wsh {
+                network,
+                script_hash: program.try_into().unwrap(),
+            },
+            (1, 32) => Address::P2tr {
+                network,
+                output_key: program.try_into().unwrap(),
+            },
+            _ => return Err(BitcoinError::InvalidAddress("unsupported witness program")),
+        })
+    }
+}
+
+impl FromStr for Address {
+    type Err = BitcoinError;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let lower = s.to_ascii_lowercase();
+        if ["bc1", "tb1", "bcrt1"].iter().any(|p| lower.starts_with(p)) {
+            Self::from_bech32(s)
+        } else {
+            Self::from_base58(s)
+        }
+    }
+}
+
+impl fmt::Display for Address {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let network = self.network();
+        let (prefix, hash) = match self {
+            Address::P2pkh { pubkey_hash, .. } => (p2pkh_prefix(network), pubkey_hash),
+            Address::P2sh { script_hash, .. } => (p2sh_prefix(network), script_hash),
+            _ => {
+                let (version, program) = self.witness_program().unwrap();
+                let encoded = bech32::encode_segwit(hrp(network), version, program)
+                    .expect("valid witness program");
+                return f.write_str(&encoded);
+            }
+        };
+        let mut payload = Vec::from([prefix]);
+        payload.extend_from_slice(hash);
+        f.write_str(&base58::encode_check(&payload))
+    }
+}
@@ -0,0 +1,202 @@
+use crate::{
+    BitcoinError, BitcoinTransaction, CompactSize, DecodeLimits, OutPoint, Result, SEGWIT_FLAG,
+    Script, TransactionInput, TransactionOutput, Txid, Witness, Wtxid, check_count,
+    check_script_len,
+};
+use alloc::vec;
+use alloc::vec::Vec;
+use core::future::Future;
+use tokio::io::{AsyncRead, AsyncReadExt};
+
+pub trait AsyncConsensusDecode: Sized {
+    fn consensus_decode_async<R: AsyncRead + Unpin + ?Sized>(
+        r: &mut R,
+    ) -> impl Future<Output = Result<Self>>;
+}
+
+async fn read_array<const N: usize, R: AsyncRead + Unpin + ?Sized>(
+    r: &mut R,
+    context: &'static str,
+) -> Result<[u8; N]> {
+    let mut buf = [0u8; N];
+    let mut filled = 0;
+    while filled < N {
+        match r.read(&mut buf[filled..]).await? {
+            0 => return Err(BitcoinError::insufficient(context, N, filled)),
+            n => filled += n,
+        }
+    }
+    Ok(buf)
+}
+
+async fn read_compact_size<R: AsyncRead + Unpin + ?Sized>(
+    r: &mut R,
+    context: &'static str,
+) -> Result<u64> {
+    let [prefix] = read_array(r, context).await?;
+    Ok(match prefix {
+        n @ 0x00..=0xFC => n as u64,
+        0xFD => u16::from_le_bytes(read_array(r, context).await?) as u64,
+        0xFE => u32::from_le_bytes(read_array(r, context).await?) as u64,
+        0xFF => u64::from_le_bytes(read_array(r, context).await?),
+    })
+}
+
+async fn read_count<R: AsyncRead + Unpin + ?Sized>(
+    r: &mut R,
+    context: &'static str,
+    max: usize,
+) -> Result<u64> {
+    check_count(context, read_compact_size(r, context).await?, max)
+}
+
+async fn decode_list<T: AsyncConsensusDecode, R: AsyncRead + Unpin + ?Sized>(
+    r: &mut R,
+    max: usize,
+    context: &'static str,
+) -> Result<Vec<T>> {
+    let count = check_count(context, read_compact_size(r, "CompactSize").await?, max)?;
+    let mut items = vec![];
+    for _ in 0..count {
+        items.push(T::consensus_decode_async(r).await?);
+    }
+    Ok(items)
+}
+
+async fn read_bytes<R: AsyncRead + Unpin + ?Sized>(
+    r: &mut R,
+    len: u64,
+    context: &'static str,
+) -> Result<Vec<u8>> {
+    let mut data = vec![];
+    <&mut R as AsyncReadExt>::take(r, len)
+        .read_to_end(&mut data)
+        .await?;
+    if (data.len() as u64) < len {
+        let needed = usize::try_from(len).unwrap_or(usize::MAX);
+        return Err(BitcoinError::insufficient(context, needed, data.len()));
+    }
+    Ok(data)
+}
+
+impl AsyncConsensusDecode for u32 {
+    async fn consensus_decode_async<R: AsyncRead + Unpin + ?Sized>(r: &mut R) -> Result<Self> {
+        Ok(u32::from_le_bytes(read_array(r, "u32").await?))
+    }
+}
+
+impl AsyncConsensusDecode for u64 {
+    async fn consensus_decode_async<R: AsyncRead + Unpin + ?Sized>(r: &mut R) -> Result<Self> {
+        Ok(u64::from_le_bytes(read_array(r, "u64").await?))
+    }
+}
+
+impl<T: AsyncConsensusDecode> AsyncConsensusDecode for Vec<T> {
+    async fn consensus_decode_async<R: AsyncRead + Unpin + ?Sized>(r: &mut R) -> Result<Self> {
+        decode_list(r, usize::MAX, "CompactSize").await
+    }
+}
+
+impl AsyncConsensusDecode for CompactSize {
+    async fn consensus_decode_async<R: AsyncRead + Unpin + ?Sized>(r: &mut R) -> Result<Self> {
+        Ok(Self::new(read_compact_size(r, "CompactSize").await?))
+    }
+}
+
+impl AsyncConsensusDecode for Txid {
+    async fn consensus_decode_async<R: AsyncRead + Unpin + ?Sized>(r: &mut R) -> Result<Self> {
+        Ok(Txid(read_array(r, "Txid").await?))
+    }
+}
+
+impl AsyncConsensusDecode for Wtxid {
+    async fn consensus_decode_async<R: AsyncRead + Unpin + ?Sized>(r: &mut R) -> Result<Self> {
+        Ok(Wtxid(read_array(r, "Wtxid").await?))
+    }
+}
+
+impl AsyncConsensusDecode for OutPoint {
+    async fn consensus_decode_async<R: AsyncRead + Unpin + ?Sized>(r: &mut R) -> Result<Self> {
+        Ok(OutPoint {
+            txid: Txid(read_array(r, "OutPoint").await?),
+            vout: u32::from_le_bytes(read_array(r, "OutPoint").await?),
+        })
+    }
+}
+
+impl AsyncConsensusDecode for Script {
+    async fn consensus_decode_async<R: AsyncRead + Unpin + ?Sized>(r: &mut R) -> Result<Self> {
+        let limits = DecodeLimits::default();
+        let len = check_script_len(read_compact_size(r, "Script").await?, &limits)?;
+        Ok(Self::new(read_bytes(r, len, "Script").await?))
+    }
+}
+
+impl AsyncConsensusDecode for Witness {
+    async fn consensus_decode_async<R: AsyncRead + Unpin + ?Sized>(r: &mut R) -> Result<Self> {
+        let limits = DecodeLimits::default();
+        let count = read_count(r, "Witness", limits.max_witness_items).await?;
+        let mut items = vec![];
+        for _ in 0..count {
+            let len = read_count(r, "Witness", limits.max_witness_item_len).await?;
+            items.push(read_bytes(r, len, "Witness").await?);
+        }
+        Ok(Self::new(items))
+    }
+}
+
+impl AsyncConsensusDecode for TransactionInput {
+    async fn consensus_decode_async<R: AsyncRead + Unpin + ?Sized>(r: &mut R) -> Result<Self> {
+        Ok(Self::new(
+            OutPoint::consensus_decode_async(r).await?,
+            Script::consensus_decode_async(r).await?,
+            u32::from_le_bytes(read_array(r, "TransactionInput").await?),
+        ))
+    }
+}
+
+impl AsyncConsensusDecode for TransactionOutput {
+    async fn consensus_decode_async<R: AsyncRead + Unpin + ?Sized>(r: &mut R) -> Result<Self> {
+        Ok(Self::new(
+            u64::from_le_bytes(read_array(r, "TransactionOutput").await?),
+            Script::consensus_decode_async(r).await?,
+        ))
+    }
+}
+
+impl AsyncConsensusDecode for BitcoinTransaction {
+    async fn consensus_decode_async<R: AsyncRead + Unpin + ?Sized>(r: &mut R) -> Result<Self> {
+        let limits = DecodeLimits::default();
+        let version = u32::from_le_bytes(read_array(r, "BitcoinTransaction").await?);
+        let mut inputs: Vec<TransactionInput> =
+            decode_list(r, limits.max_inputs, "TransactionInput").await?;
+
+        let mut flag = 0;
+        let mut outputs = vec![];
+        if inputs.is_empty() {
+            [flag] = read_array(r, "BitcoinTransaction").await?;
+            if flag != 0 {
+                inputs = decode_list(r, limits.max_inputs, "TransactionInput").await?;
+                outputs = decode_list(r, limits.max_outputs, "TransactionOutput").await?;
+            }
+        } else {
+            outputs = decode_list(r, limits.max_outputs, "TransactionOutput").await?;
+        }
+
+        if flag & SEGWIT_FLAG != 0 {
+            flag ^= SEGWIT_FLAG;
+            for input in inputs.iter_mut() {
+                input.witness = Witness::consensus_decode_async(r).await?;
+            }
+            if inputs.iter().all(|input| input.witness.is_empty()) {
+                return Err(BitcoinError::InvalidFormat);
+            }
+        }
+        if flag != 0 {
+            return Err(BitcoinError::InvalidSegwitMarker { flag });
+        }
+
+        let lock_time = u32::from_le_bytes(read_array(r, "BitcoinTransaction").await?);
+        Ok(Self::new(version, inputs, outputs, lock_time))
+    }
+}
@@ -0,0 +1,124 @@
+//! Base58 and Base58Check, as used by legacy addresses, WIF keys and
+//! extended keys.
+
+use crate::hashes::sha256d;
+use crate::{BitcoinError, Result};
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::fmt;
+
+const ALPHABET: &[u8; 58] = b"123456789ABCDEFGHJKLMNPQRSTUVWXYZabcdefghijkmnopqrstuvwxyz";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Base58Error {
+    /// A character outside the Base58 alphabet, at byte offset `position`.
+    InvalidCharacter {
+        character: char,
+        position: usize,
+    },
+    InvalidChecksum {
+        expected: [u8; 4],
+        found: [u8; 4],
+    },
+    /// Decoded data too short to hold a checksum.
+    TooShort(usize),
+}
+
+impl fmt::Display for Base58Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Base58Error::InvalidCharacter {
+                character,
+                position,
+            } => write!(
+                f,
+                "invalid base58 character {:?} at position {}",
+                character, position
+            ),
+            Base58Error::InvalidChecksum { expected, found } => write!(
+                f,
+                "invalid checksum {}, expected {}",
+                hex::encode(found),
+                hex::encode(expected)
+            ),
+            Base58Error::TooShort(len) => {
+                write!(f, "{} bytes is too short for a checksum", len)
+            }
+        }
+    }
+}
+
+impl From<Base58Error> for BitcoinError {
+    fn from(e: Base58Error) -> Self {
+        BitcoinError::Base58(e)
+    }
+}
+
+pub fn encode(data: &[u8]) -> String {
+    // Base-58 digits, least significant first.
+    let mut digits: Vec<u8> = Vec::with_capacity(data.len() * 138 / 100 + 1);
+    for &byte in data {
+        let mut carry = byte as u32;
+        for digit in digits.iter_mut() {
+            carry += (*digit as u32) << 8;
+            *digit = (carry % 58) as u8;
+            carry /= 58;
+        }
+        while carry > 0 {
+            digits.push((carry % 58) as u8);
+            carry /= 58;
+        }
+    }
+    let zeros = data.iter().take_while(|&&b| b == 0).count();
+    core::iter::repeat_n('1', zeros)
+        .chain(digits.iter().rev().map(|&d| ALPHABET[d as usize] as char))
+        .collect()
+}
+
+pub fn decode(s: &str) -> Result<Vec<u8>> {
+    // Bytes, least significant first.
+    let mut bytes: Vec<u8> = Vec::with_capacity(s.len() * 733 / 1000 + 1);
+    for (position, character) in s.char_indices() {
+        let mut carry = ALPHABET
+            .iter()
+            .position(|&a| a as char == character)
+            .ok_or(Base58Error::InvalidCharacter {
+                character,
+                position,
+            })? as u32;
+        for byte in bytes.iter_mut() {
+            carry += *byte as u32 * 58;
+            *byte = carry as u8;
+            carry >>= 8;
+        }
+        while carry > 0 {
+            bytes.push(carry as u8);
+            carry >>= 8;
+        }
+    }
+    let zeros = s.bytes().take_while(|&c| c == b'1').count();
+    bytes.extend(core::iter::repeat_n(0, zeros));
+    bytes.reverse();
+    Ok(bytes)
+}
+
+/// Base58 of `data` followed by the first four bytes of its SHA256d.
+pub fn encode_check(data: &[u8]) -> String {
+    let mut payload = data.to_vec();
+    payload.extend_from_slice(&sha256d(data)[..4]);
+    encode(&payload)
+}
+
+/// Decodes `s` and strips its checksum after verifying it.
+pub fn decode_check(s: &str) -> Result<Vec<u8>> {
+    let mut payload = decode(s)?;
+    if payload.len() < 4 {
+        return Err(Base58Error::TooShort(payload.len()).into());
+    }
+    let found: [u8; 4] = payload.split_off(payload.len() - 4).try_into().unwrap();
+    let expected: [u8; 4] = sha256d(&payload)[..4].try_into().unwrap();
+    if found != expected {
+        return Err(Base58Error::InvalidChecksum { expected, found }.into());
+    }
+    Ok(payload)
+}
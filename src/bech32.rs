@@ -0,0 +1,296 @@
+//! Bech32 (BIP173) and Bech32m (BIP350), the encodings of native segwit
+//! addresses.
+
+use crate::{BitcoinError, Result};
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::fmt;
+
+const CHARSET: &[u8; 32] = b"qpzry9x8gf2tvdw0s3jn54khce6mua7l";
+const MAX_LENGTH: usize = 90;
+const CHECKSUM_LENGTH: usize = 6;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Variant {
+    /// BIP173, used for witness version 0.
+    Bech32,
+    /// BIP350, used for witness versions 1 and above.
+    Bech32m,
+}
+
+impl Variant {
+    /// The variant segwit addresses of `version` must use.
+    pub fn for_witness_version(version: u8) -> Variant {
+        if version == 0 {
+            Variant::Bech32
+        } else {
+            Variant::Bech32m
+        }
+    }
+
+    fn constant(self) -> u32 {
+        match self {
+            Variant::Bech32 => 1,
+            Variant::Bech32m => 0x2bc830a3,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Bech32Error {
+    /// Longer than the 90 characters BIP173 allows.
+    TooLong(usize),
+    MixedCase,
+    /// A character outside the printable ASCII range, or in the data part
+    /// outside the Bech32 charset, at byte offset `position`.
+    InvalidCharacter {
+        character: char,
+        position: usize,
+    },
+    MissingSeparator,
+    EmptyHrp,
+    /// Data part shorter than the checksum.
+    TooShort(usize),
+    InvalidChecksum,
+    /// 5-bit data whose leftover bits are not zero padding.
+    InvalidPadding,
+    InvalidWitnessVersion(u8),
+    InvalidProgramLength(usize),
+    /// A checksum valid for the other variant than the witness version
+    /// requires.
+    WrongVariant {
+        expected: Variant,
+        found: Variant,
+    },
+}
+
+impl fmt::Display for Bech32Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Bech32Error::TooLong(len) => write!(f, "{} characters exceeds {}", len, MAX_LENGTH),
+            Bech32Error::MixedCase => f.write_str("mixed case"),
+            Bech32Error::InvalidCharacter {
+                character,
+                position,
+            } => write!(
+                f,
+                "invalid character {:?} at position {}",
+                character, position
+            ),
+            Bech32Error::MissingSeparator => f.write_str("missing separator '1'"),
+            Bech32Error::EmptyHrp => f.write_str("empty human-readable part"),
+            Bech32Error::TooShort(len) => {
+                write!(
+                    f,
+                    "data part of {} characters has no room for a checksum",
+                    len
+                )
+            }
+            Bech32Error::InvalidChecksum => f.write_str("invalid checksum"),
+            Bech32Error::InvalidPadding => f.write_str("invalid padding"),
+            Bech32Error::InvalidWitnessVersion(version) => {
+                write!(f, "invalid witness version {}", version)
+            }
+            Bech32Error::InvalidProgramLength(len) => {
+                write!(f, "invalid witness program length {}", len)
+            }
+            Bech32Error::WrongVariant { expected, found } => {
+                write!(f, "{:?} checksum where {:?} is required", found, expected)
+            }
+        }
+    }
+}
+
+impl From<Bech32Error> for BitcoinError {
+    fn from(e: Bech32Error) -> Self {
+        BitcoinError::Bech32(e)
+    }
+}
+
+fn polymod(values: impl IntoIterator<Item = u8>) -> u32 {
+    const GENERATOR: [u32; 5] = [0x3b6a57b2, 0x26508e6d, 0x1ea119fa, 0x3d4233dd, 0x2a1462b3];
+    let mut chk = 1u32;
+    for v in values {
+        let top = chk >> 25;
+        chk = (chk & 0x1ffffff) << 5 ^ v as u32;
+        for (i, g) in GENERATOR.iter().enumerate() {
+            if top >> i & 1 == 1 {
+                chk ^= g;
+            }
+        }
+    }
+    chk
+}
+
+fn hrp_expand(hrp: &str) -> impl Iterator<Item = u8> + '_ {
+    hrp.bytes()
+        .map(|b| b >> 5)
+        .chain([0])
+        .chain(hrp.bytes().map(|b| b & 31))
+}
+
+/// Encodes 5-bit `data` under `hrp`, which is lowercased.
+pub fn encode(hrp: &str, data: &[u8], variant: Variant) -> Result<String> {
+    if hrp.is_empty() {
+        return Err(Bech32Error::EmptyHrp.into());
+    }
+    if let Some((position, character)) = hrp
+        .char_indices()
+        .find(|&(_, c)| !(33..=126).contains(&(c as u32)))
+    {
+        return Err(Bech32Error::InvalidCharacter {
+            character,
+            position,
+        }
+        .into());
+    }
+    if let Some(&value) = data.iter().find(|&&d| d >= 32) {
+        return Err(Bech32Error::InvalidCharacter {
+            character: value as char,
+            position: hrp.len() + 1,
+        }
+        .into());
+    }
+    let len = hrp.len() + 1 + data.len() + CHECKSUM_LENGTH;
+    if len > MAX_LENGTH {
+        return Err(Bech32Error::TooLong(len).into());
+    }
+
+    let hrp = hrp.to_ascii_lowercase();
+    let values = hrp_expand(&hrp)
+        .chain(data.iter().copied())
+        .chain([0; CHECKSUM_LENGTH]);
+    let checksum = polymod(values) ^ variant.constant();
+    let mut out = String::with_capacity(len);
+    out.push_str(&hrp);
+    out.push('1');
+    for &d in data {
+        out.push(CHARSET[d as usize] as char);
+    }
+    for i in 0..CHECKSUM_LENGTH {
+        out.push(CHARSET[(checksum >> (5 * (5 - i)) & 31) as usize] as char);
+    }
+    Ok(out)
+}
+
+/// Splits `s` into its lowercased HRP and 5-bit data, without the
+/// checksum, reporting which variant's checksum it carries.
+pub fn decode(s: &str) -> Result<(String, Vec<u8>, Variant)> {
+    if s.len() > MAX_LENGTH {
+        return Err(Bech32Error::TooLong(s.len()).into());
+    }
+    if let Some((position, character)) = s
+        .char_indices()
+        .find(|&(_, c)| !(33..=126).contains(&(c as u32)))
+    {
+        return Err(Bech32Error::InvalidCharacter {
+            character,
+            position,
+        }
+        .into());
+    }
+    if s.bytes().any(|b| b.is_ascii_lowercase()) && s.bytes().any(|b| b.is_ascii_uppercase()) {
+        return Err(Bech32Error::MixedCase.into());
+    }
+    let s = s.to_ascii_lowercase();
+    let (hrp, data) = s.rsplit_once('1').ok_or(Bech32Error::MissingSeparator)?;
+    if hrp.is_empty() {
+        return Err(Bech32Error::EmptyHrp.into());
+    }
+    if data.len() < CHECKSUM_LENGTH {
+        return Err(Bech32Error::TooShort(data.len()).into());
+    }
+    let data = data
+        .char_indices()
+        .map(|(i, character)| {
+            CHARSET
+                .iter()
+                .position(|&x| x as char == character)
+                .map(|d| d as u8)
+                .ok_or(Bech32Error::InvalidCharacter {
+                    character,
+                    position: hrp.len() + 1 + i,
+                })
+        })
+        .collect::<core::result::Result<Vec<u8>, _>>()?;
+    let variant = match polymod(hrp_expand(hrp).chain(data.iter().copied())) {
+        c if c == Variant::Bech32.constant() => Variant::Bech32,
+        c if c == Variant::Bech32m.constant() => Variant::Bech32m,
+        _ => return Err(Bech32Error::InvalidChecksum.into()),
+    };
+    Ok((
+        hrp.into(),
+        data[..data.len() - CHECKSUM_LENGTH].to_vec(),
+        variant,
+    ))
+}
+
+/// Regroups `data` from `from`-bit to `to`-bit values. Without `pad`,
+/// leftover bits must be zero padding of less than `from` bits.
+pub fn convert_bits(data: &[u8], from: u32, to: u32, pad: bool) -> Result<Vec<u8>> {
+    let mut acc = 0u32;
+    let mut bits = 0u32;
+    let max = (1u32 << to) - 1;
+    let mut out = Vec::with_capacity(data.len() * from as usize / to as usize + 1);
+    for (position, &value) in data.iter().enumerate() {
+        if (value as u32) >> from != 0 {
+            return Err(Bech32Error::InvalidCharacter {
+                character: value as char,
+                position,
+            }
+            .into());
+        }
+        acc = acc << from | value as u32;
+        bits += from;
+        while bits >= to {
+            bits -= to;
+            out.push((acc >> bits & max) as u8);
+        }
+    }
+    if pad {
+        if bits > 0 {
+            out.push((acc << (to - bits) & max) as u8);
+        }
+    } else if bits >= from || acc << (to - bits) & max != 0 {
+        return Err(Bech32Error::InvalidPadding.into());
+    }
+    Ok(out)
+}
+
+/// Encodes a segwit address for witness `version` and `program`.
+pub fn encode_segwit(hrp: &str, version: u8, program: &[u8]) -> Result<String> {
+    check_witness_program(version, program)?;
+    let mut data = Vec::from([version]);
+    data.extend(convert_bits(program, 8, 5, true)?);
+    encode(hrp, &data, Variant::for_witness_version(version))
+}
+
+/// Decodes a segwit address into its HRP, witness version and program,
+/// enforcing the BIP173/BIP350 version, length and variant rules.
+pub fn decode_segwit(s: &str) -> Result<(String, u8, Vec<u8>)> {
+    let (hrp, data, variant) = decode(s)?;
+    let (&version, data) = data
+        .split_first()
+        .ok_or(Bech32Error::InvalidProgramLength(0))?;
+    let program = convert_bits(data, 5, 8, false)?;
+    check_witness_program(version, &program)?;
+    let expected = Variant::for_witness_version(version);
+    if variant != expected {
+        return Err(Bech32Error::WrongVariant {
+            expected,
+            found: variant,
+        }
+        .into());
+    }
+    Ok((hrp, version, program))
+}
+
+fn check_witness_program(version: u8, program: &[u8]) -> Result<()> {
+    if version > 16 {
+        return Err(Bech32Error::InvalidWitnessVersion(version).into());
+    }
+    if !(2..=40).contains(&program.len()) || (version == 0 && ![20, 32].contains(&program.len())) {
+        return Err(Bech32Error::InvalidProgramLength(program.len()).into());
+    }
+    Ok(())
+}
@@ -0,0 +1,45 @@
+use crate::{Wtxid, hashes};
+use alloc::vec::Vec;
+
+/// SipHash key shared by every short ID in one compact block.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ShortIdKey {
+    pub k0: u64,
+    pub k1: u64,
+}
+
+impl ShortIdKey {
+    /// Derives the key from the serialized block header and the sender's nonce.
+    pub fn new(header: &[u8], nonce: u64) -> Self {
+        let mut preimage = Vec::with_capacity(header.len() + 8);
+        preimage.extend_from_slice(header);
+        preimage.extend_from_slice(&nonce.to_le_bytes());
+        let hash = hashes::sha256(&preimage);
+        Self {
+            k0: u64::from_le_bytes(hash[..8].try_into().unwrap()),
+            k1: u64::from_le_bytes(hash[8..16].try_into().unwrap()),
+        }
+    }
+
+    pub fn short_id(&self, wtxid: &Wtxid) -> ShortId {
+        ShortId::from_u64(hashes::siphash24(self.k0, self.k1, &wtxid.0))
+    }
+}
+
+/// The low 6 bytes of a SipHash, in wire (little-endian) order.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct ShortId(pub [u8; 6]);
+
+impl ShortId {
+    pub fn from_u64(value: u64) -> Self {
+        let mut bytes = [0u8; 6];
+        bytes.copy_from_slice(&value.to_le_bytes()[..6]);
+        Self(bytes)
+    }
+
+    pub fn to_u64(&self) -> u64 {
+        let mut bytes = [0u8; 8];
+        bytes[..6].copy_from_slice(&self.0);
+        u64::from_le_bytes(bytes)
+    }
+}
@@ -0,0 +1,206 @@
+use crate::{Address, BitcoinError, Result};
+use alloc::format;
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::fmt;
+use core::str::FromStr;
+
+const SCHEME: &str = "bitcoin:";
+const SATS_PER_BTC: u64 = 100_000_000;
+
+/// A BIP21 `bitcoin:` payment URI.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Bip21Uri {
+    pub address: Address,
+    /// In satoshis.
+    pub amount: Option<u64>,
+    pub label: Option<String>,
+    pub message: Option<String>,
+    /// A BOLT11 invoice offered as an alternative, as unified QR codes do.
+    pub lightning: Option<String>,
+    /// Other parameters, percent-decoded and in their original order.
+    pub extras: Vec<(String, String)>,
+}
+
+impl Bip21Uri {
+    pub fn new(address: Address) -> Self {
+        Self {
+            address,
+            amount: None,
+            label: None,
+            message: None,
+            lightning: None,
+            extras: Vec::new(),
+        }
+    }
+
+    pub fn with_amount(mut self, sats: u64) -> Self {
+        self.amount = Some(sats);
+        self
+    }
+
+    pub fn with_label(mut self, label: impl Into<String>) -> Self {
+        self.label = Some(label.into());
+        self
+    }
+
+    pub fn with_message(mut self, message: impl Into<String>) -> Self {
+        self.message = Some(message.into());
+        self
+    }
+
+    pub fn with_lightning(mut self, invoice: impl Into<String>) -> Self {
+        self.lightning = Some(invoice.into());
+        self
+    }
+
+    pub fn with_param(mut self, key: impl Into<String>, value: impl Into<String>) -> Self {
+        self.extras.push((key.into(), value.into()));
+        self
+    }
+}
+
+fn invalid(reason: &'static str) -> BitcoinError {
+    BitcoinError::InvalidUri(reason)
+}
+
+// BIP21 amounts are decimal BTC with at most 8 fractional digits.
+fn parse_amount(s: &str) -> Result<u64> {
+    let (whole, frac) = s.split_once('.').unwrap_or((s, ""));
+    let is_digits = |part: &str| part.bytes().all(|b| b.is_ascii_digit());
+    if (whole.is_empty() && frac.is_empty())
+        || frac.len() > 8
+        || !is_digits(whole)
+        || !is_digits(frac)
+    {
+        return Err(invalid("invalid amount"));
+    }
+    let mut frac_sats = 0;
+    for (i, digit) in frac.bytes().enumerate() {
+        frac_sats += (digit - b'0') as u64 * 10u64.pow(7 - i as u32);
+    }
+    let whole: u64 = if whole.is_empty() {
+        0
+    } else {
+        whole.parse().map_err(|_| invalid("invalid amount"))?
+    };
+    whole
+        .checked_mul(SATS_PER_BTC)
+        .and_then(|sats| sats.checked_add(frac_sats))
+        .ok_or(invalid("amount overflows"))
+}
+
+fn format_amount(sats: u64) -> String {
+    let whole = sats / SATS_PER_BTC;
+    let frac = sats % SATS_PER_BTC;
+    if frac == 0 {
+        return format!("{}", whole);
+    }
+    let frac = format!("{:08}", frac);
+    format!("{}.{}", whole, frac.trim_end_matches('0'))
+}
+
+fn percent_decode(s: &str) -> Result<String> {
+    let mut bytes = Vec::with_capacity(s.len());
+    let mut iter = s.bytes();
+    while let Some(b) = iter.next() {
+        if b != b'%' {
+            bytes.push(b);
+            continue;
+        }
+        let hex = [
+            iter.next().ok_or(invalid("truncated percent-encoding"))?,
+            iter.next().ok_or(invalid("truncated percent-encoding"))?,
+        ];
+        let mut byte = [0u8];
+        hex::decode_to_slice(hex, &mut byte).map_err(|_| invalid("invalid percent-encoding"))?;
+        bytes.push(byte[0]);
+    }
+    String::from_utf8(bytes).map_err(|_| invalid("percent-decoded value is not UTF-8"))
+}
+
+// Escapes everything but RFC 3986 unreserved characters.
+fn percent_encode(s: &str, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    for b in s.bytes() {
+        if b.is_ascii_alphanumeric() || matches!(b, b'-' | b'.' | b'_' | b'~') {
+            write!(f, "{}", b as char)?;
+        } else {
+            write!(f, "%{:02X}", b)?;
+        }
+    }
+    Ok(())
+}
+
+impl FromStr for Bip21Uri {
+    type Err = BitcoinError;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let scheme = s
+            .get(..SCHEME.len())
+            .ok_or(invalid("missing bitcoin: scheme"))?;
+        if !scheme.eq_ignore_ascii_case(SCHEME) {
+            return Err(invalid("missing bitcoin: scheme"));
+        }
+        let rest = &s[SCHEME.len()..];
+        let (address, query) = rest.split_once('?').unwrap_or((rest, ""));
+        let mut uri = Bip21Uri::new(address.parse()?);
+
+        for param in query.split('&').filter(|p| !p.is_empty()) {
+            let (key, value) = param.split_once('=').unwrap_or((param, ""));
+            let value = percent_decode(value)?;
+            let slot = match key {
+                "amount" => {
+                    if uri.amount.replace(parse_amount(&value)?).is_some() {
+                        return Err(invalid("duplicate amount"));
+                    }
+                    continue;
+                }
+                "label" => &mut uri.label,
+                "message" => &mut uri.message,
+                "lightning" => &mut uri.lightning,
+                // Parameters the payer must understand to pay correctly.
+                _ if key.starts_with("req-") => {
+                    return Err(invalid("unsupported required parameter"));
+                }
+                _ => {
+                    uri.extras.push((percent_decode(key)?, value));
+                    continue;
+                }
+            };
+            if slot.replace(value).is_some() {
+                return Err(invalid("duplicate parameter"));
+            }
+        }
+        Ok(uri)
+    }
+}
+
+impl fmt::Display for Bip21Uri {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}{}", SCHEME, self.address)?;
+        let mut separator = '?';
+        let mut param = |f: &mut fmt::Formatter<'_>, key: &str, value: &str| {
+            write!(f, "{}", separator)?;
+            separator = '&';
+            percent_encode(key, f)?;
+            f.write_str("=")?;
+            percent_encode(value, f)
+        };
+        if let Some(amount) = self.amount {
+            param(f, "amount", &format_amount(amount))?;
+        }
+        for (key, value) in [
+            ("label", &self.label),
+            ("message", &self.message),
+            ("lightning", &self.lightning),
+        ] {
+            if let Some(value) = value {
+                param(f, key, value)?;
+            }
+        }
+        for (key, value) in &self.extras {
+            param(f, key, value)?;
+        }
+        Ok(())
+    }
+}
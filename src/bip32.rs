@@ -0,0 +1,673 @@
+use crate::base58;
+use crate::curve::{self, Point};
+use crate::hashes::{hash160, hmac_sha512};
+use crate::{BitcoinError, Network, PrivateKey, PublicKey, Result};
+use alloc::format;
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::fmt;
+use core::str::FromStr;
+use serde::{Deserialize, Serialize};
+
+/// Child numbers at or above this derive hardened keys.
+pub const HARDENED: u32 = 1 << 31;
+
+const XPUB_MAINNET: [u8; 4] = [0x04, 0x88, 0xb2, 0x1e];
+const XPUB_TESTNET: [u8; 4] = [0x04, 0x35, 0x87, 0xcf];
+const XPRV_MAINNET: [u8; 4] = [0x04, 0x88, 0xad, 0xe4];
+const XPRV_TESTNET: [u8; 4] = [0x04, 0x35, 0x83, 0x94];
+
+/// The script type a SLIP-132 version prefix says a key is used with.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum KeyApplication {
+    /// `xpub`/`tpub`: P2PKH, or P2SH multisig.
+    Legacy,
+    /// `ypub`/`upub`: P2WPKH nested in P2SH.
+    NestedSegwit,
+    /// `zpub`/`vpub`: P2WPKH.
+    Segwit,
+    /// `Ypub`/`Upub`: multisig P2WSH nested in P2SH.
+    NestedSegwitMultisig,
+    /// `Zpub`/`Vpub`: multisig P2WSH.
+    SegwitMultisig,
+}
+
+// Mainnet public, mainnet private, testnet public and testnet private
+// version bytes for each application.
+const SLIP132_VERSIONS: [(KeyApplication, [[u8; 4]; 4]); 5] = [
+    (
+        KeyApplication::Legacy,
+        [XPUB_MAINNET, XPRV_MAINNET, XPUB_TESTNET, XPRV_TESTNET],
+    ),
+    (
+        KeyApplication::NestedSegwit,
+        [
+            [0x04, 0x9d, 0x7c, 0xb2],
+            [0x04, 0x9d, 0x78, 0x78],
+            [0x04, 0x4a, 0x52, 0x62],
+            [0x04, 0x4a, 0x4e, 0x28],
+        ],
+    ),
+    (
+        KeyApplication::Segwit,
+        [
+            [0x04, 0xb2, 0x47, 0x46],
+            [0x04, 0xb2, 0x43, 0x0c],
+            [0x04, 0x5f, 0x1c, 0xf6],
+            [0x04, 0x5f, 0x18, 0xbc],
+        ],
+    ),
+    (
+        KeyApplication::NestedSegwitMultisig,
+        [
+            [0x02, 0x95, 0xb4, 0x3f],
+            [0x02, 0x95, 0xb0, 0x05],
+            [0x02, 0x42, 0x89, 0xef],
+            [0x02, 0x42, 0x85, 0xb5],
+        ],
+    ),
+    (
+        KeyApplication::SegwitMultisig,
+        [
+            [0x02, 0xaa, 0x7e, 0xd3],
+            [0x02, 0xaa, 0x7a, 0x99],
+            [0x02, 0x57, 0x54, 0x83],
+            [0x02, 0x57, 0x50, 0x48],
+        ],
+    ),
+];
+
+fn slip132_version(application: KeyApplication, network: Network, private: bool) -> [u8; 4] {
+    let (_, versions) = SLIP132_VERSIONS
+        .iter()
+        .find(|(a, _)| *a == application)
+        .expect("every application has versions");
+    let testnet = network != Network::Mainnet;
+    versions[usize::from(testnet) * 2 + usize::from(private)]
+}
+
+// Decodes a SLIP-132 string to its application and the same key data
+// under the plain xpub/xprv version bytes.
+fn decode_slip132(s: &str, private: bool) -> Result<(Vec<u8>, KeyApplication)> {
+    let mut data = base58::decode_check(s)?;
+    let version = data
+        .get(..4)
+        .ok_or(BitcoinError::InvalidExtendedKey("expected 78 bytes"))?;
+    for &(application, versions) in &SLIP132_VERSIONS {
+        for (i, candidate) in versions.iter().enumerate() {
+            if version == candidate && (i % 2 == 1) == private {
+                let network = if i < 2 {
+                    Network::Mainnet
+                } else {
+                    Network::Testnet
+                };
+                let plain = slip132_version(KeyApplication::Legacy, network, private);
+                data[..4].copy_from_slice(&plain);
+                return Ok((data, application));
+            }
+        }
+    }
+    Err(BitcoinError::InvalidExtendedKey("unknown version bytes"))
+}
+
+/// One step of a derivation path, with `index` below 2^31 in both cases.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum ChildNumber {
+    Normal { index: u32 },
+    Hardened { index: u32 },
+}
+
+impl ChildNumber {
+    pub fn from_normal_idx(index: u32) -> Result<ChildNumber> {
+        if index >= HARDENED {
+            return Err(BitcoinError::InvalidDerivationPath(format!(
+                "child index {} is too large",
+                index
+            )));
+        }
+        Ok(ChildNumber::Normal { index })
+    }
+
+    pub fn from_hardened_idx(index: u32) -> Result<ChildNumber> {
+        ChildNumber::from_normal_idx(index).map(|_| ChildNumber::Hardened { index })
+    }
+
+    pub fn is_hardened(&self) -> bool {
+        matches!(self, ChildNumber::Hardened { .. })
+    }
+
+    pub fn index(&self) -> u32 {
+        match *self {
+            ChildNumber::Normal { index } | ChildNumber::Hardened { index } => index,
+        }
+    }
+}
+
+/// Splits a raw BIP32 child number, hardened at or above [`HARDENED`].
+impl From<u32> for ChildNumber {
+    fn from(n: u32) -> ChildNumber {
+        if n >= HARDENED {
+            ChildNumber::Hardened {
+                index: n & !HARDENED,
+            }
+        } else {
+            ChildNumber::Normal { index: n }
+        }
+    }
+}
+
+impl From<ChildNumber> for u32 {
+    fn from(n: ChildNumber) -> u32 {
+        match n {
+            ChildNumber::Normal { index } => index,
+            ChildNumber::Hardened { index } => index | HARDENED,
+        }
+    }
+}
+
+/// A BIP32 derivation path such as `m/84'/0'/0'/0/5`.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct DerivationPath(Vec<ChildNumber>);
+
+impl DerivationPath {
+    /// The empty path, `m`.
+    pub fn master() -> DerivationPath {
+        DerivationPath(Vec::new())
+    }
+
+    pub fn is_master(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    pub fn len(&self) -> usize {
+        self.0.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    pub fn iter(&self) -> core::slice::Iter<'_, ChildNumber> {
+        self.0.iter()
+    }
+
+    /// This path followed by `child`.
+    pub fn child(&self, child: ChildNumber) -> DerivationPath {
+        self.extend([child])
+    }
+
+    /// This path followed by every step of `path`.
+    pub fn extend<P: AsRef<[ChildNumber]>>(&self, path: P) -> DerivationPath {
+        DerivationPath([self.0.as_slice(), path.as_ref()].concat())
+    }
+}
+
+/// A BIP32 extended public key.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Xpub {
+    /// Mainnet for `xpub`, Testnet for `tpub`, which every test network
+    /// shares.
+    pub network: Network,
+    pub depth: u8,
+    pub parent_fingerprint: [u8; 4],
+    pub child_number: u32,
+    pub chain_code: [u8; 32],
+    /// Compressed SEC encoding.
+    pub public_key: [u8; 33],
+}
+
+/// A BIP32 extended private key.
+#[derive(Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Xpriv {
+    /// Mainnet for `xprv`, Testnet for `tprv`.
+    pub network: Network,
+    pub depth: u8,
+    pub parent_fingerprint: [u8; 4],
+    pub child_number: u32,
+    pub chain_code: [u8; 32],
+    private_key: [u8; 32],
+}
+
+// The 78-byte serialization shared by both key types, with `key_data` the
+// compressed public key or the private key prefixed by a zero byte.
+fn encode_extended(
+    version: [u8; 4],
+    depth: u8,
+    parent_fingerprint: &[u8; 4],
+    child_number: u32,
+    chain_code: &[u8; 32],
+    key_data: &[u8; 33],
+) -> [u8; 78] {
+    let mut out = [0u8; 78];
+    out[..4].copy_from_slice(&version);
+    out[4] = depth;
+    out[5..9].copy_from_slice(parent_fingerprint);
+    out[9..13].copy_from_slice(&child_number.to_be_bytes());
+    out[13..45].copy_from_slice(chain_code);
+    out[45..].copy_from_slice(key_data);
+    out
+}
+
+fn decode_header(data: &[u8]) -> Result<&[u8; 78]> {
+    let data: &[u8; 78] = data
+        .try_into()
+        .map_err(|_| BitcoinError::InvalidExtendedKey("expected 78 bytes"))?;
+    let (depth, parent_fingerprint, child_number) = (data[4], &data[5..9], &data[9..13]);
+    if depth == 0 && (parent_fingerprint != [0; 4] || child_number != [0; 4]) {
+        return Err(BitcoinError::InvalidExtendedKey(
+            "master key with a parent fingerprint or child number",
+        ));
+    }
+    Ok(data)
+}
+
+fn next_depth(depth: u8) -> Result<u8> {
+    depth
+        .checked_add(1)
+        .ok_or(BitcoinError::InvalidExtendedKey("depth overflow"))
+}
+
+impl Xpub {
+    pub fn identifier(&self) -> [u8; 20] {
+        hash160(&self.public_key)
+    }
+
+    pub fn fingerprint(&self) -> [u8; 4] {
+        self.identifier()[..4].try_into().unwrap()
+    }
+
+    pub fn to_public_key(&self) -> PublicKey {
+        PublicKey::from_slice(&self.public_key).expect("checked when constructed")
+    }
+
+    /// Derives the non-hardened child `index` (CKDpub).
+    pub fn ckd_pub(&self, index: u32) -> Result<Xpub> {
+        if index >= HARDENED {
+            return Err(BitcoinError::InvalidExtendedKey(
+                "hardened derivation requires a private key",
+            ));
+        }
+        let mut data = [0u8; 37];
+        data[..33].copy_from_slice(&self.public_key);
+        data[33..].copy_from_slice(&index.to_be_bytes());
+        let i = hmac_sha512(&self.chain_code, &data);
+        let (tweak, chain_code) = i.split_at(32);
+
+        let parent = Point::from_sec(&self.public_key)
+            .ok_or(BitcoinError::InvalidExtendedKey("invalid public key"))?;
+        // BIP32 says to move on to the next index in this (astronomically
+        // unlikely) case.
+        let child = parent
+            .add_tweak(tweak.try_into().unwrap())
+            .ok_or(BitcoinError::InvalidExtendedKey("invalid child key"))?;
+        Ok(Xpub {
+            network: self.network,
+            depth: next_depth(self.depth)?,
+            parent_fingerprint: self.fingerprint(),
+            child_number: index,
+            chain_code: chain_code.try_into().unwrap(),
+            public_key: child.serialize_compressed(),
+        })
+    }
+
+    pub fn derive_pub<P: AsRef<[ChildNumber]>>(&self, path: P) -> Result<Xpub> {
+        path.as_ref()
+            .iter()
+            .try_fold(*self, |key, &child| key.ckd_pub(child.into()))
+    }
+
+    pub fn encode(&self) -> [u8; 78] {
+        let version = match self.network {
+            Network::Mainnet => XPUB_MAINNET,
+            _ => XPUB_TESTNET,
+        };
+        encode_extended(
+            version,
+            self.depth,
+            &self.parent_fingerprint,
+            self.child_number,
+            &self.chain_code,
+            &self.public_key,
+        )
+    }
+
+    /// Serializes with the SLIP-132 prefix for `application`, e.g. `zpub`.
+    pub fn to_slip132_string(&self, application: KeyApplication) -> String {
+        let mut data = self.encode();
+        data[..4].copy_from_slice(&slip132_version(application, self.network, false));
+        base58::encode_check(&data)
+    }
+
+    /// Parses any SLIP-132 public key prefix, including plain `xpub` and
+    /// `tpub`, returning the key with the application its prefix implied.
+    pub fn from_slip132_str(s: &str) -> Result<(Xpub, KeyApplication)> {
+        let (data, application) = decode_slip132(s, false)?;
+        Ok((Xpub::decode(&data)?, application))
+    }
+
+    pub fn decode(data: &[u8]) -> Result<Xpub> {
+        let data = decode_header(data)?;
+        let network = match data[..4].try_into().unwrap() {
+            XPUB_MAINNET => Network::Mainnet,
+            XPUB_TESTNET => Network::Testnet,
+            _ => return Err(BitcoinError::InvalidExtendedKey("unknown version bytes")),
+        };
+        let public_key: [u8; 33] = data[45..].try_into().unwrap();
+        if Point::from_sec(&public_key).is_none() || public_key[0] == 0x04 {
+            return Err(BitcoinError::InvalidExtendedKey("invalid public key"));
+        }
+        Ok(Xpub {
+            network,
+            depth: data[4],
+            parent_fingerprint: data[5..9].try_into().unwrap(),
+            child_number: u32::from_be_bytes(data[9..13].try_into().unwrap()),
+            chain_code: data[13..45].try_into().unwrap(),
+            public_key,
+        })
+    }
+}
+
+impl Xpriv {
+    /// The master key for `seed`, which BIP32 requires to be 16 to 64
+    /// bytes.
+    pub fn new_master(network: Network, seed: &[u8]) -> Result<Xpriv> {
+        if !(16..=64).contains(&seed.len()) {
+            return Err(BitcoinError::InvalidExtendedKey(
+                "seed must be 16 to 64 bytes",
+            ));
+        }
+        let i = hmac_sha512(b"Bitcoin seed", seed);
+        let private_key: [u8; 32] = i[..32].try_into().unwrap();
+        if !curve::is_valid_scalar(&private_key) {
+            return Err(BitcoinError::InvalidExtendedKey("invalid master key"));
+        }
+        Ok(Xpriv {
+            network,
+            depth: 0,
+            parent_fingerprint: [0; 4],
+            child_number: 0,
+            chain_code: i[32..].try_into().unwrap(),
+            private_key,
+        })
+    }
+
+    pub fn private_key(&self) -> PrivateKey {
+        PrivateKey::new(self.private_key, self.network, true).expect("checked when constructed")
+    }
+
+    #[cfg(feature = "secp256k1")]
+    pub fn to_xpub(&self) -> Xpub {
+        Xpub {
+            network: self.network,
+            depth: self.depth,
+            parent_fingerprint: self.parent_fingerprint,
+            child_number: self.child_number,
+            chain_code: self.chain_code,
+            public_key: self.private_key().public_key().serialize(),
+        }
+    }
+
+    #[cfg(feature = "secp256k1")]
+    pub fn identifier(&self) -> [u8; 20] {
+        self.to_xpub().identifier()
+    }
+
+    #[cfg(feature = "secp256k1")]
+    pub fn fingerprint(&self) -> [u8; 4] {
+        self.to_xpub().fingerprint()
+    }
+
+    #[cfg(feature = "secp256k1")]
+    /// Derives child `index` (CKDpriv), hardened if `index >= HARDENED`.
+    pub fn ckd_priv(&self, index: u32) -> Result<Xpriv> {
+        let public_key = self.private_key().public_key().serialize();
+        let mut data = [0u8; 37];
+        if index >= HARDENED {
+            data[1..33].copy_from_slice(&self.private_key);
+        } else {
+            data[..33].copy_from_slice(&public_key);
+        }
+        data[33..].copy_from_slice(&index.to_be_bytes());
+        let i = hmac_sha512(&self.chain_code, &data);
+        let tweak: [u8; 32] = i[..32].try_into().unwrap();
+
+        // As in `ckd_pub`, BIP32 skips the index if this ever fails.
+        if !curve::is_valid_scalar(&tweak) {
+            return Err(BitcoinError::InvalidExtendedKey("invalid child key"));
+        }
+        // libsecp256k1's addition doesn't leak the parent key through timing.
+        let private_key = secp256k1::SecretKey::from_slice(&self.private_key)
+            .expect("checked when constructed")
+            .add_tweak(&secp256k1::Scalar::from_be_bytes(tweak).expect("checked above"))
+            .map_err(|_| BitcoinError::InvalidExtendedKey("invalid child key"))?
+            .secret_bytes();
+        Ok(Xpriv {
+            network: self.network,
+            depth: next_depth(self.depth)?,
+            parent_fingerprint: hash160(&public_key)[..4].try_into().unwrap(),
+            child_number: index,
+            chain_code: i[32..].try_into().unwrap(),
+            private_key,
+        })
+    }
+
+    #[cfg(feature = "secp256k1")]
+    pub fn derive_priv<P: AsRef<[ChildNumber]>>(&self, path: P) -> Result<Xpriv> {
+        path.as_ref()
+            .iter()
+            .try_fold(*self, |key, &child| key.ckd_priv(child.into()))
+    }
+
+    pub fn encode(&self) -> [u8; 78] {
+        let version = match self.network {
+            Network::Mainnet => XPRV_MAINNET,
+            _ => XPRV_TESTNET,
+        };
+        let mut key_data = [0u8; 33];
+        key_data[1..].copy_from_slice(&self.private_key);
+        encode_extended(
+            version,
+            self.depth,
+            &self.parent_fingerprint,
+            self.child_number,
+            &self.chain_code,
+            &key_data,
+        )
+    }
+
+    /// Serializes with the SLIP-132 prefix for `application`, e.g. `zprv`.
+    pub fn to_slip132_string(&self, application: KeyApplication) -> String {
+        let mut data = self.encode();
+        data[..4].copy_from_slice(&slip132_version(application, self.network, true));
+        base58::encode_check(&data)
+    }
+
+    /// Parses any SLIP-132 private key prefix, as
+    /// [`Xpub::from_slip132_str`] does for public keys.
+    pub fn from_slip132_str(s: &str) -> Result<(Xpriv, KeyApplication)> {
+        let (data, application) = decode_slip132(s, true)?;
+        Ok((Xpriv::decode(&data)?, application))
+    }
+
+    pub fn decode(data: &[u8]) -> Result<Xpriv> {
+        let data = decode_header(data)?;
+        let network = match data[..4].try_into().unwrap() {
+            XPRV_MAINNET => Network::Mainnet,
+            XPRV_TESTNET => Network::Testnet,
+            _ => return Err(BitcoinError::InvalidExtendedKey("unknown version bytes")),
+        };
+        let private_key: [u8; 32] = data[46..].try_into().unwrap();
+        if data[45] != 0 || !curve::is_valid_scalar(&private_key) {
+            return Err(BitcoinError::InvalidExtendedKey("invalid private key"));
+        }
+        Ok(Xpriv {
+            network,
+            depth: data[4],
+            parent_fingerprint: data[5..9].try_into().unwrap(),
+            child_number: u32::from_be_bytes(data[9..13].try_into().unwrap()),
+            chain_code: data[13..45].try_into().unwrap(),
+            private_key,
+        })
+    }
+}
+
+impl FromStr for ChildNumber {
+    type Err = BitcoinError;
+
+    /// Parses `5`, or `5'`, `5h` or `5H` for hardened steps.
+    fn from_str(s: &str) -> Result<Self> {
+        let (digits, hardened) = match s.strip_suffix(['\'', 'h', 'H']) {
+            Some(digits) => (digits, true),
+            None => (s, false),
+        };
+        let index = match digits.parse::<u32>() {
+            Ok(index) if digits.bytes().all(|b| b.is_ascii_digit()) => index,
+            _ => {
+                return Err(BitcoinError::InvalidDerivationPath(format!(
+                    "invalid child number '{}'",
+                    s
+                )));
+            }
+        };
+        if hardened {
+            ChildNumber::from_hardened_idx(index)
+        } else {
+            ChildNumber::from_normal_idx(index)
+        }
+    }
+}
+
+impl fmt::Display for ChildNumber {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ChildNumber::Normal { index } => write!(f, "{}", index),
+            ChildNumber::Hardened { index } => write!(f, "{}'", index),
+        }
+    }
+}
+
+impl AsRef<[ChildNumber]> for DerivationPath {
+    fn as_ref(&self) -> &[ChildNumber] {
+        &self.0
+    }
+}
+
+impl From<Vec<ChildNumber>> for DerivationPath {
+    fn from(path: Vec<ChildNumber>) -> DerivationPath {
+        DerivationPath(path)
+    }
+}
+
+impl From<&[ChildNumber]> for DerivationPath {
+    fn from(path: &[ChildNumber]) -> DerivationPath {
+        DerivationPath(path.to_vec())
+    }
+}
+
+impl FromIterator<ChildNumber> for DerivationPath {
+    fn from_iter<I: IntoIterator<Item = ChildNumber>>(iter: I) -> DerivationPath {
+        DerivationPath(iter.into_iter().collect())
+    }
+}
+
+impl<'a> IntoIterator for &'a DerivationPath {
+    type Item = &'a ChildNumber;
+    type IntoIter = core::slice::Iter<'a, ChildNumber>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.0.iter()
+    }
+}
+
+impl IntoIterator for DerivationPath {
+    type Item = ChildNumber;
+    type IntoIter = alloc::vec::IntoIter<ChildNumber>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.0.into_iter()
+    }
+}
+
+impl FromStr for DerivationPath {
+    type Err = BitcoinError;
+
+    /// Accepts paths with or without the leading `m/`; `m` alone is the
+    /// master path.
+    fn from_str(s: &str) -> Result<Self> {
+        let steps = match s {
+            "m" => return Ok(DerivationPath::master()),
+            _ => s.strip_prefix("m/").unwrap_or(s),
+        };
+        steps.split('/').map(ChildNumber::from_str).collect()
+    }
+}
+
+impl fmt::Display for DerivationPath {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("m")?;
+        for child in &self.0 {
+            write!(f, "/{}", child)?;
+        }
+        Ok(())
+    }
+}
+
+impl Serialize for DerivationPath {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        serializer.collect_str(self)
+    }
+}
+
+impl<'de> Deserialize<'de> for DerivationPath {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        let s = String::deserialize(deserializer)?;
+        s.parse().map_err(serde::de::Error::custom)
+    }
+}
+
+impl FromStr for Xpub {
+    type Err = BitcoinError;
+
+    fn from_str(s: &str) -> Result<Self> {
+        Xpub::decode(&base58::decode_check(s)?)
+    }
+}
+
+impl fmt::Display for Xpub {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&base58::encode_check(&self.encode()))
+    }
+}
+
+impl FromStr for Xpriv {
+    type Err = BitcoinError;
+
+    fn from_str(s: &str) -> Result<Self> {
+        Xpriv::decode(&base58::decode_check(s)?)
+    }
+}
+
+impl fmt::Display for Xpriv {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&base58::encode_check(&self.encode()))
+    }
+}
+
+// Like `PrivateKey`, keeps the key out of logs.
+impl fmt::Debug for Xpriv {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Xpriv")
+            .field("network", &self.network)
+            .field("depth", &self.depth)
+            .field("parent_fingerprint", &self.parent_fingerprint)
+            .field("child_number", &self.child_number)
+            .field("private_key", &"[redacted]")
+            .finish_non_exhaustive()
+    }
+}
@@ -0,0 +1,174 @@
+//! BIP322 generic signed messages. A proof spends a virtual `to_spend`
+//! transaction, whose output pays to the address and commits to the
+//! message, with a virtual `to_sign` transaction; any script the
+//! interpreter can run works, as do BIP86 key-path P2TR addresses. Simple
+//! proofs carry only the witness, full proofs the whole `to_sign`. Signing
+//! and verification need the `secp256k1` feature.
+
+use crate::hashes::tagged_hash;
+use crate::{BitcoinTransaction, Opcode, OutPoint, Script, TransactionInput, TransactionOutput};
+use alloc::vec;
+#[cfg(feature = "secp256k1")]
+use {
+    crate::{
+        Address, BitcoinError, ConsensusDecode, ConsensusEncode, PrivateKey, Result, ScriptType,
+        Witness, XOnlyPublicKey, base64,
+        schnorr::{self, SchnorrSignature},
+        script::interpreter::{TransactionSignatureChecker, VerifyFlags, verify_script},
+        sighash::{Prevouts, SighashCache, SighashType},
+    },
+    alloc::string::String,
+};
+
+const MESSAGE_TAG: &str = "BIP0322-signed-message";
+
+/// The tagged hash of `message` that `to_spend` commits to.
+pub fn message_hash(message: &str) -> [u8; 32] {
+    tagged_hash(MESSAGE_TAG, message.as_bytes())
+}
+
+/// The virtual transaction whose only output, paying to `script_pubkey`,
+/// a proof spends.
+pub fn to_spend(script_pubkey: &Script, message: &str) -> BitcoinTransaction {
+    let script_sig = Script::builder()
+        .push_opcode(Opcode::OP_0)
+        .push_slice(&message_hash(message))
+        .into_script();
+    BitcoinTransaction::new(
+        0,
+        vec![TransactionInput::new(OutPoint::null(), script_sig, 0)],
+        vec![TransactionOutput::new(0, script_pubkey.clone())],
+        0,
+    )
+}
+
+/// The unsigned virtual transaction spending `to_spend` to an empty
+/// `OP_RETURN`.
+pub fn to_sign(to_spend: &BitcoinTransaction) -> BitcoinTransaction {
+    BitcoinTransaction::new(
+        0,
+        vec![TransactionInput::new(
+            OutPoint::new(to_spend.txid().0, 0),
+            Script::new(vec![]),
+            0,
+        )],
+        vec![TransactionOutput::new(0, op_return())],
+        0,
+    )
+}
+
+/// A simple proof: the Base64 of the signed `to_sign`'s witness. Only
+/// native segwit addresses have an empty scriptSig, so others fail.
+#[cfg(feature = "secp256k1")]
+pub fn sign_simple(address: &Address, message: &str, key: &PrivateKey) -> Result<String> {
+    let to_sign = sign(address, message, key)?;
+    let input = &to_sign.inputs[0];
+    if !input.script_sig.bytes.is_empty() {
+        return Err(BitcoinError::CannotSign(
+            "simple proofs need a native segwit address",
+        ));
+    }
+    Ok(base64::encode(&input.witness.to_bytes()))
+}
+
+/// A full proof: the Base64 of the whole signed `to_sign`.
+#[cfg(feature = "secp256k1")]
+pub fn sign_full(address: &Address, message: &str, key: &PrivateKey) -> Result<String> {
+    Ok(base64::encode(&sign(address, message, key)?.to_bytes()))
+}
+
+/// Whether the simple proof `proof` signs `message` for `address`.
+/// Undecodable proofs are errors.
+#[cfg(feature = "secp256k1")]
+pub fn verify_simple(address: &Address, message: &str, proof: &str) -> Result<bool> {
+    let witness = Witness::decode_exact(&decode_proof(proof)?)?;
+    let to_spend = to_spend(&address.script_pubkey(), message);
+    let mut to_sign = to_sign(&to_spend);
+    to_sign.inputs[0].witness = witness;
+    verify(&to_spend, &to_sign)
+}
+
+/// Whether the full proof `proof` signs `message` for `address`. Proofs
+/// that are not a `to_sign` for this message are errors, as are proofs of
+/// funds with extra inputs, which would need their prevouts.
+#[cfg(feature = "secp256k1")]
+pub fn verify_full(address: &Address, message: &str, proof: &str) -> Result<bool> {
+    let to_sign = BitcoinTransaction::decode_exact(&decode_proof(proof)?)?;
+    let to_spend = to_spend(&address.script_pubkey(), message);
+    let [input] = &to_sign.inputs[..] else {
+        return Err(BitcoinError::InvalidSignature(
+            "proofs of funds are not supported",
+        ));
+    };
+    if input.previous_output != OutPoint::new(to_spend.txid().0, 0)
+        || to_sign.outputs != [TransactionOutput::new(0, op_return())]
+    {
+        return Err(BitcoinError::InvalidSignature(
+            "not a to_sign transaction for this message",
+        ));
+    }
+    verify(&to_spend, &to_sign)
+}
+
+fn op_return() -> Script {
+    Script::builder()
+        .push_opcode(Opcode::OP_RETURN)
+        .into_script()
+}
+
+#[cfg(feature = "secp256k1")]
+fn decode_proof(proof: &str) -> Result<alloc::vec::Vec<u8>> {
+    base64::decode(proof).ok_or(BitcoinError::InvalidSignature("BIP322 proofs are Base64"))
+}
+
+#[cfg(feature = "secp256k1")]
+fn sign(address: &Address, message: &str, key: &PrivateKey) -> Result<BitcoinTransaction> {
+    let to_spend = to_spend(&address.script_pubkey(), message);
+    let mut to_sign = to_sign(&to_spend);
+    let sighash_type = match address.script_pubkey().classify() {
+        ScriptType::P2tr => SighashType::Default,
+        _ => SighashType::All,
+    };
+    to_sign.sign_input(0, &to_spend.outputs[0], key, sighash_type)?;
+    Ok(to_sign)
+}
+
+#[cfg(feature = "secp256k1")]
+fn verify(to_spend: &BitcoinTransaction, to_sign: &BitcoinTransaction) -> Result<bool> {
+    let prevout = &to_spend.outputs[0];
+    let input = &to_sign.inputs[0];
+    let cache = SighashCache::new(to_sign);
+    if prevout.script_pubkey.classify() != ScriptType::P2tr {
+        let checker = TransactionSignatureChecker::new(&cache, 0, prevout.value);
+        return Ok(verify_script(
+            &input.script_sig,
+            &prevout.script_pubkey,
+            &input.witness,
+            VerifyFlags::STANDARD,
+            checker,
+        )
+        .is_ok());
+    }
+
+    // The interpreter does not run taproot, so only key-path spends verify.
+    let [sig] = &input.witness.items[..] else {
+        return Err(BitcoinError::InvalidSignature(
+            "only taproot key-path proofs are supported",
+        ));
+    };
+    let Ok(sig) = SchnorrSignature::from_slice(sig) else {
+        return Ok(false);
+    };
+    let output_key = XOnlyPublicKey::from_slice(&prevout.script_pubkey.bytes[2..])?;
+    let sighash = SighashType::from_u8(sig.sighash_type, true).and_then(|sighash_type| {
+        cache.taproot_signature_hash(
+            0,
+            &Prevouts::All(&to_spend.outputs),
+            None,
+            None,
+            sighash_type,
+        )
+    });
+    Ok(input.script_sig.bytes.is_empty()
+        && sighash.is_ok_and(|sighash| schnorr::verify(&output_key, &sighash, &sig.signature)))
+}
@@ -0,0 +1,166 @@
+//! BIP39 mnemonic phrases with the English wordlist.
+
+mod english;
+
+use crate::hashes::{pbkdf2_hmac_sha512, sha256};
+use crate::{BitcoinError, Result};
+use alloc::string::{String, ToString};
+use alloc::vec;
+use alloc::vec::Vec;
+use core::fmt;
+use core::str::FromStr;
+
+use english::WORDS;
+
+const PBKDF2_ROUNDS: u32 = 2048;
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum Bip39Error {
+    /// Entropy must be 16 to 32 bytes, in steps of 4.
+    BadEntropyLength(usize),
+    /// Mnemonics have 12, 15, 18, 21 or 24 words.
+    BadWordCount(usize),
+    UnknownWord {
+        word: String,
+        position: usize,
+    },
+    InvalidChecksum,
+}
+
+impl fmt::Display for Bip39Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Bip39Error::BadEntropyLength(len) => write!(f, "{} bytes of entropy", len),
+            Bip39Error::BadWordCount(count) => write!(f, "{} words", count),
+            Bip39Error::UnknownWord { word, position } => {
+                write!(f, "unknown word '{}' at position {}", word, position)
+            }
+            Bip39Error::InvalidChecksum => f.write_str("invalid checksum"),
+        }
+    }
+}
+
+impl From<Bip39Error> for BitcoinError {
+    fn from(e: Bip39Error) -> Self {
+        BitcoinError::Bip39(e)
+    }
+}
+
+/// A BIP39 mnemonic, stored as the entropy it encodes.
+#[derive(Clone, PartialEq, Eq, Hash)]
+pub struct Mnemonic {
+    entropy: Vec<u8>,
+}
+
+impl Mnemonic {
+    pub fn from_entropy(entropy: &[u8]) -> Result<Mnemonic> {
+        if !(16..=32).contains(&entropy.len()) || !entropy.len().is_multiple_of(4) {
+            return Err(Bip39Error::BadEntropyLength(entropy.len()).into());
+        }
+        Ok(Mnemonic {
+            entropy: entropy.to_vec(),
+        })
+    }
+
+    /// A new mnemonic of `word_count` words, with `fill` supplying the
+    /// entropy. It must come from a cryptographically secure RNG.
+    pub fn generate(word_count: usize, fill: impl FnOnce(&mut [u8])) -> Result<Mnemonic> {
+        if !matches!(word_count, 12 | 15 | 18 | 21 | 24) {
+            return Err(Bip39Error::BadWordCount(word_count).into());
+        }
+        let mut entropy = vec![0u8; word_count * 4 / 3];
+        fill(&mut entropy);
+        Mnemonic::from_entropy(&entropy)
+    }
+
+    pub fn entropy(&self) -> &[u8] {
+        &self.entropy
+    }
+
+    pub fn word_count(&self) -> usize {
+        self.entropy.len() * 3 / 4
+    }
+
+    pub fn words(&self) -> impl Iterator<Item = &'static str> + '_ {
+        self.indices().map(|index| WORDS[index])
+    }
+
+    /// The 64-byte seed for `passphrase`, ready for
+    /// [`Xpriv::new_master`](crate::bip32::Xpriv::new_master). Passphrases
+    /// are used as given, so non-ASCII ones must already be NFKD-normalized.
+    pub fn to_seed(&self, passphrase: &str) -> [u8; 64] {
+        let mut salt = String::from("mnemonic");
+        salt.push_str(passphrase);
+        pbkdf2_hmac_sha512(self.to_string().as_bytes(), salt.as_bytes(), PBKDF2_ROUNDS)
+    }
+
+    // The 11-bit word indices of the entropy followed by its checksum, the
+    // first `entropy bits / 32` bits of its SHA256.
+    fn indices(&self) -> impl Iterator<Item = usize> + '_ {
+        let checksum = sha256(&self.entropy)[0];
+        let bit = move |i: usize| {
+            let byte = self.entropy.get(i / 8).copied().unwrap_or(checksum);
+            usize::from(byte >> (7 - i % 8) & 1)
+        };
+        (0..self.word_count())
+            .map(move |word| (0..11).fold(0, |acc, i| acc << 1 | bit(word * 11 + i)))
+    }
+}
+
+impl FromStr for Mnemonic {
+    type Err = BitcoinError;
+
+    /// Parses lowercase words separated by whitespace.
+    fn from_str(s: &str) -> Result<Self> {
+        let indices = s
+            .split_whitespace()
+            .enumerate()
+            .map(|(position, word)| {
+                WORDS
+                    .binary_search(&word)
+                    .map_err(|_| Bip39Error::UnknownWord {
+                        word: word.into(),
+                        position,
+                    })
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        if !matches!(indices.len(), 12 | 15 | 18 | 21 | 24) {
+            return Err(Bip39Error::BadWordCount(indices.len()).into());
+        }
+
+        let mut entropy = vec![0u8; indices.len() * 4 / 3];
+        let bits = indices
+            .iter()
+            .flat_map(|index| (0..11).rev().map(move |i| (index >> i) as u8 & 1));
+        for (i, bit) in bits.take(entropy.len() * 8).enumerate() {
+            entropy[i / 8] |= bit << (7 - i % 8);
+        }
+        let mnemonic = Mnemonic { entropy };
+        if !mnemonic.indices().eq(indices) {
+            return Err(Bip39Error::InvalidChecksum.into());
+        }
+        Ok(mnemonic)
+    }
+}
+
+impl fmt::Display for Mnemonic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (i, word) in self.words().enumerate() {
+            if i > 0 {
+                f.write_str(" ")?;
+            }
+            f.write_str(word)?;
+        }
+        Ok(())
+    }
+}
+
+// Like `PrivateKey`, keeps the phrase out of logs.
+impl fmt::Debug for Mnemonic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Mnemonic")
+            .field("word_count", &self.word_count())
+            .field("words", &"[redacted]")
+            .finish()
+    }
+}
@@ -0,0 +1,69 @@
+//! BIP85 deterministic entropy: child secrets derived from one root key.
+//! Deriving them needs the `secp256k1` feature, as BIP32 private
+//! derivation does.
+
+use crate::bip32::{ChildNumber, DerivationPath, Xpriv};
+#[cfg(feature = "bip39")]
+use crate::bip39::{Bip39Error, Mnemonic};
+use crate::hashes::hmac_sha512;
+use crate::{BitcoinError, PrivateKey, Result};
+use alloc::vec::Vec;
+
+const PURPOSE: u32 = 83696968;
+#[cfg(feature = "bip39")]
+const APP_BIP39: u32 = 39;
+const APP_WIF: u32 = 2;
+const APP_HEX: u32 = 128169;
+#[cfg(feature = "bip39")]
+const LANGUAGE_ENGLISH: u32 = 0;
+
+/// The 64 bytes of entropy at `m/83696968'/{path}`. Every step of `path`,
+/// which starts with the application number, must be hardened.
+pub fn derive_entropy(root: &Xpriv, path: &DerivationPath) -> Result<[u8; 64]> {
+    if !path.iter().all(ChildNumber::is_hardened) {
+        return Err(BitcoinError::InvalidBip85Request(
+            "every path step must be hardened",
+        ));
+    }
+    let path = hardened_path(&[PURPOSE])?.extend(path);
+    let key = root.derive_priv(&path)?.private_key().secret_bytes();
+    Ok(hmac_sha512(b"bip-entropy-from-k", &key))
+}
+
+/// A 12, 18 or 24 word English mnemonic (application 39').
+#[cfg(feature = "bip39")]
+pub fn mnemonic(root: &Xpriv, word_count: u32, index: u32) -> Result<Mnemonic> {
+    if !matches!(word_count, 12 | 18 | 24) {
+        return Err(Bip39Error::BadWordCount(word_count as usize).into());
+    }
+    let entropy = derive_entropy(
+        root,
+        &hardened_path(&[APP_BIP39, LANGUAGE_ENGLISH, word_count, index])?,
+    )?;
+    Mnemonic::from_entropy(&entropy[..word_count as usize * 4 / 3])
+}
+
+/// A compressed private key for the root key's network (application 2').
+pub fn wif(root: &Xpriv, index: u32) -> Result<PrivateKey> {
+    let entropy = derive_entropy(root, &hardened_path(&[APP_WIF, index])?)?;
+    // Fails with odds of about 2^-128, like BIP32 derivation.
+    PrivateKey::new(entropy[..32].try_into().unwrap(), root.network, true)
+}
+
+/// `num_bytes` (16 to 64) raw bytes (application 128169').
+pub fn hex(root: &Xpriv, num_bytes: usize, index: u32) -> Result<Vec<u8>> {
+    if !(16..=64).contains(&num_bytes) {
+        return Err(BitcoinError::InvalidBip85Request(
+            "hex entropy must be 16 to 64 bytes",
+        ));
+    }
+    let entropy = derive_entropy(root, &hardened_path(&[APP_HEX, num_bytes as u32, index])?)?;
+    Ok(entropy[..num_bytes].to_vec())
+}
+
+fn hardened_path(indices: &[u32]) -> Result<DerivationPath> {
+    indices
+        .iter()
+        .map(|&index| ChildNumber::from_hardened_idx(index))
+        .collect()
+}
@@ -0,0 +1,144 @@
+use crate::{
+    BitcoinTransaction, BlockHash, CompactSize, ConsensusDecode, ConsensusEncode, DecodeOptions,
+    MAX_BLOCK_WEIGHT, MIN_TRANSACTION_WEIGHT, MerkleRoot, Result, Txid, WITNESS_SCALE_FACTOR,
+    decode_list, hashes, io, merkle, read_array, read_u32,
+};
+use alloc::vec::Vec;
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, PartialEq, Eq, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
+pub struct BlockHeader {
+    pub version: i32,
+    pub prev_blockhash: BlockHash,
+    pub merkle_root: MerkleRoot,
+    pub time: u32,
+    pub bits: u32,
+    pub nonce: u32,
+}
+
+impl BlockHeader {
+    pub const SIZE: usize = 80;
+
+    pub fn block_hash(&self) -> BlockHash {
+        let mut engine = hashes::HashEngine::new();
+        self.consensus_encode(&mut engine)
+            .expect("hashing cannot fail");
+        BlockHash(engine.finalize_double())
+    }
+}
+
+/// Number of trailing headers whose timestamps form the median time past.
+pub const MEDIAN_TIME_SPAN: usize = 11;
+
+/// Median timestamp of the last [`MEDIAN_TIME_SPAN`] headers in `headers`,
+/// which must be in chain order. Returns 0 for an empty slice.
+pub fn median_time_past(headers: &[BlockHeader]) -> u32 {
+    let start = headers.len().saturating_sub(MEDIAN_TIME_SPAN);
+    let mut times: Vec<u32> = headers[start..].iter().map(|h| h.time).collect();
+    if times.is_empty() {
+        return 0;
+    }
+    times.sort_unstable();
+    times[times.len() / 2]
+}
+
+impl ConsensusEncode for BlockHeader {
+    fn consensus_encode<W: io::Write + ?Sized>(&self, w: &mut W) -> io::Result<usize> {
+        w.write_all(&self.version.to_le_bytes())?;
+        self.prev_blockhash.consensus_encode(w)?;
+        self.merkle_root.consensus_encode(w)?;
+        self.time.consensus_encode(w)?;
+        self.bits.consensus_encode(w)?;
+        self.nonce.consensus_encode(w)?;
+        Ok(Self::SIZE)
+    }
+
+    fn serialized_size(&self) -> usize {
+        Self::SIZE
+    }
+}
+
+impl ConsensusDecode for BlockHeader {
+    fn consensus_decode<R: io::Read + ?Sized>(r: &mut R) -> Result<Self> {
+        Ok(BlockHeader {
+            version: i32::from_le_bytes(read_array(r, "BlockHeader")?),
+            prev_blockhash: BlockHash(read_array(r, "BlockHeader")?),
+            merkle_root: MerkleRoot(read_array(r, "BlockHeader")?),
+            time: read_u32(r, "BlockHeader")?,
+            bits: read_u32(r, "BlockHeader")?,
+            nonce: read_u32(r, "BlockHeader")?,
+        })
+    }
+}
+
+#[derive(Debug, PartialEq, Eq, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
+pub struct Block {
+    pub header: BlockHeader,
+    pub txdata: Vec<BitcoinTransaction>,
+}
+
+impl Block {
+    pub fn block_hash(&self) -> BlockHash {
+        self.header.block_hash()
+    }
+
+    pub fn compute_merkle_root(&self) -> MerkleRoot {
+        let txids: Vec<Txid> = self.txdata.iter().map(BitcoinTransaction::txid).collect();
+        merkle::merkle_root(&txids)
+    }
+
+    pub fn check_merkle_root(&self) -> bool {
+        self.compute_merkle_root() == self.header.merkle_root
+    }
+
+    pub fn base_size(&self) -> usize {
+        BlockHeader::SIZE
+            + CompactSize::new(self.txdata.len() as u64).serialized_size()
+            + self
+                .txdata
+                .iter()
+                .map(BitcoinTransaction::base_size)
+                .sum::<usize>()
+    }
+
+    pub fn total_size(&self) -> usize {
+        self.serialized_size()
+    }
+
+    pub fn weight(&self) -> usize {
+        self.base_size() * (WITNESS_SCALE_FACTOR - 1) + self.total_size()
+    }
+}
+
+impl ConsensusEncode for Block {
+    fn consensus_encode<W: io::Write + ?Sized>(&self, w: &mut W) -> io::Result<usize> {
+        Ok(self.header.consensus_encode(w)? + self.txdata.consensus_encode(w)?)
+    }
+
+    fn serialized_size(&self) -> usize {
+        BlockHeader::SIZE + self.txdata.serialized_size()
+    }
+}
+
+impl ConsensusDecode for Block {
+    fn consensus_decode<R: io::Read + ?Sized>(r: &mut R) -> Result<Self> {
+        Self::consensus_decode_with(r, &DecodeOptions::default())
+    }
+
+    fn consensus_decode_with<R: io::Read + ?Sized>(
+        r: &mut R,
+        options: &DecodeOptions,
+    ) -> Result<Self> {
+        Ok(Block {
+            header: BlockHeader::consensus_decode_with(r, options)?,
+            txdata: decode_list(
+                r,
+                options,
+                MAX_BLOCK_WEIGHT / MIN_TRANSACTION_WEIGHT,
+                "Block",
+            )?,
+        })
+    }
+}
@@ -0,0 +1,182 @@
+use crate::hashes::sha256d;
+use crate::{
+    BitcoinError, Block, BlockHash, BlockHeader, BlockUndo, ConsensusDecode, Network, Result, io,
+    read_array, read_bytes, read_u32,
+};
+use alloc::vec::Vec;
+
+/// Largest record length Core will write, matching its `MAX_BLOCK_SERIALIZED_SIZE`.
+pub const MAX_BLOCK_SERIALIZED_SIZE: u32 = 4_000_000;
+
+/// Iterates the blocks stored in a Core `blk*.dat` file.
+///
+/// Each record is the network magic, a little-endian length and the
+/// serialized block. Anything between records that isn't a valid preamble,
+/// such as the zero padding Core preallocates, is skipped.
+#[derive(Debug)]
+pub struct BlockFileReader<R> {
+    reader: XorReader<R>,
+    magic: [u8; 4],
+    done: bool,
+}
+
+impl<R: io::Read> BlockFileReader<R> {
+    pub fn new(reader: R, network: Network) -> Self {
+        Self {
+            reader: XorReader {
+                inner: reader,
+                key: [0; 8],
+                pos: 0,
+            },
+            magic: network.magic(),
+            done: false,
+        }
+    }
+
+    /// Deobfuscates the file with the key from the datadir's `blocks/xor.dat`.
+    pub fn with_xor_key(mut self, key: [u8; 8]) -> Self {
+        self.reader.key = key;
+        self
+    }
+
+    /// Offset into the file of the next byte to be read.
+    pub fn position(&self) -> u64 {
+        self.reader.pos
+    }
+
+    /// Returns the next record's bytes without decoding them, or `None` once
+    /// the file holds no further preamble.
+    pub fn next_raw(&mut self) -> Result<Option<Vec<u8>>> {
+        self.next_record(BlockHeader::SIZE as u32, "BlockFileReader")
+    }
+
+    // Lengths below `min_len` can't start a record, so are skipped like any
+    // other junk.
+    fn next_record(&mut self, min_len: u32, context: &'static str) -> Result<Option<Vec<u8>>> {
+        loop {
+            if !self.seek_magic()? {
+                return Ok(None);
+            }
+            let len = match read_u32(&mut self.reader, context) {
+                Ok(len) => len,
+                Err(BitcoinError::InsufficientBytes { .. }) => return Ok(None),
+                Err(e) => return Err(e),
+            };
+            if !(min_len..=MAX_BLOCK_SERIALIZED_SIZE).contains(&len) {
+                continue;
+            }
+            return read_bytes(&mut self.reader, len as u64, context).map(Some);
+        }
+    }
+
+    fn seek_magic(&mut self) -> Result<bool> {
+        let mut window = [0u8; 4];
+        let mut seen = 0;
+        let mut byte = [0u8; 1];
+        loop {
+            match io::Read::read(&mut self.reader, &mut byte) {
+                Ok(0) => return Ok(false),
+                Ok(_) => {}
+                Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+                Err(e) => return Err(e.into()),
+            }
+            window.copy_within(1.., 0);
+            window[3] = byte[0];
+            seen += 1;
+            if seen >= 4 && window == self.magic {
+                return Ok(true);
+            }
+        }
+    }
+}
+
+impl<R: io::Read> Iterator for BlockFileReader<R> {
+    type Item = Result<Block>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.done {
+            return None;
+        }
+        match self.next_raw() {
+            Ok(Some(bytes)) => Some(Block::decode_exact(&bytes)),
+            Ok(None) => {
+                self.done = true;
+                None
+            }
+            Err(e) => {
+                self.done = true;
+                Some(Err(e))
+            }
+        }
+    }
+}
+
+/// Reads the block undo records stored in a Core `rev*.dat` file.
+///
+/// Records share the framing of `blk*.dat`, but the length covers only the
+/// undo data, which is followed by a 32-byte checksum. Undo data for a
+/// block with only a coinbase is a single zero byte.
+#[derive(Debug)]
+pub struct UndoFileReader<R> {
+    records: BlockFileReader<R>,
+}
+
+impl<R: io::Read> UndoFileReader<R> {
+    pub fn new(reader: R, network: Network) -> Self {
+        Self {
+            records: BlockFileReader::new(reader, network),
+        }
+    }
+
+    /// Deobfuscates the file with the key from the datadir's `blocks/xor.dat`.
+    pub fn with_xor_key(mut self, key: [u8; 8]) -> Self {
+        self.records = self.records.with_xor_key(key);
+        self
+    }
+
+    /// Offset into the file of the next byte to be read.
+    pub fn position(&self) -> u64 {
+        self.records.position()
+    }
+
+    /// Returns the next record's undo data and checksum without checking
+    /// or decoding them, or `None` once the file holds no further preamble.
+    pub fn next_raw(&mut self) -> Result<Option<(Vec<u8>, [u8; 32])>> {
+        let Some(data) = self.records.next_record(1, "UndoFileReader")? else {
+            return Ok(None);
+        };
+        let checksum = read_array(&mut self.records.reader, "UndoFileReader")?;
+        Ok(Some((data, checksum)))
+    }
+
+    /// Decodes the next record, which must undo a block whose parent is
+    /// `prev_block_hash`: as in Core's `UndoReadFromDisk`, the checksum is
+    /// the double SHA256 of that hash followed by the undo data.
+    pub fn next_undo(&mut self, prev_block_hash: &BlockHash) -> Result<Option<BlockUndo>> {
+        let Some((data, checksum)) = self.next_raw()? else {
+            return Ok(None);
+        };
+        if sha256d(&[&prev_block_hash.0[..], &data].concat()) != checksum {
+            return Err(BitcoinError::UndoChecksumMismatch);
+        }
+        BlockUndo::decode_exact(&data).map(Some)
+    }
+}
+
+#[derive(Debug)]
+struct XorReader<R> {
+    inner: R,
+    key: [u8; 8],
+    pos: u64,
+}
+
+impl<R: io::Read> io::Read for XorReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let n = self.inner.read(buf)?;
+        for byte in &mut buf[..n] {
+            *byte ^= self.key[(self.pos % 8) as usize];
+            self.pos += 1;
+        }
+        Ok(n)
+    }
+}
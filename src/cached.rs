@@ -0,0 +1,53 @@
+use crate::{BitcoinTransaction, Txid, Wtxid};
+use core::ops::Deref;
+use std::sync::OnceLock;
+
+#[derive(Debug, Clone)]
+pub struct CachedTransaction {
+    tx: BitcoinTransaction,
+    txid: OnceLock<Txid>,
+    wtxid: OnceLock<Wtxid>,
+}
+
+impl CachedTransaction {
+    pub fn new(tx: BitcoinTransaction) -> Self {
+        Self {
+            tx,
+            txid: OnceLock::new(),
+            wtxid: OnceLock::new(),
+        }
+    }
+
+    pub fn txid(&self) -> &Txid {
+        self.txid.get_or_init(|| self.tx.txid())
+    }
+
+    pub fn wtxid(&self) -> &Wtxid {
+        self.wtxid.get_or_init(|| self.tx.wtxid())
+    }
+
+    pub fn into_inner(self) -> BitcoinTransaction {
+        self.tx
+    }
+}
+
+impl From<BitcoinTransaction> for CachedTransaction {
+    fn from(tx: BitcoinTransaction) -> Self {
+        Self::new(tx)
+    }
+}
+
+impl Deref for CachedTransaction {
+    type Target = BitcoinTransaction;
+    fn deref(&self) -> &Self::Target {
+        &self.tx
+    }
+}
+
+impl PartialEq for CachedTransaction {
+    fn eq(&self, other: &Self) -> bool {
+        self.tx == other.tx
+    }
+}
+
+impl Eq for CachedTransaction {}
@@ -0,0 +1,79 @@
+use crate::stream::{DEFAULT_MAX_FRAME_SIZE, frame_len};
+use crate::{BitcoinError, BitcoinTransaction, ConsensusDecode, ConsensusEncode, Result};
+use bytes::{BufMut, BytesMut};
+use tokio_util::codec;
+
+#[derive(Debug, Clone)]
+pub struct TxCodec {
+    max_frame_size: usize,
+}
+
+impl TxCodec {
+    pub fn new() -> Self {
+        Self::with_max_frame_size(DEFAULT_MAX_FRAME_SIZE)
+    }
+
+    pub fn with_max_frame_size(max_frame_size: usize) -> Self {
+        Self { max_frame_size }
+    }
+
+    pub fn max_frame_size(&self) -> usize {
+        self.max_frame_size
+    }
+
+    fn check_size(&self, size: usize) -> Result<()> {
+        if size > self.max_frame_size {
+            return Err(BitcoinError::OversizedFrame {
+                size,
+                max: self.max_frame_size,
+            });
+        }
+        Ok(())
+    }
+}
+
+impl Default for TxCodec {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl codec::Decoder for TxCodec {
+    type Item = BitcoinTransaction;
+    type Error = BitcoinError;
+
+    fn decode(&mut self, src: &mut BytesMut) -> Result<Option<Self::Item>, Self::Error> {
+        match frame_len(src) {
+            Err(needed) => {
+                self.check_size(src.len().saturating_add(needed))?;
+                src.reserve(needed);
+                Ok(None)
+            }
+            Ok(len) => {
+                self.check_size(len)?;
+                let frame = src.split_to(len);
+                BitcoinTransaction::decode_exact(&frame).map(Some)
+            }
+        }
+    }
+}
+
+impl codec::Encoder<&BitcoinTransaction> for TxCodec {
+    type Error = BitcoinError;
+
+    fn encode(&mut self, tx: &BitcoinTransaction, dst: &mut BytesMut) -> Result<(), Self::Error> {
+        let size = tx.serialized_size();
+        self.check_size(size)?;
+        dst.reserve(size);
+        tx.consensus_encode(&mut dst.writer())?;
+        Ok(())
+    }
+}
+
+impl codec::Encoder<BitcoinTransaction> for TxCodec {
+    type Error = BitcoinError;
+
+    fn encode(&mut self, tx: BitcoinTransaction, dst: &mut BytesMut) -> Result<(), Self::Error> {
+        self.encode(&tx, dst)
+    }
+}
@@ -0,0 +1,180 @@
+//! Bitcoin Core's compact encodings for coins: the base-128 `VARINT`, the
+//! amount compression and the special-cased script compression.
+
+use crate::script::Opcode::{self, *};
+use crate::{BitcoinError, Result, Script, TransactionOutput, field, io, read_array, read_bytes};
+use alloc::vec;
+use alloc::vec::Vec;
+
+const MAX_SCRIPT_SIZE: u64 = 10_000;
+const SPECIAL_SCRIPTS: u64 = 6;
+
+/// Shrinks an amount by factoring out trailing decimal zeros.
+pub fn compress_amount(mut n: u64) -> u64 {
+    if n == 0 {
+        return 0;
+    }
+    let mut e = 0;
+    while n.is_multiple_of(10) && e < 9 {
+        n /= 10;
+        e += 1;
+    }
+    if e < 9 {
+        let d = n % 10;
+        n /= 10;
+        1 + (n * 9 + d - 1) * 10 + e
+    } else {
+        1 + (n - 1) * 10 + 9
+    }
+}
+
+pub fn decompress_amount(x: u64) -> u64 {
+    if x == 0 {
+        return 0;
+    }
+    let mut x = x - 1;
+    let mut e = x % 10;
+    x /= 10;
+    let mut n = if e < 9 {
+        let d = x % 9 + 1;
+        x /= 9;
+        x.wrapping_mul(10).wrapping_add(d)
+    } else {
+        x.wrapping_add(1)
+    };
+    while e > 0 {
+        n = n.wrapping_mul(10);
+        e -= 1;
+    }
+    n
+}
+
+pub(crate) fn write_varint<W: io::Write + ?Sized>(w: &mut W, mut n: u64) -> io::Result<usize> {
+    let mut tmp = [0u8; 10];
+    let mut len = 0;
+    loop {
+        tmp[len] = (n & 0x7f) as u8 | if len > 0 { 0x80 } else { 0x00 };
+        if n <= 0x7f {
+            break;
+        }
+        n = (n >> 7) - 1;
+        len += 1;
+    }
+    tmp[..=len].reverse();
+    w.write_all(&tmp[..=len])?;
+    Ok(len + 1)
+}
+
+pub(crate) fn varint_size(n: u64) -> usize {
+    let mut sink = Vec::new();
+    write_varint(&mut sink, n).expect("writing to a Vec cannot fail")
+}
+
+pub(crate) fn read_varint<R: io::Read + ?Sized>(r: &mut R, context: &'static str) -> Result<u64> {
+    let mut n: u64 = 0;
+    loop {
+        let [byte] = read_array(r, context)?;
+        if n > u64::MAX >> 7 {
+            return Err(BitcoinError::InvalidFormat);
+        }
+        n = n << 7 | (byte & 0x7f) as u64;
+        if byte & 0x80 == 0 {
+            return Ok(n);
+        }
+        n = n.checked_add(1).ok_or(BitcoinError::InvalidFormat)?;
+    }
+}
+
+pub(crate) fn encode_txout<W: io::Write + ?Sized>(
+    w: &mut W,
+    output: &TransactionOutput,
+) -> io::Result<usize> {
+    let len = write_varint(w, compress_amount(output.value))?;
+    Ok(len + encode_script(w, &output.script_pubkey.bytes)?)
+}
+
+pub(crate) fn txout_size(output: &TransactionOutput) -> usize {
+    let mut sink = Vec::new();
+    encode_txout(&mut sink, output).expect("writing to a Vec cannot fail")
+}
+
+pub(crate) fn decode_txout<R: io::Read + ?Sized>(r: &mut R) -> Result<TransactionOutput> {
+    let value = decompress_amount(read_varint(r, "TxOutCompression")?);
+    Ok(TransactionOutput::new(value, decode_script(r)?))
+}
+
+fn encode_script<W: io::Write + ?Sized>(w: &mut W, script: &[u8]) -> io::Result<usize> {
+    if let Some(special) = compress_special(script) {
+        w.write_all(&special)?;
+        return Ok(special.len());
+    }
+    let len = write_varint(w, script.len() as u64 + SPECIAL_SCRIPTS)?;
+    w.write_all(script)?;
+    Ok(len + script.len())
+}
+
+fn compress_special(script: &[u8]) -> Option<Vec<u8>> {
+    let special = |kind: u8, payload: &[u8]| [&[kind][..], payload].concat();
+    match script {
+        [0x76, 0xa9, 0x14, hash @ .., 0x88, 0xac] if hash.len() == 20 => Some(special(0x00, hash)),
+        [0xa9, 0x14, hash @ .., 0x87] if hash.len() == 20 => Some(special(0x01, hash)),
+        [0x21, prefix @ (0x02 | 0x03), x @ .., 0xac] if x.len() == 32 => Some(special(*prefix, x)),
+        [0x41, 0x04, point @ .., 0xac] if point.len() == 64 => {
+            let (x, y) = point.split_at(32);
+            if !field::is_on_curve(x.try_into().unwrap(), y.try_into().unwrap()) {
+                return None;
+            }
+            Some(special(0x04 | (y[31] & 1), x))
+        }
+        _ => None,
+    }
+}
+
+fn decode_script<R: io::Read + ?Sized>(r: &mut R) -> Result<Script> {
+    let context = "ScriptCompression";
+    let size = read_varint(r, context)?;
+    if size >= SPECIAL_SCRIPTS {
+        let len = size - SPECIAL_SCRIPTS;
+        let bytes = read_bytes(r, len, context)?;
+        // Core keeps oversized scripts out of memory by storing them as
+        // a bare unspendable OP_RETURN.
+        if len > MAX_SCRIPT_SIZE {
+            return Ok(Script::new(vec![OP_RETURN.to_u8()]));
+        }
+        return Ok(Script::new(bytes));
+    }
+
+    let bytes = match size {
+        0x00 => {
+            let hash: [u8; 20] = read_array(r, context)?;
+            script(
+                &[OP_DUP, OP_HASH160, OP_PUSHBYTES_20],
+                &hash,
+                &[OP_EQUALVERIFY, OP_CHECKSIG],
+            )
+        }
+        0x01 => {
+            let hash: [u8; 20] = read_array(r, context)?;
+            script(&[OP_HASH160, OP_PUSHBYTES_20], &hash, &[OP_EQUAL])
+        }
+        0x02 | 0x03 => {
+            let x: [u8; 32] = read_array(r, context)?;
+            let key = [&[size as u8][..], &x].concat();
+            script(&[OP_PUSHBYTES_33], &key, &[OP_CHECKSIG])
+        }
+        _ => {
+            let x: [u8; 32] = read_array(r, context)?;
+            let y = field::decompress_y(&x, size == 0x05).ok_or(BitcoinError::InvalidFormat)?;
+            let key = [&[0x04][..], &x, &y].concat();
+            script(&[OP_PUSHBYTES_65], &key, &[OP_CHECKSIG])
+        }
+    };
+    Ok(Script::new(bytes))
+}
+
+fn script(prefix: &[Opcode], data: &[u8], suffix: &[Opcode]) -> Vec<u8> {
+    let mut bytes: Vec<u8> = prefix.iter().map(|op| op.to_u8()).collect();
+    bytes.extend_from_slice(data);
+    bytes.extend(suffix.iter().map(|op| op.to_u8()));
+    bytes
+}
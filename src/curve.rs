@@ -0,0 +1,317 @@
+//! secp256k1 point and scalar arithmetic: taproot tweaks, BIP32 derivation
+//! and parsing public keys. None of it is constant time, so it never
+//! touches private keys: computing public keys, private derivation and
+//! signing are left to libsecp256k1 behind the `secp256k1` feature.
+
+use crate::field::{self, Fe};
+
+// The group order.
+const N: Fe = [
+    0xBFD2_5E8C_D036_4141,
+    0xBAAE_DCE6_AF48_A03B,
+    0xFFFF_FFFF_FFFF_FFFE,
+    0xFFFF_FFFF_FFFF_FFFF,
+];
+
+const G_X: Fe = [
+    0x59F2_815B_16F8_1798,
+    0x029B_FCDB_2DCE_28D9,
+    0x55A0_6295_CE87_0B07,
+    0x79BE_667E_F9DC_BBAC,
+];
+
+const G_Y: Fe = [
+    0x9C47_D08F_FB10_D4B8,
+    0xFD17_B448_A685_5419,
+    0x5DA4_FBFC_0E11_08A8,
+    0x483A_DA77_26A3_C465,
+];
+
+const ZERO: Fe = [0; 4];
+const ONE: Fe = [1, 0, 0, 0];
+
+/// Half the group order; larger ECDSA S values have a low-S twin.
+pub(crate) const HALF_ORDER: [u8; 32] = [
+    0x7f, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff,
+    0x5d, 0x57, 0x6e, 0x73, 0x57, 0xa4, 0x50, 0x1d, 0xdf, 0xe9, 0x2f, 0x46, 0x68, 0x1b, 0x20, 0xa0,
+];
+
+/// Whether `bytes` is a valid non-zero scalar, i.e. below the group order.
+pub(crate) fn is_valid_scalar(bytes: &[u8; 32]) -> bool {
+    let n = scalar_from_be_bytes(bytes);
+    n != ZERO && !field::ge(&n, &N)
+}
+
+/// `(a + b) mod n` for valid scalars, or `None` if the sum is zero.
+#[cfg(feature = "musig2")]
+pub(crate) fn scalar_add(a: &[u8; 32], b: &[u8; 32]) -> Option<[u8; 32]> {
+    let sum = add_mod_n(&scalar_from_be_bytes(a), &scalar_from_be_bytes(b));
+    (sum != ZERO).then(|| field::to_be_bytes(&sum))
+}
+
+/// `(a * b) mod n` for scalars below the group order.
+#[cfg(feature = "musig2")]
+pub(crate) fn scalar_mul(a: &[u8; 32], b: &[u8; 32]) -> [u8; 32] {
+    let a = scalar_from_be_bytes(a);
+    let mut product = ZERO;
+    for byte in b {
+        for bit in (0..8).rev() {
+            product = add_mod_n(&product, &product);
+            if byte >> bit & 1 == 1 {
+                product = add_mod_n(&product, &a);
+            }
+        }
+    }
+    field::to_be_bytes(&product)
+}
+
+/// `bytes mod n`, for hashes used as scalars.
+#[cfg(feature = "musig2")]
+pub(crate) fn scalar_reduce(bytes: &[u8; 32]) -> [u8; 32] {
+    field::to_be_bytes(&add_mod_n(&scalar_from_be_bytes(bytes), &ZERO))
+}
+
+#[cfg(feature = "musig2")]
+fn add_mod_n(a: &Fe, b: &Fe) -> Fe {
+    let mut sum = [0u64; 4];
+    let mut carry = 0u128;
+    for i in 0..4 {
+        let v = a[i] as u128 + b[i] as u128 + carry;
+        sum[i] = v as u64;
+        carry = v >> 64;
+    }
+    if carry != 0 || field::ge(&sum, &N) {
+        let mut borrow = false;
+        for (limb, n) in sum.iter_mut().zip(N) {
+            let (d1, b1) = limb.overflowing_sub(n);
+            let (d2, b2) = d1.overflowing_sub(borrow as u64);
+            *limb = d2;
+            borrow = b1 || b2;
+        }
+    }
+    sum
+}
+
+/// `n - a` for a scalar below the group order, leaving zero as it is.
+pub(crate) fn scalar_negate(a: &[u8; 32]) -> [u8; 32] {
+    let a = scalar_from_be_bytes(a);
+    if a == ZERO {
+        return field::to_be_bytes(&a);
+    }
+    let mut out = [0u64; 4];
+    let mut borrow = false;
+    for i in 0..4 {
+        let (d1, b1) = N[i].overflowing_sub(a[i]);
+        let (d2, b2) = d1.overflowing_sub(borrow as u64);
+        out[i] = d2;
+        borrow = b1 || b2;
+    }
+    field::to_be_bytes(&out)
+}
+
+fn scalar_from_be_bytes(bytes: &[u8; 32]) -> Fe {
+    let mut n = [0u64; 4];
+    for (i, chunk) in bytes.chunks_exact(8).enumerate() {
+        n[3 - i] = u64::from_be_bytes(chunk.try_into().unwrap());
+    }
+    n
+}
+
+/// A point on the curve other than the point at infinity.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub(crate) struct Point {
+    x: Fe,
+    y: Fe,
+}
+
+// Jacobian coordinates, with `z == 0` for the point at infinity.
+#[derive(Clone, Copy)]
+struct Jacobian {
+    x: Fe,
+    y: Fe,
+    z: Fe,
+}
+
+impl Jacobian {
+    const INFINITY: Self = Self {
+        x: ONE,
+        y: ONE,
+        z: ZERO,
+    };
+
+    fn double(&self) -> Self {
+        if self.z == ZERO || self.y == ZERO {
+            return Self::INFINITY;
+        }
+        let a = field::mul(&self.x, &self.x);
+        let b = field::mul(&self.y, &self.y);
+        let c = field::mul(&b, &b);
+        let xb = field::add(&self.x, &b);
+        let d = field::sub(&field::sub(&field::mul(&xb, &xb), &a), &c);
+        let d = field::add(&d, &d);
+        let e = field::add(&field::add(&a, &a), &a);
+        let f = field::mul(&e, &e);
+        let x = field::sub(&f, &field::add(&d, &d));
+        let c8 = field::add(&c, &c);
+        let c8 = field::add(&c8, &c8);
+        let c8 = field::add(&c8, &c8);
+        let y = field::sub(&field::mul(&e, &field::sub(&d, &x)), &c8);
+        let yz = field::mul(&self.y, &self.z);
+        Self {
+            x,
+            y,
+            z: field::add(&yz, &yz),
+        }
+    }
+
+    fn add(&self, other: &Self) -> Self {
+        if self.z == ZERO {
+            return *other;
+        }
+        if other.z == ZERO {
+            return *self;
+        }
+        let z1z1 = field::mul(&self.z, &self.z);
+        let z2z2 = field::mul(&other.z, &other.z);
+        let u1 = field::mul(&self.x, &z2z2);
+        let u2 = field::mul(&other.x, &z1z1);
+        let s1 = field::mul(&field::mul(&self.y, &other.z), &z2z2);
+        let s2 = field::mul(&field::mul(&other.y, &self.z), &z1z1);
+        let h = field::sub(&u2, &u1);
+        let r = field::sub(&s2, &s1);
+        if h == ZERO {
+            return if r == ZERO {
+                self.double()
+            } else {
+                Self::INFINITY
+            };
+        }
+        let hh = field::mul(&h, &h);
+        let hhh = field::mul(&h, &hh);
+        let v = field::mul(&u1, &hh);
+        let x = field::sub(&field::sub(&field::mul(&r, &r), &hhh), &field::add(&v, &v));
+        let y = field::sub(&field::mul(&r, &field::sub(&v, &x)), &field::mul(&s1, &hhh));
+        let z = field::mul(&field::mul(&self.z, &other.z), &h);
+        Self { x, y, z }
+    }
+
+    fn to_affine(self) -> Option<Point> {
+        if self.z == ZERO {
+            return None;
+        }
+        let z_inv = field::inv(&self.z);
+        let z_inv2 = field::mul(&z_inv, &z_inv);
+        Some(Point {
+            x: field::mul(&self.x, &z_inv2),
+            y: field::mul(&self.y, &field::mul(&z_inv2, &z_inv)),
+        })
+    }
+}
+
+impl From<Point> for Jacobian {
+    fn from(p: Point) -> Self {
+        Self {
+            x: p.x,
+            y: p.y,
+            z: ONE,
+        }
+    }
+}
+
+impl Point {
+    pub(crate) const GENERATOR: Point = Point { x: G_X, y: G_Y };
+
+    /// Parses a 33-byte compressed or 65-byte uncompressed SEC encoding.
+    pub(crate) fn from_sec(bytes: &[u8]) -> Option<Self> {
+        match bytes {
+            [prefix @ (0x02 | 0x03), x @ ..] if x.len() == 32 => {
+                let x: &[u8; 32] = x.try_into().unwrap();
+                let y = field::decompress_y(x, *prefix == 0x03)?;
+                Self::from_coordinates(x, &y)
+            }
+            [0x04, xy @ ..] if xy.len() == 64 => {
+                let (x, y) = xy.split_at(32);
+                Self::from_coordinates(x.try_into().unwrap(), y.try_into().unwrap())
+            }
+            _ => None,
+        }
+    }
+
+    fn from_coordinates(x: &[u8; 32], y: &[u8; 32]) -> Option<Self> {
+        if !field::is_on_curve(x, y) {
+            return None;
+        }
+        Some(Self {
+            x: field::from_be_bytes(x)?,
+            y: field::from_be_bytes(y)?,
+        })
+    }
+
+    /// The point with x-coordinate `x` and an even y, as BIP340's `lift_x`.
+    pub(crate) fn lift_x(x: &[u8; 32]) -> Option<Self> {
+        let y = field::decompress_y(x, false)?;
+        Self::from_coordinates(x, &y)
+    }
+
+    pub(crate) fn x_bytes(&self) -> [u8; 32] {
+        field::to_be_bytes(&self.x)
+    }
+
+    pub(crate) fn has_odd_y(&self) -> bool {
+        self.y[0] & 1 == 1
+    }
+
+    #[cfg(feature = "musig2")]
+    pub(crate) fn negate(&self) -> Point {
+        Point {
+            x: self.x,
+            y: field::neg(&self.y),
+        }
+    }
+
+    pub(crate) fn serialize_compressed(&self) -> [u8; 33] {
+        let mut out = [0u8; 33];
+        out[0] = if self.has_odd_y() { 0x03 } else { 0x02 };
+        out[1..].copy_from_slice(&self.x_bytes());
+        out
+    }
+
+    pub(crate) fn serialize_uncompressed(&self) -> [u8; 65] {
+        let mut out = [0u8; 65];
+        out[0] = 0x04;
+        out[1..33].copy_from_slice(&self.x_bytes());
+        out[33..].copy_from_slice(&field::to_be_bytes(&self.y));
+        out
+    }
+
+    /// `self + other`, or `None` for the point at infinity.
+    pub(crate) fn add(&self, other: &Point) -> Option<Point> {
+        Jacobian::from(*self)
+            .add(&Jacobian::from(*other))
+            .to_affine()
+    }
+
+    /// `scalar * self` for a valid non-zero scalar.
+    pub(crate) fn mul(&self, scalar: &[u8; 32]) -> Option<Point> {
+        if !is_valid_scalar(scalar) {
+            return None;
+        }
+        let base = Jacobian::from(*self);
+        let mut acc = Jacobian::INFINITY;
+        for byte in scalar {
+            for bit in (0..8).rev() {
+                acc = acc.double();
+                if byte >> bit & 1 == 1 {
+                    acc = acc.add(&base);
+                }
+            }
+        }
+        acc.to_affine()
+    }
+
+    /// `self + tweak * G`, or `None` if `tweak` is out of range or the
+    /// result is the point at infinity.
+    pub(crate) fn add_tweak(&self, tweak: &[u8; 32]) -> Option<Point> {
+        Self::GENERATOR.mul(tweak)?.add(self)
+    }
+}
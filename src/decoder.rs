@@ -0,0 +1,144 @@
+use crate::io;
+use crate::{
+    BitcoinError, BitcoinTransaction, CompactSize, ConsensusDecode, DecodeLimits, DecodeOptions,
+    OutPoint, Result, Script, TransactionInput, TransactionOutput, Txid, Witness, check_count,
+};
+
+#[derive(Debug, Clone)]
+pub struct Decoder<'a> {
+    bytes: &'a [u8],
+    pos: usize,
+    options: DecodeOptions,
+}
+
+impl<'a> Decoder<'a> {
+    pub fn new(bytes: &'a [u8]) -> Self {
+        Self::with_options(bytes, DecodeOptions::default())
+    }
+
+    pub fn with_limits(bytes: &'a [u8], limits: DecodeLimits) -> Self {
+        Self::with_options(
+            bytes,
+            DecodeOptions {
+                limits,
+                ..DecodeOptions::default()
+            },
+        )
+    }
+
+    pub fn with_options(bytes: &'a [u8], options: DecodeOptions) -> Self {
+        Self {
+            bytes,
+            pos: 0,
+            options,
+        }
+    }
+
+    pub fn limits(&self) -> &DecodeLimits {
+        &self.options.limits
+    }
+
+    pub fn options(&self) -> &DecodeOptions {
+        &self.options
+    }
+
+    pub fn position(&self) -> usize {
+        self.pos
+    }
+
+    pub fn remaining(&self) -> &'a [u8] {
+        &self.bytes[self.pos..]
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.pos == self.bytes.len()
+    }
+
+    pub fn finish(self) -> Result<()> {
+        if !self.is_empty() {
+            return Err(BitcoinError::TrailingBytes);
+        }
+        Ok(())
+    }
+
+    pub fn read<T: ConsensusDecode>(&mut self) -> Result<T> {
+        let options = self.options;
+        T::consensus_decode_with(self, &options).map_err(|e| e.at_end_offset(self.pos))
+    }
+
+    pub fn read_slice(&mut self, len: usize) -> Result<&'a [u8]> {
+        self.read_slice_in(len, "slice")
+    }
+
+    pub fn read_var_slice(&mut self) -> Result<&'a [u8]> {
+        let len = check_count("var slice", self.read_compact_size()?.value, usize::MAX)?;
+        self.read_slice_in(len as usize, "var slice")
+    }
+
+    pub fn read_u8(&mut self) -> Result<u8> {
+        Ok(self.read_slice_in(1, "u8")?[0])
+    }
+
+    fn read_slice_in(&mut self, len: usize, context: &'static str) -> Result<&'a [u8]> {
+        let remaining = self.remaining();
+        let slice = remaining
+            .get(..len)
+            .ok_or(BitcoinError::InsufficientBytes {
+                context,
+                offset: self.pos,
+                needed: len,
+                available: remaining.len(),
+            })?;
+        self.pos += len;
+        Ok(slice)
+    }
+
+    pub fn read_u32(&mut self) -> Result<u32> {
+        self.read()
+    }
+
+    pub fn read_u64(&mut self) -> Result<u64> {
+        self.read()
+    }
+
+    pub fn read_compact_size(&mut self) -> Result<CompactSize> {
+        self.read()
+    }
+
+    pub fn read_txid(&mut self) -> Result<Txid> {
+        self.read()
+    }
+
+    pub fn read_outpoint(&mut self) -> Result<OutPoint> {
+        self.read()
+    }
+
+    pub fn read_script(&mut self) -> Result<Script> {
+        self.read()
+    }
+
+    pub fn read_witness(&mut self) -> Result<Witness> {
+        self.read()
+    }
+
+    pub fn read_input(&mut self) -> Result<TransactionInput> {
+        self.read()
+    }
+
+    pub fn read_output(&mut self) -> Result<TransactionOutput> {
+        self.read()
+    }
+
+    pub fn read_transaction(&mut self) -> Result<BitcoinTransaction> {
+        self.read()
+    }
+}
+
+impl io::Read for Decoder<'_> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let n = buf.len().min(self.bytes.len() - self.pos);
+        buf[..n].copy_from_slice(&self.bytes[self.pos..self.pos + n]);
+        self.pos += n;
+        Ok(n)
+    }
+}
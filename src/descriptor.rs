@@ -0,0 +1,807 @@
+use crate::bip32::{ChildNumber, DerivationPath, Xpub};
+use crate::curve::Point;
+use crate::hashes::hash160;
+use crate::script::{MAX_PUBKEYS_PER_MULTISIG, MAX_SCRIPT_ELEMENT_SIZE, Opcode, ScriptBuilder};
+use crate::taproot::{TAPSCRIPT_LEAF_VERSION, tap_branch_hash, tap_leaf_hash, tweak_internal_key};
+use crate::{BitcoinError, Result, Script};
+use alloc::boxed::Box;
+use alloc::format;
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+use core::fmt;
+use core::ops::Range;
+use core::str::FromStr;
+
+// Core's limits on bare multisig and on `multi_a` key counts.
+const MAX_BARE_MULTISIG_KEYS: usize = 3;
+const MAX_PUBKEYS_PER_MULTI_A: usize = 999;
+const MAX_TAPROOT_DEPTH: usize = 128;
+
+/// The `[fingerprint/path]` prefix recording where a key was derived from.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct KeyOrigin {
+    pub fingerprint: [u8; 4],
+    pub path: DerivationPath,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum SinglePubKey {
+    Compressed([u8; 33]),
+    Uncompressed([u8; 65]),
+    /// Only valid inside `tr()`.
+    XOnly([u8; 32]),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum DescriptorPublicKey {
+    Single(SinglePubKey),
+    /// An xpub and the unhardened path below it, ending in `/*` when
+    /// `wildcard` is set.
+    Extended {
+        xpub: Xpub,
+        path: DerivationPath,
+        wildcard: bool,
+    },
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct DescriptorKey {
+    pub origin: Option<KeyOrigin>,
+    pub key: DescriptorPublicKey,
+}
+
+impl DescriptorKey {
+    pub fn is_ranged(&self) -> bool {
+        matches!(
+            self.key,
+            DescriptorPublicKey::Extended { wildcard: true, .. }
+        )
+    }
+
+    /// Derives an extended key down to a single key, substituting `index`
+    /// for the wildcard. The origin is extended to record the full path.
+    pub fn at_derivation_index(&self, index: u32) -> Result<DescriptorKey> {
+        let DescriptorPublicKey::Extended {
+            xpub,
+            path,
+            wildcard,
+        } = &self.key
+        else {
+            return Ok(self.clone());
+        };
+        let path = match wildcard {
+            true => path.child(ChildNumber::from_normal_idx(index)?),
+            false => path.clone(),
+        };
+        let derived = xpub.derive_pub(&path)?;
+        let origin = match &self.origin {
+            Some(origin) => KeyOrigin {
+                fingerprint: origin.fingerprint,
+                path: origin.path.extend(&path),
+            },
+            None => KeyOrigin {
+                fingerprint: xpub.fingerprint(),
+                path,
+            },
+        };
+        Ok(DescriptorKey {
+            origin: Some(origin),
+            key: DescriptorPublicKey::Single(SinglePubKey::Compressed(derived.public_key)),
+        })
+    }
+
+    fn single_key(&self) -> SinglePubKey {
+        match &self.key {
+            DescriptorPublicKey::Single(key) => *key,
+            DescriptorPublicKey::Extended { xpub, path, .. } => {
+                assert!(!self.is_ranged(), "ranged descriptor needs an index");
+                let derived = xpub.derive_pub(path).expect("derivable path");
+                SinglePubKey::Compressed(derived.public_key)
+            }
+        }
+    }
+
+    /// The key as it appears in scripts: SEC-encoded, or x-only inside
+    /// `tr()`.
+    fn script_bytes(&self, tap: bool) -> Vec<u8> {
+        match (&self.single_key(), tap) {
+            (SinglePubKey::Compressed(key), true) => key[1..].to_vec(),
+            (SinglePubKey::Compressed(key), false) => key.to_vec(),
+            (SinglePubKey::Uncompressed(key), _) => key.to_vec(),
+            (SinglePubKey::XOnly(key), _) => key.to_vec(),
+        }
+    }
+
+    fn x_only(&self) -> [u8; 32] {
+        self.script_bytes(true).try_into().unwrap()
+    }
+}
+
+/// A script tree in `tr()`, each leaf being a tapscript descriptor.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum TapTree {
+    Leaf(Box<Descriptor>),
+    Branch(Box<TapTree>, Box<TapTree>),
+}
+
+impl TapTree {
+    fn map_keys(
+        &self,
+        f: &mut impl FnMut(&DescriptorKey) -> Result<DescriptorKey>,
+    ) -> Result<TapTree> {
+        Ok(match self {
+            TapTree::Leaf(leaf) => TapTree::Leaf(Box::new(leaf.map_keys(f)?)),
+            TapTree::Branch(left, right) => {
+                TapTree::Branch(Box::new(left.map_keys(f)?), Box::new(right.map_keys(f)?))
+            }
+        })
+    }
+
+    fn keys(&self) -> Vec<&DescriptorKey> {
+        match self {
+            TapTree::Leaf(leaf) => leaf.keys(),
+            TapTree::Branch(left, right) => [left.keys(), right.keys()].concat(),
+        }
+    }
+
+    pub fn merkle_root(&self) -> [u8; 32] {
+        match self {
+            TapTree::Leaf(leaf) => tap_leaf_hash(&leaf.script(true).bytes, TAPSCRIPT_LEAF_VERSION),
+            TapTree::Branch(left, right) => {
+                tap_branch_hash(&left.merkle_root(), &right.merkle_root())
+            }
+        }
+    }
+}
+
+/// An output descriptor, as accepted by Bitcoin Core.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum Descriptor {
+    Pk(DescriptorKey),
+    Pkh(DescriptorKey),
+    Wpkh(DescriptorKey),
+    Sh(Box<Descriptor>),
+    Wsh(Box<Descriptor>),
+    /// `multi` or, when `sorted`, `sortedmulti`.
+    Multi {
+        threshold: usize,
+        keys: Vec<DescriptorKey>,
+        sorted: bool,
+    },
+    /// `multi_a` or `sortedmulti_a`, only valid in a `tr()` leaf.
+    MultiA {
+        threshold: usize,
+        keys: Vec<DescriptorKey>,
+        sorted: bool,
+    },
+    Tr {
+        internal_key: DescriptorKey,
+        tree: Option<TapTree>,
+    },
+}
+
+impl Descriptor {
+    /// # Panics
+    ///
+    /// If the descriptor is ranged; derive it with `at_derivation_index`
+    /// or `derive_scripts` instead.
+    pub fn script_pubkey(&self) -> Script {
+        match self {
+            Descriptor::Wpkh(key) => Script::new_p2wpkh(&hash160(&key.script_bytes(false))),
+            Descriptor::Sh(inner) => inner.script(false).to_p2sh(),
+            Descriptor::Wsh(inner) => inner.script(false).to_p2wsh(),
+            Descriptor::Tr { internal_key, tree } => {
+                let root = tree.as_ref().map(TapTree::merkle_root);
+                // Keys are checked to be on the curve when parsed.
+                let (output_key, _) = tweak_internal_key(&internal_key.x_only(), root.as_ref())
+                    .expect("valid internal key");
+                Script::new_p2tr(&output_key)
+            }
+            _ => self.script(false),
+        }
+    }
+
+    /// The redeem script of an `sh()` descriptor.
+    pub fn redeem_script(&self) -> Option<Script> {
+        match self {
+            Descriptor::Sh(inner) => Some(inner.script(false)),
+            _ => None,
+        }
+    }
+
+    /// The witness script of a `wsh()` descriptor, possibly inside `sh()`.
+    pub fn witness_script(&self) -> Option<Script> {
+        match self {
+            Descriptor::Wsh(inner) => Some(inner.script(false)),
+            Descriptor::Sh(inner) => inner.witness_script(),
+            _ => None,
+        }
+    }
+
+    /// All keys in the descriptor, including those in tapscript leaves.
+    pub fn keys(&self) -> Vec<&DescriptorKey> {
+        match self {
+            Descriptor::Pk(key) | Descriptor::Pkh(key) | Descriptor::Wpkh(key) => [key].into(),
+            Descriptor::Sh(inner) | Descriptor::Wsh(inner) => inner.keys(),
+            Descriptor::Multi { keys, .. } | Descriptor::MultiA { keys, .. } => {
+                keys.iter().collect()
+            }
+            Descriptor::Tr { internal_key, tree } => {
+                let mut keys = Vec::from([internal_key]);
+                if let Some(tree) = tree {
+                    keys.extend(tree.keys());
+                }
+                keys
+            }
+        }
+    }
+
+    /// Whether any key ends in a `/*` wildcard.
+    pub fn is_ranged(&self) -> bool {
+        self.keys().iter().any(|key| key.is_ranged())
+    }
+
+    /// The descriptor with every extended key derived to a single key,
+    /// wildcards taking the value `index`.
+    pub fn at_derivation_index(&self, index: u32) -> Result<Descriptor> {
+        self.map_keys(&mut |key| key.at_derivation_index(index))
+    }
+
+    /// The scriptPubKeys at each index in `range`, for scanning a ranged
+    /// descriptor.
+    pub fn derive_scripts(&self, range: Range<u32>) -> DerivedScripts<'_> {
+        DerivedScripts {
+            descriptor: self,
+            range,
+        }
+    }
+
+    fn map_keys(
+        &self,
+        f: &mut impl FnMut(&DescriptorKey) -> Result<DescriptorKey>,
+    ) -> Result<Descriptor> {
+        let mut map_all = |keys: &[DescriptorKey]| keys.iter().map(&mut *f).collect::<Result<_>>();
+        Ok(match self {
+            Descriptor::Pk(key) => Descriptor::Pk(f(key)?),
+            Descriptor::Pkh(key) => Descriptor::Pkh(f(key)?),
+            Descriptor::Wpkh(key) => Descriptor::Wpkh(f(key)?),
+            Descriptor::Sh(inner) => Descriptor::Sh(Box::new(inner.map_keys(f)?)),
+            Descriptor::Wsh(inner) => Descriptor::Wsh(Box::new(inner.map_keys(f)?)),
+            Descriptor::Multi {
+                threshold,
+                keys,
+                sorted,
+            } => Descriptor::Multi {
+                threshold: *threshold,
+                keys: map_all(keys)?,
+                sorted: *sorted,
+            },
+            Descriptor::MultiA {
+                threshold,
+                keys,
+                sorted,
+            } => Descriptor::MultiA {
+                threshold: *threshold,
+                keys: map_all(keys)?,
+                sorted: *sorted,
+            },
+            Descriptor::Tr { internal_key, tree } => Descriptor::Tr {
+                internal_key: f(internal_key)?,
+                tree: tree.as_ref().map(|tree| tree.map_keys(f)).transpose()?,
+            },
+        })
+    }
+
+    /// The descriptor in the form `importdescriptors` expects.
+    pub fn to_string_with_checksum(&self) -> String {
+        add_checksum(&self.to_string()).expect("descriptors display in the input charset")
+    }
+
+    // The script this descriptor places in an output, redeem script,
+    // witness script or tapscript leaf.
+    fn script(&self, tap: bool) -> Script {
+        match self {
+            Descriptor::Pk(key) => ScriptBuilder::new()
+                .push_slice(&key.script_bytes(tap))
+                .push_opcode(Opcode::OP_CHECKSIG)
+                .into_script(),
+            Descriptor::Pkh(key) => Script::new_p2pkh(&hash160(&key.script_bytes(tap))),
+            Descriptor::Multi {
+                threshold,
+                keys,
+                sorted,
+            } => {
+                let mut builder = ScriptBuilder::new().push_int(*threshold as i64);
+                for key in sorted_keys(keys, *sorted, false) {
+                    builder = builder.push_slice(&key);
+                }
+                builder
+                    .push_int(keys.len() as i64)
+                    .push_opcode(Opcode::OP_CHECKMULTISIG)
+                    .into_script()
+            }
+            Descriptor::MultiA {
+                threshold,
+                keys,
+                sorted,
+            } => {
+                let mut builder = ScriptBuilder::new();
+                for (i, key) in sorted_keys(keys, *sorted, true).iter().enumerate() {
+                    let op = if i == 0 {
+                        Opcode::OP_CHECKSIG
+                    } else {
+                        Opcode::OP_CHECKSIGADD
+                    };
+                    builder = builder.push_slice(key).push_opcode(op);
+                }
+                builder
+                    .push_int(*threshold as i64)
+                    .push_opcode(Opcode::OP_NUMEQUAL)
+                    .into_script()
+            }
+            _ => self.script_pubkey(),
+        }
+    }
+}
+
+/// Iterator over `(index, scriptPubKey)` pairs of a descriptor, returned by
+/// [`Descriptor::derive_scripts`].
+#[derive(Debug, Clone)]
+pub struct DerivedScripts<'a> {
+    descriptor: &'a Descriptor,
+    range: Range<u32>,
+}
+
+impl Iterator for DerivedScripts<'_> {
+    type Item = Result<(u32, Script)>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let index = self.range.next()?;
+        Some(
+            self.descriptor
+                .at_derivation_index(index)
+                .map(|derived| (index, derived.script_pubkey())),
+        )
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.range.size_hint()
+    }
+}
+
+fn sorted_keys(keys: &[DescriptorKey], sorted: bool, tap: bool) -> Vec<Vec<u8>> {
+    let mut keys: Vec<_> = keys.iter().map(|key| key.script_bytes(tap)).collect();
+    if sorted {
+        keys.sort();
+    }
+    keys
+}
+
+// Where a descriptor or key appears, which decides what is allowed there.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Context {
+    Top,
+    Sh,
+    Wsh,
+    Tap,
+}
+
+struct Parser<'a> {
+    s: &'a str,
+    pos: usize,
+}
+
+fn invalid(msg: impl Into<String>) -> BitcoinError {
+    BitcoinError::InvalidDescriptor(msg.into())
+}
+
+impl<'a> Parser<'a> {
+    fn rest(&self) -> &'a str {
+        &self.s[self.pos..]
+    }
+
+    fn eat(&mut self, c: char) -> bool {
+        let found = self.rest().starts_with(c);
+        if found {
+            self.pos += 1;
+        }
+        found
+    }
+
+    fn expect(&mut self, c: char) -> Result<()> {
+        if !self.eat(c) {
+            return Err(invalid(format!(
+                "expected '{}' at position {}",
+                c, self.pos
+            )));
+        }
+        Ok(())
+    }
+
+    // Everything up to the next delimiter: a function name, number or key.
+    fn token(&mut self) -> &'a str {
+        let rest = self.rest();
+        let len = rest.find(['(', ')', ',', '{', '}']).unwrap_or(rest.len());
+        self.pos += len;
+        &rest[..len]
+    }
+
+    fn descriptor(&mut self, ctx: Context) -> Result<Descriptor> {
+        let name = self.token();
+        self.expect('(')?;
+        let descriptor = match (name, ctx) {
+            ("pk", _) => Descriptor::Pk(self.key(ctx)?),
+            ("pkh", _) => Descriptor::Pkh(self.key(ctx)?),
+            ("wpkh", Context::Top | Context::Sh) => Descriptor::Wpkh(self.key(Context::Wsh)?),
+            ("sh", Context::Top) => Descriptor::Sh(Box::new(self.descriptor(Context::Sh)?)),
+            ("wsh", Context::Top | Context::Sh) => {
+                Descriptor::Wsh(Box::new(self.descriptor(Context::Wsh)?))
+            }
+            ("multi" | "sortedmulti", Context::Top | Context::Sh | Context::Wsh) => {
+                let (threshold, keys) = self.multi(ctx)?;
+                if ctx == Context::Top && keys.len() > MAX_BARE_MULTISIG_KEYS {
+                    return Err(invalid(format!(
+                        "bare multisig allows at most {} keys",
+                        MAX_BARE_MULTISIG_KEYS
+                    )));
+                }
+                Descriptor::Multi {
+                    threshold,
+                    keys,
+                    sorted: name == "sortedmulti",
+                }
+            }
+            ("multi_a" | "sortedmulti_a", Context::Tap) => {
+                let (threshold, keys) = self.multi(ctx)?;
+                Descriptor::MultiA {
+                    threshold,
+                    keys,
+                    sorted: name == "sortedmulti_a",
+                }
+            }
+            ("tr", Context::Top) => {
+                let internal_key = self.key(Context::Tap)?;
+                let tree = if self.eat(',') {
+                    Some(self.tap_tree(0)?)
+                } else {
+                    None
+                };
+                Descriptor::Tr { internal_key, tree }
+            }
+            (
+                "wpkh" | "sh" | "wsh" | "multi" | "sortedmulti" | "multi_a" | "sortedmulti_a"
+                | "tr",
+                _,
+            ) => return Err(invalid(format!("{}() is not allowed here", name))),
+            _ => return Err(invalid(format!("unknown function '{}'", name))),
+        };
+        self.expect(')')?;
+
+        // Every index gives keys, and so a script, of the same size.
+        if ctx == Context::Sh
+            && descriptor.at_derivation_index(0)?.script(false).bytes.len()
+                > MAX_SCRIPT_ELEMENT_SIZE
+        {
+            return Err(invalid("redeem script is larger than 520 bytes"));
+        }
+        Ok(descriptor)
+    }
+
+    fn multi(&mut self, ctx: Context) -> Result<(usize, Vec<DescriptorKey>)> {
+        let threshold = self.token();
+        let threshold: usize = threshold
+            .parse()
+            .map_err(|_| invalid(format!("invalid threshold '{}'", threshold)))?;
+        let mut keys = Vec::new();
+        while self.eat(',') {
+            keys.push(self.key(ctx)?);
+        }
+        let max = if ctx == Context::Tap {
+            MAX_PUBKEYS_PER_MULTI_A
+        } else {
+            MAX_PUBKEYS_PER_MULTISIG
+        };
+        if keys.is_empty() || keys.len() > max {
+            return Err(invalid(format!("multisig needs 1 to {} keys", max)));
+        }
+        if threshold == 0 || threshold > keys.len() {
+            return Err(invalid(format!(
+                "threshold {} out of range for {} keys",
+                threshold,
+                keys.len()
+            )));
+        }
+        Ok((threshold, keys))
+    }
+
+    fn tap_tree(&mut self, depth: usize) -> Result<TapTree> {
+        if depth > MAX_TAPROOT_DEPTH {
+            return Err(invalid("script tree is too deep"));
+        }
+        if !self.eat('{') {
+            return Ok(TapTree::Leaf(Box::new(self.descriptor(Context::Tap)?)));
+        }
+        let left = self.tap_tree(depth + 1)?;
+        self.expect(',')?;
+        let right = self.tap_tree(depth + 1)?;
+        self.expect('}')?;
+        Ok(TapTree::Branch(Box::new(left), Box::new(right)))
+    }
+
+    fn key(&mut self, ctx: Context) -> Result<DescriptorKey> {
+        let token = self.token();
+        let (origin, key) = match token.strip_prefix('[') {
+            Some(rest) => {
+                let (origin, key) = rest
+                    .split_once(']')
+                    .ok_or_else(|| invalid(format!("unterminated key origin in '{}'", token)))?;
+                (Some(parse_origin(origin)?), key)
+            }
+            None => (None, token),
+        };
+        if key.starts_with("xpub") || key.starts_with("tpub") {
+            return Ok(DescriptorKey {
+                origin,
+                key: parse_extended_key(key)?,
+            });
+        }
+        let bad_key = || invalid(format!("invalid public key '{}'", key));
+        let bytes = hex::decode(key).map_err(|_| bad_key())?;
+        let key = match (bytes.len(), ctx) {
+            (32, Context::Tap) => {
+                let key: [u8; 32] = bytes.try_into().unwrap();
+                Point::lift_x(&key).ok_or_else(bad_key)?;
+                SinglePubKey::XOnly(key)
+            }
+            (33, _) => {
+                Point::from_sec(&bytes).ok_or_else(bad_key)?;
+                SinglePubKey::Compressed(bytes.try_into().unwrap())
+            }
+            (65, Context::Top | Context::Sh) => {
+                Point::from_sec(&bytes).ok_or_else(bad_key)?;
+                SinglePubKey::Uncompressed(bytes.try_into().unwrap())
+            }
+            (65, _) => return Err(invalid("uncompressed keys are not allowed in segwit")),
+            _ => return Err(bad_key()),
+        };
+        Ok(DescriptorKey {
+            origin,
+            key: DescriptorPublicKey::Single(key),
+        })
+    }
+}
+
+fn parse_extended_key(s: &str) -> Result<DescriptorPublicKey> {
+    let mut parts = s.split('/');
+    let xpub = parts.next().unwrap_or_default();
+    let xpub: Xpub = xpub
+        .parse()
+        .map_err(|e| invalid(format!("invalid xpub '{}': {}", xpub, e)))?;
+    let mut path = Vec::new();
+    let mut wildcard = false;
+    for part in parts {
+        if wildcard {
+            return Err(invalid("'*' must be the last path element"));
+        }
+        match part {
+            "*" => wildcard = true,
+            "*'" | "*h" | "*H" => {
+                return Err(invalid("hardened derivation requires a private key"));
+            }
+            _ => {
+                let child = parse_child_number(part)?;
+                if child.is_hardened() {
+                    return Err(invalid("hardened derivation requires a private key"));
+                }
+                path.push(child);
+            }
+        }
+    }
+    Ok(DescriptorPublicKey::Extended {
+        xpub,
+        path: path.into(),
+        wildcard,
+    })
+}
+
+fn parse_origin(s: &str) -> Result<KeyOrigin> {
+    let mut parts = s.split('/');
+    let fingerprint = parts.next().unwrap_or_default();
+    let fingerprint = hex::decode(fingerprint)
+        .ok()
+        .and_then(|bytes| bytes.try_into().ok())
+        .ok_or_else(|| invalid(format!("invalid fingerprint '{}'", fingerprint)))?;
+    let path = parts.map(parse_child_number).collect::<Result<_>>()?;
+    Ok(KeyOrigin { fingerprint, path })
+}
+
+fn parse_child_number(s: &str) -> Result<ChildNumber> {
+    s.parse()
+        .map_err(|_| invalid(format!("invalid path element '{}'", s)))
+}
+
+impl FromStr for Descriptor {
+    type Err = BitcoinError;
+
+    /// Accepts descriptors with or without a `#checksum` suffix, verifying
+    /// it when present.
+    fn from_str(s: &str) -> Result<Self> {
+        let s = if s.contains('#') {
+            verify_checksum(s)?
+        } else {
+            s
+        };
+        let mut parser = Parser { s, pos: 0 };
+        let descriptor = parser.descriptor(Context::Top)?;
+        if !parser.rest().is_empty() {
+            return Err(invalid(format!(
+                "unexpected '{}' after descriptor",
+                parser.rest()
+            )));
+        }
+        Ok(descriptor)
+    }
+}
+
+const INPUT_CHARSET: &[u8] =
+    b"0123456789()[],'/*abcdefgh@:$%{}IJKLMNOPQRSTUVWXYZ&+-.;<=>?!^_|~ijklmnopqrstuvwxyzABCDEFGH`#\"\\ ";
+const CHECKSUM_CHARSET: &[u8] = b"qpzry9x8gf2tvdw0s3jn54khce6mua7l";
+
+fn polymod(c: u64, val: u64) -> u64 {
+    const GENERATOR: [u64; 5] = [
+        0xf5dee51989,
+        0xa9fdca3312,
+        0x1bab10e32d,
+        0x3706b1677a,
+        0x644d626ffd,
+    ];
+    let top = c >> 35;
+    let mut c = ((c & 0x7ffffffff) << 5) ^ val;
+    for (i, g) in GENERATOR.iter().enumerate() {
+        if top >> i & 1 == 1 {
+            c ^= g;
+        }
+    }
+    c
+}
+
+/// The 8-character checksum Bitcoin Core appends to descriptors after `#`.
+pub fn descriptor_checksum(desc: &str) -> Result<String> {
+    let mut c = 1;
+    let mut class = 0;
+    let mut class_count = 0;
+    for ch in desc.chars() {
+        let pos = INPUT_CHARSET
+            .iter()
+            .position(|&b| b as char == ch)
+            .ok_or_else(|| invalid(format!("invalid character '{}'", ch)))?
+            as u64;
+        // Symbols within a group of 32 are checked one by one, the group
+        // numbers three at a time.
+        c = polymod(c, pos & 31);
+        class = class * 3 + (pos >> 5);
+        class_count += 1;
+        if class_count == 3 {
+            c = polymod(c, class);
+            class = 0;
+            class_count = 0;
+        }
+    }
+    if class_count > 0 {
+        c = polymod(c, class);
+    }
+    for _ in 0..8 {
+        c = polymod(c, 0);
+    }
+    c ^= 1;
+    Ok((0..8)
+        .map(|i| CHECKSUM_CHARSET[(c >> (5 * (7 - i)) & 31) as usize] as char)
+        .collect())
+}
+
+/// `desc` followed by `#` and its checksum.
+pub fn add_checksum(desc: &str) -> Result<String> {
+    Ok(format!("{}#{}", desc, descriptor_checksum(desc)?))
+}
+
+/// Checks the `#checksum` suffix of `desc` and returns the descriptor
+/// without it.
+pub fn verify_checksum(desc: &str) -> Result<&str> {
+    let (body, checksum) = desc
+        .rsplit_once('#')
+        .ok_or_else(|| invalid("missing checksum"))?;
+    let expected = descriptor_checksum(body)?;
+    if checksum != expected {
+        return Err(invalid(format!(
+            "checksum '{}' does not match '{}'",
+            checksum, expected
+        )));
+    }
+    Ok(body)
+}
+
+impl fmt::Display for KeyOrigin {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", hex::encode(self.fingerprint))?;
+        for child in &self.path {
+            write!(f, "/{}", child)?;
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Display for DescriptorKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if let Some(origin) = &self.origin {
+            write!(f, "[{}]", origin)?;
+        }
+        match &self.key {
+            DescriptorPublicKey::Single(SinglePubKey::Compressed(key)) => {
+                f.write_str(&hex::encode(key))
+            }
+            DescriptorPublicKey::Single(SinglePubKey::Uncompressed(key)) => {
+                f.write_str(&hex::encode(key))
+            }
+            DescriptorPublicKey::Single(SinglePubKey::XOnly(key)) => f.write_str(&hex::encode(key)),
+            DescriptorPublicKey::Extended {
+                xpub,
+                path,
+                wildcard,
+            } => {
+                write!(f, "{}", xpub)?;
+                for index in path {
+                    write!(f, "/{}", index)?;
+                }
+                if *wildcard {
+                    f.write_str("/*")?;
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+impl fmt::Display for TapTree {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TapTree::Leaf(leaf) => write!(f, "{}", leaf),
+            TapTree::Branch(left, right) => write!(f, "{{{},{}}}", left, right),
+        }
+    }
+}
+
+impl fmt::Display for Descriptor {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let multi = |f: &mut fmt::Formatter<'_>, name, threshold, keys: &[DescriptorKey]| {
+            let keys: Vec<_> = keys.iter().map(ToString::to_string).collect();
+            write!(f, "{}({},{})", name, threshold, keys.join(","))
+        };
+        match self {
+            Descriptor::Pk(key) => write!(f, "pk({})", key),
+            Descriptor::Pkh(key) => write!(f, "pkh({})", key),
+            Descriptor::Wpkh(key) => write!(f, "wpkh({})", key),
+            Descriptor::Sh(inner) => write!(f, "sh({})", inner),
+            Descriptor::Wsh(inner) => write!(f, "wsh({})", inner),
+            Descriptor::Multi {
+                threshold,
+                keys,
+                sorted,
+            } => {
+                let name = if *sorted { "sortedmulti" } else { "multi" };
+                multi(f, name, threshold, keys)
+            }
+            Descriptor::MultiA {
+                threshold,
+                keys,
+                sorted,
+            } => {
+                let name = if *sorted { "sortedmulti_a" } else { "multi_a" };
+                multi(f, name, threshold, keys)
+            }
+            Descriptor::Tr { internal_key, tree } => match tree {
+                Some(tree) => write!(f, "tr({},{})", internal_key, tree),
+                None => write!(f, "tr({})", internal_key),
+            },
+        }
+    }
+}
@@ -0,0 +1,174 @@
+//! ECDSA signatures as they appear in scripts: DER-encoded and followed by
+//! the sighash type byte. Signing and verification go through libsecp256k1
+//! and need the `secp256k1` feature.
+
+use crate::curve::{self, HALF_ORDER};
+use crate::script::interpreter::is_valid_signature_encoding;
+use crate::{BitcoinError, Result};
+use alloc::vec::Vec;
+#[cfg(feature = "secp256k1")]
+use {
+    crate::{PrivateKey, PublicKey},
+    secp256k1::{Message, Secp256k1, SecretKey, ecdsa},
+};
+
+/// A parsed ECDSA signature with its sighash type.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct EcdsaSignature {
+    /// Big-endian, left-padded with zeros.
+    pub r: [u8; 32],
+    pub s: [u8; 32],
+    pub sighash_type: u8,
+}
+
+impl EcdsaSignature {
+    /// Parses a signature followed by its sighash type byte. With `strict`
+    /// the encoding must be BIP66 DER; otherwise it is parsed as laxly as
+    /// consensus allows for pre-BIP66 signatures.
+    pub fn from_slice(bytes: &[u8], strict: bool) -> Result<EcdsaSignature> {
+        if strict && !is_valid_signature_encoding(bytes) {
+            return Err(BitcoinError::InvalidSignature("not strict DER"));
+        }
+        let (&sighash_type, der) = bytes
+            .split_last()
+            .ok_or(BitcoinError::InvalidSignature("empty signature"))?;
+        let (r, s) = parse_der_lax(der)?;
+        Ok(EcdsaSignature { r, s, sighash_type })
+    }
+
+    /// Whether S is in the lower half of the order, as BIP146 requires of
+    /// standard transactions.
+    pub fn is_low_s(&self) -> bool {
+        self.s <= HALF_ORDER
+    }
+
+    /// Replaces a high S with its low-S twin, `n - s`, which signs the
+    /// same message.
+    pub fn normalize_s(&mut self) {
+        if !self.is_low_s() {
+            self.s = curve::scalar_negate(&self.s);
+        }
+    }
+
+    /// The canonical DER encoding, without the sighash type.
+    pub fn serialize_der(&self) -> Vec<u8> {
+        let r = der_integer(&self.r);
+        let s = der_integer(&self.s);
+        let mut out = Vec::with_capacity(6 + r.len() + s.len());
+        out.extend_from_slice(&[0x30, (4 + r.len() + s.len()) as u8, 0x02, r.len() as u8]);
+        out.extend_from_slice(&r);
+        out.extend_from_slice(&[0x02, s.len() as u8]);
+        out.extend_from_slice(&s);
+        out
+    }
+
+    /// The DER encoding followed by the sighash type, as pushed in scripts.
+    pub fn to_vec(&self) -> Vec<u8> {
+        let mut out = self.serialize_der();
+        out.push(self.sighash_type);
+        out
+    }
+}
+
+// Minimal big-endian with a zero byte in front of a set sign bit.
+fn der_integer(n: &[u8; 32]) -> Vec<u8> {
+    let start = n.iter().position(|&b| b != 0).unwrap_or(31);
+    let mut out = Vec::with_capacity(33);
+    if n[start] & 0x80 != 0 {
+        out.push(0);
+    }
+    out.extend_from_slice(&n[start..]);
+    out
+}
+
+// libsecp256k1's `ecdsa_signature_parse_der_lax`, which Core uses for
+// consensus: lengths may be in long form, integers may carry extra zero
+// padding and the sequence length and trailing bytes are ignored.
+fn parse_der_lax(der: &[u8]) -> Result<([u8; 32], [u8; 32])> {
+    let invalid = || BitcoinError::InvalidSignature("invalid DER");
+    let mut pos = 0;
+    let mut next = || -> Result<u8> {
+        let byte = *der.get(pos).ok_or_else(invalid)?;
+        pos += 1;
+        Ok(byte)
+    };
+    if next()? != 0x30 {
+        return Err(invalid());
+    }
+    let len = next()?;
+    if len & 0x80 != 0 {
+        for _ in 0..len & 0x7f {
+            next()?;
+        }
+    }
+
+    let mut integer = || -> Result<[u8; 32]> {
+        if next()? != 0x02 {
+            return Err(invalid());
+        }
+        let mut len = usize::from(next()?);
+        if len & 0x80 != 0 {
+            let mut len_bytes = len & 0x7f;
+            len = 0;
+            while len_bytes > 0 {
+                len = len
+                    .checked_mul(256)
+                    .ok_or_else(invalid)?
+                    .checked_add(usize::from(next()?))
+                    .ok_or_else(invalid)?;
+                len_bytes -= 1;
+            }
+        }
+        let mut value = Vec::with_capacity(len.min(33));
+        for _ in 0..len {
+            let byte = next()?;
+            if !(value.is_empty() && byte == 0) {
+                value.push(byte);
+            }
+        }
+        if value.len() > 32 {
+            return Err(BitcoinError::InvalidSignature("integer overflows 32 bytes"));
+        }
+        let mut out = [0u8; 32];
+        out[32 - value.len()..].copy_from_slice(&value);
+        Ok(out)
+    };
+    let r = integer()?;
+    let s = integer()?;
+    Ok((r, s))
+}
+
+/// A deterministic (RFC 6979), low-S signature of `sighash` in DER.
+#[cfg(feature = "secp256k1")]
+pub fn sign(key: &PrivateKey, sighash: &[u8; 32]) -> Vec<u8> {
+    let secret = SecretKey::from_slice(&key.secret_bytes()).expect("checked when constructed");
+    Secp256k1::signing_only()
+        .sign_ecdsa(&Message::from_digest(*sighash), &secret)
+        .serialize_der()
+        .to_vec()
+}
+
+/// [`sign`], with `sighash_type` appended as scripts expect.
+#[cfg(feature = "secp256k1")]
+pub fn sign_with_sighash_type(key: &PrivateKey, sighash: &[u8; 32], sighash_type: u8) -> Vec<u8> {
+    let mut sig = sign(key, sighash);
+    sig.push(sighash_type);
+    sig
+}
+
+/// Verifies a DER signature without its sighash type byte. Like
+/// consensus, this accepts lax DER and high-S signatures; policy checks
+/// such as BIP66 belong to the script interpreter.
+#[cfg(feature = "secp256k1")]
+pub fn verify(pubkey: &PublicKey, sighash: &[u8; 32], sig: &[u8]) -> bool {
+    let (Ok(mut sig), Ok(pubkey)) = (
+        ecdsa::Signature::from_der_lax(sig),
+        secp256k1::PublicKey::from_slice(&pubkey.to_bytes()),
+    ) else {
+        return false;
+    };
+    sig.normalize_s();
+    Secp256k1::verification_only()
+        .verify_ecdsa(&Message::from_digest(*sighash), &sig, &pubkey)
+        .is_ok()
+}
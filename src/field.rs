@@ -0,0 +1,165 @@
+//! Just enough secp256k1 field arithmetic to recover and check the
+//! y-coordinate of a public key, and to back the point arithmetic in
+//! `curve`.
+
+pub(crate) type Fe = [u64; 4];
+
+const P: Fe = [
+    0xFFFF_FFFE_FFFF_FC2F,
+    0xFFFF_FFFF_FFFF_FFFF,
+    0xFFFF_FFFF_FFFF_FFFF,
+    0xFFFF_FFFF_FFFF_FFFF,
+];
+
+// 2^256 mod p.
+const C: u64 = 0x1_0000_03D1;
+
+// (p + 1) / 4, the square-root exponent since p = 3 mod 4.
+const SQRT_EXP: Fe = [
+    0xFFFF_FFFF_BFFF_FF0C,
+    0xFFFF_FFFF_FFFF_FFFF,
+    0xFFFF_FFFF_FFFF_FFFF,
+    0x3FFF_FFFF_FFFF_FFFF,
+];
+
+pub(crate) fn from_be_bytes(bytes: &[u8; 32]) -> Option<Fe> {
+    let mut fe = [0u64; 4];
+    for (i, chunk) in bytes.chunks_exact(8).enumerate() {
+        fe[3 - i] = u64::from_be_bytes(chunk.try_into().unwrap());
+    }
+    (!ge(&fe, &P)).then_some(fe)
+}
+
+pub(crate) fn to_be_bytes(fe: &Fe) -> [u8; 32] {
+    let mut bytes = [0u8; 32];
+    for (i, chunk) in bytes.chunks_exact_mut(8).enumerate() {
+        chunk.copy_from_slice(&fe[3 - i].to_be_bytes());
+    }
+    bytes
+}
+
+pub(crate) fn ge(a: &Fe, b: &Fe) -> bool {
+    a.iter().rev().cmp(b.iter().rev()).is_ge()
+}
+
+fn sub_p(a: &mut Fe) {
+    let mut borrow = false;
+    for (limb, p) in a.iter_mut().zip(P) {
+        let (d1, b1) = limb.overflowing_sub(p);
+        let (d2, b2) = d1.overflowing_sub(borrow as u64);
+        *limb = d2;
+        borrow = b1 || b2;
+    }
+}
+
+pub(crate) fn add(a: &Fe, b: &Fe) -> Fe {
+    let mut out = [0u64; 4];
+    let mut carry = 0u128;
+    for i in 0..4 {
+        let v = a[i] as u128 + b[i] as u128 + carry;
+        out[i] = v as u64;
+        carry = v >> 64;
+    }
+    // A carry out means the sum wrapped past 2^256; subtracting p undoes it.
+    if carry != 0 || ge(&out, &P) {
+        sub_p(&mut out);
+    }
+    out
+}
+
+pub(crate) fn mul(a: &Fe, b: &Fe) -> Fe {
+    let mut wide = [0u64; 8];
+    for i in 0..4 {
+        let mut carry = 0u128;
+        for j in 0..4 {
+            let v = wide[i + j] as u128 + a[i] as u128 * b[j] as u128 + carry;
+            wide[i + j] = v as u64;
+            carry = v >> 64;
+        }
+        wide[i + 4] = carry as u64;
+    }
+
+    // Fold the high half down twice using 2^256 = C (mod p).
+    let mut folded = [0u64; 4];
+    let mut carry = 0u128;
+    for i in 0..4 {
+        let v = wide[i] as u128 + wide[i + 4] as u128 * C as u128 + carry;
+        folded[i] = v as u64;
+        carry = v >> 64;
+    }
+    let mut carry = carry * C as u128;
+    for limb in folded.iter_mut() {
+        let v = *limb as u128 + carry;
+        *limb = v as u64;
+        carry = v >> 64;
+    }
+    if carry != 0 {
+        folded = add(&folded, &[C, 0, 0, 0]);
+    }
+    if ge(&folded, &P) {
+        sub_p(&mut folded);
+    }
+    folded
+}
+
+fn pow(base: &Fe, exp: &Fe) -> Fe {
+    let mut acc = [1, 0, 0, 0];
+    for i in (0..256).rev() {
+        acc = mul(&acc, &acc);
+        if exp[i / 64] >> (i % 64) & 1 == 1 {
+            acc = mul(&acc, base);
+        }
+    }
+    acc
+}
+
+pub(crate) fn neg(a: &Fe) -> Fe {
+    if *a == [0; 4] {
+        return *a;
+    }
+    let mut out = P;
+    let mut borrow = false;
+    for (limb, v) in out.iter_mut().zip(a) {
+        let (d1, b1) = limb.overflowing_sub(*v);
+        let (d2, b2) = d1.overflowing_sub(borrow as u64);
+        *limb = d2;
+        borrow = b1 || b2;
+    }
+    out
+}
+
+pub(crate) fn sub(a: &Fe, b: &Fe) -> Fe {
+    add(a, &neg(b))
+}
+
+/// The multiplicative inverse, by Fermat's little theorem. Zero maps to zero.
+pub(crate) fn inv(a: &Fe) -> Fe {
+    let mut exp = P;
+    exp[0] -= 2;
+    pow(a, &exp)
+}
+
+pub(crate) fn curve_rhs(x: &Fe) -> Fe {
+    add(&mul(&mul(x, x), x), &[7, 0, 0, 0])
+}
+
+/// The y-coordinate with the requested parity for `x`, if `x` is on the curve.
+pub(crate) fn decompress_y(x: &[u8; 32], odd: bool) -> Option<[u8; 32]> {
+    let x = from_be_bytes(x)?;
+    let rhs = curve_rhs(&x);
+    let mut y = pow(&rhs, &SQRT_EXP);
+    if mul(&y, &y) != rhs {
+        return None;
+    }
+    if (y[0] & 1 == 1) != odd {
+        y = neg(&y);
+    }
+    Some(to_be_bytes(&y))
+}
+
+pub(crate) fn is_on_curve(x: &[u8; 32], y: &[u8; 32]) -> bool {
+    match (from_be_bytes(x), from_be_bytes(y)) {
+        (Some(x), Some(y)) => mul(&y, &y) == curve_rhs(&x),
+        _ => false,
+    }
+}
@@ -0,0 +1,54 @@
+use crate::{
+    BitcoinTransaction, CompactSize, ConsensusDecode, ConsensusEncode, DecodeOptions, Script,
+    TransactionRef, TxStreamParser,
+};
+
+fn canonical() -> DecodeOptions {
+    DecodeOptions {
+        canonical: true,
+        ..DecodeOptions::default()
+    }
+}
+
+/// Decodes `data` as a `CompactSize` and panics if a canonical decode does
+/// not re-encode to exactly the bytes it consumed.
+pub fn fuzz_decode_compact_size(data: &[u8]) {
+    let lenient = CompactSize::from_bytes(data);
+    if let Ok((size, consumed)) = CompactSize::from_bytes_with(data, &canonical()) {
+        assert_eq!(size.to_bytes(), data[..consumed]);
+        assert_eq!(lenient, Ok((size, consumed)));
+    }
+}
+
+/// Decodes `data` as a length-prefixed `Script` and panics if a canonical
+/// decode does not re-encode to exactly the bytes it consumed.
+pub fn fuzz_decode_script(data: &[u8]) {
+    if let Ok((script, consumed)) = Script::from_bytes_with(data, &canonical()) {
+        assert_eq!(script.to_bytes(), data[..consumed]);
+        assert_eq!(script.serialized_size(), consumed);
+    }
+}
+
+/// Runs `data` through every transaction decoder in the crate and panics if
+/// they disagree, or if a canonical decode does not round-trip.
+pub fn fuzz_decode_transaction(data: &[u8]) {
+    let owned = BitcoinTransaction::from_bytes(data);
+    let borrowed =
+        TransactionRef::from_bytes(data).map(|(tx, consumed)| (tx.to_transaction(), consumed));
+    assert_eq!(owned.as_ref().ok(), borrowed.as_ref().ok());
+
+    if let Ok((tx, consumed)) = &owned {
+        let mut parser = TxStreamParser::new();
+        for chunk in data[..*consumed].chunks(7) {
+            parser
+                .push(chunk)
+                .expect("stream parser rejected a valid frame");
+        }
+        assert_eq!(parser.buffered(), 0);
+        assert_eq!(tx.serialized_size(), tx.to_bytes().len());
+    }
+
+    if let Ok((tx, consumed)) = BitcoinTransaction::from_bytes_with(data, &canonical()) {
+        assert_eq!(tx.to_bytes(), data[..consumed]);
+    }
+}
@@ -0,0 +1,111 @@
+use crate::{BitcoinError, ConsensusDecode, ConsensusEncode, Result, fmt_hex, io, read_array};
+use alloc::string::String;
+use core::fmt;
+use core::str::FromStr;
+use serde::de::Unexpected;
+use serde::{Deserialize, Serialize};
+
+// Maps a hex error onto serde's own kinds, so that deserializers can
+// report where the bad input was instead of a bare message.
+fn hex_de_error<E: serde::de::Error>(s: &str, e: hex::FromHexError) -> E {
+    match e {
+        hex::FromHexError::InvalidHexCharacter { c, .. } => {
+            E::invalid_value(Unexpected::Char(c), &"a hex digit")
+        }
+        hex::FromHexError::OddLength | hex::FromHexError::InvalidStringLength => {
+            E::invalid_length(s.len(), &"64 hex digits")
+        }
+    }
+}
+
+// Hashes are displayed and parsed byte-reversed, matching Bitcoin Core RPC
+// and block explorers, while the wire encoding keeps internal byte order.
+macro_rules! hash_newtype {
+    ($name:ident) => {
+        #[derive(Debug, PartialEq, Eq, Clone, Hash)]
+        #[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
+        pub struct $name(pub [u8; 32]);
+
+        impl fmt::Display for $name {
+            fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                fmt::LowerHex::fmt(self, f)
+            }
+        }
+
+        impl fmt::LowerHex for $name {
+            fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                fmt_hex(f, self.0.iter().rev(), false)
+            }
+        }
+
+        impl fmt::UpperHex for $name {
+            fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                fmt_hex(f, self.0.iter().rev(), true)
+            }
+        }
+
+        impl FromStr for $name {
+            type Err = BitcoinError;
+
+            fn from_str(s: &str) -> Result<Self> {
+                let mut bytes = [0u8; 32];
+                hex::decode_to_slice(s, &mut bytes)?;
+                bytes.reverse();
+                Ok($name(bytes))
+            }
+        }
+
+        impl Serialize for $name {
+            fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+            where
+                S: serde::Serializer,
+            {
+                if serializer.is_human_readable() {
+                    serializer.collect_str(self)
+                } else {
+                    self.0.serialize(serializer)
+                }
+            }
+        }
+
+        impl<'de> Deserialize<'de> for $name {
+            fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+            where
+                D: serde::Deserializer<'de>,
+            {
+                if deserializer.is_human_readable() {
+                    let s = String::deserialize(deserializer)?;
+                    let mut bytes = [0u8; 32];
+                    hex::decode_to_slice(&s, &mut bytes).map_err(|e| hex_de_error(&s, e))?;
+                    bytes.reverse();
+                    Ok($name(bytes))
+                } else {
+                    Ok($name(<[u8; 32]>::deserialize(deserializer)?))
+                }
+            }
+        }
+
+        impl ConsensusEncode for $name {
+            fn consensus_encode<W: io::Write + ?Sized>(&self, w: &mut W) -> io::Result<usize> {
+                w.write_all(&self.0)?;
+                Ok(32)
+            }
+
+            fn serialized_size(&self) -> usize {
+                32
+            }
+        }
+
+        impl ConsensusDecode for $name {
+            fn consensus_decode<R: io::Read + ?Sized>(r: &mut R) -> Result<Self> {
+                Ok($name(read_array(r, stringify!($name))?))
+            }
+        }
+    };
+}
+
+hash_newtype!(Txid);
+hash_newtype!(Wtxid);
+hash_newtype!(BlockHash);
+hash_newtype!(ScriptHash);
+hash_newtype!(MerkleRoot);
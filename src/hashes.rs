@@ -0,0 +1,180 @@
+use crate::io;
+use alloc::vec::Vec;
+
+#[cfg(not(feature = "rustcrypto"))]
+mod builtin;
+
+#[cfg(not(feature = "rustcrypto"))]
+pub use builtin::{ripemd160, sha1, sha256, sha512};
+
+#[cfg(feature = "rustcrypto")]
+pub fn sha256(data: &[u8]) -> [u8; 32] {
+    use sha2::Digest;
+    sha2::Sha256::digest(data).into()
+}
+
+#[cfg(feature = "rustcrypto")]
+pub fn sha512(data: &[u8]) -> [u8; 64] {
+    use sha2::Digest;
+    sha2::Sha512::digest(data).into()
+}
+
+#[cfg(feature = "rustcrypto")]
+pub fn ripemd160(data: &[u8]) -> [u8; 20] {
+    use ripemd::Digest;
+    ripemd::Ripemd160::digest(data).into()
+}
+
+#[cfg(feature = "rustcrypto")]
+pub fn sha1(data: &[u8]) -> [u8; 20] {
+    use sha1::Digest;
+    sha1::Sha1::digest(data).into()
+}
+
+/// Incremental SHA256, for hashing data as it is encoded rather than
+/// buffering it first.
+#[derive(Debug, Clone)]
+pub struct HashEngine {
+    #[cfg(not(feature = "rustcrypto"))]
+    inner: builtin::Sha256Engine,
+    #[cfg(feature = "rustcrypto")]
+    inner: sha2::Sha256,
+}
+
+impl HashEngine {
+    pub fn new() -> Self {
+        Self {
+            #[cfg(not(feature = "rustcrypto"))]
+            inner: builtin::Sha256Engine::new(),
+            #[cfg(feature = "rustcrypto")]
+            inner: <sha2::Sha256 as sha2::Digest>::new(),
+        }
+    }
+
+    pub fn input(&mut self, data: &[u8]) {
+        #[cfg(not(feature = "rustcrypto"))]
+        self.inner.input(data);
+        #[cfg(feature = "rustcrypto")]
+        sha2::Digest::update(&mut self.inner, data);
+    }
+
+    pub fn finalize(self) -> [u8; 32] {
+        #[cfg(not(feature = "rustcrypto"))]
+        return self.inner.finalize();
+        #[cfg(feature = "rustcrypto")]
+        return sha2::Digest::finalize(self.inner).into();
+    }
+
+    /// Finishes the hash and hashes the digest again, giving SHA256d.
+    pub fn finalize_double(self) -> [u8; 32] {
+        sha256(&self.finalize())
+    }
+}
+
+impl Default for HashEngine {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl io::Write for HashEngine {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.input(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+pub fn sha256d(data: &[u8]) -> [u8; 32] {
+    sha256(&sha256(data))
+}
+
+pub fn hash160(data: &[u8]) -> [u8; 20] {
+    ripemd160(&sha256(data))
+}
+
+pub fn hmac_sha512(key: &[u8], data: &[u8]) -> [u8; 64] {
+    const BLOCK_SIZE: usize = 128;
+    let mut block = [0u8; BLOCK_SIZE];
+    if key.len() > BLOCK_SIZE {
+        block[..64].copy_from_slice(&sha512(key));
+    } else {
+        block[..key.len()].copy_from_slice(key);
+    }
+
+    let mut inner = Vec::with_capacity(BLOCK_SIZE + data.len());
+    inner.extend(block.iter().map(|b| b ^ 0x36));
+    inner.extend_from_slice(data);
+    let mut outer = Vec::with_capacity(BLOCK_SIZE + 64);
+    outer.extend(block.iter().map(|b| b ^ 0x5c));
+    outer.extend_from_slice(&sha512(&inner));
+    sha512(&outer)
+}
+
+/// PBKDF2 with HMAC-SHA512, producing a single 64-byte block.
+pub fn pbkdf2_hmac_sha512(password: &[u8], salt: &[u8], rounds: u32) -> [u8; 64] {
+    let mut block = Vec::with_capacity(salt.len() + 4);
+    block.extend_from_slice(salt);
+    block.extend_from_slice(&1u32.to_be_bytes());
+    let mut u = hmac_sha512(password, &block);
+    let mut out = u;
+    for _ in 1..rounds {
+        u = hmac_sha512(password, &u);
+        out.iter_mut().zip(&u).for_each(|(o, b)| *o ^= b);
+    }
+    out
+}
+
+pub fn tagged_hash(tag: &str, msg: &[u8]) -> [u8; 32] {
+    let tag_hash = sha256(tag.as_bytes());
+    let mut preimage = Vec::with_capacity(64 + msg.len());
+    preimage.extend_from_slice(&tag_hash);
+    preimage.extend_from_slice(&tag_hash);
+    preimage.extend_from_slice(msg);
+    sha256(&preimage)
+}
+
+/// SipHash-2-4 keyed with `(k0, k1)`, as used for BIP152 short IDs.
+pub fn siphash24(k0: u64, k1: u64, data: &[u8]) -> u64 {
+    let mut v = [
+        k0 ^ 0x736f6d6570736575,
+        k1 ^ 0x646f72616e646f6d,
+        k0 ^ 0x6c7967656e657261,
+        k1 ^ 0x7465646279746573,
+    ];
+    let mut chunks = data.chunks_exact(8);
+    for chunk in &mut chunks {
+        let m = u64::from_le_bytes(chunk.try_into().unwrap());
+        v[3] ^= m;
+        sip_rounds(&mut v, 2);
+        v[0] ^= m;
+    }
+    let mut last = [0u8; 8];
+    last[..chunks.remainder().len()].copy_from_slice(chunks.remainder());
+    last[7] = data.len() as u8;
+    let m = u64::from_le_bytes(last);
+    v[3] ^= m;
+    sip_rounds(&mut v, 2);
+    v[0] ^= m;
+    v[2] ^= 0xff;
+    sip_rounds(&mut v, 4);
+    v[0] ^ v[1] ^ v[2] ^ v[3]
+}
+
+fn sip_rounds(v: &mut [u64; 4], rounds: usize) {
+    for _ in 0..rounds {
+        v[0] = v[0].wrapping_add(v[1]);
+        v[1] = v[1].rotate_left(13) ^ v[0];
+        v[0] = v[0].rotate_left(32);
+        v[2] = v[2].wrapping_add(v[3]);
+        v[3] = v[3].rotate_left(16) ^ v[2];
+        v[0] = v[0].wrapping_add(v[3]);
+        v[3] = v[3].rotate_left(21) ^ v[0];
+        v[2] = v[2].wrapping_add(v[1]);
+        v[1] = v[1].rotate_left(17) ^ v[2];
+        v[2] = v[2].rotate_left(32);
+    }
+}
@@ -0,0 +1,283 @@
+use crate::curve::{self, Point};
+use crate::hashes::hash160;
+use crate::{BitcoinError, Network, Result, base58, taproot};
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::fmt;
+use core::str::FromStr;
+use serde::{Deserialize, Serialize};
+
+/// A secp256k1 secret key with the network and public key encoding it is
+/// used with, as carried by WIF.
+#[derive(Clone, Copy, PartialEq, Eq, Hash)]
+pub struct PrivateKey {
+    /// Mainnet, or Testnet for the prefix every other network shares.
+    pub network: Network,
+    /// Whether the corresponding public key is serialized compressed.
+    pub compressed: bool,
+    secret: [u8; 32],
+}
+
+impl PrivateKey {
+    /// Fails unless `secret` is a valid scalar, i.e. non-zero and below the
+    /// curve order.
+    pub fn new(secret: [u8; 32], network: Network, compressed: bool) -> Result<PrivateKey> {
+        if !curve::is_valid_scalar(&secret) {
+            return Err(BitcoinError::InvalidKey("secret key out of range"));
+        }
+        Ok(PrivateKey {
+            network,
+            compressed,
+            secret,
+        })
+    }
+
+    pub fn secret_bytes(&self) -> [u8; 32] {
+        self.secret
+    }
+
+    /// Computed by libsecp256k1 in constant time, so that timing doesn't
+    /// leak the secret.
+    #[cfg(feature = "secp256k1")]
+    pub fn public_key(&self) -> PublicKey {
+        use secp256k1::{Secp256k1, SecretKey};
+
+        let secret = SecretKey::from_slice(&self.secret).expect("secret is a valid scalar");
+        let pubkey = secp256k1::PublicKey::from_secret_key(&Secp256k1::signing_only(), &secret);
+        PublicKey {
+            compressed: self.compressed,
+            point: Point::from_sec(&pubkey.serialize()).expect("a valid point"),
+        }
+    }
+
+    /// The key that signs for the output key [`XOnlyPublicKey::tap_tweak`]
+    /// derives from this key's public key, for taproot key-path spends.
+    #[cfg(feature = "secp256k1")]
+    pub fn tap_tweak(&self, merkle_root: Option<&[u8; 32]>) -> PrivateKey {
+        use secp256k1::{Keypair, Scalar, Secp256k1};
+
+        let secp = Secp256k1::new();
+        let keypair =
+            Keypair::from_seckey_slice(&secp, &self.secret).expect("secret is a valid scalar");
+        let (internal_key, _) = keypair.x_only_public_key();
+        let tweak = taproot::tap_tweak_hash(&internal_key.serialize(), merkle_root);
+        let tweaked = Scalar::from_be_bytes(tweak)
+            .ok()
+            .and_then(|tweak| keypair.add_xonly_tweak(&secp, &tweak).ok())
+            .expect("tweak is below the curve order");
+        PrivateKey {
+            secret: tweaked.secret_bytes(),
+            ..*self
+        }
+    }
+
+    pub fn from_wif(wif: &str) -> Result<PrivateKey> {
+        let data = base58::decode_check(wif)?;
+        let (network, compressed) = match (data.first(), data.len()) {
+            (Some(0x80), 33) => (Network::Mainnet, false),
+            (Some(0x80), 34) => (Network::Mainnet, true),
+            (Some(0xef), 33) => (Network::Testnet, false),
+            (Some(0xef), 34) => (Network::Testnet, true),
+            (Some(0x80 | 0xef), _) => {
+                return Err(BitcoinError::InvalidKey("invalid WIF length"));
+            }
+            _ => return Err(BitcoinError::InvalidKey("unknown WIF version byte")),
+        };
+        if compressed && data[33] != 0x01 {
+            return Err(BitcoinError::InvalidKey("invalid WIF compression flag"));
+        }
+        PrivateKey::new(data[1..33].try_into().unwrap(), network, compressed)
+    }
+
+    pub fn to_wif(&self) -> String {
+        let prefix = match self.network {
+            Network::Mainnet => 0x80,
+            _ => 0xef,
+        };
+        let mut data = Vec::with_capacity(34);
+        data.push(prefix);
+        data.extend_from_slice(&self.secret);
+        if self.compressed {
+            data.push(0x01);
+        }
+        base58::encode_check(&data)
+    }
+}
+
+impl FromStr for PrivateKey {
+    type Err = BitcoinError;
+
+    fn from_str(s: &str) -> Result<Self> {
+        PrivateKey::from_wif(s)
+    }
+}
+
+impl fmt::Display for PrivateKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.to_wif())
+    }
+}
+
+// Keeps the secret out of logs and panic messages.
+impl fmt::Debug for PrivateKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("PrivateKey")
+            .field("network", &self.network)
+            .field("compressed", &self.compressed)
+            .field("secret", &"[redacted]")
+            .finish()
+    }
+}
+
+/// A secp256k1 public key, remembering whether it is serialized compressed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct PublicKey {
+    pub compressed: bool,
+    point: Point,
+}
+
+impl PublicKey {
+    /// Parses a 33-byte compressed or 65-byte uncompressed SEC encoding.
+    pub fn from_slice(bytes: &[u8]) -> Result<PublicKey> {
+        let point = Point::from_sec(bytes).ok_or(BitcoinError::InvalidKey(
+            "not a valid SEC-encoded point on the curve",
+        ))?;
+        Ok(PublicKey {
+            compressed: bytes.len() == 33,
+            point,
+        })
+    }
+
+    /// The SEC encoding, compressed or not as the key was parsed.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        if self.compressed {
+            self.serialize().to_vec()
+        } else {
+            self.serialize_uncompressed().to_vec()
+        }
+    }
+
+    pub fn serialize(&self) -> [u8; 33] {
+        self.point.serialize_compressed()
+    }
+
+    pub fn serialize_uncompressed(&self) -> [u8; 65] {
+        self.point.serialize_uncompressed()
+    }
+
+    /// HASH160 of the key as serialized, committed to by P2PKH.
+    pub fn pubkey_hash(&self) -> [u8; 20] {
+        hash160(&self.to_bytes())
+    }
+
+    /// The P2WPKH program. Segwit only allows compressed keys.
+    pub fn wpubkey_hash(&self) -> Result<[u8; 20]> {
+        if !self.compressed {
+            return Err(BitcoinError::InvalidKey(
+                "uncompressed keys are not allowed in segwit",
+            ));
+        }
+        Ok(self.pubkey_hash())
+    }
+}
+
+impl FromStr for PublicKey {
+    type Err = BitcoinError;
+
+    fn from_str(s: &str) -> Result<Self> {
+        PublicKey::from_slice(&hex::decode(s)?)
+    }
+}
+
+impl fmt::Display for PublicKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&hex::encode(self.to_bytes()))
+    }
+}
+
+impl Serialize for PublicKey {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        serializer.collect_str(self)
+    }
+}
+
+impl<'de> Deserialize<'de> for PublicKey {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        let s = String::deserialize(deserializer)?;
+        s.parse().map_err(serde::de::Error::custom)
+    }
+}
+
+/// A BIP340 public key: the x-coordinate of a point with even y.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct XOnlyPublicKey([u8; 32]);
+
+impl XOnlyPublicKey {
+    pub fn from_slice(bytes: &[u8]) -> Result<XOnlyPublicKey> {
+        let bytes: [u8; 32] = bytes
+            .try_into()
+            .map_err(|_| BitcoinError::InvalidKey("x-only keys are 32 bytes"))?;
+        Point::lift_x(&bytes).ok_or(BitcoinError::InvalidKey(
+            "not the x-coordinate of a point on the curve",
+        ))?;
+        Ok(XOnlyPublicKey(bytes))
+    }
+
+    pub fn serialize(&self) -> [u8; 32] {
+        self.0
+    }
+
+    /// The BIP341 output key committing to this internal key and an
+    /// optional script tree root, with the parity of its y-coordinate.
+    pub fn tap_tweak(&self, merkle_root: Option<&[u8; 32]>) -> (XOnlyPublicKey, bool) {
+        let (output_key, odd_y) = taproot::tweak_internal_key(&self.0, merkle_root)
+            .expect("tweak is below the curve order");
+        (XOnlyPublicKey(output_key), odd_y)
+    }
+}
+
+/// Drops the y-coordinate, whose parity BIP340 keys leave implicit.
+impl From<PublicKey> for XOnlyPublicKey {
+    fn from(key: PublicKey) -> Self {
+        XOnlyPublicKey(key.point.x_bytes())
+    }
+}
+
+impl FromStr for XOnlyPublicKey {
+    type Err = BitcoinError;
+
+    fn from_str(s: &str) -> Result<Self> {
+        XOnlyPublicKey::from_slice(&hex::decode(s)?)
+    }
+}
+
+impl fmt::Display for XOnlyPublicKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&hex::encode(self.0))
+    }
+}
+
+impl Serialize for XOnlyPublicKey {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        serializer.collect_str(self)
+    }
+}
+
+impl<'de> Deserialize<'de> for XOnlyPublicKey {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        let s = String::deserialize(deserializer)?;
+        s.parse().map_err(serde::de::Error::custom)
+    }
+}
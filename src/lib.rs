@@ -1,17 +1,687 @@
+#![no_std]
+
+extern crate alloc;
+#[cfg(feature = "std")]
+extern crate std;
+
+use alloc::string::String;
+use alloc::vec;
+use alloc::vec::Vec;
+use core::fmt;
+use core::ops::Deref;
 use hex::{decode, encode};
 use serde::{Deserialize, Serialize};
-use std::fmt;
-use std::ops::Deref;
+
+pub mod account;
+mod address;
+#[cfg(feature = "async")]
+mod async_decode;
+pub mod base58;
+#[cfg(feature = "secp256k1")]
+mod base64;
+pub mod bech32;
+pub mod bip152;
+mod bip21;
+pub mod bip32;
+pub mod bip322;
+#[cfg(feature = "bip39")]
+pub mod bip39;
+#[cfg(feature = "secp256k1")]
+pub mod bip85;
+mod block;
+mod blockfile;
+#[cfg(feature = "std")]
+mod cached;
+#[cfg(feature = "codec")]
+mod codec;
+mod compress;
+mod curve;
+mod decoder;
+pub mod descriptor;
+pub mod ecdsa;
+mod field;
+mod fuzz;
+mod hash_types;
+pub mod hashes;
+pub mod io;
+mod key;
+pub mod merkle;
+pub mod message;
+#[cfg(feature = "musig2")]
+pub mod musig2;
+mod network;
+pub mod policy;
+mod pow;
+pub mod schnorr;
+pub mod script;
+pub mod sighash;
+mod snapshot;
+mod stream;
+mod taproot;
+mod undo;
+mod view;
+
+pub use address::Address;
+#[cfg(feature = "async")]
+pub use async_decode::AsyncConsensusDecode;
+pub use bip21::Bip21Uri;
+pub use block::{Block, BlockHeader, MEDIAN_TIME_SPAN, median_time_past};
+pub use blockfile::{BlockFileReader, MAX_BLOCK_SERIALIZED_SIZE, UndoFileReader};
+#[cfg(feature = "std")]
+pub use cached::CachedTransaction;
+#[cfg(feature = "codec")]
+pub use codec::TxCodec;
+pub use compress::{compress_amount, decompress_amount};
+pub use decoder::Decoder;
+pub use fuzz::{fuzz_decode_compact_size, fuzz_decode_script, fuzz_decode_transaction};
+pub use hash_types::{BlockHash, MerkleRoot, ScriptHash, Txid, Wtxid};
+pub use key::{PrivateKey, PublicKey, XOnlyPublicKey};
+pub use network::Network;
+pub use pow::{
+    CompactTarget, Params, Target, U256, calculate_next_work_required, min_difficulty_bits,
+};
+pub use script::{Instruction, Opcode, ScriptBuilder, ScriptType};
+pub use snapshot::{SNAPSHOT_VERSION, SnapshotMetadata, SnapshotReader, SnapshotWriter};
+pub use stream::{DEFAULT_MAX_FRAME_SIZE, ParseProgress, TxStreamParser};
+pub use taproot::{
+    ControlBlock, TAPROOT_CONTROL_MAX_NODE_COUNT, TAPSCRIPT_LEAF_VERSION, TapLeafHash, TapNodeHash,
+    TaprootBuilder, TaprootSpendInfo,
+};
+pub use undo::{BlockUndo, Coin, TxUndo};
+pub use view::{ScriptRef, TransactionRef, TxInputRef, TxOutputRef, WitnessRef};
+
+const SEGWIT_MARKER: u8 = 0x00;
+const SEGWIT_FLAG: u8 = 0x01;
+
+pub const WITNESS_SCALE_FACTOR: usize = 4;
+pub const MIN_COINBASE_SCRIPT_SIG_SIZE: usize = 2;
+pub const MAX_COINBASE_SCRIPT_SIG_SIZE: usize = 100;
+pub const MAX_SIZE: u64 = 0x0200_0000;
+pub const MAX_BLOCK_WEIGHT: usize = 4_000_000;
+// The smallest possible transaction bounds how many fit in a block.
+const MIN_TRANSACTION_WEIGHT: usize = 4 * 60;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DecodeLimits {
+    pub max_inputs: usize,
+    pub max_outputs: usize,
+    pub max_script_len: usize,
+    pub max_witness_items: usize,
+    pub max_witness_item_len: usize,
+}
+
+impl Default for DecodeLimits {
+    // The tightest bounds a transaction fitting in a single block can reach:
+    // 41 base bytes per input, 9 per output, and every witness byte weighing 1.
+    fn default() -> Self {
+        let max_base_size = MAX_BLOCK_WEIGHT / WITNESS_SCALE_FACTOR;
+        Self {
+            max_inputs: max_base_size / 41,
+            max_outputs: max_base_size / 9,
+            max_script_len: max_base_size,
+            max_witness_items: MAX_BLOCK_WEIGHT,
+            max_witness_item_len: MAX_BLOCK_WEIGHT,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DecodeOptions {
+    pub limits: DecodeLimits,
+    pub canonical: bool,
+    pub allow_segwit: bool,
+    pub allow_trailing: bool,
+}
+
+impl DecodeOptions {
+    pub fn strict() -> Self {
+        Self {
+            canonical: true,
+            allow_trailing: false,
+            ..Self::default()
+        }
+    }
+}
+
+impl Default for DecodeOptions {
+    fn default() -> Self {
+        Self {
+            limits: DecodeLimits::default(),
+            canonical: false,
+            allow_segwit: true,
+            allow_trailing: true,
+        }
+    }
+}
 
 #[derive(Debug, PartialEq, Eq, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
 pub struct CompactSize {
     pub value: u64,
 }
 
-#[derive(Debug, PartialEq, Eq, Clone)]
+pub type Result<T, E = BitcoinError> = core::result::Result<T, E>;
+
+#[derive(Debug, PartialEq, Clone)]
+#[non_exhaustive]
 pub enum BitcoinError {
-    InsufficientBytes,
+    InsufficientBytes {
+        context: &'static str,
+        offset: usize,
+        needed: usize,
+        available: usize,
+    },
     InvalidFormat,
+    TrailingBytes,
+    Io(io::ErrorKind),
+    OversizedFrame {
+        size: usize,
+        max: usize,
+    },
+    NonCanonicalCompactSize {
+        value: u64,
+        encoded_len: usize,
+    },
+    OversizedScript {
+        len: u64,
+        max: u64,
+    },
+    ValueOverflow {
+        context: &'static str,
+        value: u64,
+        max: u64,
+    },
+    InvalidSegwitMarker {
+        flag: u8,
+    },
+    InvalidHex(hex::FromHexError),
+    SizeLimitExceeded {
+        context: &'static str,
+        size: u64,
+        max: usize,
+    },
+    InvalidMerkleTree(&'static str),
+    InvalidTarget {
+        bits: u32,
+    },
+    InsufficientProofOfWork,
+    InvalidAsm(String),
+    PrevoutsMismatch {
+        inputs: usize,
+        prevouts: usize,
+    },
+    NotPushOnly {
+        offset: usize,
+        opcode: Opcode,
+    },
+    NonMinimalPush {
+        offset: usize,
+        used: Opcode,
+        expected: Opcode,
+    },
+    Script(script::interpreter::ScriptError),
+    NonStandard(policy::NonStandardReason),
+    InvalidDescriptor(String),
+    InvalidExtendedKey(&'static str),
+    InvalidAddress(&'static str),
+    Base58(base58::Base58Error),
+    Bech32(bech32::Bech32Error),
+    WrongNetwork {
+        expected: Network,
+        found: Network,
+    },
+    InvalidKey(&'static str),
+    InvalidUri(&'static str),
+    InvalidDerivationPath(String),
+    #[cfg(feature = "bip39")]
+    Bip39(bip39::Bip39Error),
+    InvalidBip85Request(&'static str),
+    InvalidSignature(&'static str),
+    InputIndexOutOfRange {
+        index: usize,
+        inputs: usize,
+    },
+    InvalidSighash(&'static str),
+    CannotSign(&'static str),
+    /// A MuSig2 signer, or the aggregator if `signer` is `None`, sent an
+    /// invalid nonce or partial signature.
+    InvalidContribution {
+        signer: Option<usize>,
+        reason: &'static str,
+    },
+    InvalidTapTree(&'static str),
+    InvalidControlBlock(&'static str),
+    /// A `rev*.dat` record's checksum doesn't match its undo data and the
+    /// parent block hash it was checked against.
+    UndoChecksumMismatch,
+    /// The scripts of input `index` failed to verify.
+    InputScript {
+        index: usize,
+        error: script::interpreter::ScriptError,
+    },
+    /// Input `index` spends its output in a way that cannot be verified
+    /// here, so whether it is valid is unknown.
+    UnsupportedInput {
+        index: usize,
+        reason: &'static str,
+    },
+    BadCoinbaseLength {
+        len: usize,
+    },
+}
+
+impl Eq for BitcoinError {}
+
+impl fmt::Display for BitcoinError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            BitcoinError::InsufficientBytes {
+                context,
+                offset,
+                needed,
+                available,
+            } => write!(
+                f,
+                "unexpected end of input decoding {} at offset {}: needed {} bytes, {} available",
+                context, offset, needed, available
+            ),
+            BitcoinError::InvalidFormat => write!(f, "invalid data format"),
+            BitcoinError::TrailingBytes => write!(f, "trailing bytes after decoded value"),
+            BitcoinError::Io(kind) => write!(f, "I/O error: {}", kind),
+            BitcoinError::OversizedFrame { size, max } => {
+                write!(f, "frame of {} bytes exceeds maximum of {}", size, max)
+            }
+            BitcoinError::NonCanonicalCompactSize { value, encoded_len } => write!(
+                f,
+                "non-canonical CompactSize: {} encoded in {} bytes",
+                value, encoded_len
+            ),
+            BitcoinError::OversizedScript { len, max } => {
+                write!(f, "script of {} bytes exceeds maximum of {}", len, max)
+            }
+            BitcoinError::ValueOverflow {
+                context,
+                value,
+                max,
+            } => write!(f, "{} count {} exceeds maximum of {}", context, value, max),
+            BitcoinError::InvalidSegwitMarker { flag } => {
+                write!(f, "invalid segwit flag 0x{:02x}", flag)
+            }
+            BitcoinError::InvalidHex(e) => write!(f, "invalid hex: {}", e),
+            BitcoinError::SizeLimitExceeded { context, size, max } => write!(
+                f,
+                "{} size {} exceeds configured limit of {}",
+                context, size, max
+            ),
+            BitcoinError::InvalidMerkleTree(reason) => {
+                write!(f, "invalid partial merkle tree: {}", reason)
+            }
+            BitcoinError::InvalidTarget { bits } => {
+                write!(f, "invalid compact target 0x{:08x}", bits)
+            }
+            BitcoinError::InsufficientProofOfWork => {
+                write!(f, "block hash does not meet its target")
+            }
+            BitcoinError::InvalidAsm(token) => write!(f, "invalid script ASM token '{}'", token),
+            BitcoinError::PrevoutsMismatch { inputs, prevouts } => write!(
+                f,
+                "{} prevouts supplied for a transaction with {} inputs",
+                prevouts, inputs
+            ),
+            BitcoinError::NotPushOnly { offset, opcode } => write!(
+                f,
+                "script is not push-only: {} at offset {}",
+                opcode, offset
+            ),
+            BitcoinError::NonMinimalPush {
+                offset,
+                used,
+                expected,
+            } => write!(
+                f,
+                "non-minimal push at offset {}: {} used where {} is required",
+                offset, used, expected
+            ),
+            BitcoinError::Script(e) => write!(f, "script verification failed: {}", e),
+            BitcoinError::NonStandard(reason) => write!(f, "non-standard transaction: {}", reason),
+            BitcoinError::InvalidDescriptor(reason) => write!(f, "invalid descriptor: {}", reason),
+            BitcoinError::InvalidExtendedKey(reason) => {
+                write!(f, "invalid extended key: {}", reason)
+            }
+            BitcoinError::InvalidAddress(reason) => write!(f, "invalid address: {}", reason),
+            BitcoinError::Base58(e) => write!(f, "invalid base58: {}", e),
+            BitcoinError::Bech32(e) => write!(f, "invalid bech32: {}", e),
+            BitcoinError::WrongNetwork { expected, found } => {
+                write!(f, "address is for {:?}, expected {:?}", found, expected)
+            }
+            BitcoinError::InvalidKey(reason) => write!(f, "invalid key: {}", reason),
+            BitcoinError::InvalidUri(reason) => write!(f, "invalid BIP21 URI: {}", reason),
+            BitcoinError::InvalidDerivationPath(reason) => {
+                write!(f, "invalid derivation path: {}", reason)
+            }
+            #[cfg(feature = "bip39")]
+            BitcoinError::Bip39(e) => write!(f, "invalid mnemonic: {}", e),
+            BitcoinError::InvalidBip85Request(reason) => {
+                write!(f, "invalid BIP85 request: {}", reason)
+            }
+            BitcoinError::InvalidSignature(reason) => write!(f, "invalid signature: {}", reason),
+            BitcoinError::InputIndexOutOfRange { index, inputs } => write!(
+                f,
+                "input {} out of range for a transaction with {} inputs",
+                index, inputs
+            ),
+            BitcoinError::InvalidSighash(reason) => write!(f, "invalid sighash: {}", reason),
+            BitcoinError::CannotSign(reason) => write!(f, "cannot sign input: {}", reason),
+            BitcoinError::InvalidContribution {
+                signer: Some(signer),
+                reason,
+            } => write!(f, "signer {} sent an {}", signer, reason),
+            BitcoinError::InvalidContribution {
+                signer: None,
+                reason,
+            } => write!(f, "aggregator sent an {}", reason),
+            BitcoinError::InvalidTapTree(reason) => write!(f, "invalid taproot tree: {}", reason),
+            BitcoinError::InvalidControlBlock(reason) => {
+                write!(f, "invalid control block: {}", reason)
+            }
+            BitcoinError::UndoChecksumMismatch => write!(f, "undo data checksum mismatch"),
+            BitcoinError::InputScript { index, error } => {
+                write!(f, "input {} failed script verification: {}", index, error)
+            }
+            BitcoinError::UnsupportedInput { index, reason } => {
+                write!(f, "input {} cannot be verified: {}", index, reason)
+            }
+            BitcoinError::BadCoinbaseLength { len } => write!(
+                f,
+                "coinbase scriptSig is {} bytes, expected {}..={}",
+                len, MIN_COINBASE_SCRIPT_SIG_SIZE, MAX_COINBASE_SCRIPT_SIG_SIZE
+            ),
+        }
+    }
+}
+
+impl core::error::Error for BitcoinError {
+    fn source(&self) -> Option<&(dyn core::error::Error + 'static)> {
+        match self {
+            #[cfg(feature = "std")]
+            BitcoinError::InvalidHex(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<hex::FromHexError> for BitcoinError {
+    fn from(e: hex::FromHexError) -> Self {
+        BitcoinError::InvalidHex(e)
+    }
+}
+
+impl From<io::Error> for BitcoinError {
+    fn from(e: io::Error) -> Self {
+        BitcoinError::Io(e.kind())
+    }
+}
+
+impl BitcoinError {
+    fn insufficient(context: &'static str, needed: usize, available: usize) -> Self {
+        BitcoinError::InsufficientBytes {
+            context,
+            offset: 0,
+            needed,
+            available,
+        }
+    }
+
+    fn at_end_offset(self, end: usize) -> Self {
+        match self {
+            BitcoinError::InsufficientBytes {
+                context,
+                needed,
+                available,
+                ..
+            } => BitcoinError::InsufficientBytes {
+                context,
+                offset: end - available,
+                needed,
+                available,
+            },
+            other => other,
+        }
+    }
+}
+
+pub trait ConsensusEncode {
+    fn consensus_encode<W: io::Write + ?Sized>(&self, w: &mut W) -> io::Result<usize>;
+
+    fn serialized_size(&self) -> usize;
+
+    fn encode_into(&self, buf: &mut Vec<u8>) -> usize {
+        buf.reserve(self.serialized_size());
+        self.consensus_encode(buf)
+            .expect("writing to a Vec cannot fail")
+    }
+
+    fn to_bytes(&self) -> Vec<u8> {
+        let mut v = vec![];
+        self.encode_into(&mut v);
+        v
+    }
+}
+
+pub trait ConsensusDecode: Sized {
+    fn consensus_decode<R: io::Read + ?Sized>(r: &mut R) -> Result<Self>;
+
+    fn consensus_decode_with<R: io::Read + ?Sized>(
+        r: &mut R,
+        _options: &DecodeOptions,
+    ) -> Result<Self> {
+        Self::consensus_decode(r)
+    }
+
+    fn from_bytes(bytes: &[u8]) -> Result<(Self, usize)> {
+        Self::from_bytes_with(bytes, &DecodeOptions::default())
+    }
+
+    fn from_bytes_with_limits(bytes: &[u8], limits: &DecodeLimits) -> Result<(Self, usize)> {
+        let options = DecodeOptions {
+            limits: *limits,
+            ..DecodeOptions::default()
+        };
+        Self::from_bytes_with(bytes, &options)
+    }
+
+    fn from_bytes_with(bytes: &[u8], options: &DecodeOptions) -> Result<(Self, usize)> {
+        let mut decoder = Decoder::with_options(bytes, *options);
+        let value = decoder.read()?;
+        if !options.allow_trailing && !decoder.is_empty() {
+            return Err(BitcoinError::TrailingBytes);
+        }
+        Ok((value, decoder.position()))
+    }
+
+    fn decode_exact(bytes: &[u8]) -> Result<Self> {
+        let mut decoder = Decoder::new(bytes);
+        let value = decoder.read()?;
+        decoder.finish()?;
+        Ok(value)
+    }
+
+    fn from_hex(s: &str) -> Result<Self> {
+        Self::decode_exact(&decode(s)?)
+    }
+}
+
+impl ConsensusEncode for u32 {
+    fn consensus_encode<W: io::Write + ?Sized>(&self, w: &mut W) -> io::Result<usize> {
+        w.write_all(&self.to_le_bytes())?;
+        Ok(4)
+    }
+
+    fn serialized_size(&self) -> usize {
+        4
+    }
+}
+
+impl ConsensusDecode for u32 {
+    fn consensus_decode<R: io::Read + ?Sized>(r: &mut R) -> Result<Self> {
+        read_u32(r, "u32")
+    }
+}
+
+impl ConsensusEncode for u64 {
+    fn consensus_encode<W: io::Write + ?Sized>(&self, w: &mut W) -> io::Result<usize> {
+        w.write_all(&self.to_le_bytes())?;
+        Ok(8)
+    }
+
+    fn serialized_size(&self) -> usize {
+        8
+    }
+}
+
+impl ConsensusDecode for u64 {
+    fn consensus_decode<R: io::Read + ?Sized>(r: &mut R) -> Result<Self> {
+        read_u64(r, "u64")
+    }
+}
+
+impl<T: ConsensusEncode> ConsensusEncode for Vec<T> {
+    fn consensus_encode<W: io::Write + ?Sized>(&self, w: &mut W) -> io::Result<usize> {
+        let mut len = CompactSize::new(self.len() as u64).consensus_encode(w)?;
+        for item in self {
+            len += item.consensus_encode(w)?;
+        }
+        Ok(len)
+    }
+
+    fn serialized_size(&self) -> usize {
+        CompactSize::new(self.len() as u64).serialized_size()
+            + self.iter().map(T::serialized_size).sum::<usize>()
+    }
+}
+
+impl<T: ConsensusDecode> ConsensusDecode for Vec<T> {
+    fn consensus_decode<R: io::Read + ?Sized>(r: &mut R) -> Result<Self> {
+        Self::consensus_decode_with(r, &DecodeOptions::default())
+    }
+
+    fn consensus_decode_with<R: io::Read + ?Sized>(
+        r: &mut R,
+        options: &DecodeOptions,
+    ) -> Result<Self> {
+        decode_list(r, options, usize::MAX, "CompactSize")
+    }
+}
+
+fn decode_list<T: ConsensusDecode, R: io::Read + ?Sized>(
+    r: &mut R,
+    options: &DecodeOptions,
+    max: usize,
+    context: &'static str,
+) -> Result<Vec<T>> {
+    let count = read_compact_size(r, "CompactSize", options.canonical)?;
+    let count = check_count(context, count, max)?;
+    let mut items = vec![];
+    for _ in 0..count {
+        items.push(T::consensus_decode_with(r, options)?);
+    }
+    Ok(items)
+}
+
+fn encode_var_bytes<W: io::Write + ?Sized>(data: &[u8], w: &mut W) -> io::Result<usize> {
+    let len = CompactSize::new(data.len() as u64).consensus_encode(w)?;
+    w.write_all(data)?;
+    Ok(len + data.len())
+}
+
+fn var_bytes_size(data: &[u8]) -> usize {
+    CompactSize::new(data.len() as u64).serialized_size() + data.len()
+}
+
+fn read_bytes<R: io::Read + ?Sized>(r: &mut R, len: u64, context: &'static str) -> Result<Vec<u8>> {
+    // Grow the buffer as bytes actually arrive rather than trusting `len`.
+    let needed = usize::try_from(len).unwrap_or(usize::MAX);
+    let mut data = vec![];
+    let mut chunk = [0u8; 4096];
+    while data.len() < needed {
+        let want = (needed - data.len()).min(chunk.len());
+        match r.read(&mut chunk[..want]) {
+            Ok(0) => return Err(BitcoinError::insufficient(context, needed, data.len())),
+            Ok(n) => data.extend_from_slice(&chunk[..n]),
+            Err(e) if e.kind() == io::ErrorKind::Interrupted => {}
+            Err(e) => return Err(e.into()),
+        }
+    }
+    Ok(data)
+}
+
+fn read_array<const N: usize, R: io::Read + ?Sized>(
+    r: &mut R,
+    context: &'static str,
+) -> Result<[u8; N]> {
+    let mut buf = [0u8; N];
+    let mut filled = 0;
+    while filled < N {
+        match r.read(&mut buf[filled..]) {
+            Ok(0) => return Err(BitcoinError::insufficient(context, N, filled)),
+            Ok(n) => filled += n,
+            Err(e) if e.kind() == io::ErrorKind::Interrupted => {}
+            Err(e) => return Err(e.into()),
+        }
+    }
+    Ok(buf)
+}
+
+fn read_u32<R: io::Read + ?Sized>(r: &mut R, context: &'static str) -> Result<u32> {
+    Ok(u32::from_le_bytes(read_array(r, context)?))
+}
+
+fn read_u64<R: io::Read + ?Sized>(r: &mut R, context: &'static str) -> Result<u64> {
+    Ok(u64::from_le_bytes(read_array(r, context)?))
+}
+
+fn read_compact_size<R: io::Read + ?Sized>(
+    r: &mut R,
+    context: &'static str,
+    canonical: bool,
+) -> Result<u64> {
+    let [prefix] = read_array(r, context)?;
+    let (value, encoded_len) = match prefix {
+        n @ 0x00..=0xFC => return Ok(n as u64),
+        0xFD => (u16::from_le_bytes(read_array(r, context)?) as u64, 3),
+        0xFE => (read_u32(r, context)? as u64, 5),
+        0xFF => (read_u64(r, context)?, 9),
+    };
+    if canonical && CompactSize::new(value).serialized_size() != encoded_len {
+        return Err(BitcoinError::NonCanonicalCompactSize { value, encoded_len });
+    }
+    Ok(value)
+}
+
+fn check_count(context: &'static str, value: u64, max: usize) -> Result<u64> {
+    if value > MAX_SIZE {
+        return Err(BitcoinError::ValueOverflow {
+            context,
+            value,
+            max: MAX_SIZE,
+        });
+    }
+    if value > max as u64 {
+        return Err(BitcoinError::SizeLimitExceeded {
+            context,
+            size: value,
+            max,
+        });
+    }
+    Ok(value)
+}
+
+fn check_script_len(len: u64, limits: &DecodeLimits) -> Result<u64> {
+    let max = MAX_SIZE.min(limits.max_script_len as u64);
+    if len > max {
+        return Err(BitcoinError::OversizedScript { len, max });
+    }
+    Ok(len)
 }
 
 impl CompactSize {
@@ -19,90 +689,69 @@ impl CompactSize {
         Self { value }
     }
 
-    pub fn to_bytes(&self) -> Vec<u8> {
+    pub fn from_bytes_canonical(bytes: &[u8]) -> Result<(Self, usize)> {
+        let options = DecodeOptions {
+            canonical: true,
+            ..DecodeOptions::default()
+        };
+        Self::from_bytes_with(bytes, &options)
+    }
+}
+
+impl ConsensusEncode for CompactSize {
+    fn consensus_encode<W: io::Write + ?Sized>(&self, w: &mut W) -> io::Result<usize> {
         match self.value {
-            0..=0xFC => vec![self.value as u8],
+            0..=0xFC => {
+                w.write_all(&[self.value as u8])?;
+                Ok(1)
+            }
             0xFD..=0xFFFF => {
-                let mut v = vec![0xFD];
-                v.extend_from_slice(&(self.value as u16).to_le_bytes());
-                v
+                w.write_all(&[0xFD])?;
+                w.write_all(&(self.value as u16).to_le_bytes())?;
+                Ok(3)
             }
             0x10000..=0xFFFFFFFF => {
-                let mut v = vec![0xFE];
-                v.extend_from_slice(&(self.value as u32).to_le_bytes());
-                v
+                w.write_all(&[0xFE])?;
+                w.write_all(&(self.value as u32).to_le_bytes())?;
+                Ok(5)
             }
             _ => {
-                let mut v = vec![0xFF];
-                v.extend_from_slice(&self.value.to_le_bytes());
-                v
+                w.write_all(&[0xFF])?;
+                w.write_all(&self.value.to_le_bytes())?;
+                Ok(9)
             }
         }
     }
 
-    pub fn from_bytes(bytes: &[u8]) -> Result<(Self, usize), BitcoinError> {
-        if bytes.is_empty() {
-            return Err(BitcoinError::InsufficientBytes);
-        }
-        match bytes[0] {
-            n @ 0x00..=0xFC => Ok((Self::new(n as u64), 1)),
-            0xFD => {
-                if bytes.len() < 3 {
-                    return Err(BitcoinError::InsufficientBytes);
-                }
-                let mut arr = [0u8; 2];
-                arr.copy_from_slice(&bytes[1..3]);
-                Ok((Self::new(u16::from_le_bytes(arr) as u64), 3))
-            }
-            0xFE => {
-                if bytes.len() < 5 {
-                    return Err(BitcoinError::InsufficientBytes);
-                }
-                let mut arr = [0u8; 4];
-                arr.copy_from_slice(&bytes[1..5]);
-                Ok((Self::new(u32::from_le_bytes(arr) as u64), 5))
-            }
-            0xFF => {
-                if bytes.len() < 9 {
-                    return Err(BitcoinError::InsufficientBytes);
-                }
-                let mut arr = [0u8; 8];
-                arr.copy_from_slice(&bytes[1..9]);
-                Ok((Self::new(u64::from_le_bytes(arr)), 9))
-            }
+    fn serialized_size(&self) -> usize {
+        match self.value {
+            0..=0xFC => 1,
+            0xFD..=0xFFFF => 3,
+            0x10000..=0xFFFFFFFF => 5,
+            _ => 9,
         }
     }
 }
 
-#[derive(Debug, PartialEq, Eq, Clone)]
-pub struct Txid(pub [u8; 32]);
-
-impl Serialize for Txid {
-    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
-    where
-        S: serde::Serializer,
-    {
-        serializer.serialize_str(&encode(self.0))
+impl ConsensusDecode for CompactSize {
+    fn consensus_decode<R: io::Read + ?Sized>(r: &mut R) -> Result<Self> {
+        Self::consensus_decode_with(r, &DecodeOptions::default())
     }
-}
 
-impl<'de> Deserialize<'de> for Txid {
-    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
-    where
-        D: serde::Deserializer<'de>,
-    {
-        let s = String::deserialize(deserializer)?;
-        let bytes = decode(&s).map_err(serde::de::Error::custom)?;
-        if bytes.len() != 32 {
-            return Err(serde::de::Error::custom("Invalid Txid length"));
-        }
-        let mut arr = [0u8; 32];
-        arr.copy_from_slice(&bytes);
-        Ok(Txid(arr))
+    fn consensus_decode_with<R: io::Read + ?Sized>(
+        r: &mut R,
+        options: &DecodeOptions,
+    ) -> Result<Self> {
+        Ok(Self::new(read_compact_size(
+            r,
+            "CompactSize",
+            options.canonical,
+        )?))
     }
 }
 
 #[derive(Debug, PartialEq, Eq, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
 pub struct OutPoint {
     pub txid: Txid,
     pub vout: u32,
@@ -116,25 +765,36 @@ impl OutPoint {
         }
     }
 
-    pub fn to_bytes(&self) -> Vec<u8> {
-        let mut v = self.txid.0.to_vec();
-        v.extend_from_slice(&self.vout.to_le_bytes());
-        v
+    pub fn null() -> Self {
+        Self::new([0u8; 32], u32::MAX)
     }
 
-    pub fn from_bytes(bytes: &[u8]) -> Result<(Self, usize), BitcoinError> {
-        if bytes.len() < 36 {
-            return Err(BitcoinError::InsufficientBytes);
-        }
-        let mut txid = [0u8; 32];
-        txid.copy_from_slice(&bytes[0..32]);
-        let mut vout = [0u8; 4];
-        vout.copy_from_slice(&bytes[32..36]);
-        Ok((OutPoint::new(txid, u32::from_le_bytes(vout)), 36))
+    pub fn is_null(&self) -> bool {
+        self.txid.0 == [0u8; 32] && self.vout == u32::MAX
     }
 }
 
-#[derive(Debug, PartialEq, Eq, Clone, Serialize, Deserialize)]
+impl ConsensusEncode for OutPoint {
+    fn consensus_encode<W: io::Write + ?Sized>(&self, w: &mut W) -> io::Result<usize> {
+        Ok(self.txid.consensus_encode(w)? + self.vout.consensus_encode(w)?)
+    }
+
+    fn serialized_size(&self) -> usize {
+        36
+    }
+}
+
+impl ConsensusDecode for OutPoint {
+    fn consensus_decode<R: io::Read + ?Sized>(r: &mut R) -> Result<Self> {
+        Ok(OutPoint {
+            txid: Txid(read_array(r, "OutPoint")?),
+            vout: read_u32(r, "OutPoint")?,
+        })
+    }
+}
+
+#[derive(Debug, PartialEq, Eq, Clone)]
+#[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
 pub struct Script {
     pub bytes: Vec<u8>,
 }
@@ -144,20 +804,86 @@ impl Script {
         Self { bytes }
     }
 
-    pub fn to_bytes(&self) -> Vec<u8> {
-        let mut v = CompactSize::new(self.bytes.len() as u64).to_bytes();
-        v.extend_from_slice(&self.bytes);
-        v
+    pub fn script_hash(&self) -> ScriptHash {
+        ScriptHash(hashes::sha256(&self.bytes))
+    }
+}
+
+impl ConsensusEncode for Script {
+    fn consensus_encode<W: io::Write + ?Sized>(&self, w: &mut W) -> io::Result<usize> {
+        encode_var_bytes(&self.bytes, w)
+    }
+
+    fn serialized_size(&self) -> usize {
+        var_bytes_size(&self.bytes)
+    }
+}
+
+impl ConsensusDecode for Script {
+    fn consensus_decode<R: io::Read + ?Sized>(r: &mut R) -> Result<Self> {
+        Self::consensus_decode_with(r, &DecodeOptions::default())
+    }
+
+    fn consensus_decode_with<R: io::Read + ?Sized>(
+        r: &mut R,
+        options: &DecodeOptions,
+    ) -> Result<Self> {
+        let len = read_compact_size(r, "Script", options.canonical)?;
+        let len = check_script_len(len, &options.limits)?;
+        Ok(Self::new(read_bytes(r, len, "Script")?))
+    }
+}
+
+impl Serialize for Script {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        if serializer.is_human_readable() {
+            serializer.collect_str(&format_args!("{:x}", self))
+        } else {
+            serializer.serialize_bytes(&self.bytes)
+        }
     }
+}
 
-    pub fn from_bytes(bytes: &[u8]) -> Result<(Self, usize), BitcoinError> {
-        let (len_prefix, consumed) = CompactSize::from_bytes(bytes)?;
-        let total_len = consumed + (len_prefix.value as usize);
-        if bytes.len() < total_len {
-            return Err(BitcoinError::InsufficientBytes);
+impl<'de> Deserialize<'de> for Script {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        if deserializer.is_human_readable() {
+            let s = String::deserialize(deserializer)?;
+            Ok(Script::new(decode(s).map_err(serde::de::Error::custom)?))
+        } else {
+            deserializer.deserialize_byte_buf(ScriptBytesVisitor)
         }
-        let data = bytes[consumed..total_len].to_vec();
-        Ok((Self::new(data), total_len))
+    }
+}
+
+struct ScriptBytesVisitor;
+
+impl<'de> serde::de::Visitor<'de> for ScriptBytesVisitor {
+    type Value = Script;
+
+    fn expecting(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("raw script bytes")
+    }
+
+    fn visit_bytes<E: serde::de::Error>(self, v: &[u8]) -> Result<Script, E> {
+        Ok(Script::new(v.to_vec()))
+    }
+
+    fn visit_byte_buf<E: serde::de::Error>(self, v: Vec<u8>) -> Result<Script, E> {
+        Ok(Script::new(v))
+    }
+
+    fn visit_seq<A: serde::de::SeqAccess<'de>>(self, mut seq: A) -> Result<Script, A::Error> {
+        let mut bytes = vec![];
+        while let Some(byte) = seq.next_element()? {
+            bytes.push(byte);
+        }
+        Ok(Script::new(bytes))
     }
 }
 
@@ -168,11 +894,127 @@ impl Deref for Script {
     }
 }
 
-#[derive(Debug, PartialEq, Eq, Clone, Serialize, Deserialize)]
+#[derive(Debug, PartialEq, Eq, Clone, Default, Serialize, Deserialize)]
+#[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
+pub struct Witness {
+    pub items: Vec<Vec<u8>>,
+}
+
+impl Witness {
+    pub fn new(items: Vec<Vec<u8>>) -> Self {
+        Self { items }
+    }
+
+    pub fn push(&mut self, item: impl Into<Vec<u8>>) {
+        self.items.push(item.into());
+    }
+
+    pub fn len(&self) -> usize {
+        self.items.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.items.is_empty()
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &[u8]> {
+        self.items.iter().map(Vec::as_slice)
+    }
+
+    pub fn last(&self) -> Option<&[u8]> {
+        self.items.last().map(Vec::as_slice)
+    }
+
+    /// The witness spending a P2WPKH output: `sig` (with its sighash type
+    /// byte) and the compressed `pubkey`.
+    pub fn new_p2wpkh(sig: &[u8], pubkey: &PublicKey) -> Self {
+        Self::new(vec![sig.to_vec(), pubkey.to_bytes()])
+    }
+
+    /// The witness for a taproot key-path spend.
+    pub fn new_p2tr_key_spend(sig: &schnorr::SchnorrSignature) -> Self {
+        Self::new(vec![sig.to_vec()])
+    }
+}
+
+impl ConsensusEncode for Witness {
+    fn consensus_encode<W: io::Write + ?Sized>(&self, w: &mut W) -> io::Result<usize> {
+        let mut len = CompactSize::new(self.items.len() as u64).consensus_encode(w)?;
+        for item in &self.items {
+            len += encode_var_bytes(item, w)?;
+        }
+        Ok(len)
+    }
+
+    fn serialized_size(&self) -> usize {
+        CompactSize::new(self.items.len() as u64).serialized_size()
+            + self
+                .items
+                .iter()
+                .map(|item| var_bytes_size(item))
+                .sum::<usize>()
+    }
+}
+
+impl ConsensusDecode for Witness {
+    fn consensus_decode<R: io::Read + ?Sized>(r: &mut R) -> Result<Self> {
+        Self::consensus_decode_with(r, &DecodeOptions::default())
+    }
+
+    fn consensus_decode_with<R: io::Read + ?Sized>(
+        r: &mut R,
+        options: &DecodeOptions,
+    ) -> Result<Self> {
+        let count = read_compact_size(r, "Witness", options.canonical)?;
+        let count = check_count("Witness", count, options.limits.max_witness_items)?;
+        let mut items = vec![];
+        for _ in 0..count {
+            let len = read_compact_size(r, "Witness", options.canonical)?;
+            let len = check_count("Witness", len, options.limits.max_witness_item_len)?;
+            items.push(read_bytes(r, len, "Witness")?);
+        }
+        Ok(Self::new(items))
+    }
+}
+
+impl From<Vec<Vec<u8>>> for Witness {
+    fn from(items: Vec<Vec<u8>>) -> Self {
+        Self::new(items)
+    }
+}
+
+#[derive(Debug, PartialEq, Eq, Clone, Deserialize)]
+#[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
 pub struct TransactionInput {
     pub previous_output: OutPoint,
     pub script_sig: Script,
     pub sequence: u32,
+    #[serde(default)]
+    pub witness: Witness,
+}
+
+// Binary formats are not self-describing, so the witness field may only be
+// omitted from human-readable output.
+impl Serialize for TransactionInput {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        use serde::ser::SerializeStruct;
+
+        let skip_witness = serializer.is_human_readable() && self.witness.is_empty();
+        let mut state =
+            serializer.serialize_struct("TransactionInput", if skip_witness { 3 } else { 4 })?;
+        state.serialize_field("previous_output", &self.previous_output)?;
+        state.serialize_field("script_sig", &self.script_sig)?;
+        state.serialize_field("sequence", &self.sequence)?;
+        if skip_witness {
+            state.skip_field("witness")?;
+        } else {
+            state.serialize_field("witness", &self.witness)?;
+        }
+        state.end()
+    }
 }
 
 impl TransactionInput {
@@ -181,89 +1023,316 @@ impl TransactionInput {
             previous_output,
             script_sig,
             sequence,
+            witness: Witness::default(),
         }
     }
+}
 
-    pub fn to_bytes(&self) -> Vec<u8> {
-        let mut v = self.previous_output.to_bytes();
-        v.extend_from_slice(&self.script_sig.to_bytes());
-        v.extend_from_slice(&self.sequence.to_le_bytes());
-        v
+impl ConsensusEncode for TransactionInput {
+    fn consensus_encode<W: io::Write + ?Sized>(&self, w: &mut W) -> io::Result<usize> {
+        let mut len = self.previous_output.consensus_encode(w)?;
+        len += self.script_sig.consensus_encode(w)?;
+        len += self.sequence.consensus_encode(w)?;
+        Ok(len)
     }
 
-    pub fn from_bytes(bytes: &[u8]) -> Result<(Self, usize), BitcoinError> {
-        let (outpoint, oconsumed) = OutPoint::from_bytes(bytes)?;
-        let (script_sig, sconsumed) = Script::from_bytes(&bytes[oconsumed..])?;
-        let total = oconsumed + sconsumed;
-        if bytes.len() < total + 4 {
-            return Err(BitcoinError::InsufficientBytes);
+    fn serialized_size(&self) -> usize {
+        self.previous_output.serialized_size() + self.script_sig.serialized_size() + 4
+    }
+}
+
+impl ConsensusDecode for TransactionInput {
+    fn consensus_decode<R: io::Read + ?Sized>(r: &mut R) -> Result<Self> {
+        Self::consensus_decode_with(r, &DecodeOptions::default())
+    }
+
+    fn consensus_decode_with<R: io::Read + ?Sized>(
+        r: &mut R,
+        options: &DecodeOptions,
+    ) -> Result<Self> {
+        Ok(Self::new(
+            OutPoint::consensus_decode(r)?,
+            Script::consensus_decode_with(r, options)?,
+            read_u32(r, "TransactionInput")?,
+        ))
+    }
+}
+
+#[derive(Debug, PartialEq, Eq, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
+pub struct TransactionOutput {
+    pub value: u64,
+    pub script_pubkey: Script,
+}
+
+impl TransactionOutput {
+    pub fn new(value: u64, script_pubkey: Script) -> Self {
+        Self {
+            value,
+            script_pubkey,
         }
-        let mut seq = [0u8; 4];
-        seq.copy_from_slice(&bytes[total..total + 4]);
-        Ok((
-            Self::new(outpoint, script_sig, u32::from_le_bytes(seq)),
-            total + 4,
+    }
+}
+
+impl ConsensusEncode for TransactionOutput {
+    fn consensus_encode<W: io::Write + ?Sized>(&self, w: &mut W) -> io::Result<usize> {
+        Ok(self.value.consensus_encode(w)? + self.script_pubkey.consensus_encode(w)?)
+    }
+
+    fn serialized_size(&self) -> usize {
+        8 + self.script_pubkey.serialized_size()
+    }
+}
+
+impl ConsensusDecode for TransactionOutput {
+    fn consensus_decode<R: io::Read + ?Sized>(r: &mut R) -> Result<Self> {
+        Self::consensus_decode_with(r, &DecodeOptions::default())
+    }
+
+    fn consensus_decode_with<R: io::Read + ?Sized>(
+        r: &mut R,
+        options: &DecodeOptions,
+    ) -> Result<Self> {
+        Ok(Self::new(
+            read_u64(r, "TransactionOutput")?,
+            Script::consensus_decode_with(r, options)?,
         ))
     }
 }
 
 #[derive(Debug, PartialEq, Eq, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
 pub struct BitcoinTransaction {
     pub version: u32,
     pub inputs: Vec<TransactionInput>,
+    pub outputs: Vec<TransactionOutput>,
     pub lock_time: u32,
 }
 
 impl BitcoinTransaction {
-    pub fn new(version: u32, inputs: Vec<TransactionInput>, lock_time: u32) -> Self {
+    pub fn new(
+        version: u32,
+        inputs: Vec<TransactionInput>,
+        outputs: Vec<TransactionOutput>,
+        lock_time: u32,
+    ) -> Self {
         Self {
             version,
             inputs,
+            outputs,
             lock_time,
         }
     }
 
-    pub fn to_bytes(&self) -> Vec<u8> {
-        let mut v = self.version.to_le_bytes().to_vec();
-        v.extend_from_slice(&CompactSize::new(self.inputs.len() as u64).to_bytes());
-        for input in &self.inputs {
-            v.extend_from_slice(&input.to_bytes());
-        }
-        v.extend_from_slice(&self.lock_time.to_le_bytes());
+    pub fn is_coinbase(&self) -> bool {
+        self.inputs.len() == 1 && self.inputs[0].previous_output.is_null()
+    }
+
+    pub fn has_witness(&self) -> bool {
+        self.inputs.iter().any(|input| !input.witness.is_empty())
+    }
+
+    pub fn txid(&self) -> Txid {
+        let mut engine = hashes::HashEngine::new();
+        self.consensus_encode_legacy(&mut engine)
+            .expect("hashing cannot fail");
+        Txid(engine.finalize_double())
+    }
+
+    pub fn wtxid(&self) -> Wtxid {
+        let mut engine = hashes::HashEngine::new();
+        self.consensus_encode(&mut engine)
+            .expect("hashing cannot fail");
+        Wtxid(engine.finalize_double())
+    }
+
+    pub fn base_size(&self) -> usize {
+        self.size_with(false)
+    }
+
+    pub fn total_size(&self) -> usize {
+        self.serialized_size()
+    }
+
+    pub fn weight(&self) -> usize {
+        self.base_size() * (WITNESS_SCALE_FACTOR - 1) + self.total_size()
+    }
+
+    pub fn vsize(&self) -> usize {
+        self.weight().div_ceil(WITNESS_SCALE_FACTOR)
+    }
+
+    pub fn to_bytes_legacy(&self) -> Vec<u8> {
+        let mut v = Vec::with_capacity(self.base_size());
+        self.consensus_encode_legacy(&mut v)
+            .expect("writing to a Vec cannot fail");
         v
     }
 
-    pub fn from_bytes(bytes: &[u8]) -> Result<(Self, usize), BitcoinError> {
-        if bytes.len() < 4 {
-            return Err(BitcoinError::InsufficientBytes);
+    pub fn consensus_encode_legacy<W: io::Write + ?Sized>(&self, w: &mut W) -> io::Result<usize> {
+        self.encode_with(w, false)
+    }
+
+    /// Checks the coinbase scriptSig length rule from Core's
+    /// `CheckTransaction`. Decoding doesn't enforce it, so call this when
+    /// validating blocks.
+    pub fn check_coinbase(&self) -> Result<()> {
+        if self.is_coinbase() {
+            let len = self.inputs[0].script_sig.len();
+            if !(MIN_COINBASE_SCRIPT_SIG_SIZE..=MAX_COINBASE_SCRIPT_SIG_SIZE).contains(&len) {
+                return Err(BitcoinError::BadCoinbaseLength { len });
+            }
         }
-        let version = u32::from_le_bytes(bytes[0..4].try_into().unwrap());
-        let (cs, cconsumed) = CompactSize::from_bytes(&bytes[4..])?;
-        let mut inputs = vec![];
-        let mut offset = 4 + cconsumed;
-        for _ in 0..cs.value {
-            let (input, consumed) = TransactionInput::from_bytes(&bytes[offset..])?;
-            inputs.push(input);
-            offset += consumed;
+        Ok(())
+    }
+
+    fn size_with(&self, segwit: bool) -> usize {
+        let mut len = 4 + self.inputs.serialized_size() + self.outputs.serialized_size() + 4;
+        if segwit {
+            len += 2 + self
+                .inputs
+                .iter()
+                .map(|input| input.witness.serialized_size())
+                .sum::<usize>();
         }
-        if bytes.len() < offset + 4 {
-            return Err(BitcoinError::InsufficientBytes);
+        len
+    }
+
+    fn encode_with<W: io::Write + ?Sized>(&self, w: &mut W, segwit: bool) -> io::Result<usize> {
+        let mut len = self.version.consensus_encode(w)?;
+        if segwit {
+            w.write_all(&[SEGWIT_MARKER, SEGWIT_FLAG])?;
+            len += 2;
         }
-        let lock_time = u32::from_le_bytes(bytes[offset..offset + 4].try_into().unwrap());
-        Ok((Self::new(version, inputs, lock_time), offset + 4))
+        len += self.inputs.consensus_encode(w)?;
+        len += self.outputs.consensus_encode(w)?;
+        if segwit {
+            for input in &self.inputs {
+                len += input.witness.consensus_encode(w)?;
+            }
+        }
+        len += self.lock_time.consensus_encode(w)?;
+        Ok(len)
+    }
+}
+
+impl ConsensusEncode for BitcoinTransaction {
+    fn consensus_encode<W: io::Write + ?Sized>(&self, w: &mut W) -> io::Result<usize> {
+        self.encode_with(w, self.has_witness())
+    }
+
+    fn serialized_size(&self) -> usize {
+        self.size_with(self.has_witness())
+    }
+}
+
+impl ConsensusDecode for BitcoinTransaction {
+    fn consensus_decode<R: io::Read + ?Sized>(r: &mut R) -> Result<Self> {
+        Self::consensus_decode_with(r, &DecodeOptions::default())
+    }
+
+    fn consensus_decode_with<R: io::Read + ?Sized>(
+        r: &mut R,
+        options: &DecodeOptions,
+    ) -> Result<Self> {
+        let version = read_u32(r, "BitcoinTransaction")?;
+        let mut inputs: Vec<TransactionInput> =
+            decode_list(r, options, options.limits.max_inputs, "TransactionInput")?;
+
+        // BIP144: an empty input vector is either the segwit marker or a
+        // transaction with no inputs and no outputs, told apart by the flag.
+        let mut flag = 0;
+        let mut outputs = vec![];
+        if inputs.is_empty() {
+            [flag] = read_array(r, "BitcoinTransaction")?;
+            if flag != 0 {
+                inputs = decode_list(r, options, options.limits.max_inputs, "TransactionInput")?;
+                outputs = decode_list(r, options, options.limits.max_outputs, "TransactionOutput")?;
+            }
+        } else {
+            outputs = decode_list(r, options, options.limits.max_outputs, "TransactionOutput")?;
+        }
+
+        if flag & SEGWIT_FLAG != 0 && options.allow_segwit {
+            flag ^= SEGWIT_FLAG;
+            for input in inputs.iter_mut() {
+                input.witness = Witness::consensus_decode_with(r, options)?;
+            }
+            if inputs.iter().all(|input| input.witness.is_empty()) {
+                return Err(BitcoinError::InvalidFormat);
+            }
+        }
+        if flag != 0 {
+            return Err(BitcoinError::InvalidSegwitMarker { flag });
+        }
+
+        let lock_time = read_u32(r, "BitcoinTransaction")?;
+        Ok(Self::new(version, inputs, outputs, lock_time))
+    }
+}
+
+fn fmt_hex<'a>(
+    f: &mut fmt::Formatter<'_>,
+    bytes: impl IntoIterator<Item = &'a u8>,
+    upper: bool,
+) -> fmt::Result {
+    for byte in bytes {
+        if upper {
+            write!(f, "{:02X}", byte)?;
+        } else {
+            write!(f, "{:02x}", byte)?;
+        }
+    }
+    Ok(())
+}
+
+impl fmt::LowerHex for Script {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt_hex(f, &self.bytes, false)
+    }
+}
+
+impl fmt::UpperHex for Script {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt_hex(f, &self.bytes, true)
+    }
+}
+
+impl fmt::LowerHex for Witness {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt_hex(f, &self.to_bytes(), false)
+    }
+}
+
+impl fmt::UpperHex for Witness {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt_hex(f, &self.to_bytes(), true)
+    }
+}
+
+impl fmt::LowerHex for BitcoinTransaction {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt_hex(f, &self.to_bytes(), false)
+    }
+}
+
+impl fmt::UpperHex for BitcoinTransaction {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt_hex(f, &self.to_bytes(), true)
     }
 }
 
 impl fmt::Display for BitcoinTransaction {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         writeln!(f, "Version: {}", self.version)?;
+        let coinbase = self.is_coinbase();
         for input in &self.inputs {
-            writeln!(
-                f,
-                "Previous Output Txid: {}",
-                encode(input.previous_output.txid.0)
-            )?;
-            writeln!(f, "Previous Output Vout: {}", input.previous_output.vout)?;
+            if coinbase {
+                writeln!(f, "Coinbase: {}", encode(&input.script_sig.bytes))?;
+            } else {
+                writeln!(f, "Previous Output Txid: {}", input.previous_output.txid)?;
+                writeln!(f, "Previous Output Vout: {}", input.previous_output.vout)?;
+            }
             writeln!(
                 f,
                 "Script Sig ({} bytes): {:02X?}",
@@ -271,6 +1340,18 @@ impl fmt::Display for BitcoinTransaction {
                 input.script_sig.bytes
             )?;
             writeln!(f, "Sequence: {:08X}", input.sequence)?;
+            for item in input.witness.iter() {
+                writeln!(f, "Witness Item ({} bytes): {}", item.len(), encode(item))?;
+            }
+        }
+        for output in &self.outputs {
+            writeln!(f, "Output Value: {}", output.value)?;
+            writeln!(
+                f,
+                "Script PubKey ({} bytes): {:02X?}",
+                output.script_pubkey.len(),
+                output.script_pubkey.bytes
+            )?;
         }
         writeln!(f, "Lock Time: {}", self.lock_time)
     }
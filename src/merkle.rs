@@ -0,0 +1,285 @@
+use crate::{
+    BitcoinError, CompactSize, ConsensusDecode, ConsensusEncode, MAX_BLOCK_WEIGHT,
+    MIN_TRANSACTION_WEIGHT, MerkleRoot, Result, Txid, check_count, encode_var_bytes, hashes, io,
+    read_array, read_bytes, read_compact_size, read_u32, var_bytes_size,
+};
+use alloc::vec;
+use alloc::vec::Vec;
+
+pub fn merkle_root(txids: &[Txid]) -> MerkleRoot {
+    if txids.is_empty() {
+        return MerkleRoot([0u8; 32]);
+    }
+    let mut level: Vec<[u8; 32]> = txids.iter().map(|txid| txid.0).collect();
+    while level.len() > 1 {
+        // An odd node out is paired with itself.
+        level = level
+            .chunks(2)
+            .map(|pair| hash_pair(&pair[0], &pair[pair.len() - 1]))
+            .collect();
+    }
+    MerkleRoot(level[0])
+}
+
+pub(crate) fn hash_pair(left: &[u8; 32], right: &[u8; 32]) -> [u8; 32] {
+    let mut buf = [0u8; 64];
+    buf[..32].copy_from_slice(left);
+    buf[32..].copy_from_slice(right);
+    hashes::sha256d(&buf)
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MerkleProof {
+    pub index: usize,
+    pub path: Vec<[u8; 32]>,
+}
+
+impl MerkleProof {
+    pub fn generate(txids: &[Txid], index: usize) -> Option<Self> {
+        if index >= txids.len() {
+            return None;
+        }
+        let mut level: Vec<[u8; 32]> = txids.iter().map(|txid| txid.0).collect();
+        let mut pos = index;
+        let mut path = vec![];
+        while level.len() > 1 {
+            path.push(level[(pos ^ 1).min(level.len() - 1)]);
+            level = level
+                .chunks(2)
+                .map(|pair| hash_pair(&pair[0], &pair[pair.len() - 1]))
+                .collect();
+            pos /= 2;
+        }
+        Some(Self { index, path })
+    }
+
+    pub fn verify(&self, root: &MerkleRoot, txid: &Txid) -> bool {
+        if self.path.len() < usize::BITS as usize && self.index >> self.path.len() != 0 {
+            return false;
+        }
+        let mut hash = txid.0;
+        let mut pos = self.index;
+        for sibling in &self.path {
+            hash = if pos & 1 == 1 {
+                hash_pair(sibling, &hash)
+            } else {
+                hash_pair(&hash, sibling)
+            };
+            pos >>= 1;
+        }
+        hash == root.0
+    }
+}
+
+/// A BIP37 partial merkle tree, as carried by the `merkleblock` message.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PartialMerkleTree {
+    pub num_transactions: u32,
+    pub bits: Vec<bool>,
+    pub hashes: Vec<[u8; 32]>,
+}
+
+impl PartialMerkleTree {
+    pub fn from_txids(txids: &[Txid], matches: &[bool]) -> Result<Self> {
+        if txids.is_empty() {
+            return Err(BitcoinError::InvalidMerkleTree("no transactions"));
+        }
+        if txids.len() != matches.len() {
+            return Err(BitcoinError::InvalidMerkleTree(
+                "match flags don't line up with txids",
+            ));
+        }
+        let mut tree = Self {
+            num_transactions: txids.len() as u32,
+            bits: vec![],
+            hashes: vec![],
+        };
+        let height = tree.height();
+        tree.build(height, 0, txids, matches);
+        Ok(tree)
+    }
+
+    /// Walks the tree, returning the merkle root and appending every matched
+    /// txid together with its position in the block.
+    pub fn extract_matches(
+        &self,
+        matches: &mut Vec<Txid>,
+        indexes: &mut Vec<u32>,
+    ) -> Result<MerkleRoot> {
+        if self.num_transactions == 0 {
+            return Err(BitcoinError::InvalidMerkleTree("no transactions"));
+        }
+        if self.num_transactions as usize > MAX_BLOCK_WEIGHT / MIN_TRANSACTION_WEIGHT {
+            return Err(BitcoinError::InvalidMerkleTree("too many transactions"));
+        }
+        if self.hashes.len() > self.num_transactions as usize {
+            return Err(BitcoinError::InvalidMerkleTree(
+                "more hashes than transactions",
+            ));
+        }
+        if self.bits.len() < self.hashes.len() {
+            return Err(BitcoinError::InvalidMerkleTree("fewer bits than hashes"));
+        }
+        let (mut bits_used, mut hashes_used) = (0, 0);
+        let root = self.extract(
+            self.height(),
+            0,
+            &mut bits_used,
+            &mut hashes_used,
+            matches,
+            indexes,
+        )?;
+        // Only padding in the final flag byte may go unused.
+        if bits_used.div_ceil(8) != self.bits.len().div_ceil(8) {
+            return Err(BitcoinError::InvalidMerkleTree("unused flag bits"));
+        }
+        if hashes_used != self.hashes.len() {
+            return Err(BitcoinError::InvalidMerkleTree("unused hashes"));
+        }
+        Ok(MerkleRoot(root))
+    }
+
+    fn height(&self) -> u32 {
+        let mut height = 0;
+        while self.width(height) > 1 {
+            height += 1;
+        }
+        height
+    }
+
+    fn width(&self, height: u32) -> u32 {
+        ((self.num_transactions as u64 + (1 << height) - 1) >> height) as u32
+    }
+
+    fn hash(&self, height: u32, pos: u32, txids: &[Txid]) -> [u8; 32] {
+        if height == 0 {
+            return txids[pos as usize].0;
+        }
+        let left = self.hash(height - 1, pos * 2, txids);
+        let right = if pos * 2 + 1 < self.width(height - 1) {
+            self.hash(height - 1, pos * 2 + 1, txids)
+        } else {
+            left
+        };
+        hash_pair(&left, &right)
+    }
+
+    fn build(&mut self, height: u32, pos: u32, txids: &[Txid], matches: &[bool]) {
+        let start = (pos as usize) << height;
+        let end = ((pos as usize + 1) << height).min(txids.len());
+        let parent_of_match = matches[start..end].iter().any(|&m| m);
+        self.bits.push(parent_of_match);
+        if height == 0 || !parent_of_match {
+            let hash = self.hash(height, pos, txids);
+            self.hashes.push(hash);
+        } else {
+            self.build(height - 1, pos * 2, txids, matches);
+            if pos * 2 + 1 < self.width(height - 1) {
+                self.build(height - 1, pos * 2 + 1, txids, matches);
+            }
+        }
+    }
+
+    fn extract(
+        &self,
+        height: u32,
+        pos: u32,
+        bits_used: &mut usize,
+        hashes_used: &mut usize,
+        matches: &mut Vec<Txid>,
+        indexes: &mut Vec<u32>,
+    ) -> Result<[u8; 32]> {
+        let parent_of_match = *self
+            .bits
+            .get(*bits_used)
+            .ok_or(BitcoinError::InvalidMerkleTree("ran out of flag bits"))?;
+        *bits_used += 1;
+        if height == 0 || !parent_of_match {
+            let hash = *self
+                .hashes
+                .get(*hashes_used)
+                .ok_or(BitcoinError::InvalidMerkleTree("ran out of hashes"))?;
+            *hashes_used += 1;
+            if height == 0 && parent_of_match {
+                matches.push(Txid(hash));
+                indexes.push(pos);
+            }
+            return Ok(hash);
+        }
+        let left = self.extract(
+            height - 1,
+            pos * 2,
+            bits_used,
+            hashes_used,
+            matches,
+            indexes,
+        )?;
+        let right = if pos * 2 + 1 < self.width(height - 1) {
+            let right = self.extract(
+                height - 1,
+                pos * 2 + 1,
+                bits_used,
+                hashes_used,
+                matches,
+                indexes,
+            )?;
+            // Identical siblings would let a malleated block share this root
+            // (CVE-2012-2459).
+            if right == left {
+                return Err(BitcoinError::InvalidMerkleTree("duplicate sibling hashes"));
+            }
+            right
+        } else {
+            left
+        };
+        Ok(hash_pair(&left, &right))
+    }
+
+    fn flag_bytes(&self) -> Vec<u8> {
+        let mut bytes = vec![0u8; self.bits.len().div_ceil(8)];
+        for (i, &bit) in self.bits.iter().enumerate() {
+            bytes[i / 8] |= (bit as u8) << (i % 8);
+        }
+        bytes
+    }
+}
+
+impl ConsensusEncode for PartialMerkleTree {
+    fn consensus_encode<W: io::Write + ?Sized>(&self, w: &mut W) -> io::Result<usize> {
+        let mut len = self.num_transactions.consensus_encode(w)?;
+        len += CompactSize::new(self.hashes.len() as u64).consensus_encode(w)?;
+        for hash in &self.hashes {
+            w.write_all(hash)?;
+            len += 32;
+        }
+        Ok(len + encode_var_bytes(&self.flag_bytes(), w)?)
+    }
+
+    fn serialized_size(&self) -> usize {
+        4 + CompactSize::new(self.hashes.len() as u64).serialized_size()
+            + self.hashes.len() * 32
+            + var_bytes_size(&self.flag_bytes())
+    }
+}
+
+impl ConsensusDecode for PartialMerkleTree {
+    fn consensus_decode<R: io::Read + ?Sized>(r: &mut R) -> Result<Self> {
+        let context = "PartialMerkleTree";
+        let num_transactions = read_u32(r, context)?;
+        let count = check_count(context, read_compact_size(r, context, false)?, usize::MAX)?;
+        let mut hashes = vec![];
+        for _ in 0..count {
+            hashes.push(read_array(r, context)?);
+        }
+        let len = check_count(context, read_compact_size(r, context, false)?, usize::MAX)?;
+        let bits = read_bytes(r, len, context)?
+            .iter()
+            .flat_map(|byte| (0..8).map(move |i| byte & (1 << i) != 0))
+            .collect();
+        Ok(Self {
+            num_transactions,
+            bits,
+            hashes,
+        })
+    }
+}
@@ -0,0 +1,103 @@
+//! Legacy signed messages, as made by Core's `signmessage` and Electrum: a
+//! recoverable ECDSA signature over the framed message, checked against a
+//! P2PKH address. Signing and verification need the `secp256k1` feature.
+
+use crate::hashes::HashEngine;
+use crate::{CompactSize, ConsensusEncode};
+#[cfg(feature = "secp256k1")]
+use {
+    crate::{Address, BitcoinError, PrivateKey, PublicKey, Result, base64},
+    alloc::string::String,
+    secp256k1::{
+        Message, Secp256k1, SecretKey,
+        ecdsa::{RecoverableSignature, RecoveryId},
+    },
+};
+
+const MESSAGE_MAGIC: &str = "Bitcoin Signed Message:\n";
+
+/// The digest a message signature signs: SHA256d of the magic string and
+/// the message, each prefixed with its length.
+pub fn signed_message_hash(message: &str) -> [u8; 32] {
+    let mut engine = HashEngine::new();
+    for part in [MESSAGE_MAGIC, message] {
+        CompactSize::new(part.len() as u64)
+            .consensus_encode(&mut engine)
+            .expect("hashing cannot fail");
+        engine.input(part.as_bytes());
+    }
+    engine.finalize_double()
+}
+
+/// Signs `message` with `key`, giving the Base64 of a header byte
+/// (27 + recovery id, plus 4 for a compressed key) and the compact signature.
+#[cfg(feature = "secp256k1")]
+pub fn sign_message(key: &PrivateKey, message: &str) -> String {
+    let secret = SecretKey::from_slice(&key.secret_bytes()).expect("checked when constructed");
+    let msg = Message::from_digest(signed_message_hash(message));
+    let (recovery_id, signature) = Secp256k1::signing_only()
+        .sign_ecdsa_recoverable(&msg, &secret)
+        .serialize_compact();
+    let mut bytes = [0u8; 65];
+    bytes[0] = 27 + recovery_id.to_i32() as u8 + if key.compressed { 4 } else { 0 };
+    bytes[1..].copy_from_slice(&signature);
+    base64::encode(&bytes)
+}
+
+/// The public key that made `signature` over `message`, serialized as the
+/// header says it was.
+#[cfg(feature = "secp256k1")]
+pub fn recover_pubkey(signature: &str, message: &str) -> Result<PublicKey> {
+    let (signature, compressed) = parse_signature(signature)?;
+    recover(&signature, compressed, message).ok_or(BitcoinError::InvalidSignature(
+        "no key recovers from this signature",
+    ))
+}
+
+/// Whether `signature` signs `message` for the P2PKH `address`, as Core's
+/// `verifymessage`. Malformed signatures and other address types are errors.
+#[cfg(feature = "secp256k1")]
+pub fn verify_message(address: &Address, signature: &str, message: &str) -> Result<bool> {
+    let Address::P2pkh { pubkey_hash, .. } = address else {
+        return Err(BitcoinError::InvalidAddress(
+            "message signatures need a P2PKH address",
+        ));
+    };
+    let (signature, compressed) = parse_signature(signature)?;
+    Ok(recover(&signature, compressed, message)
+        .is_some_and(|pubkey| pubkey.pubkey_hash() == *pubkey_hash))
+}
+
+#[cfg(feature = "secp256k1")]
+fn parse_signature(signature: &str) -> Result<(RecoverableSignature, bool)> {
+    let bytes: [u8; 65] = base64::decode(signature)
+        .ok_or(BitcoinError::InvalidSignature(
+            "message signatures are Base64",
+        ))?
+        .try_into()
+        .map_err(|_| BitcoinError::InvalidSignature("message signatures are 65 bytes"))?;
+    let header = bytes[0];
+    if !(27..=34).contains(&header) {
+        return Err(BitcoinError::InvalidSignature(
+            "not a P2PKH message signature",
+        ));
+    }
+    let recovery_id = RecoveryId::from_i32(i32::from((header - 27) & 3)).expect("0 to 3");
+    let signature = RecoverableSignature::from_compact(&bytes[1..], recovery_id)
+        .map_err(|_| BitcoinError::InvalidSignature("invalid recoverable signature"))?;
+    Ok((signature, header >= 31))
+}
+
+#[cfg(feature = "secp256k1")]
+fn recover(signature: &RecoverableSignature, compressed: bool, message: &str) -> Option<PublicKey> {
+    let msg = Message::from_digest(signed_message_hash(message));
+    let pubkey = Secp256k1::verification_only()
+        .recover_ecdsa(&msg, signature)
+        .ok()?;
+    let key = if compressed {
+        PublicKey::from_slice(&pubkey.serialize())
+    } else {
+        PublicKey::from_slice(&pubkey.serialize_uncompressed())
+    };
+    key.ok()
+}
@@ -0,0 +1,369 @@
+//! MuSig2 (BIP327) multi-signatures: signers aggregate their keys into one
+//! key, optionally tweaked into a taproot output key, and combine partial
+//! signatures into a single BIP340 signature for it. Arithmetic on secret
+//! keys and nonces goes through libsecp256k1; the rest uses `curve`.
+
+use crate::curve::{self, Point};
+use crate::hashes::tagged_hash;
+use crate::{BitcoinError, PrivateKey, PublicKey, Result, XOnlyPublicKey, taproot};
+use alloc::vec::Vec;
+use core::fmt;
+use secp256k1::{Scalar, Secp256k1, SecretKey};
+
+const ONE: [u8; 32] = {
+    let mut one = [0; 32];
+    one[31] = 1;
+    one
+};
+
+/// Sorts keys by their compressed encoding, as BIP327's `KeySort`, for
+/// signers who want the aggregate key not to depend on their order.
+pub fn sort_keys(pubkeys: &mut [PublicKey]) {
+    pubkeys.sort_by_key(|key| key.serialize());
+}
+
+/// The aggregate of the signers' keys, in order, with any tweaks applied.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct KeyAggContext {
+    pubkeys: Vec<[u8; 33]>,
+    list_hash: [u8; 32],
+    q: Point,
+    // The accumulated sign, which is always 1 or -1, and tweak.
+    gacc_negated: bool,
+    tacc: [u8; 32],
+}
+
+impl KeyAggContext {
+    pub fn new(pubkeys: &[PublicKey]) -> Result<KeyAggContext> {
+        let pubkeys: Vec<[u8; 33]> = pubkeys.iter().map(PublicKey::serialize).collect();
+        let mut ctx = KeyAggContext {
+            list_hash: tagged_hash("KeyAgg list", &pubkeys.concat()),
+            pubkeys,
+            q: Point::GENERATOR,
+            gacc_negated: false,
+            tacc: [0; 32],
+        };
+        let q = ctx.pubkeys.iter().fold(None, |q, pubkey| {
+            let point = Point::from_sec(pubkey).expect("serialized from a valid key");
+            add(q, point.mul(&ctx.coefficient(pubkey)))
+        });
+        ctx.q = q.ok_or(BitcoinError::InvalidKey(
+            "aggregate key is the point at infinity",
+        ))?;
+        Ok(ctx)
+    }
+
+    /// Adds `tweak * G` to the aggregate key, first negating it to have an
+    /// even y if `x_only`, as BIP327's `ApplyTweak`.
+    pub fn tweak(mut self, tweak: &[u8; 32], x_only: bool) -> Result<KeyAggContext> {
+        if *tweak != [0; 32] && !curve::is_valid_scalar(tweak) {
+            return Err(BitcoinError::InvalidKey("tweak out of range"));
+        }
+        let negate = x_only && self.q.has_odd_y();
+        let q = if negate { self.q.negate() } else { self.q };
+        self.q = add(Some(q), Point::GENERATOR.mul(tweak)).ok_or(BitcoinError::InvalidKey(
+            "tweaked key is the point at infinity",
+        ))?;
+        self.gacc_negated ^= negate;
+        if negate {
+            self.tacc = curve::scalar_negate(&self.tacc);
+        }
+        self.tacc = scalar_add(tweak, &self.tacc);
+        Ok(self)
+    }
+
+    /// Tweaks the aggregate key, as an internal key, into the BIP341 output
+    /// key committing to `merkle_root`.
+    pub fn tap_tweak(self, merkle_root: Option<&[u8; 32]>) -> Result<KeyAggContext> {
+        let tweak = taproot::tap_tweak_hash(&self.q.x_bytes(), merkle_root);
+        self.tweak(&tweak, true)
+    }
+
+    pub fn aggregated_pubkey(&self) -> PublicKey {
+        PublicKey::from_slice(&self.q.serialize_compressed()).expect("a valid point")
+    }
+
+    pub fn x_only_public_key(&self) -> XOnlyPublicKey {
+        XOnlyPublicKey::from(self.aggregated_pubkey())
+    }
+
+    fn coefficient(&self, pubkey: &[u8; 33]) -> [u8; 32] {
+        let first = &self.pubkeys[0];
+        if self.pubkeys.iter().find(|&key| key != first) == Some(pubkey) {
+            return ONE;
+        }
+        let mut data = self.list_hash.to_vec();
+        data.extend_from_slice(pubkey);
+        curve::scalar_reduce(&tagged_hash("KeyAgg coefficient", &data))
+    }
+
+    // `g * gacc`, the sign the signers' keys take in the final key.
+    fn keys_negated(&self) -> bool {
+        self.q.has_odd_y() ^ self.gacc_negated
+    }
+}
+
+/// A signer's secret nonces and public key. Using it twice reveals the
+/// secret key, so signing consumes it and it cannot be cloned.
+pub struct SecNonce([u8; 97]);
+
+impl SecNonce {
+    /// Restores a nonce from its BIP327 encoding, which must never have
+    /// been used to sign.
+    pub fn from_bytes(bytes: [u8; 97]) -> SecNonce {
+        SecNonce(bytes)
+    }
+}
+
+impl fmt::Debug for SecNonce {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("SecNonce([redacted])")
+    }
+}
+
+/// The two compressed nonce points a signer shares.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct PubNonce(pub [u8; 66]);
+
+/// The sums of the signers' nonce points, with all-zero encoding infinity.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct AggNonce(pub [u8; 66]);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct PartialSignature(pub [u8; 32]);
+
+/// Derives a signer's nonces from `rand`, which must be fresh uniform
+/// randomness, as BIP327's `NonceGen`. The optional inputs add
+/// defence in depth should `rand` repeat.
+pub fn nonce_gen(
+    rand: &[u8; 32],
+    key: Option<&PrivateKey>,
+    pubkey: &PublicKey,
+    aggpk: Option<&XOnlyPublicKey>,
+    msg: Option<&[u8]>,
+    extra_in: Option<&[u8]>,
+) -> Result<(SecNonce, PubNonce)> {
+    let mut rand = *rand;
+    if let Some(key) = key {
+        let aux = tagged_hash("MuSig/aux", &rand);
+        for (r, (s, a)) in rand.iter_mut().zip(key.secret_bytes().iter().zip(aux)) {
+            *r = s ^ a;
+        }
+    }
+    let pubkey = pubkey.serialize();
+    let aggpk = aggpk.map(XOnlyPublicKey::serialize);
+    let aggpk: &[u8] = aggpk.as_ref().map_or(&[], |key| key);
+    let mut data = rand.to_vec();
+    data.push(pubkey.len() as u8);
+    data.extend_from_slice(&pubkey);
+    data.push(aggpk.len() as u8);
+    data.extend_from_slice(aggpk);
+    match msg {
+        Some(msg) => {
+            data.push(1);
+            data.extend_from_slice(&(msg.len() as u64).to_be_bytes());
+            data.extend_from_slice(msg);
+        }
+        None => data.push(0),
+    }
+    let extra_in = extra_in.unwrap_or_default();
+    data.extend_from_slice(&(extra_in.len() as u32).to_be_bytes());
+    data.extend_from_slice(extra_in);
+
+    let secp = Secp256k1::signing_only();
+    let mut secnonce = [0u8; 97];
+    let mut pubnonce = [0u8; 66];
+    for i in 0..2 {
+        data.push(i as u8);
+        let k = curve::scalar_reduce(&tagged_hash("MuSig/nonce", &data));
+        data.pop();
+        let k = SecretKey::from_slice(&k).map_err(|_| BitcoinError::CannotSign("nonce is zero"))?;
+        secnonce[32 * i..32 * (i + 1)].copy_from_slice(&k.secret_bytes());
+        pubnonce[33 * i..33 * (i + 1)]
+            .copy_from_slice(&secp256k1::PublicKey::from_secret_key(&secp, &k).serialize());
+    }
+    secnonce[64..].copy_from_slice(&pubkey);
+    Ok((SecNonce(secnonce), PubNonce(pubnonce)))
+}
+
+impl AggNonce {
+    /// Sums the signers' nonces, as BIP327's `NonceAgg`, blaming the first
+    /// signer whose nonce is invalid.
+    pub fn new(pubnonces: &[PubNonce]) -> Result<AggNonce> {
+        let mut aggnonce = [0u8; 66];
+        for j in 0..2 {
+            let mut r = None;
+            for (i, pubnonce) in pubnonces.iter().enumerate() {
+                let point = Point::from_sec(&pubnonce.0[33 * j..33 * (j + 1)]).ok_or(
+                    BitcoinError::InvalidContribution {
+                        signer: Some(i),
+                        reason: "invalid public nonce",
+                    },
+                )?;
+                r = add(r, Some(point));
+            }
+            if let Some(r) = r {
+                aggnonce[33 * j..33 * (j + 1)].copy_from_slice(&r.serialize_compressed());
+            }
+        }
+        Ok(AggNonce(aggnonce))
+    }
+}
+
+/// Everything the signers of one message share once nonces are aggregated.
+#[derive(Debug, Clone)]
+pub struct Session<'a> {
+    key_agg: &'a KeyAggContext,
+    b: [u8; 32],
+    r: Point,
+    e: [u8; 32],
+}
+
+impl<'a> Session<'a> {
+    pub fn new(key_agg: &'a KeyAggContext, aggnonce: &AggNonce, msg: &[u8]) -> Result<Session<'a>> {
+        let invalid = BitcoinError::InvalidContribution {
+            signer: None,
+            reason: "invalid aggregate nonce",
+        };
+        let q = key_agg.q.x_bytes();
+        let b = curve::scalar_reduce(&tagged_hash(
+            "MuSig/noncecoef",
+            &[&aggnonce.0[..], &q, msg].concat(),
+        ));
+        let mut nonces = [None; 2];
+        for (j, nonce) in nonces.iter_mut().enumerate() {
+            let bytes = &aggnonce.0[33 * j..33 * (j + 1)];
+            if bytes.iter().any(|&byte| byte != 0) {
+                *nonce = Some(Point::from_sec(bytes).ok_or(invalid.clone())?);
+            }
+        }
+        let r = add(nonces[0], nonces[1].and_then(|r2| r2.mul(&b))).unwrap_or(Point::GENERATOR);
+        let e = curve::scalar_reduce(&tagged_hash(
+            "BIP0340/challenge",
+            &[&r.x_bytes()[..], &q, msg].concat(),
+        ));
+        Ok(Session { key_agg, b, r, e })
+    }
+
+    /// Signs with `key`, whose nonce `secnonce` is, as BIP327's `Sign`.
+    pub fn partial_sign(&self, secnonce: SecNonce, key: &PrivateKey) -> Result<PartialSignature> {
+        let SecNonce(secnonce) = secnonce;
+        let pubkey = key.public_key();
+        if secnonce[64..] != pubkey.serialize() {
+            return Err(BitcoinError::CannotSign("nonce is for another key"));
+        }
+        let coefficient = self.coefficient(&pubkey)?;
+        let nonce = |i: usize| {
+            let k = SecretKey::from_slice(&secnonce[32 * i..32 * (i + 1)])
+                .map_err(|_| BitcoinError::CannotSign("invalid secret nonce"))?;
+            Ok::<_, BitcoinError>(if self.r.has_odd_y() { k.negate() } else { k })
+        };
+        let (k1, k2) = (nonce(0)?, nonce(1)?);
+        let mut d = SecretKey::from_slice(&key.secret_bytes()).expect("checked when constructed");
+        if self.key_agg.keys_negated() {
+            d = d.negate();
+        }
+
+        // s = k1 + b * k2 + e * a * d
+        let scalar = |bytes| Scalar::from_be_bytes(bytes).expect("below the group order");
+        let ea = curve::scalar_mul(&self.e, &coefficient);
+        let s = k2
+            .mul_tweak(&scalar(self.b))
+            .and_then(|bk2| k1.add_tweak(&bk2.into()))
+            .and_then(|s| {
+                d.mul_tweak(&scalar(ea))
+                    .and_then(|ead| s.add_tweak(&ead.into()))
+            })
+            .map_err(|_| BitcoinError::CannotSign("partial signature is zero"))?;
+        let psig = PartialSignature(s.secret_bytes());
+
+        let secp = Secp256k1::signing_only();
+        let mut pubnonce = [0u8; 66];
+        for (i, chunk) in pubnonce.chunks_exact_mut(33).enumerate() {
+            let k = SecretKey::from_slice(&secnonce[32 * i..32 * (i + 1)]).expect("checked above");
+            chunk.copy_from_slice(&secp256k1::PublicKey::from_secret_key(&secp, &k).serialize());
+        }
+        if !self.verify_partial(&psig, &PubNonce(pubnonce), &pubkey) {
+            return Err(BitcoinError::CannotSign(
+                "partial signature does not verify",
+            ));
+        }
+        Ok(psig)
+    }
+
+    /// Whether `psig` is the partial signature of the signer with
+    /// `pubnonce` and `pubkey`, as BIP327's `PartialSigVerifyInternal`.
+    pub fn verify_partial(
+        &self,
+        psig: &PartialSignature,
+        pubnonce: &PubNonce,
+        pubkey: &PublicKey,
+    ) -> bool {
+        let (Ok(coefficient), Some(r1), Some(r2)) = (
+            self.coefficient(pubkey),
+            Point::from_sec(&pubnonce.0[..33]),
+            Point::from_sec(&pubnonce.0[33..]),
+        ) else {
+            return false;
+        };
+        if psig.0 != [0; 32] && !curve::is_valid_scalar(&psig.0) {
+            return false;
+        }
+        let mut re = add(Some(r1), r2.mul(&self.b));
+        if self.r.has_odd_y() {
+            re = re.map(|re| re.negate());
+        }
+        let mut p = Point::from_sec(&pubkey.serialize()).expect("a valid key");
+        if self.key_agg.keys_negated() {
+            p = p.negate();
+        }
+        let ea = curve::scalar_mul(&self.e, &coefficient);
+        Point::GENERATOR.mul(&psig.0) == add(re, p.mul(&ea))
+    }
+
+    /// Combines every signer's partial signature into a BIP340 signature
+    /// for the (tweaked) aggregate key, as BIP327's `PartialSigAgg`.
+    pub fn aggregate(&self, psigs: &[PartialSignature]) -> Result<[u8; 64]> {
+        let mut s = [0u8; 32];
+        for (i, psig) in psigs.iter().enumerate() {
+            if psig.0 != [0; 32] && !curve::is_valid_scalar(&psig.0) {
+                return Err(BitcoinError::InvalidContribution {
+                    signer: Some(i),
+                    reason: "partial signature out of range",
+                });
+            }
+            s = scalar_add(&s, &psig.0);
+        }
+        let mut tacc = curve::scalar_mul(&self.e, &self.key_agg.tacc);
+        if self.key_agg.q.has_odd_y() {
+            tacc = curve::scalar_negate(&tacc);
+        }
+        let mut signature = [0u8; 64];
+        signature[..32].copy_from_slice(&self.r.x_bytes());
+        signature[32..].copy_from_slice(&scalar_add(&s, &tacc));
+        Ok(signature)
+    }
+
+    fn coefficient(&self, pubkey: &PublicKey) -> Result<[u8; 32]> {
+        let pubkey = pubkey.serialize();
+        if !self.key_agg.pubkeys.contains(&pubkey) {
+            return Err(BitcoinError::CannotSign(
+                "key is not among the aggregated keys",
+            ));
+        }
+        Ok(self.key_agg.coefficient(&pubkey))
+    }
+}
+
+// Point addition with `None` as the point at infinity.
+fn add(a: Option<Point>, b: Option<Point>) -> Option<Point> {
+    match (a, b) {
+        (Some(a), Some(b)) => a.add(&b),
+        (a, None) => a,
+        (None, b) => b,
+    }
+}
+
+fn scalar_add(a: &[u8; 32], b: &[u8; 32]) -> [u8; 32] {
+    curve::scalar_add(a, b).unwrap_or([0; 32])
+}
@@ -0,0 +1,79 @@
+use crate::{BlockHash, BlockHeader, MerkleRoot, Params};
+use core::str::FromStr;
+use serde::{Deserialize, Serialize};
+
+const GENESIS_MERKLE_ROOT: &str =
+    "4a5e1e4baab89f3a32518a88c31bc87f618f76673e2cc77ab2127b7afdeda33b";
+const TESTNET4_GENESIS_MERKLE_ROOT: &str =
+    "7aa0a7ae1e223414cb807e40cd57e667b718e42aaf9306db9102fe28912b7b4e";
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+#[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
+pub enum Network {
+    Mainnet,
+    Testnet,
+    Testnet4,
+    Signet,
+    Regtest,
+}
+
+impl Network {
+    /// Message start bytes prefixing every P2P message, in wire order.
+    pub fn magic(self) -> [u8; 4] {
+        match self {
+            Network::Mainnet => [0xf9, 0xbe, 0xb4, 0xd9],
+            Network::Testnet => [0x0b, 0x11, 0x09, 0x07],
+            Network::Testnet4 => [0x1c, 0x16, 0x3f, 0x28],
+            Network::Signet => [0x0a, 0x03, 0xcf, 0x40],
+            Network::Regtest => [0xfa, 0xbf, 0xb5, 0xda],
+        }
+    }
+
+    pub fn default_port(self) -> u16 {
+        match self {
+            Network::Mainnet => 8333,
+            Network::Testnet => 18333,
+            Network::Testnet4 => 48333,
+            Network::Signet => 38333,
+            Network::Regtest => 18444,
+        }
+    }
+
+    pub fn params(self) -> Params {
+        match self {
+            Network::Mainnet => Params::MAINNET,
+            Network::Testnet => Params::TESTNET,
+            Network::Testnet4 => Params::TESTNET4,
+            Network::Signet => Params::SIGNET,
+            Network::Regtest => Params::REGTEST,
+        }
+    }
+
+    pub fn genesis_header(self) -> BlockHeader {
+        let (merkle_root, time, bits, nonce) = match self {
+            Network::Mainnet => (GENESIS_MERKLE_ROOT, 1231006505, 0x1d00ffff, 2083236893),
+            Network::Testnet => (GENESIS_MERKLE_ROOT, 1296688602, 0x1d00ffff, 414098458),
+            Network::Testnet4 => (
+                TESTNET4_GENESIS_MERKLE_ROOT,
+                1714777860,
+                0x1d00ffff,
+                393743547,
+            ),
+            Network::Signet => (GENESIS_MERKLE_ROOT, 1598918400, 0x1e0377ae, 52613770),
+            Network::Regtest => (GENESIS_MERKLE_ROOT, 1296688602, 0x207fffff, 2),
+        };
+        BlockHeader {
+            version: 1,
+            prev_blockhash: BlockHash([0; 32]),
+            merkle_root: MerkleRoot::from_str(merkle_root).expect("valid constant"),
+            time,
+            bits,
+            nonce,
+        }
+    }
+
+    pub fn genesis_hash(self) -> BlockHash {
+        self.genesis_header().block_hash()
+    }
+}
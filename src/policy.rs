@@ -0,0 +1,256 @@
+use crate::ecdsa::EcdsaSignature;
+use crate::script::interpreter::is_valid_signature_encoding;
+use crate::script::{Instruction, MAX_SCRIPT_SIZE};
+use crate::{
+    BitcoinError, BitcoinTransaction, ConsensusEncode, Opcode, Result, ScriptType,
+    TransactionOutput, WITNESS_SCALE_FACTOR,
+};
+use alloc::vec::Vec;
+use core::fmt;
+
+pub const TX_MIN_STANDARD_VERSION: u32 = 1;
+pub const TX_MAX_STANDARD_VERSION: u32 = 3;
+pub const MAX_STANDARD_TX_WEIGHT: usize = 400_000;
+/// Large enough for a 15-of-15 P2SH multisig spend with uncompressed keys.
+pub const MAX_STANDARD_SCRIPTSIG_SIZE: usize = 1650;
+/// The largest relayed `OP_RETURN` scriptPubKey: 80 bytes of data plus the
+/// opcode and push overhead.
+pub const MAX_OP_RETURN_RELAY: usize = 83;
+/// Core's `-dustrelayfee` default, in satoshis per 1000 virtual bytes.
+pub const DUST_RELAY_TX_FEE: u64 = 3000;
+
+/// The relay policy knobs Core exposes as command-line options.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PolicyOptions {
+    /// Maximum `OP_RETURN` scriptPubKey size, or `None` to reject data
+    /// carrier outputs entirely (`-datacarrier=0`).
+    pub max_datacarrier_bytes: Option<usize>,
+    pub permit_bare_multisig: bool,
+    /// Satoshis per 1000 virtual bytes.
+    pub dust_relay_fee: u64,
+}
+
+impl Default for PolicyOptions {
+    fn default() -> Self {
+        Self {
+            max_datacarrier_bytes: Some(MAX_OP_RETURN_RELAY),
+            permit_bare_multisig: true,
+            dust_relay_fee: DUST_RELAY_TX_FEE,
+        }
+    }
+}
+
+/// Why a transaction fails `IsStandardTx`. Input and output indices locate
+/// the offending script.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum NonStandardReason {
+    Version(u32),
+    TxSize(usize),
+    ScriptSigSize(usize),
+    ScriptSigNotPushOnly(usize),
+    ScriptPubKey(usize),
+    DataCarrierSize(usize),
+    BareMultisig(usize),
+    Dust(usize),
+    MultiOpReturn,
+}
+
+impl NonStandardReason {
+    /// Core's reject reason, as reported by `testmempoolaccept`.
+    pub fn reject_reason(&self) -> &'static str {
+        match self {
+            NonStandardReason::Version(_) => "version",
+            NonStandardReason::TxSize(_) => "tx-size",
+            NonStandardReason::ScriptSigSize(_) => "scriptsig-size",
+            NonStandardReason::ScriptSigNotPushOnly(_) => "scriptsig-not-pushonly",
+            NonStandardReason::ScriptPubKey(_) | NonStandardReason::DataCarrierSize(_) => {
+                "scriptpubkey"
+            }
+            NonStandardReason::BareMultisig(_) => "bare-multisig",
+            NonStandardReason::Dust(_) => "dust",
+            NonStandardReason::MultiOpReturn => "multi-op-return",
+        }
+    }
+}
+
+impl fmt::Display for NonStandardReason {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            NonStandardReason::Version(version) => write!(f, "version {}", version),
+            NonStandardReason::TxSize(weight) => write!(f, "weight {}", weight),
+            NonStandardReason::ScriptSigSize(i) | NonStandardReason::ScriptSigNotPushOnly(i) => {
+                write!(f, "{} at input {}", self.reject_reason(), i)
+            }
+            NonStandardReason::ScriptPubKey(i)
+            | NonStandardReason::DataCarrierSize(i)
+            | NonStandardReason::BareMultisig(i)
+            | NonStandardReason::Dust(i) => {
+                write!(f, "{} at output {}", self.reject_reason(), i)
+            }
+            NonStandardReason::MultiOpReturn => f.write_str(self.reject_reason()),
+        }
+    }
+}
+
+impl From<NonStandardReason> for BitcoinError {
+    fn from(reason: NonStandardReason) -> Self {
+        BitcoinError::NonStandard(reason)
+    }
+}
+
+impl TransactionOutput {
+    /// The smallest value Core relays for this output, as `GetDustThreshold`:
+    /// the fee at `dust_relay_fee` (satoshis per 1000 virtual bytes) for the
+    /// output plus a typical input spending it. Unspendable outputs have no
+    /// threshold.
+    pub fn dust_threshold(&self, dust_relay_fee: u64) -> u64 {
+        let script = &self.script_pubkey;
+        if script.bytes.first() == Some(&Opcode::OP_RETURN.to_u8())
+            || script.bytes.len() > MAX_SCRIPT_SIZE
+        {
+            return 0;
+        }
+        // Outpoint, scriptSig length and sequence, plus a P2PKH-sized
+        // signature and key, discounted when they go in the witness.
+        let spend_size = if script.is_witness_program() {
+            32 + 4 + 1 + 107 / WITNESS_SCALE_FACTOR + 4
+        } else {
+            32 + 4 + 1 + 107 + 4
+        };
+        let size = (self.serialized_size() + spend_size) as u64;
+        (dust_relay_fee * size).div_ceil(1000)
+    }
+
+    pub fn is_dust(&self, dust_relay_fee: u64) -> bool {
+        self.value < self.dust_threshold(dust_relay_fee)
+    }
+}
+
+/// Checks `tx` against Core's `IsStandardTx` relay policy, reporting the
+/// first rule it breaks.
+pub fn check_standard_tx(tx: &BitcoinTransaction, options: &PolicyOptions) -> Result<()> {
+    if !(TX_MIN_STANDARD_VERSION..=TX_MAX_STANDARD_VERSION).contains(&tx.version) {
+        return Err(NonStandardReason::Version(tx.version).into());
+    }
+    let weight = tx.weight();
+    if weight > MAX_STANDARD_TX_WEIGHT {
+        return Err(NonStandardReason::TxSize(weight).into());
+    }
+
+    for (i, input) in tx.inputs.iter().enumerate() {
+        if input.script_sig.bytes.len() > MAX_STANDARD_SCRIPTSIG_SIZE {
+            return Err(NonStandardReason::ScriptSigSize(i).into());
+        }
+        if !input.script_sig.is_push_only() {
+            return Err(NonStandardReason::ScriptSigNotPushOnly(i).into());
+        }
+    }
+
+    let mut data_outputs = 0;
+    for (i, output) in tx.outputs.iter().enumerate() {
+        let script = &output.script_pubkey;
+        match script.classify() {
+            ScriptType::NonStandard => return Err(NonStandardReason::ScriptPubKey(i).into()),
+            ScriptType::OpReturn => {
+                if options
+                    .max_datacarrier_bytes
+                    .is_none_or(|max| script.bytes.len() > max)
+                {
+                    return Err(NonStandardReason::DataCarrierSize(i).into());
+                }
+                data_outputs += 1;
+            }
+            ScriptType::Multisig => {
+                let (_, total) = script.multisig_params().unwrap();
+                if total > 3 {
+                    return Err(NonStandardReason::ScriptPubKey(i).into());
+                }
+                if !options.permit_bare_multisig {
+                    return Err(NonStandardReason::BareMultisig(i).into());
+                }
+            }
+            _ => {}
+        }
+        if output.is_dust(options.dust_relay_fee) {
+            return Err(NonStandardReason::Dust(i).into());
+        }
+    }
+    if data_outputs > 1 {
+        return Err(NonStandardReason::MultiOpReturn.into());
+    }
+    Ok(())
+}
+
+/// A way a third party could change an input's scriptSig, and with it the
+/// txid, without invalidating the transaction. Each locates the input.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Malleability {
+    ScriptSigNotPushOnly(usize),
+    NonMinimalPush(usize),
+    /// A signature that is not BIP66 DER.
+    NonDerSignature(usize),
+    /// A signature whose S could be swapped for `n - S`.
+    HighS(usize),
+}
+
+impl fmt::Display for Malleability {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Malleability::ScriptSigNotPushOnly(i) => {
+                write!(f, "scriptSig of input {} is not push-only", i)
+            }
+            Malleability::NonMinimalPush(i) => write!(f, "non-minimal push at input {}", i),
+            Malleability::NonDerSignature(i) => write!(f, "non-DER signature at input {}", i),
+            Malleability::HighS(i) => write!(f, "high-S signature at input {}", i),
+        }
+    }
+}
+
+/// The scriptSig malleability vectors in `tx`, in input order. Witnesses
+/// are left out since they don't affect the txid, as are rules such as
+/// CLEANSTACK and NULLDUMMY that need the spent script.
+pub fn malleability_vectors(tx: &BitcoinTransaction) -> Vec<Malleability> {
+    let mut found = Vec::new();
+    for (i, input) in tx.inputs.iter().enumerate() {
+        let script = &input.script_sig;
+        if !script.is_push_only() {
+            found.push(Malleability::ScriptSigNotPushOnly(i));
+            continue;
+        }
+        if script.check_minimal_pushes().is_err() {
+            found.push(Malleability::NonMinimalPush(i));
+        }
+        let (mut non_der, mut high_s) = (false, false);
+        for instruction in script.instructions() {
+            let Ok(Instruction::PushBytes(data)) = instruction else {
+                continue;
+            };
+            // Anything that parses as a signature is treated as one.
+            let Ok(sig) = EcdsaSignature::from_slice(data, false) else {
+                continue;
+            };
+            non_der |= !is_valid_signature_encoding(data);
+            high_s |= !sig.is_low_s();
+        }
+        if non_der {
+            found.push(Malleability::NonDerSignature(i));
+        }
+        if high_s {
+            found.push(Malleability::HighS(i));
+        }
+    }
+    found
+}
+
+impl BitcoinTransaction {
+    /// Whether Core would relay this transaction under its default policy.
+    pub fn is_standard(&self) -> bool {
+        check_standard_tx(self, &PolicyOptions::default()).is_ok()
+    }
+
+    /// Whether a third party could change this transaction's txid, so it
+    /// should not be tracked by txid until confirmed.
+    pub fn has_malleable_txid(&self) -> bool {
+        !malleability_vectors(self).is_empty()
+    }
+}
@@ -0,0 +1,341 @@
+use crate::{BitcoinError, BlockHash, BlockHeader, Result, fmt_hex};
+use core::cmp::Ordering;
+use core::fmt;
+use serde::{Deserialize, Serialize};
+
+/// The packed `nBits` encoding of a target: an 8-bit exponent and a
+/// 23-bit mantissa with a sign bit.
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Hash, Serialize, Deserialize)]
+#[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
+pub struct CompactTarget(pub u32);
+
+impl CompactTarget {
+    /// Expands to the full target, rejecting negative, zero and overflowing
+    /// encodings the way Core's `CheckProofOfWork` does. The network's
+    /// `pow_limit` is checked by [`BlockHeader::validate_pow`].
+    pub fn to_target(self) -> Result<Target> {
+        let bits = self.0;
+        let exponent = (bits >> 24) as usize;
+        let mantissa = bits & 0x007f_ffff;
+        let negative = mantissa != 0 && bits & 0x0080_0000 != 0;
+        let overflow = mantissa != 0
+            && (exponent > 34
+                || (mantissa > 0xff && exponent > 33)
+                || (mantissa > 0xffff && exponent > 32));
+        if negative || overflow || mantissa == 0 {
+            return Err(BitcoinError::InvalidTarget { bits });
+        }
+
+        let mut be = [0u8; 32];
+        if exponent <= 3 {
+            let value = mantissa >> (8 * (3 - exponent));
+            if value == 0 {
+                return Err(BitcoinError::InvalidTarget { bits });
+            }
+            be[28..].copy_from_slice(&value.to_be_bytes());
+        } else {
+            for (i, byte) in mantissa.to_le_bytes()[..3].iter().enumerate() {
+                let pos = exponent - 3 + i;
+                if pos < 32 {
+                    be[31 - pos] = *byte;
+                }
+            }
+        }
+        Ok(Target(be))
+    }
+}
+
+/// A 256-bit proof-of-work target, stored big-endian so that the derived
+/// ordering is numeric.
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Clone, Copy, Hash)]
+#[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
+pub struct Target(pub [u8; 32]);
+
+impl Target {
+    /// Packs the target into `nBits`, dropping precision below the top
+    /// three significant bytes as Core's `GetCompact` does.
+    pub fn to_compact_lossy(&self) -> CompactTarget {
+        let Some(first) = self.0.iter().position(|&b| b != 0) else {
+            return CompactTarget(0);
+        };
+        let mut size = (32 - first) as u32;
+        let byte = |i: usize| self.0.get(i).copied().unwrap_or(0) as u32;
+        let mut mantissa = byte(first) << 16 | byte(first + 1) << 8 | byte(first + 2);
+        // The top mantissa bit is the sign, so shift it out of the way.
+        if mantissa & 0x0080_0000 != 0 {
+            mantissa >>= 8;
+            size += 1;
+        }
+        CompactTarget(size << 24 | mantissa)
+    }
+
+    /// The expected number of hashes to meet this target, `2^256 / (target + 1)`.
+    pub fn to_work(&self) -> U256 {
+        let target = U256::from_be_bytes(self.0);
+        if target == U256::MAX {
+            return U256::ONE;
+        }
+        // 2^256 itself doesn't fit, so use (2^256 - target - 1) / (target + 1) + 1.
+        let (quotient, _) = (!target).div_rem(target.wrapping_add(U256::ONE));
+        quotient.wrapping_add(U256::ONE)
+    }
+
+    /// Whether `hash`, read as a little-endian number, is at or below the target.
+    pub fn is_met_by(&self, hash: &BlockHash) -> bool {
+        let mut be = hash.0;
+        be.reverse();
+        be <= self.0
+    }
+}
+
+/// Consensus parameters governing difficulty retargeting.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct Params {
+    pub pow_limit: Target,
+    pub pow_target_spacing: u32,
+    pub pow_target_timespan: u32,
+    pub allow_min_difficulty_blocks: bool,
+    pub no_pow_retargeting: bool,
+    /// BIP94: retarget from the first block of the period, not the last.
+    pub enforce_bip94: bool,
+}
+
+impl Params {
+    pub const MAINNET: Params = Params {
+        pow_limit: pow_limit(&[0, 0, 0, 0], 0xff),
+        pow_target_spacing: 10 * 60,
+        pow_target_timespan: 14 * 24 * 60 * 60,
+        allow_min_difficulty_blocks: false,
+        no_pow_retargeting: false,
+        enforce_bip94: false,
+    };
+
+    pub const TESTNET: Params = Params {
+        allow_min_difficulty_blocks: true,
+        ..Params::MAINNET
+    };
+
+    pub const TESTNET4: Params = Params {
+        allow_min_difficulty_blocks: true,
+        enforce_bip94: true,
+        ..Params::MAINNET
+    };
+
+    pub const SIGNET: Params = Params {
+        pow_limit: pow_limit(&[0x00, 0x00, 0x03, 0x77, 0xae], 0x00),
+        ..Params::MAINNET
+    };
+
+    pub const REGTEST: Params = Params {
+        pow_limit: pow_limit(&[0x7f], 0xff),
+        allow_min_difficulty_blocks: true,
+        no_pow_retargeting: true,
+        ..Params::MAINNET
+    };
+
+    /// Number of blocks between retargets, 2016 on every public network.
+    pub fn difficulty_adjustment_interval(&self) -> u32 {
+        self.pow_target_timespan / self.pow_target_spacing
+    }
+}
+
+const fn pow_limit(prefix: &[u8], fill: u8) -> Target {
+    let mut bytes = [fill; 32];
+    let mut i = 0;
+    while i < prefix.len() {
+        bytes[i] = prefix[i];
+        i += 1;
+    }
+    Target(bytes)
+}
+
+/// Computes the `nBits` for the block after `last`, which closes the retarget
+/// period that began with `first`.
+pub fn calculate_next_work_required(
+    first: &BlockHeader,
+    last: &BlockHeader,
+    params: &Params,
+) -> Result<CompactTarget> {
+    if params.no_pow_retargeting {
+        return Ok(CompactTarget(last.bits));
+    }
+    let timespan = params.pow_target_timespan as i64;
+    let actual = (last.time as i64 - first.time as i64).clamp(timespan / 4, timespan * 4);
+
+    let base = if params.enforce_bip94 { first } else { last };
+    let target = U256::from_be_bytes(base.target()?.0);
+    let (retargeted, _) = target
+        .wrapping_mul_u64(actual as u64)
+        .div_rem(U256::from_u64(timespan as u64));
+    let limit = U256::from_be_bytes(params.pow_limit.0);
+    Ok(Target(retargeted.min(limit).to_be_bytes()).to_compact_lossy())
+}
+
+/// Testnet's escape hatch: a block timestamped more than twice the target
+/// spacing after `last` may be mined at the minimum difficulty. Otherwise
+/// such networks expect the bits of the last block not mined at minimum
+/// difficulty, which the caller has to look up.
+pub fn min_difficulty_bits(
+    last: &BlockHeader,
+    new_time: u32,
+    params: &Params,
+) -> Option<CompactTarget> {
+    let late = new_time as u64 > last.time as u64 + 2 * params.pow_target_spacing as u64;
+    (params.allow_min_difficulty_blocks && late).then(|| params.pow_limit.to_compact_lossy())
+}
+
+impl BlockHeader {
+    pub fn target(&self) -> Result<Target> {
+        CompactTarget(self.bits).to_target()
+    }
+
+    pub fn work(&self) -> Result<U256> {
+        Ok(self.target()?.to_work())
+    }
+
+    /// Core's `CheckProofOfWork`: the target must be well formed and no
+    /// easier than `params.pow_limit`, and the header hash must meet it.
+    pub fn validate_pow(&self, params: &Params) -> Result<BlockHash> {
+        let target = self.target()?;
+        if target > params.pow_limit {
+            return Err(BitcoinError::InvalidTarget { bits: self.bits });
+        }
+        let hash = self.block_hash();
+        if !target.is_met_by(&hash) {
+            return Err(BitcoinError::InsufficientProofOfWork);
+        }
+        Ok(hash)
+    }
+}
+
+/// Minimal unsigned 256-bit integer for chainwork accounting.
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Hash, Default)]
+pub struct U256([u64; 4]);
+
+impl U256 {
+    pub const ZERO: U256 = U256([0; 4]);
+    pub const ONE: U256 = U256([1, 0, 0, 0]);
+    pub const MAX: U256 = U256([u64::MAX; 4]);
+
+    pub fn from_u64(value: u64) -> Self {
+        U256([value, 0, 0, 0])
+    }
+
+    pub fn from_be_bytes(bytes: [u8; 32]) -> Self {
+        let mut limbs = [0u64; 4];
+        for (i, chunk) in bytes.chunks_exact(8).enumerate() {
+            limbs[3 - i] = u64::from_be_bytes(chunk.try_into().unwrap());
+        }
+        U256(limbs)
+    }
+
+    pub fn to_be_bytes(&self) -> [u8; 32] {
+        let mut bytes = [0u8; 32];
+        for (i, chunk) in bytes.chunks_exact_mut(8).enumerate() {
+            chunk.copy_from_slice(&self.0[3 - i].to_be_bytes());
+        }
+        bytes
+    }
+
+    pub fn checked_add(self, rhs: U256) -> Option<U256> {
+        let (sum, carry) = self.overflowing_add(rhs);
+        (!carry).then_some(sum)
+    }
+
+    pub fn saturating_add(self, rhs: U256) -> U256 {
+        self.checked_add(rhs).unwrap_or(U256::MAX)
+    }
+
+    fn wrapping_add(self, rhs: U256) -> U256 {
+        self.overflowing_add(rhs).0
+    }
+
+    fn overflowing_add(self, rhs: U256) -> (U256, bool) {
+        let mut out = [0u64; 4];
+        let mut carry = false;
+        for (i, limb) in out.iter_mut().enumerate() {
+            let (sum, c1) = self.0[i].overflowing_add(rhs.0[i]);
+            let (sum, c2) = sum.overflowing_add(carry as u64);
+            *limb = sum;
+            carry = c1 || c2;
+        }
+        (U256(out), carry)
+    }
+
+    fn wrapping_sub(self, rhs: U256) -> U256 {
+        let mut out = [0u64; 4];
+        let mut borrow = false;
+        for (i, limb) in out.iter_mut().enumerate() {
+            let (diff, b1) = self.0[i].overflowing_sub(rhs.0[i]);
+            let (diff, b2) = diff.overflowing_sub(borrow as u64);
+            *limb = diff;
+            borrow = b1 || b2;
+        }
+        U256(out)
+    }
+
+    fn wrapping_mul_u64(self, rhs: u64) -> U256 {
+        let mut out = [0u64; 4];
+        let mut carry = 0u128;
+        for (i, limb) in out.iter_mut().enumerate() {
+            let product = self.0[i] as u128 * rhs as u128 + carry;
+            *limb = product as u64;
+            carry = product >> 64;
+        }
+        U256(out)
+    }
+
+    fn bit(&self, index: usize) -> bool {
+        self.0[index / 64] >> (index % 64) & 1 == 1
+    }
+
+    fn shl1(self) -> U256 {
+        let mut out = [0u64; 4];
+        for (i, limb) in out.iter_mut().enumerate() {
+            *limb = self.0[i] << 1 | if i > 0 { self.0[i - 1] >> 63 } else { 0 };
+        }
+        U256(out)
+    }
+
+    /// Schoolbook binary long division. Panics if `divisor` is zero.
+    pub fn div_rem(self, divisor: U256) -> (U256, U256) {
+        assert!(divisor != U256::ZERO, "division by zero");
+        let mut quotient = U256::ZERO;
+        let mut remainder = U256::ZERO;
+        for i in (0..256).rev() {
+            remainder = remainder.shl1();
+            remainder.0[0] |= self.bit(i) as u64;
+            if remainder >= divisor {
+                remainder = remainder.wrapping_sub(divisor);
+                quotient.0[i / 64] |= 1 << (i % 64);
+            }
+        }
+        (quotient, remainder)
+    }
+}
+
+impl core::ops::Not for U256 {
+    type Output = U256;
+
+    fn not(self) -> U256 {
+        U256(self.0.map(|limb| !limb))
+    }
+}
+
+impl Ord for U256 {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.0.iter().rev().cmp(other.0.iter().rev())
+    }
+}
+
+impl PartialOrd for U256 {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl fmt::LowerHex for U256 {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt_hex(f, &self.to_be_bytes(), false)
+    }
+}
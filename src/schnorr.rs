@@ -0,0 +1,79 @@
+//! BIP340 Schnorr signatures, as used by taproot spends. Signing and
+//! verification need the `secp256k1` feature.
+
+use crate::{BitcoinError, Result};
+use alloc::vec::Vec;
+#[cfg(feature = "secp256k1")]
+use {
+    crate::{PrivateKey, XOnlyPublicKey},
+    secp256k1::{Keypair, Message, Secp256k1, schnorr},
+};
+
+/// The taproot-only sighash type that signs everything and is left out of
+/// the serialized signature.
+pub const SIGHASH_DEFAULT: u8 = 0x00;
+
+/// A Schnorr signature as it appears in a taproot witness.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct SchnorrSignature {
+    pub signature: [u8; 64],
+    pub sighash_type: u8,
+}
+
+impl SchnorrSignature {
+    /// Parses 64 bytes, implying [`SIGHASH_DEFAULT`], or 65 with an
+    /// explicit sighash type, which BIP341 forbids from being the default.
+    pub fn from_slice(bytes: &[u8]) -> Result<SchnorrSignature> {
+        let (signature, sighash_type) = match bytes.len() {
+            64 => (bytes, SIGHASH_DEFAULT),
+            65 if bytes[64] != SIGHASH_DEFAULT => (&bytes[..64], bytes[64]),
+            65 => {
+                return Err(BitcoinError::InvalidSignature(
+                    "explicit SIGHASH_DEFAULT byte",
+                ));
+            }
+            _ => {
+                return Err(BitcoinError::InvalidSignature(
+                    "Schnorr signatures are 64 or 65 bytes",
+                ));
+            }
+        };
+        Ok(SchnorrSignature {
+            signature: signature.try_into().unwrap(),
+            sighash_type,
+        })
+    }
+
+    pub fn to_vec(&self) -> Vec<u8> {
+        let mut bytes = self.signature.to_vec();
+        if self.sighash_type != SIGHASH_DEFAULT {
+            bytes.push(self.sighash_type);
+        }
+        bytes
+    }
+}
+
+/// Signs `msg` with fresh auxiliary randomness `aux_rand`, as BIP340
+/// recommends. For a key-path spend, `key` must already be tweaked with
+/// [`PrivateKey::tap_tweak`].
+#[cfg(feature = "secp256k1")]
+pub fn sign(key: &PrivateKey, msg: &[u8; 32], aux_rand: &[u8; 32]) -> [u8; 64] {
+    let secp = Secp256k1::signing_only();
+    let keypair =
+        Keypair::from_seckey_slice(&secp, &key.secret_bytes()).expect("checked when constructed");
+    secp.sign_schnorr_with_aux_rand(&Message::from_digest(*msg), &keypair, aux_rand)
+        .serialize()
+}
+
+#[cfg(feature = "secp256k1")]
+pub fn verify(pubkey: &XOnlyPublicKey, msg: &[u8; 32], sig: &[u8; 64]) -> bool {
+    let (Ok(sig), Ok(pubkey)) = (
+        schnorr::Signature::from_slice(sig),
+        secp256k1::XOnlyPublicKey::from_slice(&pubkey.serialize()),
+    ) else {
+        return false;
+    };
+    Secp256k1::verification_only()
+        .verify_schnorr(&sig, &Message::from_digest(*msg), &pubkey)
+        .is_ok()
+}
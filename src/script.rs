@@ -0,0 +1,24 @@
+mod asm;
+mod builder;
+mod classify;
+mod htlc;
+mod instruction;
+pub mod interpreter;
+mod limits;
+mod num;
+mod opcodes;
+mod push;
+mod sigops;
+mod templates;
+mod timelock;
+
+pub use builder::ScriptBuilder;
+pub use classify::ScriptType;
+pub use htlc::{OfferedHtlc, ReceivedHtlc};
+pub use instruction::{Instruction, Instructions};
+pub use interpreter::{
+    MAX_OPS_PER_SCRIPT, MAX_SCRIPT_ELEMENT_SIZE, MAX_SCRIPT_SIZE, MAX_STACK_SIZE, ScriptLimits,
+};
+pub use num::{decode_script_num, encode_script_num};
+pub use opcodes::Opcode;
+pub use sigops::MAX_PUBKEYS_PER_MULTISIG;
@@ -0,0 +1,99 @@
+use super::{Instruction, Opcode, ScriptBuilder, decode_script_num};
+use crate::{BitcoinError, Result, Script};
+use alloc::string::{String, ToString};
+use core::fmt::{self, Write};
+
+impl Script {
+    /// Parses whitespace-separated ASM: opcode names (the `OP_` prefix is
+    /// optional), decimal numbers without leading zeros up to `0xffffffff`
+    /// in magnitude, hex data pushes, and `0x`-prefixed hex inserted into
+    /// the script verbatim.
+    pub fn from_asm(asm: &str) -> Result<Script> {
+        let mut builder = ScriptBuilder::new();
+        for token in asm.split_whitespace() {
+            let invalid = || BitcoinError::InvalidAsm(token.to_string());
+            if let Some(raw) = token.strip_prefix("0x") {
+                builder = builder.push_raw(&hex::decode(raw).map_err(|_| invalid())?);
+            } else if let Some(n) = parse_number(token) {
+                builder = builder.push_int(n);
+            } else if let Some(op) = Opcode::from_name(token) {
+                builder = builder.push_opcode(op);
+            } else if let Ok(data) = hex::decode(token) {
+                builder = builder.push_slice(&data);
+            } else {
+                return Err(invalid());
+            }
+        }
+        Ok(builder.into_script())
+    }
+
+    /// Renders the script the way Core's `decodescript` shows `asm`: pushes
+    /// of up to four bytes as numbers, longer ones as hex.
+    pub fn asm(&self) -> String {
+        let mut out = String::new();
+        write_asm(&mut out, self).expect("writing to a String cannot fail");
+        out
+    }
+}
+
+fn write_asm(f: &mut dyn Write, script: &Script) -> fmt::Result {
+    for (i, instruction) in script.instructions().enumerate() {
+        if i > 0 {
+            f.write_char(' ')?;
+        }
+        match instruction {
+            Ok(Instruction::PushBytes(data)) if data.len() <= 4 => {
+                write!(f, "{}", decode_script_num(data))?
+            }
+            Ok(Instruction::PushBytes(data)) => {
+                for byte in data {
+                    write!(f, "{:02x}", byte)?;
+                }
+            }
+            Ok(Instruction::Op(op)) => f.write_str(asm_name(op))?,
+            Err(_) => return f.write_str("[error]"),
+        }
+    }
+    Ok(())
+}
+
+// Only canonical decimals as `asm()` writes them count as numbers, so hex
+// pushes made of decimal digits, like `0000000001`, stay data.
+fn parse_number(token: &str) -> Option<i64> {
+    let digits = token.strip_prefix('-').unwrap_or(token);
+    if digits.is_empty() || digits.len() > 10 || !digits.bytes().all(|b| b.is_ascii_digit()) {
+        return None;
+    }
+    if digits.len() > 1 && digits.starts_with('0') {
+        return None;
+    }
+    token
+        .parse::<i64>()
+        .ok()
+        .filter(|n| n.unsigned_abs() <= u32::MAX as u64)
+}
+
+/// Core's `GetOpName`: small integers print as numbers, unassigned
+/// opcodes as `OP_UNKNOWN`.
+fn asm_name(op: Opcode) -> &'static str {
+    const SMALL: [&str; 16] = [
+        "1", "2", "3", "4", "5", "6", "7", "8", "9", "10", "11", "12", "13", "14", "15", "16",
+    ];
+    match op.small_int() {
+        Some(-1) => "-1",
+        Some(n @ 1..=16) => SMALL[n as usize - 1],
+        _ if (0xbb..=0xfe).contains(&op.to_u8()) => "OP_UNKNOWN",
+        _ => op.name(),
+    }
+}
+
+impl fmt::Display for Script {
+    /// Hex by default; the alternate form `{:#}` prints ASM.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if f.alternate() {
+            write_asm(f, self)
+        } else {
+            fmt::LowerHex::fmt(self, f)
+        }
+    }
+}
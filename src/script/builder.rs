@@ -0,0 +1,75 @@
+use super::{Opcode, encode_script_num};
+use crate::Script;
+use alloc::vec::Vec;
+
+/// Assembles a script, always choosing the minimal encoding for pushes.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ScriptBuilder {
+    bytes: Vec<u8>,
+}
+
+impl ScriptBuilder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn push_opcode(mut self, op: Opcode) -> Self {
+        self.bytes.push(op.to_u8());
+        self
+    }
+
+    /// Pushes `data`, using `OP_0`, `OP_1NEGATE` or `OP_1`..`OP_16` where a
+    /// single opcode represents it, as BIP62 requires.
+    pub fn push_slice(mut self, data: &[u8]) -> Self {
+        let op = minimal_push_opcode(data);
+        self.bytes.push(op.to_u8());
+        let len = data.len();
+        match op {
+            Opcode::OP_PUSHDATA1 => self.bytes.push(len as u8),
+            Opcode::OP_PUSHDATA2 => self.bytes.extend_from_slice(&(len as u16).to_le_bytes()),
+            Opcode::OP_PUSHDATA4 => self.bytes.extend_from_slice(&(len as u32).to_le_bytes()),
+            op if op.is_push_data() => {}
+            _ => return self,
+        }
+        self.bytes.extend_from_slice(data);
+        self
+    }
+
+    /// Appends bytes without a push opcode.
+    pub fn push_raw(mut self, bytes: &[u8]) -> Self {
+        self.bytes.extend_from_slice(bytes);
+        self
+    }
+
+    pub fn push_int(self, n: i64) -> Self {
+        match Opcode::from_small_int(n.clamp(-2, 17) as i32) {
+            Some(op) => self.push_opcode(op),
+            None => self.push_slice(&encode_script_num(n)),
+        }
+    }
+
+    pub fn into_script(self) -> Script {
+        Script::new(self.bytes)
+    }
+}
+
+/// The opcode BIP62 requires for pushing `data`.
+pub(crate) fn minimal_push_opcode(data: &[u8]) -> Opcode {
+    match data {
+        [] => Opcode::OP_0,
+        [n @ 1..=16] => Opcode::from_small_int(*n as i32).unwrap(),
+        [0x81] => Opcode::OP_1NEGATE,
+        _ if data.len() <= Opcode::OP_PUSHBYTES_75.to_u8() as usize => {
+            Opcode::from(data.len() as u8)
+        }
+        _ if data.len() <= 0xff => Opcode::OP_PUSHDATA1,
+        _ if data.len() <= 0xffff => Opcode::OP_PUSHDATA2,
+        _ => Opcode::OP_PUSHDATA4,
+    }
+}
+
+impl Script {
+    pub fn builder() -> ScriptBuilder {
+        ScriptBuilder::new()
+    }
+}
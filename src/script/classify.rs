@@ -0,0 +1,154 @@
+use super::{Instruction, Opcode};
+use crate::{Result, Script};
+use alloc::vec::Vec;
+
+/// Output script templates recognised by Core's `Solver`.
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Hash)]
+pub enum ScriptType {
+    P2pk,
+    P2pkh,
+    P2sh,
+    P2wpkh,
+    P2wsh,
+    P2tr,
+    /// A witness program of a version or length with no defined meaning yet.
+    WitnessUnknown,
+    OpReturn,
+    Multisig,
+    NonStandard,
+}
+
+impl Script {
+    pub fn classify(&self) -> ScriptType {
+        if self.is_p2sh() {
+            return ScriptType::P2sh;
+        }
+        if let Some((version, program)) = self.witness_program() {
+            return match (version, program.len()) {
+                (0, 20) => ScriptType::P2wpkh,
+                (0, 32) => ScriptType::P2wsh,
+                (0, _) => ScriptType::NonStandard,
+                (1, 32) => ScriptType::P2tr,
+                _ => ScriptType::WitnessUnknown,
+            };
+        }
+        if self.is_op_return() {
+            ScriptType::OpReturn
+        } else if self.is_p2pk() {
+            ScriptType::P2pk
+        } else if self.is_p2pkh() {
+            ScriptType::P2pkh
+        } else if self.is_multisig() {
+            ScriptType::Multisig
+        } else {
+            ScriptType::NonStandard
+        }
+    }
+
+    /// The witness version and program if this is a segwit output: a
+    /// version opcode followed by a single 2 to 40 byte direct push.
+    pub fn witness_program(&self) -> Option<(u8, &[u8])> {
+        let (&version, rest) = self.bytes.split_first()?;
+        let (&len, program) = rest.split_first()?;
+        let version = match Opcode::from(version) {
+            Opcode::OP_0 => 0,
+            op => op.small_int().filter(|n| *n > 0)? as u8,
+        };
+        ((2..=40).contains(&len) && program.len() == len as usize).then_some((version, program))
+    }
+
+    pub fn is_witness_program(&self) -> bool {
+        self.witness_program().is_some()
+    }
+
+    pub fn is_p2pk(&self) -> bool {
+        match self.bytes.as_slice() {
+            [0x21, key @ .., 0xac] if key.len() == 33 => matches!(key[0], 0x02 | 0x03),
+            [0x41, key @ .., 0xac] if key.len() == 65 => matches!(key[0], 0x04 | 0x06 | 0x07),
+            _ => false,
+        }
+    }
+
+    pub fn is_p2pkh(&self) -> bool {
+        let b = &self.bytes;
+        b.len() == 25
+            && b[0] == Opcode::OP_DUP.to_u8()
+            && b[1] == Opcode::OP_HASH160.to_u8()
+            && b[2] == Opcode::OP_PUSHBYTES_20.to_u8()
+            && b[23] == Opcode::OP_EQUALVERIFY.to_u8()
+            && b[24] == Opcode::OP_CHECKSIG.to_u8()
+    }
+
+    pub fn is_p2sh(&self) -> bool {
+        let b = &self.bytes;
+        b.len() == 23
+            && b[0] == Opcode::OP_HASH160.to_u8()
+            && b[1] == Opcode::OP_PUSHBYTES_20.to_u8()
+            && b[22] == Opcode::OP_EQUAL.to_u8()
+    }
+
+    pub fn is_p2wpkh(&self) -> bool {
+        self.classify() == ScriptType::P2wpkh
+    }
+
+    pub fn is_p2wsh(&self) -> bool {
+        self.classify() == ScriptType::P2wsh
+    }
+
+    pub fn is_p2tr(&self) -> bool {
+        self.classify() == ScriptType::P2tr
+    }
+
+    /// `OP_RETURN` followed only by pushes.
+    pub fn is_op_return(&self) -> bool {
+        match self.bytes.split_first() {
+            Some((&first, _)) if first == Opcode::OP_RETURN.to_u8() => self
+                .instructions()
+                .skip(1)
+                .all(|i| i.is_ok_and(|i| i.is_push())),
+            _ => false,
+        }
+    }
+
+    /// The payloads pushed after `OP_RETURN`, or `None` if this isn't an
+    /// `OP_RETURN` output made only of data pushes. Small-integer opcodes
+    /// carry no bytes, so a script using them is rejected rather than
+    /// silently losing part of its payload.
+    pub fn op_return_data(&self) -> Option<Vec<&[u8]>> {
+        let mut instructions = self.instructions();
+        if instructions.next()?.ok()? != Instruction::Op(Opcode::OP_RETURN) {
+            return None;
+        }
+        instructions
+            .map(|i| i.ok().and_then(|i| i.push_bytes()))
+            .collect()
+    }
+
+    /// Bare `m <keys> n OP_CHECKMULTISIG` with 1 <= m <= n <= 16.
+    pub fn is_multisig(&self) -> bool {
+        self.multisig_params().is_some()
+    }
+
+    /// `(m, n)` for a bare multisig script.
+    pub fn multisig_params(&self) -> Option<(u8, u8)> {
+        let instructions: Vec<Instruction> = self.instructions().collect::<Result<_>>().ok()?;
+        let [
+            Instruction::Op(m),
+            keys @ ..,
+            Instruction::Op(n),
+            Instruction::Op(last),
+        ] = instructions.as_slice()
+        else {
+            return None;
+        };
+        let (required, total) = (m.small_int()?, n.small_int()?);
+        let keys_valid = keys
+            .iter()
+            .all(|key| matches!(key.push_bytes(), Some(k) if matches!(k.len(), 33 | 65)));
+        let valid = *last == Opcode::OP_CHECKMULTISIG
+            && keys_valid
+            && keys.len() == total as usize
+            && (1..=total).contains(&required);
+        valid.then_some((required as u8, total as u8))
+    }
+}
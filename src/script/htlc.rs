@@ -0,0 +1,196 @@
+use super::{Instruction, Opcode, ScriptBuilder, decode_script_num};
+use crate::Script;
+use crate::hashes::{hash160, ripemd160};
+use alloc::vec::Vec;
+
+/// The HTLC output a node offers on its own commitment transaction, as in
+/// BOLT 3. Only hashes of the revocation key and payment hash appear in the
+/// script, so those are what is stored.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct OfferedHtlc {
+    pub revocation_pubkey_hash: [u8; 20],
+    pub local_htlc_pubkey: [u8; 33],
+    pub remote_htlc_pubkey: [u8; 33],
+    /// RIPEMD160 of the SHA256 payment hash.
+    pub payment_hash160: [u8; 20],
+    /// Whether the `option_anchors` one-block CSV delay is included.
+    pub anchors: bool,
+}
+
+/// The HTLC output a node receives on its own commitment transaction: the
+/// local node claims it with the preimage, the remote node after
+/// `cltv_expiry`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct ReceivedHtlc {
+    pub revocation_pubkey_hash: [u8; 20],
+    pub local_htlc_pubkey: [u8; 33],
+    pub remote_htlc_pubkey: [u8; 33],
+    pub payment_hash160: [u8; 20],
+    pub cltv_expiry: u32,
+    pub anchors: bool,
+}
+
+impl OfferedHtlc {
+    pub fn new(
+        revocation_pubkey: &[u8; 33],
+        local_htlc_pubkey: [u8; 33],
+        remote_htlc_pubkey: [u8; 33],
+        payment_hash: &[u8; 32],
+    ) -> Self {
+        Self {
+            revocation_pubkey_hash: hash160(revocation_pubkey),
+            local_htlc_pubkey,
+            remote_htlc_pubkey,
+            payment_hash160: ripemd160(payment_hash),
+            anchors: false,
+        }
+    }
+
+    /// The witness script. The remote node claims with the preimage; the
+    /// local node times out through a 2-of-2 HTLC-timeout transaction.
+    pub fn to_script(&self) -> Script {
+        let builder = revocation_branch(&self.revocation_pubkey_hash, &self.remote_htlc_pubkey)
+            .push_opcode(Opcode::OP_NOTIF)
+            .push_opcode(Opcode::OP_DROP);
+        let builder = two_of_two(builder, &self.local_htlc_pubkey)
+            .push_opcode(Opcode::OP_ELSE)
+            .push_opcode(Opcode::OP_HASH160)
+            .push_slice(&self.payment_hash160)
+            .push_opcode(Opcode::OP_EQUALVERIFY)
+            .push_opcode(Opcode::OP_CHECKSIG);
+        finish(builder, self.anchors)
+    }
+
+    /// Recognizes a script produced by [`OfferedHtlc::to_script`].
+    pub fn from_script(script: &Script) -> Option<Self> {
+        let [revocation, remote, _, local, payment] = pushes(script)?.try_into().ok()?;
+        [false, true].into_iter().find_map(|anchors| {
+            let htlc = Self {
+                revocation_pubkey_hash: revocation.try_into().ok()?,
+                local_htlc_pubkey: local.try_into().ok()?,
+                remote_htlc_pubkey: remote.try_into().ok()?,
+                payment_hash160: payment.try_into().ok()?,
+                anchors,
+            };
+            (htlc.to_script() == *script).then_some(htlc)
+        })
+    }
+}
+
+impl ReceivedHtlc {
+    pub fn new(
+        revocation_pubkey: &[u8; 33],
+        local_htlc_pubkey: [u8; 33],
+        remote_htlc_pubkey: [u8; 33],
+        payment_hash: &[u8; 32],
+        cltv_expiry: u32,
+    ) -> Self {
+        Self {
+            revocation_pubkey_hash: hash160(revocation_pubkey),
+            local_htlc_pubkey,
+            remote_htlc_pubkey,
+            payment_hash160: ripemd160(payment_hash),
+            cltv_expiry,
+            anchors: false,
+        }
+    }
+
+    /// The witness script. The local node claims with the preimage through
+    /// a 2-of-2 HTLC-success transaction; the remote node after the expiry.
+    pub fn to_script(&self) -> Script {
+        let builder = revocation_branch(&self.revocation_pubkey_hash, &self.remote_htlc_pubkey)
+            .push_opcode(Opcode::OP_IF)
+            .push_opcode(Opcode::OP_HASH160)
+            .push_slice(&self.payment_hash160)
+            .push_opcode(Opcode::OP_EQUALVERIFY);
+        let builder = two_of_two(builder, &self.local_htlc_pubkey)
+            .push_opcode(Opcode::OP_ELSE)
+            .push_opcode(Opcode::OP_DROP)
+            .push_int(self.cltv_expiry as i64)
+            .push_opcode(Opcode::OP_CHECKLOCKTIMEVERIFY)
+            .push_opcode(Opcode::OP_DROP)
+            .push_opcode(Opcode::OP_CHECKSIG);
+        finish(builder, self.anchors)
+    }
+
+    /// Recognizes a script produced by [`ReceivedHtlc::to_script`].
+    pub fn from_script(script: &Script) -> Option<Self> {
+        let pushes = pushes(script)?;
+        let [revocation, remote, _, payment, local] = pushes.get(..5)?.try_into().ok()?;
+        let cltv_expiry = cltv_expiry(script)?;
+        [false, true].into_iter().find_map(|anchors| {
+            let htlc = Self {
+                revocation_pubkey_hash: revocation.try_into().ok()?,
+                local_htlc_pubkey: local.try_into().ok()?,
+                remote_htlc_pubkey: remote.try_into().ok()?,
+                payment_hash160: payment.try_into().ok()?,
+                cltv_expiry,
+                anchors,
+            };
+            (htlc.to_script() == *script).then_some(htlc)
+        })
+    }
+}
+
+// `OP_DUP OP_HASH160 <revocation hash> OP_EQUAL OP_IF OP_CHECKSIG OP_ELSE
+// <remote key> OP_SWAP OP_SIZE 32 OP_EQUAL`, shared by both HTLC kinds.
+fn revocation_branch(revocation_pubkey_hash: &[u8; 20], remote: &[u8; 33]) -> ScriptBuilder {
+    ScriptBuilder::new()
+        .push_opcode(Opcode::OP_DUP)
+        .push_opcode(Opcode::OP_HASH160)
+        .push_slice(revocation_pubkey_hash)
+        .push_opcode(Opcode::OP_EQUAL)
+        .push_opcode(Opcode::OP_IF)
+        .push_opcode(Opcode::OP_CHECKSIG)
+        .push_opcode(Opcode::OP_ELSE)
+        .push_slice(remote)
+        .push_opcode(Opcode::OP_SWAP)
+        .push_opcode(Opcode::OP_SIZE)
+        .push_int(32)
+        .push_opcode(Opcode::OP_EQUAL)
+}
+
+fn two_of_two(builder: ScriptBuilder, local: &[u8; 33]) -> ScriptBuilder {
+    builder
+        .push_opcode(Opcode::OP_2)
+        .push_opcode(Opcode::OP_SWAP)
+        .push_slice(local)
+        .push_opcode(Opcode::OP_2)
+        .push_opcode(Opcode::OP_CHECKMULTISIG)
+}
+
+fn finish(builder: ScriptBuilder, anchors: bool) -> Script {
+    let mut builder = builder.push_opcode(Opcode::OP_ENDIF);
+    if anchors {
+        builder = builder
+            .push_opcode(Opcode::OP_1)
+            .push_opcode(Opcode::OP_CHECKSEQUENCEVERIFY)
+            .push_opcode(Opcode::OP_DROP);
+    }
+    builder.push_opcode(Opcode::OP_ENDIF).into_script()
+}
+
+// `push_int` writes expiries up to 16 as `OP_1`..`OP_16`, so the expiry is
+// read from whatever precedes `OP_CHECKLOCKTIMEVERIFY`, push or opcode.
+fn cltv_expiry(script: &Script) -> Option<u32> {
+    let instructions = script.instructions().collect::<Result<Vec<_>, _>>().ok()?;
+    let cltv = instructions
+        .iter()
+        .position(|i| *i == Instruction::Op(Opcode::OP_CHECKLOCKTIMEVERIFY))?;
+    let n = match instructions.get(cltv.checked_sub(1)?)? {
+        Instruction::PushBytes(data) if data.len() <= 5 => decode_script_num(data),
+        Instruction::PushBytes(_) => return None,
+        Instruction::Op(op) => op.small_int()?.into(),
+    };
+    u32::try_from(n).ok()
+}
+
+fn pushes(script: &Script) -> Option<Vec<&[u8]>> {
+    let mut pushes = Vec::new();
+    for instruction in script.instructions() {
+        if let Instruction::PushBytes(data) = instruction.ok()? {
+            pushes.push(data);
+        }
+    }
+    Some(pushes)
+}
@@ -0,0 +1,100 @@
+use super::Opcode;
+use crate::{BitcoinError, Result, Script};
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum Instruction<'a> {
+    /// Data pushed by `OP_0`, `OP_PUSHBYTES_n` or `OP_PUSHDATAn`.
+    PushBytes(&'a [u8]),
+    Op(Opcode),
+}
+
+impl<'a> Instruction<'a> {
+    pub fn opcode(&self) -> Option<Opcode> {
+        match self {
+            Instruction::Op(op) => Some(*op),
+            Instruction::PushBytes(_) => None,
+        }
+    }
+
+    /// Whether this only pushes data, counting `OP_1NEGATE` and `OP_1`..`OP_16`.
+    pub fn is_push(&self) -> bool {
+        match self {
+            Instruction::PushBytes(_) => true,
+            Instruction::Op(op) => op.is_push(),
+        }
+    }
+
+    pub fn push_bytes(&self) -> Option<&'a [u8]> {
+        match self {
+            Instruction::PushBytes(bytes) => Some(bytes),
+            Instruction::Op(_) => None,
+        }
+    }
+}
+
+/// Iterator over a script's instructions. Stops after the first error.
+#[derive(Debug, Clone)]
+pub struct Instructions<'a> {
+    bytes: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> Instructions<'a> {
+    pub fn new(bytes: &'a [u8]) -> Self {
+        Self { bytes, pos: 0 }
+    }
+
+    /// Offset of the next instruction within the script.
+    pub fn position(&self) -> usize {
+        self.pos
+    }
+
+    fn take(&mut self, len: usize, start: usize) -> Result<&'a [u8]> {
+        let available = self.bytes.len() - self.pos;
+        if len > available {
+            self.pos = self.bytes.len();
+            return Err(BitcoinError::InsufficientBytes {
+                context: "script push",
+                offset: start,
+                needed: len,
+                available,
+            });
+        }
+        let data = &self.bytes[self.pos..self.pos + len];
+        self.pos += len;
+        Ok(data)
+    }
+
+    fn take_len(&mut self, width: usize, start: usize) -> Result<usize> {
+        let mut buf = [0u8; 4];
+        buf[..width].copy_from_slice(self.take(width, start)?);
+        Ok(u32::from_le_bytes(buf) as usize)
+    }
+}
+
+impl<'a> Iterator for Instructions<'a> {
+    type Item = Result<Instruction<'a>>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let start = self.pos;
+        let op = Opcode::from(*self.bytes.get(self.pos)?);
+        self.pos += 1;
+        let len = match op {
+            Opcode::OP_PUSHDATA1 => self.take_len(1, start),
+            Opcode::OP_PUSHDATA2 => self.take_len(2, start),
+            Opcode::OP_PUSHDATA4 => self.take_len(4, start),
+            op if op.is_push_data() => Ok(op.to_u8() as usize),
+            op => return Some(Ok(Instruction::Op(op))),
+        };
+        Some(
+            len.and_then(|len| self.take(len, start))
+                .map(Instruction::PushBytes),
+        )
+    }
+}
+
+impl Script {
+    pub fn instructions(&self) -> Instructions<'_> {
+        Instructions::new(&self.bytes)
+    }
+}
@@ -0,0 +1,708 @@
+mod encoding;
+mod eval;
+
+use super::builder::minimal_push_opcode;
+use crate::hashes::sha256;
+use crate::sighash::SighashCache;
+use crate::{BitcoinError, BitcoinTransaction, Result, Script, TransactionInput, Witness};
+#[cfg(feature = "secp256k1")]
+use crate::{
+    ControlBlock, PublicKey, ScriptType, TAPROOT_CONTROL_MAX_NODE_COUNT, TransactionOutput,
+    XOnlyPublicKey, ecdsa,
+    schnorr::{self, SchnorrSignature},
+    sighash::{Prevouts, SighashType},
+};
+use alloc::vec::Vec;
+use core::borrow::Borrow;
+use core::fmt;
+use core::ops::{BitOr, BitOrAssign};
+
+pub use encoding::is_valid_signature_encoding;
+
+pub const MAX_SCRIPT_SIZE: usize = 10_000;
+pub const MAX_SCRIPT_ELEMENT_SIZE: usize = 520;
+pub const MAX_OPS_PER_SCRIPT: usize = 201;
+pub const MAX_STACK_SIZE: usize = 1000;
+
+/// Resource limits enforced while executing a script. The defaults are the
+/// consensus values.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ScriptLimits {
+    pub max_script_size: usize,
+    pub max_element_size: usize,
+    pub max_ops: usize,
+    /// Applies to the main and alt stacks combined.
+    pub max_stack_size: usize,
+}
+
+impl Default for ScriptLimits {
+    fn default() -> Self {
+        Self {
+            max_script_size: MAX_SCRIPT_SIZE,
+            max_element_size: MAX_SCRIPT_ELEMENT_SIZE,
+            max_ops: MAX_OPS_PER_SCRIPT,
+            max_stack_size: MAX_STACK_SIZE,
+        }
+    }
+}
+
+/// Script verification flags, using the same bits as Core's
+/// `SCRIPT_VERIFY_*` constants.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub struct VerifyFlags(pub u32);
+
+impl VerifyFlags {
+    pub const NONE: Self = Self(0);
+    pub const P2SH: Self = Self(1 << 0);
+    pub const STRICTENC: Self = Self(1 << 1);
+    pub const DERSIG: Self = Self(1 << 2);
+    pub const LOW_S: Self = Self(1 << 3);
+    pub const NULLDUMMY: Self = Self(1 << 4);
+    pub const SIGPUSHONLY: Self = Self(1 << 5);
+    pub const MINIMALDATA: Self = Self(1 << 6);
+    pub const DISCOURAGE_UPGRADABLE_NOPS: Self = Self(1 << 7);
+    pub const CLEANSTACK: Self = Self(1 << 8);
+    pub const CHECKLOCKTIMEVERIFY: Self = Self(1 << 9);
+    pub const CHECKSEQUENCEVERIFY: Self = Self(1 << 10);
+    pub const WITNESS: Self = Self(1 << 11);
+    pub const DISCOURAGE_UPGRADABLE_WITNESS_PROGRAM: Self = Self(1 << 12);
+    pub const MINIMALIF: Self = Self(1 << 13);
+    pub const NULLFAIL: Self = Self(1 << 14);
+    pub const WITNESS_PUBKEYTYPE: Self = Self(1 << 15);
+    pub const CONST_SCRIPTCODE: Self = Self(1 << 16);
+
+    /// The soft forks enforced on every block since segwit.
+    pub const CONSENSUS: Self = Self(
+        Self::P2SH.0
+            | Self::DERSIG.0
+            | Self::NULLDUMMY.0
+            | Self::CHECKLOCKTIMEVERIFY.0
+            | Self::CHECKSEQUENCEVERIFY.0
+            | Self::WITNESS.0,
+    );
+
+    /// Core's relay policy flags, minus taproot.
+    pub const STANDARD: Self = Self(
+        Self::CONSENSUS.0
+            | Self::STRICTENC.0
+            | Self::LOW_S.0
+            | Self::MINIMALDATA.0
+            | Self::DISCOURAGE_UPGRADABLE_NOPS.0
+            | Self::CLEANSTACK.0
+            | Self::DISCOURAGE_UPGRADABLE_WITNESS_PROGRAM.0
+            | Self::MINIMALIF.0
+            | Self::NULLFAIL.0
+            | Self::WITNESS_PUBKEYTYPE.0
+            | Self::CONST_SCRIPTCODE.0,
+    );
+
+    pub const fn contains(self, other: Self) -> bool {
+        self.0 & other.0 == other.0
+    }
+}
+
+impl BitOr for VerifyFlags {
+    type Output = Self;
+
+    fn bitor(self, rhs: Self) -> Self {
+        Self(self.0 | rhs.0)
+    }
+}
+
+impl BitOrAssign for VerifyFlags {
+    fn bitor_assign(&mut self, rhs: Self) {
+        self.0 |= rhs.0;
+    }
+}
+
+/// The rules a script is executed under, which also selects the sighash
+/// algorithm.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum SigVersion {
+    Base,
+    WitnessV0,
+}
+
+/// Supplies the transaction-dependent checks to the interpreter. Every
+/// method fails by default, like Core's `BaseSignatureChecker`.
+pub trait SignatureChecker {
+    /// Checks `sig`, which still carries its sighash type byte, against
+    /// `pubkey`. `script_code` is the script being signed, from the last
+    /// executed `OP_CODESEPARATOR` on.
+    fn check_ecdsa_signature(
+        &self,
+        _sig: &[u8],
+        _pubkey: &[u8],
+        _script_code: &Script,
+        _sig_version: SigVersion,
+    ) -> bool {
+        false
+    }
+
+    fn check_lock_time(&self, _lock_time: i64) -> bool {
+        false
+    }
+
+    fn check_sequence(&self, _sequence: i64) -> bool {
+        false
+    }
+}
+
+impl<C: SignatureChecker + ?Sized> SignatureChecker for &C {
+    fn check_ecdsa_signature(
+        &self,
+        sig: &[u8],
+        pubkey: &[u8],
+        script_code: &Script,
+        sig_version: SigVersion,
+    ) -> bool {
+        (**self).check_ecdsa_signature(sig, pubkey, script_code, sig_version)
+    }
+
+    fn check_lock_time(&self, lock_time: i64) -> bool {
+        (**self).check_lock_time(lock_time)
+    }
+
+    fn check_sequence(&self, sequence: i64) -> bool {
+        (**self).check_sequence(sequence)
+    }
+}
+
+/// A checker for scripts evaluated outside a transaction: every signature
+/// and timelock check fails.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct NoSignatureChecker;
+
+impl SignatureChecker for NoSignatureChecker {}
+
+const LOCKTIME_THRESHOLD: i64 = 500_000_000;
+const SEQUENCE_FINAL: u32 = 0xffff_ffff;
+const SEQUENCE_LOCKTIME_DISABLE_FLAG: u32 = 1 << 31;
+const SEQUENCE_LOCKTIME_TYPE_FLAG: u32 = 1 << 22;
+const SEQUENCE_LOCKTIME_MASK: u32 = 0x0000_ffff;
+
+/// Checks signatures and timelocks against input `input_index` of the
+/// cache's transaction, which spends `value` satoshis, as Core's
+/// `TransactionSignatureChecker`. ECDSA signatures need the `secp256k1`
+/// feature; without it they all fail.
+pub struct TransactionSignatureChecker<'a, T: Borrow<BitcoinTransaction>> {
+    cache: &'a SighashCache<T>,
+    input_index: usize,
+    value: u64,
+}
+
+impl<'a, T: Borrow<BitcoinTransaction>> TransactionSignatureChecker<'a, T> {
+    pub fn new(cache: &'a SighashCache<T>, input_index: usize, value: u64) -> Self {
+        Self {
+            cache,
+            input_index,
+            value,
+        }
+    }
+
+    pub fn input_index(&self) -> usize {
+        self.input_index
+    }
+
+    /// The value of the output being spent.
+    pub fn value(&self) -> u64 {
+        self.value
+    }
+
+    fn input(&self) -> Option<&TransactionInput> {
+        self.cache.transaction().inputs.get(self.input_index)
+    }
+}
+
+impl<T: Borrow<BitcoinTransaction>> SignatureChecker for TransactionSignatureChecker<'_, T> {
+    #[cfg(feature = "secp256k1")]
+    fn check_ecdsa_signature(
+        &self,
+        sig: &[u8],
+        pubkey: &[u8],
+        script_code: &Script,
+        sig_version: SigVersion,
+    ) -> bool {
+        let Some((&hash_type, der)) = sig.split_last() else {
+            return false;
+        };
+        let (Ok(sighash_type), Some(pubkey)) = (
+            SighashType::from_u8(hash_type, false),
+            parse_ecdsa_pubkey(pubkey),
+        ) else {
+            return false;
+        };
+        let sighash = match sig_version {
+            SigVersion::Base => {
+                self.cache
+                    .legacy_signature_hash(self.input_index, script_code, sighash_type)
+            }
+            SigVersion::WitnessV0 => self.cache.segwit_v0_signature_hash(
+                self.input_index,
+                script_code,
+                self.value,
+                sighash_type,
+            ),
+        };
+        sighash.is_ok_and(|sighash| ecdsa::verify(&pubkey, &sighash, der))
+    }
+
+    fn check_lock_time(&self, lock_time: i64) -> bool {
+        let tx_lock_time = i64::from(self.cache.transaction().lock_time);
+        // Heights and times cannot be compared.
+        if (tx_lock_time < LOCKTIME_THRESHOLD) != (lock_time < LOCKTIME_THRESHOLD) {
+            return false;
+        }
+        // A final input would let the transaction ignore its lock time.
+        lock_time <= tx_lock_time
+            && self
+                .input()
+                .is_some_and(|input| input.sequence != SEQUENCE_FINAL)
+    }
+
+    fn check_sequence(&self, sequence: i64) -> bool {
+        let Some(input) = self.input() else {
+            return false;
+        };
+        let tx_sequence = input.sequence;
+        if self.cache.transaction().version < 2 || tx_sequence & SEQUENCE_LOCKTIME_DISABLE_FLAG != 0
+        {
+            return false;
+        }
+        let mask = SEQUENCE_LOCKTIME_TYPE_FLAG | SEQUENCE_LOCKTIME_MASK;
+        let (tx_sequence, sequence) = (tx_sequence & mask, sequence & i64::from(mask));
+        let is_time = |n: i64| n >= i64::from(SEQUENCE_LOCKTIME_TYPE_FLAG);
+        is_time(i64::from(tx_sequence)) == is_time(sequence) && sequence <= i64::from(tx_sequence)
+    }
+}
+
+// Like libsecp256k1, which Core parses keys with, this also accepts hybrid
+// keys: an uncompressed key prefixed with 0x06 or 0x07 for the parity of y.
+// Only STRICTENC rejects them.
+#[cfg(feature = "secp256k1")]
+fn parse_ecdsa_pubkey(bytes: &[u8]) -> Option<PublicKey> {
+    match bytes {
+        [prefix @ (0x06 | 0x07), xy @ ..] if xy.len() == 64 => {
+            if xy[63] & 1 != prefix & 1 {
+                return None;
+            }
+            PublicKey::from_slice(&[&[0x04], xy].concat()).ok()
+        }
+        _ => PublicKey::from_slice(bytes).ok(),
+    }
+}
+
+/// Why script verification failed, mirroring Core's `ScriptError`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ScriptError {
+    EvalFalse,
+    OpReturn,
+    ScriptSize,
+    PushSize,
+    OpCount,
+    StackSize,
+    SigCount,
+    PubkeyCount,
+    Verify,
+    EqualVerify,
+    CheckMultisigVerify,
+    CheckSigVerify,
+    NumEqualVerify,
+    BadOpcode,
+    DisabledOpcode,
+    InvalidStackOperation,
+    InvalidAltstackOperation,
+    UnbalancedConditional,
+    /// A number operand was longer than allowed or, under `MINIMALDATA`,
+    /// not minimally encoded.
+    InvalidNumber,
+    NegativeLockTime,
+    UnsatisfiedLockTime,
+    SigHashType,
+    SigDer,
+    MinimalData,
+    SigPushOnly,
+    SigHighS,
+    SigNullDummy,
+    PubkeyType,
+    CleanStack,
+    MinimalIf,
+    SigNullFail,
+    DiscourageUpgradableNops,
+    DiscourageUpgradableWitnessProgram,
+    WitnessProgramWrongLength,
+    WitnessProgramWitnessEmpty,
+    WitnessProgramMismatch,
+    WitnessMalleated,
+    WitnessMalleatedP2sh,
+    WitnessUnexpected,
+    WitnessPubkeyType,
+    OpCodeSeparator,
+    SigFindAndDelete,
+    SchnorrSig,
+    SchnorrSigSize,
+    SchnorrSigHashType,
+    TaprootWrongControlSize,
+}
+
+impl fmt::Display for ScriptError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        use ScriptError::*;
+        let msg = match self {
+            EvalFalse => {
+                "script evaluated without error but finished with a false/empty top stack element"
+            }
+            OpReturn => "OP_RETURN was encountered",
+            ScriptSize => "script is too big",
+            PushSize => "push value size limit exceeded",
+            OpCount => "operation limit exceeded",
+            StackSize => "stack size limit exceeded",
+            SigCount => "signature count negative or greater than pubkey count",
+            PubkeyCount => "pubkey count negative or limit exceeded",
+            Verify => "script failed an OP_VERIFY operation",
+            EqualVerify => "script failed an OP_EQUALVERIFY operation",
+            CheckMultisigVerify => "script failed an OP_CHECKMULTISIGVERIFY operation",
+            CheckSigVerify => "script failed an OP_CHECKSIGVERIFY operation",
+            NumEqualVerify => "script failed an OP_NUMEQUALVERIFY operation",
+            BadOpcode => "opcode missing or not understood",
+            DisabledOpcode => "attempted to use a disabled opcode",
+            InvalidStackOperation => "operation not valid with the current stack size",
+            InvalidAltstackOperation => "operation not valid with the current altstack size",
+            UnbalancedConditional => "invalid OP_IF construction",
+            InvalidNumber => "script number overflow or non-minimal encoding",
+            NegativeLockTime => "negative locktime",
+            UnsatisfiedLockTime => "locktime requirement not satisfied",
+            SigHashType => "signature hash type missing or not understood",
+            SigDer => "non-canonical DER signature",
+            MinimalData => "data push larger than necessary",
+            SigPushOnly => "only push operators allowed in signatures",
+            SigHighS => "non-canonical signature: S value is unnecessarily high",
+            SigNullDummy => "dummy CHECKMULTISIG argument must be zero",
+            PubkeyType => "public key is neither compressed or uncompressed",
+            CleanStack => "stack size must be exactly one after execution",
+            MinimalIf => "OP_IF/NOTIF argument must be minimal",
+            SigNullFail => "signature must be zero for failed CHECK(MULTI)SIG operation",
+            DiscourageUpgradableNops => "NOPx reserved for soft-fork upgrades",
+            DiscourageUpgradableWitnessProgram => "witness version reserved for soft-fork upgrades",
+            WitnessProgramWrongLength => "witness program has incorrect length",
+            WitnessProgramWitnessEmpty => "witness program was passed an empty witness",
+            WitnessProgramMismatch => "witness program hash mismatch",
+            WitnessMalleated => "witness requires empty scriptSig",
+            WitnessMalleatedP2sh => "witness requires only-redeemscript scriptSig",
+            WitnessUnexpected => "witness provided for non-witness script",
+            WitnessPubkeyType => "using non-compressed keys in segwit",
+            OpCodeSeparator => "using OP_CODESEPARATOR in non-witness script",
+            SigFindAndDelete => "signature is found in scriptCode",
+            SchnorrSig => "invalid Schnorr signature",
+            SchnorrSigSize => "invalid Schnorr signature size",
+            SchnorrSigHashType => "invalid Schnorr signature hash type",
+            TaprootWrongControlSize => "invalid taproot control block size",
+        };
+        f.write_str(msg)
+    }
+}
+
+impl From<ScriptError> for BitcoinError {
+    fn from(e: ScriptError) -> Self {
+        BitcoinError::Script(e)
+    }
+}
+
+type ExecResult<T> = core::result::Result<T, ScriptError>;
+
+/// Executes scripts under a fixed set of flags and limits.
+///
+/// Witness programs of version 1 and above, including taproot, are treated
+/// as reserved for future upgrades: they pass unless
+/// `DISCOURAGE_UPGRADABLE_WITNESS_PROGRAM` is set.
+#[derive(Debug, Clone)]
+pub struct Interpreter<C> {
+    checker: C,
+    flags: VerifyFlags,
+    limits: ScriptLimits,
+}
+
+impl<C: SignatureChecker> Interpreter<C> {
+    pub fn new(checker: C, flags: VerifyFlags) -> Self {
+        Self {
+            checker,
+            flags,
+            limits: ScriptLimits::default(),
+        }
+    }
+
+    pub fn with_limits(mut self, limits: ScriptLimits) -> Self {
+        self.limits = limits;
+        self
+    }
+
+    pub fn flags(&self) -> VerifyFlags {
+        self.flags
+    }
+
+    pub fn limits(&self) -> &ScriptLimits {
+        &self.limits
+    }
+
+    /// Runs `script` on `stack`, as Core's `EvalScript`.
+    pub fn eval(
+        &self,
+        stack: &mut Vec<Vec<u8>>,
+        script: &Script,
+        sig_version: SigVersion,
+    ) -> Result<()> {
+        Ok(self.execute(stack, script, sig_version)?)
+    }
+
+    /// Verifies that `script_sig` and `witness` satisfy `script_pubkey`, as
+    /// Core's `VerifyScript`.
+    pub fn verify(
+        &self,
+        script_sig: &Script,
+        script_pubkey: &Script,
+        witness: &Witness,
+    ) -> Result<()> {
+        Ok(self.verify_inner(script_sig, script_pubkey, witness)?)
+    }
+
+    fn verify_inner(
+        &self,
+        script_sig: &Script,
+        script_pubkey: &Script,
+        witness: &Witness,
+    ) -> ExecResult<()> {
+        if self.flags.contains(VerifyFlags::SIGPUSHONLY) && !script_sig.is_push_only() {
+            return Err(ScriptError::SigPushOnly);
+        }
+
+        let mut stack = Vec::new();
+        self.execute(&mut stack, script_sig, SigVersion::Base)?;
+        let p2sh_stack = self
+            .flags
+            .contains(VerifyFlags::P2SH)
+            .then(|| stack.clone());
+        self.execute(&mut stack, script_pubkey, SigVersion::Base)?;
+        if !stack.last().is_some_and(|top| cast_to_bool(top)) {
+            return Err(ScriptError::EvalFalse);
+        }
+
+        let segwit = self.flags.contains(VerifyFlags::WITNESS);
+        let mut had_witness = false;
+        if let Some((version, program)) = script_pubkey.witness_program().filter(|_| segwit) {
+            had_witness = true;
+            if !script_sig.bytes.is_empty() {
+                return Err(ScriptError::WitnessMalleated);
+            }
+            self.verify_witness_program(witness, version, program)?;
+            stack.truncate(1);
+        }
+
+        if let Some(mut p2sh_stack) = p2sh_stack.filter(|_| script_pubkey.is_p2sh()) {
+            if !script_sig.is_push_only() {
+                return Err(ScriptError::SigPushOnly);
+            }
+            // The scriptPubKey succeeded, so the scriptSig pushed something.
+            let redeem = Script::new(p2sh_stack.pop().unwrap());
+            self.execute(&mut p2sh_stack, &redeem, SigVersion::Base)?;
+            if !p2sh_stack.last().is_some_and(|top| cast_to_bool(top)) {
+                return Err(ScriptError::EvalFalse);
+            }
+            if let Some((version, program)) = redeem.witness_program().filter(|_| segwit) {
+                had_witness = true;
+                let mut expected = Vec::with_capacity(redeem.bytes.len() + 1);
+                expected.push(redeem.bytes.len() as u8);
+                expected.extend_from_slice(&redeem.bytes);
+                if script_sig.bytes != expected {
+                    return Err(ScriptError::WitnessMalleatedP2sh);
+                }
+                self.verify_witness_program(witness, version, program)?;
+                p2sh_stack.truncate(1);
+            }
+            stack = p2sh_stack;
+        }
+
+        if self.flags.contains(VerifyFlags::CLEANSTACK) && stack.len() != 1 {
+            return Err(ScriptError::CleanStack);
+        }
+        if segwit && !had_witness && !witness.is_empty() {
+            return Err(ScriptError::WitnessUnexpected);
+        }
+        Ok(())
+    }
+
+    fn verify_witness_program(
+        &self,
+        witness: &Witness,
+        version: u8,
+        program: &[u8],
+    ) -> ExecResult<()> {
+        if version != 0 {
+            if self
+                .flags
+                .contains(VerifyFlags::DISCOURAGE_UPGRADABLE_WITNESS_PROGRAM)
+            {
+                return Err(ScriptError::DiscourageUpgradableWitnessProgram);
+            }
+            return Ok(());
+        }
+        let (mut stack, script) = match program.len() {
+            32 => {
+                let (script, stack) = witness
+                    .items
+                    .split_last()
+                    .ok_or(ScriptError::WitnessProgramWitnessEmpty)?;
+                if sha256(script) != program {
+                    return Err(ScriptError::WitnessProgramMismatch);
+                }
+                (stack.to_vec(), Script::new(script.clone()))
+            }
+            20 => {
+                if witness.items.len() != 2 {
+                    return Err(ScriptError::WitnessProgramMismatch);
+                }
+                let script = Script::new_p2pkh(program.try_into().unwrap());
+                (witness.items.clone(), script)
+            }
+            _ => return Err(ScriptError::WitnessProgramWrongLength),
+        };
+        if stack
+            .iter()
+            .any(|item| item.len() > self.limits.max_element_size)
+        {
+            return Err(ScriptError::PushSize);
+        }
+        self.execute(&mut stack, &script, SigVersion::WitnessV0)?;
+        if stack.len() != 1 {
+            return Err(ScriptError::CleanStack);
+        }
+        if !cast_to_bool(&stack[0]) {
+            return Err(ScriptError::EvalFalse);
+        }
+        Ok(())
+    }
+}
+
+/// Verifies a spend with the default limits. See [`Interpreter::verify`].
+pub fn verify_script<C: SignatureChecker>(
+    script_sig: &Script,
+    script_pubkey: &Script,
+    witness: &Witness,
+    flags: VerifyFlags,
+    checker: C,
+) -> Result<()> {
+    Interpreter::new(checker, flags).verify(script_sig, script_pubkey, witness)
+}
+
+/// Verifies every input of `tx` against `prevouts`, the outputs it spends,
+/// under [`VerifyFlags::CONSENSUS`]. Sighash midstates are shared across
+/// inputs, and taproot key-path signatures are collected and checked
+/// together once every script has run; libsecp256k1 has no batch
+/// verification yet, so they are checked one by one. Tapscript is not run,
+/// as in [`Interpreter`], so a taproot script-path spend whose control
+/// block commits to its script is an `UnsupportedInput` error rather than
+/// a success.
+#[cfg(feature = "secp256k1")]
+pub fn verify_transaction_signatures(
+    tx: &BitcoinTransaction,
+    prevouts: &[TransactionOutput],
+) -> Result<()> {
+    if prevouts.len() != tx.inputs.len() {
+        return Err(BitcoinError::PrevoutsMismatch {
+            inputs: tx.inputs.len(),
+            prevouts: prevouts.len(),
+        });
+    }
+    let cache = SighashCache::new(tx);
+    let mut schnorr_checks = Vec::new();
+    for (index, (input, prevout)) in tx.inputs.iter().zip(prevouts).enumerate() {
+        let fail = |error| BitcoinError::InputScript { index, error };
+        let script_pubkey = &prevout.script_pubkey;
+        if script_pubkey.classify() != ScriptType::P2tr {
+            let checker = TransactionSignatureChecker::new(&cache, index, prevout.value);
+            verify_script(
+                &input.script_sig,
+                script_pubkey,
+                &input.witness,
+                VerifyFlags::CONSENSUS,
+                checker,
+            )
+            .map_err(|e| match e {
+                BitcoinError::Script(error) => fail(error),
+                e => e,
+            })?;
+            continue;
+        }
+
+        if !input.script_sig.bytes.is_empty() {
+            return Err(fail(ScriptError::WitnessMalleated));
+        }
+        let mut items = &input.witness.items[..];
+        let annex = match items {
+            [rest @ .., last] if !rest.is_empty() && last.first() == Some(&0x50) => {
+                items = rest;
+                Some(&last[..])
+            }
+            _ => None,
+        };
+        let pubkey = XOnlyPublicKey::from_slice(&script_pubkey.bytes[2..]).ok();
+        let sig = match items {
+            [] => return Err(fail(ScriptError::WitnessProgramWitnessEmpty)),
+            [sig] => sig,
+            [.., script, control_block] => {
+                let size = control_block.len();
+                if size < 33
+                    || !(size - 33).is_multiple_of(32)
+                    || (size - 33) / 32 > TAPROOT_CONTROL_MAX_NODE_COUNT
+                {
+                    return Err(fail(ScriptError::TaprootWrongControlSize));
+                }
+                let script = Script::new(script.clone());
+                let committed = ControlBlock::from_slice(control_block)
+                    .ok()
+                    .zip(pubkey)
+                    .is_some_and(|(control_block, pubkey)| control_block.verify(&pubkey, &script));
+                if !committed {
+                    return Err(fail(ScriptError::WitnessProgramMismatch));
+                }
+                return Err(BitcoinError::UnsupportedInput {
+                    index,
+                    reason: "tapscript is not supported",
+                });
+            }
+        };
+        // A 65-byte signature only fails to parse for an explicit default type.
+        let sig = SchnorrSignature::from_slice(sig).map_err(|_| match sig.len() {
+            65 => fail(ScriptError::SchnorrSigHashType),
+            _ => fail(ScriptError::SchnorrSigSize),
+        })?;
+        let sighash_type = SighashType::from_u8(sig.sighash_type, true)
+            .map_err(|_| fail(ScriptError::SchnorrSigHashType))?;
+        let sighash = cache
+            .taproot_signature_hash(index, &Prevouts::All(prevouts), annex, None, sighash_type)
+            .map_err(|_| fail(ScriptError::SchnorrSigHashType))?;
+        let pubkey = pubkey.ok_or(fail(ScriptError::SchnorrSig))?;
+        schnorr_checks.push((index, pubkey, sighash, sig.signature));
+    }
+
+    for (index, pubkey, sighash, signature) in schnorr_checks {
+        if !schnorr::verify(&pubkey, &sighash, &signature) {
+            return Err(BitcoinError::InputScript {
+                index,
+                error: ScriptError::SchnorrSig,
+            });
+        }
+    }
+    Ok(())
+}
+
+/// Script truthiness: any non-zero byte, except a lone sign bit at the end.
+pub fn cast_to_bool(bytes: &[u8]) -> bool {
+    match bytes.split_last() {
+        Some((&last, rest)) => rest.iter().any(|&b| b != 0) || (last != 0 && last != 0x80),
+        None => false,
+    }
+}
+
+fn is_minimal_push(op_byte: u8, data: &[u8]) -> bool {
+    minimal_push_opcode(data).to_u8() == op_byte
+}
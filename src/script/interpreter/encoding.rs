@@ -0,0 +1,85 @@
+use super::{ExecResult, ScriptError, SigVersion, VerifyFlags};
+use crate::curve::HALF_ORDER;
+
+/// Whether `sig` is a strict DER signature followed by a sighash byte, as
+/// BIP66 defines it.
+pub fn is_valid_signature_encoding(sig: &[u8]) -> bool {
+    let len = sig.len();
+    if !(9..=73).contains(&len) || sig[0] != 0x30 || sig[1] as usize != len - 3 {
+        return false;
+    }
+    let len_r = sig[3] as usize;
+    if 5 + len_r >= len {
+        return false;
+    }
+    let len_s = sig[5 + len_r] as usize;
+    if len_r + len_s + 7 != len {
+        return false;
+    }
+    let r = &sig[4..4 + len_r];
+    let s = &sig[6 + len_r..6 + len_r + len_s];
+    sig[2] == 0x02 && sig[4 + len_r] == 0x02 && is_der_integer(r) && is_der_integer(s)
+}
+
+// Non-empty, non-negative and without superfluous leading zeros.
+fn is_der_integer(n: &[u8]) -> bool {
+    match n {
+        [] => false,
+        [first, ..] if first & 0x80 != 0 => false,
+        [0, second, ..] => second & 0x80 != 0,
+        _ => true,
+    }
+}
+
+fn is_low_s(sig: &[u8]) -> bool {
+    let len_r = sig[3] as usize;
+    let s = &sig[6 + len_r..sig.len() - 1];
+    let s = &s[s.iter().take_while(|&&b| b == 0).count()..];
+    if s.len() > 32 {
+        return false;
+    }
+    let mut padded = [0u8; 32];
+    padded[32 - s.len()..].copy_from_slice(s);
+    padded <= HALF_ORDER
+}
+
+fn is_defined_hashtype(sig: &[u8]) -> bool {
+    sig.last().is_some_and(|&b| (1..=3).contains(&(b & !0x80)))
+}
+
+pub(super) fn check_signature_encoding(sig: &[u8], flags: VerifyFlags) -> ExecResult<()> {
+    // An empty signature is a compact way to make a CHECKSIG fail.
+    if sig.is_empty() {
+        return Ok(());
+    }
+    let strict = VerifyFlags::DERSIG | VerifyFlags::LOW_S | VerifyFlags::STRICTENC;
+    if flags.0 & strict.0 != 0 && !is_valid_signature_encoding(sig) {
+        return Err(ScriptError::SigDer);
+    }
+    if flags.contains(VerifyFlags::LOW_S) && !is_low_s(sig) {
+        return Err(ScriptError::SigHighS);
+    }
+    if flags.contains(VerifyFlags::STRICTENC) && !is_defined_hashtype(sig) {
+        return Err(ScriptError::SigHashType);
+    }
+    Ok(())
+}
+
+pub(super) fn check_pubkey_encoding(
+    pubkey: &[u8],
+    flags: VerifyFlags,
+    sig_version: SigVersion,
+) -> ExecResult<()> {
+    let compressed = pubkey.len() == 33 && matches!(pubkey[0], 0x02 | 0x03);
+    let uncompressed = pubkey.len() == 65 && pubkey[0] == 0x04;
+    if flags.contains(VerifyFlags::STRICTENC) && !compressed && !uncompressed {
+        return Err(ScriptError::PubkeyType);
+    }
+    if flags.contains(VerifyFlags::WITNESS_PUBKEYTYPE)
+        && sig_version == SigVersion::WitnessV0
+        && !compressed
+    {
+        return Err(ScriptError::WitnessPubkeyType);
+    }
+    Ok(())
+}
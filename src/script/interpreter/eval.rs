@@ -0,0 +1,573 @@
+use super::encoding::{check_pubkey_encoding, check_signature_encoding};
+use super::{
+    ExecResult, Interpreter, ScriptError, SigVersion, SignatureChecker, VerifyFlags, cast_to_bool,
+    is_minimal_push,
+};
+use crate::Script;
+use crate::hashes::{hash160, ripemd160, sha1, sha256, sha256d};
+use crate::script::{
+    Instructions, MAX_PUBKEYS_PER_MULTISIG, Opcode, decode_script_num, encode_script_num,
+};
+use alloc::vec;
+use alloc::vec::Vec;
+
+// Sequence numbers with this bit set opt out of relative lock-time.
+const SEQUENCE_LOCKTIME_DISABLE_FLAG: i64 = 1 << 31;
+
+type Stack = Vec<Vec<u8>>;
+
+fn require(stack: &Stack, n: usize) -> ExecResult<()> {
+    if stack.len() < n {
+        return Err(ScriptError::InvalidStackOperation);
+    }
+    Ok(())
+}
+
+// `stack[len - depth]`, with `depth` counting from 1 at the top.
+fn top(stack: &Stack, depth: usize) -> &Vec<u8> {
+    &stack[stack.len() - depth]
+}
+
+fn pop(stack: &mut Stack) -> ExecResult<Vec<u8>> {
+    stack.pop().ok_or(ScriptError::InvalidStackOperation)
+}
+
+fn push_bool(stack: &mut Stack, value: bool) {
+    stack.push(if value { vec![1] } else { vec![] });
+}
+
+fn read_num(bytes: &[u8], require_minimal: bool, max_len: usize) -> ExecResult<i64> {
+    if bytes.len() > max_len {
+        return Err(ScriptError::InvalidNumber);
+    }
+    if require_minimal
+        && let Some((&last, rest)) = bytes.split_last()
+        && last & 0x7f == 0
+        && rest.last().is_none_or(|&b| b & 0x80 == 0)
+    {
+        return Err(ScriptError::InvalidNumber);
+    }
+    Ok(decode_script_num(bytes))
+}
+
+/// Removes every push of `sig` that starts at an instruction boundary, as
+/// Core's `FindAndDelete`. Returns the number of matches.
+fn find_and_delete(script: &mut Vec<u8>, sig: &[u8]) -> usize {
+    // `CScript() << sig` never uses OP_n.
+    let mut pattern = Vec::with_capacity(sig.len() + 5);
+    match sig.len() {
+        len @ 0..=75 => pattern.push(len as u8),
+        len @ 76..=0xff => pattern.extend_from_slice(&[Opcode::OP_PUSHDATA1.to_u8(), len as u8]),
+        len => {
+            pattern.push(Opcode::OP_PUSHDATA2.to_u8());
+            pattern.extend_from_slice(&(len as u16).to_le_bytes());
+        }
+    }
+    pattern.extend_from_slice(sig);
+
+    let mut result = Vec::with_capacity(script.len());
+    let mut found = 0;
+    let (mut pc, mut kept) = (0, 0);
+    loop {
+        result.extend_from_slice(&script[kept..pc]);
+        while script[pc..].starts_with(&pattern) {
+            pc += pattern.len();
+            found += 1;
+        }
+        kept = pc;
+        let mut instructions = Instructions::new(&script[pc..]);
+        match instructions.next() {
+            Some(Ok(_)) => pc += instructions.position(),
+            _ => break,
+        }
+    }
+    if found > 0 {
+        result.extend_from_slice(&script[kept..]);
+        *script = result;
+    }
+    found
+}
+
+impl<C: SignatureChecker> Interpreter<C> {
+    fn script_code(
+        &self,
+        code: &[u8],
+        sigs: &[&Vec<u8>],
+        sig_version: SigVersion,
+    ) -> ExecResult<Script> {
+        let mut code = code.to_vec();
+        if sig_version == SigVersion::Base {
+            for sig in sigs {
+                if find_and_delete(&mut code, sig) > 0
+                    && self.flags.contains(VerifyFlags::CONST_SCRIPTCODE)
+                {
+                    return Err(ScriptError::SigFindAndDelete);
+                }
+            }
+        }
+        Ok(Script::new(code))
+    }
+
+    fn check_sig(
+        &self,
+        sig: &[u8],
+        pubkey: &[u8],
+        script_code: &Script,
+        sig_version: SigVersion,
+    ) -> ExecResult<bool> {
+        check_signature_encoding(sig, self.flags)?;
+        check_pubkey_encoding(pubkey, self.flags, sig_version)?;
+        Ok(self
+            .checker
+            .check_ecdsa_signature(sig, pubkey, script_code, sig_version))
+    }
+
+    pub(super) fn execute(
+        &self,
+        stack: &mut Stack,
+        script: &Script,
+        sig_version: SigVersion,
+    ) -> ExecResult<()> {
+        use Opcode::*;
+
+        let limits = &self.limits;
+        let flags = self.flags;
+        let minimal = flags.contains(VerifyFlags::MINIMALDATA);
+        if script.bytes.len() > limits.max_script_size {
+            return Err(ScriptError::ScriptSize);
+        }
+
+        let mut alt_stack: Stack = vec![];
+        let mut conditions: Vec<bool> = vec![];
+        let mut op_count = 0;
+        let mut code_start = 0;
+        let mut instructions = script.instructions();
+        loop {
+            let offset = instructions.position();
+            let Some(instruction) = instructions.next() else {
+                break;
+            };
+            let instruction = instruction.map_err(|_| ScriptError::BadOpcode)?;
+            let op = Opcode::from(script.bytes[offset]);
+            let executing = conditions.iter().all(|&c| c);
+
+            if let Some(data) = instruction.push_bytes() {
+                if data.len() > limits.max_element_size {
+                    return Err(ScriptError::PushSize);
+                }
+                if executing {
+                    if minimal && !is_minimal_push(op.to_u8(), data) {
+                        return Err(ScriptError::MinimalData);
+                    }
+                    stack.push(data.to_vec());
+                }
+                if stack.len() + alt_stack.len() > limits.max_stack_size {
+                    return Err(ScriptError::StackSize);
+                }
+                continue;
+            }
+
+            if op > OP_16 {
+                op_count += 1;
+                if op_count > limits.max_ops {
+                    return Err(ScriptError::OpCount);
+                }
+            }
+            if op.is_disabled() {
+                return Err(ScriptError::DisabledOpcode);
+            }
+            if op == OP_CODESEPARATOR
+                && sig_version == SigVersion::Base
+                && flags.contains(VerifyFlags::CONST_SCRIPTCODE)
+            {
+                return Err(ScriptError::OpCodeSeparator);
+            }
+            if !executing && !(OP_IF..=OP_ENDIF).contains(&op) {
+                continue;
+            }
+
+            match op {
+                OP_1NEGATE | OP_1 | OP_2 | OP_3 | OP_4 | OP_5 | OP_6 | OP_7 | OP_8 | OP_9
+                | OP_10 | OP_11 | OP_12 | OP_13 | OP_14 | OP_15 | OP_16 => {
+                    stack.push(encode_script_num(op.small_int().unwrap() as i64));
+                }
+
+                OP_NOP => {}
+
+                OP_CHECKLOCKTIMEVERIFY if flags.contains(VerifyFlags::CHECKLOCKTIMEVERIFY) => {
+                    require(stack, 1)?;
+                    // Five bytes, since lock times reach 2^32 - 1.
+                    let lock_time = read_num(top(stack, 1), minimal, 5)?;
+                    if lock_time < 0 {
+                        return Err(ScriptError::NegativeLockTime);
+                    }
+                    if !self.checker.check_lock_time(lock_time) {
+                        return Err(ScriptError::UnsatisfiedLockTime);
+                    }
+                }
+
+                OP_CHECKSEQUENCEVERIFY if flags.contains(VerifyFlags::CHECKSEQUENCEVERIFY) => {
+                    require(stack, 1)?;
+                    let sequence = read_num(top(stack, 1), minimal, 5)?;
+                    if sequence < 0 {
+                        return Err(ScriptError::NegativeLockTime);
+                    }
+                    if sequence & SEQUENCE_LOCKTIME_DISABLE_FLAG == 0
+                        && !self.checker.check_sequence(sequence)
+                    {
+                        return Err(ScriptError::UnsatisfiedLockTime);
+                    }
+                }
+
+                OP_CHECKLOCKTIMEVERIFY | OP_CHECKSEQUENCEVERIFY => {}
+
+                OP_NOP1 | OP_NOP4 | OP_NOP5 | OP_NOP6 | OP_NOP7 | OP_NOP8 | OP_NOP9 | OP_NOP10 => {
+                    if flags.contains(VerifyFlags::DISCOURAGE_UPGRADABLE_NOPS) {
+                        return Err(ScriptError::DiscourageUpgradableNops);
+                    }
+                }
+
+                OP_IF | OP_NOTIF => {
+                    let mut value = false;
+                    if executing {
+                        let condition = stack.pop().ok_or(ScriptError::UnbalancedConditional)?;
+                        if sig_version == SigVersion::WitnessV0
+                            && flags.contains(VerifyFlags::MINIMALIF)
+                            && !matches!(condition.as_slice(), [] | [1])
+                        {
+                            return Err(ScriptError::MinimalIf);
+                        }
+                        value = cast_to_bool(&condition) == (op == OP_IF);
+                    }
+                    conditions.push(value);
+                }
+
+                OP_ELSE => {
+                    let last = conditions
+                        .last_mut()
+                        .ok_or(ScriptError::UnbalancedConditional)?;
+                    *last = !*last;
+                }
+
+                OP_ENDIF => {
+                    conditions.pop().ok_or(ScriptError::UnbalancedConditional)?;
+                }
+
+                OP_VERIFY => {
+                    require(stack, 1)?;
+                    if !cast_to_bool(top(stack, 1)) {
+                        return Err(ScriptError::Verify);
+                    }
+                    stack.pop();
+                }
+
+                OP_RETURN => return Err(ScriptError::OpReturn),
+
+                OP_TOALTSTACK => alt_stack.push(pop(stack)?),
+
+                OP_FROMALTSTACK => {
+                    let item = alt_stack
+                        .pop()
+                        .ok_or(ScriptError::InvalidAltstackOperation)?;
+                    stack.push(item);
+                }
+
+                OP_2DROP => {
+                    require(stack, 2)?;
+                    stack.truncate(stack.len() - 2);
+                }
+
+                OP_2DUP | OP_3DUP | OP_2OVER => {
+                    let (depth, count) = match op {
+                        OP_2DUP => (2, 2),
+                        OP_3DUP => (3, 3),
+                        _ => (4, 2),
+                    };
+                    require(stack, depth)?;
+                    let start = stack.len() - depth;
+                    stack.extend_from_within(start..start + count);
+                }
+
+                OP_2ROT => {
+                    require(stack, 6)?;
+                    let start = stack.len() - 6;
+                    let moved: Vec<_> = stack.drain(start..start + 2).collect();
+                    stack.extend(moved);
+                }
+
+                OP_2SWAP => {
+                    require(stack, 4)?;
+                    let len = stack.len();
+                    stack[len - 4..].rotate_left(2);
+                }
+
+                OP_IFDUP => {
+                    require(stack, 1)?;
+                    if cast_to_bool(top(stack, 1)) {
+                        stack.push(top(stack, 1).clone());
+                    }
+                }
+
+                OP_DEPTH => stack.push(encode_script_num(stack.len() as i64)),
+
+                OP_DROP => {
+                    pop(stack)?;
+                }
+
+                OP_DUP => {
+                    require(stack, 1)?;
+                    stack.push(top(stack, 1).clone());
+                }
+
+                OP_NIP => {
+                    require(stack, 2)?;
+                    stack.remove(stack.len() - 2);
+                }
+
+                OP_OVER => {
+                    require(stack, 2)?;
+                    stack.push(top(stack, 2).clone());
+                }
+
+                OP_PICK | OP_ROLL => {
+                    require(stack, 2)?;
+                    let n = read_num(top(stack, 1), minimal, 4)?;
+                    stack.pop();
+                    if n < 0 || n as usize >= stack.len() {
+                        return Err(ScriptError::InvalidStackOperation);
+                    }
+                    let index = stack.len() - 1 - n as usize;
+                    let item = if op == OP_ROLL {
+                        stack.remove(index)
+                    } else {
+                        stack[index].clone()
+                    };
+                    stack.push(item);
+                }
+
+                OP_ROT => {
+                    require(stack, 3)?;
+                    let len = stack.len();
+                    stack[len - 3..].rotate_left(1);
+                }
+
+                OP_SWAP => {
+                    require(stack, 2)?;
+                    let len = stack.len();
+                    stack.swap(len - 2, len - 1);
+                }
+
+                OP_TUCK => {
+                    require(stack, 2)?;
+                    let item = top(stack, 1).clone();
+                    stack.insert(stack.len() - 2, item);
+                }
+
+                OP_SIZE => {
+                    require(stack, 1)?;
+                    stack.push(encode_script_num(top(stack, 1).len() as i64));
+                }
+
+                OP_EQUAL | OP_EQUALVERIFY => {
+                    require(stack, 2)?;
+                    let equal = stack.pop() == stack.pop();
+                    if op == OP_EQUALVERIFY {
+                        if !equal {
+                            return Err(ScriptError::EqualVerify);
+                        }
+                    } else {
+                        push_bool(stack, equal);
+                    }
+                }
+
+                OP_1ADD | OP_1SUB | OP_NEGATE | OP_ABS | OP_NOT | OP_0NOTEQUAL => {
+                    require(stack, 1)?;
+                    let n = read_num(top(stack, 1), minimal, 4)?;
+                    let result = match op {
+                        OP_1ADD => n + 1,
+                        OP_1SUB => n - 1,
+                        OP_NEGATE => -n,
+                        OP_ABS => n.abs(),
+                        OP_NOT => (n == 0) as i64,
+                        _ => (n != 0) as i64,
+                    };
+                    stack.pop();
+                    stack.push(encode_script_num(result));
+                }
+
+                OP_ADD
+                | OP_SUB
+                | OP_BOOLAND
+                | OP_BOOLOR
+                | OP_NUMEQUAL
+                | OP_NUMEQUALVERIFY
+                | OP_NUMNOTEQUAL
+                | OP_LESSTHAN
+                | OP_GREATERTHAN
+                | OP_LESSTHANOREQUAL
+                | OP_GREATERTHANOREQUAL
+                | OP_MIN
+                | OP_MAX => {
+                    require(stack, 2)?;
+                    let a = read_num(top(stack, 2), minimal, 4)?;
+                    let b = read_num(top(stack, 1), minimal, 4)?;
+                    let result = match op {
+                        OP_ADD => a + b,
+                        OP_SUB => a - b,
+                        OP_BOOLAND => (a != 0 && b != 0) as i64,
+                        OP_BOOLOR => (a != 0 || b != 0) as i64,
+                        OP_NUMEQUAL | OP_NUMEQUALVERIFY => (a == b) as i64,
+                        OP_NUMNOTEQUAL => (a != b) as i64,
+                        OP_LESSTHAN => (a < b) as i64,
+                        OP_GREATERTHAN => (a > b) as i64,
+                        OP_LESSTHANOREQUAL => (a <= b) as i64,
+                        OP_GREATERTHANOREQUAL => (a >= b) as i64,
+                        OP_MIN => a.min(b),
+                        _ => a.max(b),
+                    };
+                    stack.truncate(stack.len() - 2);
+                    if op == OP_NUMEQUALVERIFY {
+                        if result == 0 {
+                            return Err(ScriptError::NumEqualVerify);
+                        }
+                    } else {
+                        stack.push(encode_script_num(result));
+                    }
+                }
+
+                OP_WITHIN => {
+                    require(stack, 3)?;
+                    let x = read_num(top(stack, 3), minimal, 4)?;
+                    let min = read_num(top(stack, 2), minimal, 4)?;
+                    let max = read_num(top(stack, 1), minimal, 4)?;
+                    stack.truncate(stack.len() - 3);
+                    push_bool(stack, min <= x && x < max);
+                }
+
+                OP_RIPEMD160 | OP_SHA1 | OP_SHA256 | OP_HASH160 | OP_HASH256 => {
+                    let data = pop(stack)?;
+                    stack.push(match op {
+                        OP_RIPEMD160 => ripemd160(&data).to_vec(),
+                        OP_SHA1 => sha1(&data).to_vec(),
+                        OP_SHA256 => sha256(&data).to_vec(),
+                        OP_HASH160 => hash160(&data).to_vec(),
+                        _ => sha256d(&data).to_vec(),
+                    });
+                }
+
+                OP_CODESEPARATOR => code_start = instructions.position(),
+
+                OP_CHECKSIG | OP_CHECKSIGVERIFY => {
+                    require(stack, 2)?;
+                    let (sig, pubkey) = (top(stack, 2), top(stack, 1));
+                    let script_code =
+                        self.script_code(&script.bytes[code_start..], &[sig], sig_version)?;
+                    let success = self.check_sig(sig, pubkey, &script_code, sig_version)?;
+                    if !success && flags.contains(VerifyFlags::NULLFAIL) && !sig.is_empty() {
+                        return Err(ScriptError::SigNullFail);
+                    }
+                    stack.truncate(stack.len() - 2);
+                    if op == OP_CHECKSIGVERIFY {
+                        if !success {
+                            return Err(ScriptError::CheckSigVerify);
+                        }
+                    } else {
+                        push_bool(stack, success);
+                    }
+                }
+
+                OP_CHECKMULTISIG | OP_CHECKMULTISIGVERIFY => {
+                    let success = self.check_multisig(
+                        stack,
+                        &script.bytes[code_start..],
+                        sig_version,
+                        &mut op_count,
+                    )?;
+                    if op == OP_CHECKMULTISIGVERIFY {
+                        if !success {
+                            return Err(ScriptError::CheckMultisigVerify);
+                        }
+                    } else {
+                        push_bool(stack, success);
+                    }
+                }
+
+                _ => return Err(ScriptError::BadOpcode),
+            }
+
+            if stack.len() + alt_stack.len() > limits.max_stack_size {
+                return Err(ScriptError::StackSize);
+            }
+        }
+
+        if !conditions.is_empty() {
+            return Err(ScriptError::UnbalancedConditional);
+        }
+        Ok(())
+    }
+
+    // Pops the operands of a CHECKMULTISIG and checks the signatures in
+    // order against the keys, each key being tried at most once.
+    fn check_multisig(
+        &self,
+        stack: &mut Stack,
+        code: &[u8],
+        sig_version: SigVersion,
+        op_count: &mut usize,
+    ) -> ExecResult<bool> {
+        let minimal = self.flags.contains(VerifyFlags::MINIMALDATA);
+        require(stack, 1)?;
+        let key_count = read_num(top(stack, 1), minimal, 4)?;
+        if !(0..=MAX_PUBKEYS_PER_MULTISIG as i64).contains(&key_count) {
+            return Err(ScriptError::PubkeyCount);
+        }
+        let key_count = key_count as usize;
+        *op_count += key_count;
+        if *op_count > self.limits.max_ops {
+            return Err(ScriptError::OpCount);
+        }
+        // Depths of the first key and the signature count.
+        let first_key = 2;
+        let sigs_depth = first_key + key_count;
+        require(stack, sigs_depth)?;
+        let sig_count = read_num(top(stack, sigs_depth), minimal, 4)?;
+        if !(0..=key_count as i64).contains(&sig_count) {
+            return Err(ScriptError::SigCount);
+        }
+        let sig_count = sig_count as usize;
+        let first_sig = sigs_depth + 1;
+        // One extra element: the dummy consumed by Core's off-by-one.
+        let total = first_sig + sig_count;
+        require(stack, total)?;
+
+        let sigs: Vec<_> = (0..sig_count).map(|i| top(stack, first_sig + i)).collect();
+        let script_code = self.script_code(code, &sigs, sig_version)?;
+
+        let (mut sig, mut key) = (0, 0);
+        let mut success = true;
+        while success && sig < sig_count {
+            let sig_bytes = top(stack, first_sig + sig);
+            let pubkey = top(stack, first_key + key);
+            if self.check_sig(sig_bytes, pubkey, &script_code, sig_version)? {
+                sig += 1;
+            }
+            key += 1;
+            if sig_count - sig > key_count - key {
+                success = false;
+            }
+        }
+
+        if !success
+            && self.flags.contains(VerifyFlags::NULLFAIL)
+            && (0..sig_count).any(|i| !top(stack, first_sig + i).is_empty())
+        {
+            return Err(ScriptError::SigNullFail);
+        }
+        let dummy = top(stack, total);
+        if self.flags.contains(VerifyFlags::NULLDUMMY) && !dummy.is_empty() {
+            return Err(ScriptError::SigNullDummy);
+        }
+        stack.truncate(stack.len() - total);
+        Ok(success)
+    }
+}
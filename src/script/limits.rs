@@ -0,0 +1,60 @@
+use super::interpreter::{ScriptError, ScriptLimits};
+use super::{Instruction, MAX_PUBKEYS_PER_MULTISIG, Opcode, decode_script_num};
+use crate::{Result, Script};
+
+impl Script {
+    /// Checks the consensus limits that hold however the script executes:
+    /// its size, the size of each push, and the operation count. Multisig
+    /// keys are counted when the key count is pushed just before. For
+    /// push-only scripts the number of pushes is also checked against the
+    /// stack size limit.
+    pub fn validate_limits(&self) -> Result<()> {
+        self.validate_limits_with(&ScriptLimits::default())
+    }
+
+    pub fn validate_limits_with(&self, limits: &ScriptLimits) -> Result<()> {
+        if self.bytes.len() > limits.max_script_size {
+            return Err(ScriptError::ScriptSize.into());
+        }
+        let (mut ops, mut pushes) = (0, 0);
+        let mut push_only = true;
+        // The number pushed by the previous instruction, if any.
+        let mut last_num = None;
+        for instruction in self.instructions() {
+            let instruction = instruction?;
+            match instruction {
+                Instruction::PushBytes(data) => {
+                    if data.len() > limits.max_element_size {
+                        return Err(ScriptError::PushSize.into());
+                    }
+                    pushes += 1;
+                }
+                Instruction::Op(op) if op.is_push() => pushes += 1,
+                Instruction::Op(op) => {
+                    push_only = false;
+                    ops += 1;
+                    if matches!(
+                        op,
+                        Opcode::OP_CHECKMULTISIG | Opcode::OP_CHECKMULTISIGVERIFY
+                    ) {
+                        ops += last_num
+                            .filter(|n| (0..=MAX_PUBKEYS_PER_MULTISIG as i64).contains(n))
+                            .unwrap_or(0) as usize;
+                    }
+                }
+            }
+            if ops > limits.max_ops {
+                return Err(ScriptError::OpCount.into());
+            }
+            last_num = match instruction {
+                Instruction::PushBytes(data) if data.len() <= 4 => Some(decode_script_num(data)),
+                Instruction::PushBytes(_) => None,
+                Instruction::Op(op) => op.small_int().map(i64::from),
+            };
+        }
+        if push_only && pushes > limits.max_stack_size {
+            return Err(ScriptError::StackSize.into());
+        }
+        Ok(())
+    }
+}
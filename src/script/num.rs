@@ -0,0 +1,41 @@
+use alloc::vec::Vec;
+
+/// Minimal little-endian sign-magnitude encoding used for script numbers.
+pub fn encode_script_num(n: i64) -> Vec<u8> {
+    let mut out = Vec::new();
+    let mut abs = n.unsigned_abs();
+    while abs > 0 {
+        out.push((abs & 0xff) as u8);
+        abs >>= 8;
+    }
+    if let Some(last) = out.last_mut() {
+        if *last & 0x80 != 0 {
+            out.push(if n < 0 { 0x80 } else { 0x00 });
+        } else if n < 0 {
+            *last |= 0x80;
+        }
+    }
+    out
+}
+
+/// Reads a script number of any length, tolerating non-minimal encodings.
+/// Inputs longer than eight bytes are truncated to their low eight bytes.
+pub fn decode_script_num(bytes: &[u8]) -> i64 {
+    let Some((&last, _)) = bytes.split_last() else {
+        return 0;
+    };
+    let mut magnitude: u64 = 0;
+    for (i, &byte) in bytes.iter().take(8).enumerate() {
+        let byte = if i == bytes.len() - 1 {
+            byte & 0x7f
+        } else {
+            byte
+        };
+        magnitude |= (byte as u64) << (8 * i);
+    }
+    if last & 0x80 != 0 {
+        (magnitude as i64).wrapping_neg()
+    } else {
+        magnitude as i64
+    }
+}
@@ -0,0 +1,55 @@
+use super::Opcode;
+use super::builder::minimal_push_opcode;
+use crate::{BitcoinError, Result, Script};
+
+impl Script {
+    /// Whether the script only pushes data, as Core's `IsPushOnly`. Scripts
+    /// with a truncated push are not push-only.
+    pub fn is_push_only(&self) -> bool {
+        self.check_push_only().is_ok()
+    }
+
+    /// Like [`Script::is_push_only`], but reports the first offending
+    /// opcode and its offset.
+    pub fn check_push_only(&self) -> Result<()> {
+        let mut instructions = self.instructions();
+        loop {
+            let offset = instructions.position();
+            match instructions.next().transpose()? {
+                None => return Ok(()),
+                Some(instruction) if instruction.is_push() => {}
+                Some(instruction) => {
+                    return Err(BitcoinError::NotPushOnly {
+                        offset,
+                        opcode: instruction.opcode().unwrap(),
+                    });
+                }
+            }
+        }
+    }
+
+    /// Checks that every push uses the encoding BIP62 requires: `OP_0` for
+    /// empty data, `OP_1NEGATE` and `OP_1`..`OP_16` for single bytes they
+    /// can represent, and otherwise the shortest push opcode.
+    pub fn check_minimal_pushes(&self) -> Result<()> {
+        let mut instructions = self.instructions();
+        loop {
+            let offset = instructions.position();
+            let Some(instruction) = instructions.next().transpose()? else {
+                return Ok(());
+            };
+            let Some(data) = instruction.push_bytes() else {
+                continue;
+            };
+            let used = Opcode::from(self.bytes[offset]);
+            let expected = minimal_push_opcode(data);
+            if used != expected {
+                return Err(BitcoinError::NonMinimalPush {
+                    offset,
+                    used,
+                    expected,
+                });
+            }
+        }
+    }
+}
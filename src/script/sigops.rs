@@ -0,0 +1,107 @@
+use super::Opcode;
+use crate::{
+    BitcoinError, BitcoinTransaction, Result, Script, TransactionOutput, WITNESS_SCALE_FACTOR,
+    Witness,
+};
+
+/// Sigops charged for a multisig whose key count isn't known.
+pub const MAX_PUBKEYS_PER_MULTISIG: usize = 20;
+
+impl Script {
+    /// Counts signature operations like Core's `GetSigOpCount`. Legacy
+    /// counting charges every `OP_CHECKMULTISIG` the maximum of 20; accurate
+    /// counting uses the preceding `OP_n` when there is one.
+    pub fn count_sigops(&self, accurate: bool) -> usize {
+        let mut count = 0;
+        let mut last = None;
+        for instruction in self.instructions() {
+            let Ok(instruction) = instruction else {
+                break;
+            };
+            match instruction.opcode() {
+                Some(Opcode::OP_CHECKSIG | Opcode::OP_CHECKSIGVERIFY) => count += 1,
+                Some(Opcode::OP_CHECKMULTISIG | Opcode::OP_CHECKMULTISIGVERIFY) => {
+                    count += match last.and_then(Opcode::small_int) {
+                        Some(n @ 1..=16) if accurate => n as usize,
+                        _ => MAX_PUBKEYS_PER_MULTISIG,
+                    }
+                }
+                _ => {}
+            }
+            last = instruction.opcode();
+        }
+        count
+    }
+
+    /// The last data push, provided the script is push-only, as used to find
+    /// a P2SH redeem script inside a scriptSig.
+    fn last_push_if_push_only(&self) -> Option<&[u8]> {
+        let mut last = None;
+        for instruction in self.instructions() {
+            let instruction = instruction.ok()?;
+            if !instruction.is_push() {
+                return None;
+            }
+            last = instruction.push_bytes();
+        }
+        last
+    }
+}
+
+fn witness_sigops(version: u8, program: &[u8], witness: &Witness) -> usize {
+    match (version, program.len()) {
+        (0, 20) => 1,
+        (0, 32) => witness
+            .items
+            .last()
+            .map_or(0, |script| Script::new(script.clone()).count_sigops(true)),
+        _ => 0,
+    }
+}
+
+impl BitcoinTransaction {
+    /// Signature operations in scriptSigs and output scripts, counted the
+    /// legacy (inaccurate) way.
+    pub fn legacy_sigop_count(&self) -> usize {
+        let inputs = self.inputs.iter().map(|i| i.script_sig.count_sigops(false));
+        let outputs = self
+            .outputs
+            .iter()
+            .map(|o| o.script_pubkey.count_sigops(false));
+        inputs.chain(outputs).sum()
+    }
+
+    /// Total sigop cost as Core's `GetTransactionSigOpCost` computes it with
+    /// P2SH and segwit active. `prevouts` holds the output spent by each
+    /// input, in input order.
+    pub fn sigop_cost(&self, prevouts: &[TransactionOutput]) -> Result<usize> {
+        let mut cost = self.legacy_sigop_count() * WITNESS_SCALE_FACTOR;
+        if self.is_coinbase() {
+            return Ok(cost);
+        }
+        if prevouts.len() != self.inputs.len() {
+            return Err(BitcoinError::PrevoutsMismatch {
+                inputs: self.inputs.len(),
+                prevouts: prevouts.len(),
+            });
+        }
+        for (input, prevout) in self.inputs.iter().zip(prevouts) {
+            let spk = &prevout.script_pubkey;
+            let redeem = spk
+                .is_p2sh()
+                .then(|| input.script_sig.last_push_if_push_only())
+                .flatten()
+                .map(|bytes| Script::new(bytes.to_vec()));
+            if let Some(redeem) = &redeem {
+                cost += redeem.count_sigops(true) * WITNESS_SCALE_FACTOR;
+            }
+            let program = spk
+                .witness_program()
+                .or_else(|| redeem.as_ref().and_then(Script::witness_program));
+            if let Some((version, program)) = program {
+                cost += witness_sigops(version, program, &input.witness);
+            }
+        }
+        Ok(cost)
+    }
+}
@@ -0,0 +1,89 @@
+use super::{Opcode, ScriptBuilder};
+use crate::{PublicKey, Script, ScriptHash, XOnlyPublicKey, hashes};
+
+impl Script {
+    pub fn new_p2pk(pubkey: &PublicKey) -> Script {
+        ScriptBuilder::new()
+            .push_slice(&pubkey.to_bytes())
+            .push_opcode(Opcode::OP_CHECKSIG)
+            .into_script()
+    }
+
+    pub fn new_p2pkh(pubkey_hash: &[u8; 20]) -> Script {
+        ScriptBuilder::new()
+            .push_opcode(Opcode::OP_DUP)
+            .push_opcode(Opcode::OP_HASH160)
+            .push_slice(pubkey_hash)
+            .push_opcode(Opcode::OP_EQUALVERIFY)
+            .push_opcode(Opcode::OP_CHECKSIG)
+            .into_script()
+    }
+
+    /// The scriptSig spending a P2PKH output: `sig` (with its sighash
+    /// type byte) and `pubkey`.
+    pub fn new_p2pkh_script_sig(sig: &[u8], pubkey: &PublicKey) -> Script {
+        ScriptBuilder::new()
+            .push_slice(sig)
+            .push_slice(&pubkey.to_bytes())
+            .into_script()
+    }
+
+    pub fn new_p2sh(script_hash: &[u8; 20]) -> Script {
+        ScriptBuilder::new()
+            .push_opcode(Opcode::OP_HASH160)
+            .push_slice(script_hash)
+            .push_opcode(Opcode::OP_EQUAL)
+            .into_script()
+    }
+
+    pub fn new_p2wpkh(pubkey_hash: &[u8; 20]) -> Script {
+        Self::new_witness_program(0, pubkey_hash)
+    }
+
+    pub fn new_p2wsh(script_hash: &ScriptHash) -> Script {
+        Self::new_witness_program(0, &script_hash.0)
+    }
+
+    pub fn new_p2tr(output_key: &[u8; 32]) -> Script {
+        Self::new_witness_program(1, output_key)
+    }
+
+    /// The P2TR output for `internal_key`, committing to a script tree if
+    /// `merkle_root` is given.
+    pub fn new_p2tr_with_internal_key(
+        internal_key: &XOnlyPublicKey,
+        merkle_root: Option<&[u8; 32]>,
+    ) -> Script {
+        let (output_key, _) = internal_key.tap_tweak(merkle_root);
+        Self::new_p2tr(&output_key.serialize())
+    }
+
+    /// `OP_n <program>`. Panics if `version` is above 16.
+    pub fn new_witness_program(version: u8, program: &[u8]) -> Script {
+        let version = Opcode::from_small_int(version as i32)
+            .filter(|_| version <= 16)
+            .expect("witness version must be 0 to 16");
+        // Programs are always pushed directly, even a single byte 1..16.
+        let mut script = ScriptBuilder::new().push_opcode(version).into_script();
+        script.bytes.push(program.len() as u8);
+        script.bytes.extend_from_slice(program);
+        script
+    }
+
+    pub fn new_op_return(data: &[u8]) -> Script {
+        ScriptBuilder::new()
+            .push_opcode(Opcode::OP_RETURN)
+            .push_slice(data)
+            .into_script()
+    }
+
+    /// The P2SH output paying to this script as a redeem script.
+    pub fn to_p2sh(&self) -> Script {
+        Self::new_p2sh(&hashes::hash160(&self.bytes))
+    }
+
+    /// The P2WSH output paying to this script as a witness script.
+    pub fn to_p2wsh(&self) -> Script {
+        Self::new_p2wsh(&self.script_hash())
+    }
+}
@@ -0,0 +1,50 @@
+use super::{Instruction, Opcode, ScriptBuilder, decode_script_num};
+use crate::Script;
+use alloc::vec::Vec;
+
+impl Script {
+    /// `<lock_time> OP_CHECKLOCKTIMEVERIFY OP_DROP` followed by `script`.
+    /// Lock times below 500,000,000 are block heights, the rest UNIX times.
+    pub fn new_cltv(lock_time: u32, script: &Script) -> Script {
+        timelock(lock_time, Opcode::OP_CHECKLOCKTIMEVERIFY, script)
+    }
+
+    /// `<sequence> OP_CHECKSEQUENCEVERIFY OP_DROP` followed by `script`, with
+    /// `sequence` in BIP68 relative lock-time form.
+    pub fn new_csv(sequence: u32, script: &Script) -> Script {
+        timelock(sequence, Opcode::OP_CHECKSEQUENCEVERIFY, script)
+    }
+
+    /// The lock time and remaining script of a script built by
+    /// [`Script::new_cltv`]. The number must be minimally encoded.
+    pub fn parse_cltv(&self) -> Option<(u32, &[u8])> {
+        self.parse_timelock(Opcode::OP_CHECKLOCKTIMEVERIFY)
+    }
+
+    /// The sequence and remaining script of a script built by
+    /// [`Script::new_csv`].
+    pub fn parse_csv(&self) -> Option<(u32, &[u8])> {
+        self.parse_timelock(Opcode::OP_CHECKSEQUENCEVERIFY)
+    }
+
+    fn parse_timelock(&self, op: Opcode) -> Option<(u32, &[u8])> {
+        let n = match self.instructions().next()?.ok()? {
+            Instruction::PushBytes(data) if data.len() <= 5 => decode_script_num(data),
+            Instruction::PushBytes(_) => return None,
+            Instruction::Op(op) => op.small_int()?.into(),
+        };
+        let n = u32::try_from(n).ok()?;
+        let prefix = timelock(n, op, &Script::new(Vec::new()));
+        let rest = self.bytes.strip_prefix(prefix.bytes.as_slice())?;
+        Some((n, rest))
+    }
+}
+
+fn timelock(n: u32, op: Opcode, script: &Script) -> Script {
+    ScriptBuilder::new()
+        .push_int(n as i64)
+        .push_opcode(op)
+        .push_opcode(Opcode::OP_DROP)
+        .push_raw(&script.bytes)
+        .into_script()
+}
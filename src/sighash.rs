@@ -0,0 +1,640 @@
+//! Signature hashes: the digests that transaction signatures commit to.
+
+use crate::hashes::{HashEngine, sha256, tagged_hash};
+use crate::io;
+use crate::{
+    BitcoinError, BitcoinTransaction, CompactSize, ConsensusEncode, Instruction, Opcode, Result,
+    Script, TransactionInput, TransactionOutput,
+};
+#[cfg(feature = "secp256k1")]
+use crate::{
+    PrivateKey, ScriptType, Witness, XOnlyPublicKey, ecdsa,
+    schnorr::{self, SchnorrSignature},
+};
+use alloc::vec;
+use alloc::vec::Vec;
+use core::borrow::Borrow;
+use core::cell::OnceCell;
+use core::fmt;
+use core::str::FromStr;
+
+const SIGHASH_DEFAULT: u8 = 0x00;
+const SIGHASH_ALL: u8 = 0x01;
+const SIGHASH_NONE: u8 = 0x02;
+const SIGHASH_SINGLE: u8 = 0x03;
+const SIGHASH_ANYONECANPAY: u8 = 0x80;
+
+/// Which parts of a transaction a signature commits to: every output, none
+/// or the one at the input's index, and with ANYONECANPAY only the signing
+/// input rather than all of them.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum SighashType {
+    /// Taproot's implicit type, which signs like `All` but is left out of
+    /// the signature.
+    Default,
+    All,
+    None,
+    Single,
+    AllPlusAnyoneCanPay,
+    NonePlusAnyoneCanPay,
+    SinglePlusAnyoneCanPay,
+    /// A byte outside the seven above, which consensus still accepts on
+    /// ECDSA signatures. It signs like the type its low five bits and
+    /// ANYONECANPAY bit select, but the hash commits to the byte itself.
+    Undefined(u8),
+}
+
+impl SighashType {
+    /// With `strict`, only the seven defined bytes are accepted, as BIP341
+    /// and Core's `STRICTENC` policy require. Otherwise any other byte is
+    /// kept as `Undefined`, the way consensus reads it for ECDSA.
+    pub fn from_u8(n: u8, strict: bool) -> Result<SighashType> {
+        use SighashType::*;
+        let sighash_type = match n {
+            SIGHASH_DEFAULT => Default,
+            SIGHASH_ALL => All,
+            SIGHASH_NONE => None,
+            SIGHASH_SINGLE => Single,
+            0x81 => AllPlusAnyoneCanPay,
+            0x82 => NonePlusAnyoneCanPay,
+            0x83 => SinglePlusAnyoneCanPay,
+            _ if strict => return Err(BitcoinError::InvalidSighash("undefined sighash type")),
+            _ => Undefined(n),
+        };
+        Ok(sighash_type)
+    }
+
+    pub fn to_u8(self) -> u8 {
+        match self {
+            SighashType::Default => SIGHASH_DEFAULT,
+            SighashType::All => SIGHASH_ALL,
+            SighashType::None => SIGHASH_NONE,
+            SighashType::Single => SIGHASH_SINGLE,
+            SighashType::AllPlusAnyoneCanPay => SIGHASH_ALL | SIGHASH_ANYONECANPAY,
+            SighashType::NonePlusAnyoneCanPay => SIGHASH_NONE | SIGHASH_ANYONECANPAY,
+            SighashType::SinglePlusAnyoneCanPay => SIGHASH_SINGLE | SIGHASH_ANYONECANPAY,
+            SighashType::Undefined(n) => n,
+        }
+    }
+
+    pub fn is_anyone_can_pay(self) -> bool {
+        self.to_u8() & SIGHASH_ANYONECANPAY != 0
+    }
+
+    // NONE or SINGLE, as the low five bits select them; anything else signs
+    // like ALL.
+    fn base_type(self) -> u8 {
+        match self.to_u8() & 0x1f {
+            base @ (SIGHASH_NONE | SIGHASH_SINGLE) => base,
+            _ => SIGHASH_ALL,
+        }
+    }
+}
+
+impl fmt::Display for SighashType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if let SighashType::Undefined(n) = self {
+            return write!(f, "{:#04x}", n);
+        }
+        let base = match self.to_u8() & !SIGHASH_ANYONECANPAY {
+            SIGHASH_DEFAULT => "DEFAULT",
+            SIGHASH_NONE => "NONE",
+            SIGHASH_SINGLE => "SINGLE",
+            _ => "ALL",
+        };
+        f.write_str(base)?;
+        if self.is_anyone_can_pay() {
+            f.write_str("|ANYONECANPAY")?;
+        }
+        Ok(())
+    }
+}
+
+impl FromStr for SighashType {
+    type Err = BitcoinError;
+
+    /// Parses the names Core's `signrawtransactionwithkey` takes, such as
+    /// "ALL" or "SINGLE|ANYONECANPAY".
+    fn from_str(s: &str) -> Result<Self> {
+        let (base, anyone_can_pay) = match s.split_once('|') {
+            Some((base, "ANYONECANPAY")) => (base, SIGHASH_ANYONECANPAY),
+            Some(_) => return Err(BitcoinError::InvalidSighash("unknown sighash type name")),
+            None => (s, 0),
+        };
+        let base = match base {
+            "DEFAULT" if anyone_can_pay == 0 => SIGHASH_DEFAULT,
+            "ALL" => SIGHASH_ALL,
+            "NONE" => SIGHASH_NONE,
+            "SINGLE" => SIGHASH_SINGLE,
+            _ => return Err(BitcoinError::InvalidSighash("unknown sighash type name")),
+        };
+        SighashType::from_u8(base | anyone_can_pay, true)
+    }
+}
+
+/// The `codesep_pos` of a tapscript with no executed `OP_CODESEPARATOR`.
+pub const NO_CODESEPARATOR: u32 = 0xffff_ffff;
+
+/// What the original client returned, instead of failing, for
+/// `SIGHASH_SINGLE` without a matching output. Signatures over it are
+/// valid, so consensus keeps it.
+const SIGHASH_SINGLE_BUG: [u8; 32] = {
+    let mut one = [0; 32];
+    one[0] = 1;
+    one
+};
+
+/// The pre-segwit signature hash of input `input_index`, as Core's
+/// `SignatureHash` with `SigVersion::BASE`. `script_code` is the script
+/// being executed from its last `OP_CODESEPARATOR`, with the signature
+/// already removed by `FindAndDelete`; remaining `OP_CODESEPARATOR`s are
+/// dropped here.
+pub fn legacy_signature_hash(
+    tx: &BitcoinTransaction,
+    input_index: usize,
+    script_code: &Script,
+    sighash_type: SighashType,
+) -> Result<[u8; 32]> {
+    if input_index >= tx.inputs.len() {
+        return Err(BitcoinError::InputIndexOutOfRange {
+            index: input_index,
+            inputs: tx.inputs.len(),
+        });
+    }
+    let base_type = sighash_type.base_type();
+    let anyone_can_pay = sighash_type.is_anyone_can_pay();
+    if base_type == SIGHASH_SINGLE && input_index >= tx.outputs.len() {
+        return Ok(SIGHASH_SINGLE_BUG);
+    }
+
+    let script_code = without_code_separators(script_code);
+    let inputs = tx
+        .inputs
+        .iter()
+        .enumerate()
+        .filter(|&(i, _)| !anyone_can_pay || i == input_index)
+        .map(|(i, input)| {
+            if i == input_index {
+                TransactionInput::new(
+                    input.previous_output.clone(),
+                    script_code.clone(),
+                    input.sequence,
+                )
+            } else {
+                // NONE and SINGLE let other inputs update their sequence.
+                let sequence = match base_type {
+                    SIGHASH_NONE | SIGHASH_SINGLE => 0,
+                    _ => input.sequence,
+                };
+                TransactionInput::new(input.previous_output.clone(), Script::new(vec![]), sequence)
+            }
+        })
+        .collect();
+    let outputs = match base_type {
+        SIGHASH_NONE => vec![],
+        // Outputs before ours are blanked to a value of -1 and no script.
+        SIGHASH_SINGLE => tx.outputs[..input_index]
+            .iter()
+            .map(|_| TransactionOutput::new(u64::MAX, Script::new(vec![])))
+            .chain([tx.outputs[input_index].clone()])
+            .collect(),
+        _ => tx.outputs.clone(),
+    };
+
+    let tx = BitcoinTransaction::new(tx.version, inputs, outputs, tx.lock_time);
+    let mut engine = HashEngine::new();
+    tx.consensus_encode_legacy(&mut engine)
+        .expect("hashing cannot fail");
+    u32::from(sighash_type.to_u8())
+        .consensus_encode(&mut engine)
+        .expect("hashing cannot fail");
+    Ok(engine.finalize_double())
+}
+
+/// The BIP143 signature hash of segwit v0 input `input_index`, which spends
+/// `value` satoshis. `script_code` is the P2PKH script of the key hash for
+/// P2WPKH, or the witness script from its last executed `OP_CODESEPARATOR`
+/// for P2WSH; it is signed as given.
+pub fn segwit_v0_signature_hash(
+    tx: &BitcoinTransaction,
+    input_index: usize,
+    script_code: &Script,
+    value: u64,
+    sighash_type: SighashType,
+) -> Result<[u8; 32]> {
+    SighashCache::new(tx).segwit_v0_signature_hash(input_index, script_code, value, sighash_type)
+}
+
+/// The outputs spent by a transaction's inputs, which taproot signatures
+/// commit to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Prevouts<'a> {
+    /// Every input's prevout, in input order.
+    All(&'a [TransactionOutput]),
+    /// Only the prevout of the input at the index, which is all
+    /// `SIGHASH_ANYONECANPAY` needs.
+    One(usize, &'a TransactionOutput),
+}
+
+impl<'a> Prevouts<'a> {
+    fn get(&self, input_index: usize) -> Option<&'a TransactionOutput> {
+        match *self {
+            Prevouts::All(prevouts) => prevouts.get(input_index),
+            Prevouts::One(index, prevout) => (index == input_index).then_some(prevout),
+        }
+    }
+}
+
+/// The BIP341 signature hash of taproot input `input_index`. `annex` is the
+/// witness's annex, with its leading 0x50, if it has one. `script_path`
+/// holds the leaf hash and the position of the last executed
+/// `OP_CODESEPARATOR` (or [`NO_CODESEPARATOR`]) for a script-path spend,
+/// and is `None` for a key-path spend.
+pub fn taproot_signature_hash(
+    tx: &BitcoinTransaction,
+    input_index: usize,
+    prevouts: &Prevouts,
+    annex: Option<&[u8]>,
+    script_path: Option<([u8; 32], u32)>,
+    sighash_type: SighashType,
+) -> Result<[u8; 32]> {
+    SighashCache::new(tx).taproot_signature_hash(
+        input_index,
+        prevouts,
+        annex,
+        script_path,
+        sighash_type,
+    )
+}
+
+/// Computes the signature hashes of many inputs of one transaction,
+/// hashing its outpoints, sequences and outputs once instead of once per
+/// input. Taproot hashes also cache the spent amounts and scripts, so
+/// every call must be given the same prevouts.
+#[derive(Debug, Clone)]
+pub struct SighashCache<T: Borrow<BitcoinTransaction>> {
+    tx: T,
+    sha_prevouts: OnceCell<[u8; 32]>,
+    sha_sequences: OnceCell<[u8; 32]>,
+    sha_outputs: OnceCell<[u8; 32]>,
+    sha_amounts: OnceCell<[u8; 32]>,
+    sha_script_pubkeys: OnceCell<[u8; 32]>,
+}
+
+impl<T: Borrow<BitcoinTransaction>> SighashCache<T> {
+    pub fn new(tx: T) -> Self {
+        Self {
+            tx,
+            sha_prevouts: OnceCell::new(),
+            sha_sequences: OnceCell::new(),
+            sha_outputs: OnceCell::new(),
+            sha_amounts: OnceCell::new(),
+            sha_script_pubkeys: OnceCell::new(),
+        }
+    }
+
+    pub fn transaction(&self) -> &BitcoinTransaction {
+        self.tx.borrow()
+    }
+
+    pub fn into_transaction(self) -> T {
+        self.tx
+    }
+
+    /// See [`legacy_signature_hash`]. Legacy hashes share nothing between
+    /// inputs, so this is only here for uniformity.
+    pub fn legacy_signature_hash(
+        &self,
+        input_index: usize,
+        script_code: &Script,
+        sighash_type: SighashType,
+    ) -> Result<[u8; 32]> {
+        legacy_signature_hash(self.transaction(), input_index, script_code, sighash_type)
+    }
+
+    /// See [`segwit_v0_signature_hash`].
+    pub fn segwit_v0_signature_hash(
+        &self,
+        input_index: usize,
+        script_code: &Script,
+        value: u64,
+        sighash_type: SighashType,
+    ) -> Result<[u8; 32]> {
+        let tx = self.transaction();
+        let input = tx
+            .inputs
+            .get(input_index)
+            .ok_or(BitcoinError::InputIndexOutOfRange {
+                index: input_index,
+                inputs: tx.inputs.len(),
+            })?;
+        let base_type = sighash_type.base_type();
+        let anyone_can_pay = sighash_type.is_anyone_can_pay();
+
+        let zero = [0u8; 32];
+        let hash_prevouts = if anyone_can_pay {
+            zero
+        } else {
+            sha256(self.sha_prevouts())
+        };
+        let hash_sequence = if anyone_can_pay || matches!(base_type, SIGHASH_NONE | SIGHASH_SINGLE)
+        {
+            zero
+        } else {
+            sha256(self.sha_sequences())
+        };
+        // Unlike legacy, SIGHASH_SINGLE without a matching output just signs
+        // no outputs.
+        let hash_outputs = match base_type {
+            SIGHASH_NONE => zero,
+            SIGHASH_SINGLE => match tx.outputs.get(input_index) {
+                Some(output) => sha256(&sha_encoded(|engine| output.consensus_encode(engine))),
+                None => zero,
+            },
+            _ => sha256(self.sha_outputs()),
+        };
+
+        let mut engine = HashEngine::new();
+        let mut write = || -> io::Result<()> {
+            tx.version.consensus_encode(&mut engine)?;
+            engine.input(&hash_prevouts);
+            engine.input(&hash_sequence);
+            input.previous_output.consensus_encode(&mut engine)?;
+            script_code.consensus_encode(&mut engine)?;
+            value.consensus_encode(&mut engine)?;
+            input.sequence.consensus_encode(&mut engine)?;
+            engine.input(&hash_outputs);
+            tx.lock_time.consensus_encode(&mut engine)?;
+            u32::from(sighash_type.to_u8()).consensus_encode(&mut engine)?;
+            Ok(())
+        };
+        write().expect("hashing cannot fail");
+        Ok(engine.finalize_double())
+    }
+
+    /// See [`taproot_signature_hash`].
+    pub fn taproot_signature_hash(
+        &self,
+        input_index: usize,
+        prevouts: &Prevouts,
+        annex: Option<&[u8]>,
+        script_path: Option<([u8; 32], u32)>,
+        sighash_type: SighashType,
+    ) -> Result<[u8; 32]> {
+        let tx = self.transaction();
+        let input = tx
+            .inputs
+            .get(input_index)
+            .ok_or(BitcoinError::InputIndexOutOfRange {
+                index: input_index,
+                inputs: tx.inputs.len(),
+            })?;
+        if let SighashType::Undefined(_) = sighash_type {
+            return Err(BitcoinError::InvalidSighash("undefined sighash type"));
+        }
+        let base_type = sighash_type.base_type();
+        let anyone_can_pay = sighash_type.is_anyone_can_pay();
+        if annex.is_some_and(|annex| annex.first() != Some(&0x50)) {
+            return Err(BitcoinError::InvalidSighash("annex must start with 0x50"));
+        }
+        let output = match base_type {
+            SIGHASH_SINGLE => Some(tx.outputs.get(input_index).ok_or(
+                BitcoinError::InvalidSighash("SIGHASH_SINGLE without a matching output"),
+            )?),
+            _ => None,
+        };
+        let all_prevouts = match *prevouts {
+            Prevouts::All(all) if all.len() != tx.inputs.len() => {
+                return Err(BitcoinError::PrevoutsMismatch {
+                    inputs: tx.inputs.len(),
+                    prevouts: all.len(),
+                });
+            }
+            Prevouts::All(all) => Some(all),
+            Prevouts::One(..) if !anyone_can_pay => {
+                return Err(BitcoinError::InvalidSighash(
+                    "every prevout is needed without SIGHASH_ANYONECANPAY",
+                ));
+            }
+            Prevouts::One(..) => None,
+        };
+        let prevout = prevouts
+            .get(input_index)
+            .ok_or(BitcoinError::InvalidSighash(
+                "missing the signing input's prevout",
+            ))?;
+
+        // BIP341 caps the message at 206 bytes.
+        let mut msg = Vec::with_capacity(206);
+        let mut write = || -> io::Result<()> {
+            // The epoch, then the hash type byte.
+            msg.extend_from_slice(&[0x00, sighash_type.to_u8()]);
+            tx.version.consensus_encode(&mut msg)?;
+            tx.lock_time.consensus_encode(&mut msg)?;
+            if let Some(all) = all_prevouts.filter(|_| !anyone_can_pay) {
+                msg.extend_from_slice(self.sha_prevouts());
+                msg.extend_from_slice(self.sha_amounts.get_or_init(|| {
+                    sha_encoded(|engine| {
+                        all.iter().try_for_each(|prevout| {
+                            prevout.value.consensus_encode(engine).map(drop)
+                        })
+                    })
+                }));
+                msg.extend_from_slice(self.sha_script_pubkeys.get_or_init(|| {
+                    sha_encoded(|engine| {
+                        all.iter().try_for_each(|prevout| {
+                            prevout.script_pubkey.consensus_encode(engine).map(drop)
+                        })
+                    })
+                }));
+                msg.extend_from_slice(self.sha_sequences());
+            }
+            if !matches!(base_type, SIGHASH_NONE | SIGHASH_SINGLE) {
+                msg.extend_from_slice(self.sha_outputs());
+            }
+
+            let spend_type = (u8::from(script_path.is_some()) << 1) | u8::from(annex.is_some());
+            msg.extend_from_slice(&[spend_type]);
+            if anyone_can_pay {
+                input.previous_output.consensus_encode(&mut msg)?;
+                prevout.consensus_encode(&mut msg)?;
+                input.sequence.consensus_encode(&mut msg)?;
+            } else {
+                (input_index as u32).consensus_encode(&mut msg)?;
+            }
+            if let Some(annex) = annex {
+                msg.extend_from_slice(&sha_encoded(|engine| {
+                    CompactSize::new(annex.len() as u64).consensus_encode(engine)?;
+                    engine.input(annex);
+                    Ok(())
+                }));
+            }
+            if let Some(output) = output {
+                msg.extend_from_slice(&sha_encoded(|engine| output.consensus_encode(engine)));
+            }
+            if let Some((leaf_hash, codesep_pos)) = script_path {
+                msg.extend_from_slice(&leaf_hash);
+                // key_version 0, the only one BIP342 defines.
+                msg.extend_from_slice(&[0x00]);
+                codesep_pos.consensus_encode(&mut msg)?;
+            }
+            Ok(())
+        };
+        write().expect("hashing cannot fail");
+        Ok(tagged_hash("TapSighash", &msg))
+    }
+
+    // The single SHA256s of every outpoint, sequence and output, which
+    // BIP143 hashes again and BIP341 uses as they are.
+    fn sha_prevouts(&self) -> &[u8; 32] {
+        self.sha_prevouts.get_or_init(|| {
+            sha_encoded(|engine| {
+                self.transaction()
+                    .inputs
+                    .iter()
+                    .try_for_each(|input| input.previous_output.consensus_encode(engine).map(drop))
+            })
+        })
+    }
+
+    fn sha_sequences(&self) -> &[u8; 32] {
+        self.sha_sequences.get_or_init(|| {
+            sha_encoded(|engine| {
+                self.transaction()
+                    .inputs
+                    .iter()
+                    .try_for_each(|input| input.sequence.consensus_encode(engine).map(drop))
+            })
+        })
+    }
+
+    fn sha_outputs(&self) -> &[u8; 32] {
+        self.sha_outputs.get_or_init(|| {
+            sha_encoded(|engine| {
+                self.transaction()
+                    .outputs
+                    .iter()
+                    .try_for_each(|output| output.consensus_encode(engine).map(drop))
+            })
+        })
+    }
+}
+
+#[cfg(feature = "secp256k1")]
+impl BitcoinTransaction {
+    /// Signs input `index`, which spends `prevout`, with `key` and installs
+    /// the scriptSig and witness. The sighash algorithm follows from the
+    /// output type: P2PK, P2PKH, P2WPKH, P2WPKH nested in P2SH, or a BIP86
+    /// key-path P2TR output. Taproot signatures commit to every prevout, so
+    /// with only `prevout` known those need an ANYONECANPAY type unless it
+    /// is the only input; they use all-zero auxiliary randomness, which
+    /// BIP340 allows.
+    pub fn sign_input(
+        &mut self,
+        index: usize,
+        prevout: &TransactionOutput,
+        key: &PrivateKey,
+        sighash_type: SighashType,
+    ) -> Result<()> {
+        let mismatch = BitcoinError::CannotSign("key does not match the prevout script");
+        let script_pubkey = &prevout.script_pubkey;
+        let script_type = script_pubkey.classify();
+        if sighash_type == SighashType::Default && script_type != ScriptType::P2tr {
+            return Err(BitcoinError::InvalidSighash(
+                "SIGHASH_DEFAULT is taproot-only",
+            ));
+        }
+        let pubkey = key.public_key();
+        let cache = SighashCache::new(&*self);
+        let ecdsa_sign =
+            |sighash: [u8; 32]| ecdsa::sign_with_sighash_type(key, &sighash, sighash_type.to_u8());
+
+        let (script_sig, witness) = match script_type {
+            ScriptType::P2pk | ScriptType::P2pkh => {
+                let is_p2pk = *script_pubkey == Script::new_p2pk(&pubkey);
+                if !is_p2pk && *script_pubkey != Script::new_p2pkh(&pubkey.pubkey_hash()) {
+                    return Err(mismatch);
+                }
+                let sig =
+                    ecdsa_sign(cache.legacy_signature_hash(index, script_pubkey, sighash_type)?);
+                let script_sig = if is_p2pk {
+                    Script::builder().push_slice(&sig).into_script()
+                } else {
+                    Script::new_p2pkh_script_sig(&sig, &pubkey)
+                };
+                (script_sig, Witness::default())
+            }
+            ScriptType::P2wpkh | ScriptType::P2sh => {
+                let program = pubkey
+                    .wpubkey_hash()
+                    .map(|hash| Script::new_p2wpkh(&hash))
+                    .map_err(|_| mismatch.clone())?;
+                let script_sig = if *script_pubkey == program {
+                    Script::new(vec![])
+                } else if *script_pubkey == program.to_p2sh() {
+                    Script::builder().push_slice(&program.bytes).into_script()
+                } else {
+                    return Err(mismatch);
+                };
+                let script_code = Script::new_p2pkh(&pubkey.pubkey_hash());
+                let sig = ecdsa_sign(cache.segwit_v0_signature_hash(
+                    index,
+                    &script_code,
+                    prevout.value,
+                    sighash_type,
+                )?);
+                (script_sig, Witness::new_p2wpkh(&sig, &pubkey))
+            }
+            ScriptType::P2tr => {
+                let internal_key = XOnlyPublicKey::from(pubkey);
+                if *script_pubkey != Script::new_p2tr_with_internal_key(&internal_key, None) {
+                    return Err(mismatch);
+                }
+                let prevouts = if self.inputs.len() == 1 {
+                    Prevouts::All(core::slice::from_ref(prevout))
+                } else {
+                    Prevouts::One(index, prevout)
+                };
+                let sighash =
+                    cache.taproot_signature_hash(index, &prevouts, None, None, sighash_type)?;
+                let sig = SchnorrSignature {
+                    signature: schnorr::sign(&key.tap_tweak(None), &sighash, &[0; 32]),
+                    sighash_type: sighash_type.to_u8(),
+                };
+                (Script::new(vec![]), Witness::new_p2tr_key_spend(&sig))
+            }
+            _ => return Err(BitcoinError::CannotSign("unsupported output type")),
+        };
+        // The sighash has already checked `index`.
+        let input = &mut self.inputs[index];
+        input.script_sig = script_sig;
+        input.witness = witness;
+        Ok(())
+    }
+}
+
+fn sha_encoded<T>(encode: impl FnOnce(&mut HashEngine) -> io::Result<T>) -> [u8; 32] {
+    let mut engine = HashEngine::new();
+    encode(&mut engine).expect("hashing cannot fail");
+    engine.finalize()
+}
+
+// Like Core's `SerializeScriptCode`, anything after an unparsable opcode
+// is kept as it is.
+fn without_code_separators(script: &Script) -> Script {
+    let mut bytes = Vec::with_capacity(script.bytes.len());
+    let mut instructions = script.instructions();
+    loop {
+        let start = instructions.position();
+        match instructions.next() {
+            None => break,
+            Some(Ok(Instruction::Op(Opcode::OP_CODESEPARATOR))) => {}
+            Some(Ok(_)) => bytes.extend_from_slice(&script.bytes[start..instructions.position()]),
+            Some(Err(_)) => {
+                bytes.extend_from_slice(&script.bytes[start..]);
+                break;
+            }
+        }
+    }
+    Script::new(bytes)
+}
@@ -0,0 +1,167 @@
+use crate::{
+    BitcoinError, BlockHash, Coin, CompactSize, ConsensusDecode, ConsensusEncode, OutPoint, Result,
+    Txid, check_count, io, read_array, read_compact_size, read_u64,
+};
+use alloc::vec;
+use alloc::vec::Vec;
+
+const SNAPSHOT_MAGIC: [u8; 5] = *b"utxo\xff";
+pub const SNAPSHOT_VERSION: u16 = 2;
+
+/// Header of a `dumptxoutset` file.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct SnapshotMetadata {
+    pub network_magic: [u8; 4],
+    pub base_blockhash: BlockHash,
+    pub coins_count: u64,
+}
+
+impl ConsensusEncode for SnapshotMetadata {
+    fn consensus_encode<W: io::Write + ?Sized>(&self, w: &mut W) -> io::Result<usize> {
+        w.write_all(&SNAPSHOT_MAGIC)?;
+        w.write_all(&SNAPSHOT_VERSION.to_le_bytes())?;
+        w.write_all(&self.network_magic)?;
+        self.base_blockhash.consensus_encode(w)?;
+        self.coins_count.consensus_encode(w)?;
+        Ok(self.serialized_size())
+    }
+
+    fn serialized_size(&self) -> usize {
+        5 + 2 + 4 + 32 + 8
+    }
+}
+
+impl ConsensusDecode for SnapshotMetadata {
+    /// Fails with `InvalidFormat` on a missing magic or unknown version.
+    fn consensus_decode<R: io::Read + ?Sized>(r: &mut R) -> Result<Self> {
+        let context = "SnapshotMetadata";
+        let magic: [u8; 5] = read_array(r, context)?;
+        let version = u16::from_le_bytes(read_array(r, context)?);
+        if magic != SNAPSHOT_MAGIC || version != SNAPSHOT_VERSION {
+            return Err(BitcoinError::InvalidFormat);
+        }
+        Ok(SnapshotMetadata {
+            network_magic: read_array(r, context)?,
+            base_blockhash: BlockHash(read_array(r, context)?),
+            coins_count: read_u64(r, context)?,
+        })
+    }
+}
+
+/// Streams `(OutPoint, Coin)` pairs out of a UTXO snapshot.
+#[derive(Debug)]
+pub struct SnapshotReader<R> {
+    reader: R,
+    metadata: SnapshotMetadata,
+    remaining: u64,
+    group: Option<(Txid, u64)>,
+}
+
+impl<R: io::Read> SnapshotReader<R> {
+    pub fn new(mut reader: R) -> Result<Self> {
+        let metadata = SnapshotMetadata::consensus_decode(&mut reader)?;
+        Ok(Self {
+            reader,
+            remaining: metadata.coins_count,
+            metadata,
+            group: None,
+        })
+    }
+
+    pub fn metadata(&self) -> &SnapshotMetadata {
+        &self.metadata
+    }
+
+    fn read_coin(&mut self) -> Result<(OutPoint, Coin)> {
+        let context = "SnapshotReader";
+        let (txid, left) = match self.group.take() {
+            Some((txid, left)) if left > 0 => (txid, left),
+            _ => {
+                let txid = Txid(read_array(&mut self.reader, context)?);
+                let count = read_compact_size(&mut self.reader, context, false)?;
+                if count == 0 || count > self.remaining {
+                    return Err(BitcoinError::InvalidFormat);
+                }
+                (txid, count)
+            }
+        };
+        let vout = read_compact_size(&mut self.reader, context, false)?;
+        let vout = check_count(context, vout, u32::MAX as usize)? as u32;
+        let coin = Coin::consensus_decode(&mut self.reader)?;
+        self.group = Some((txid.clone(), left - 1));
+        Ok((OutPoint::new(txid.0, vout), coin))
+    }
+}
+
+impl<R: io::Read> Iterator for SnapshotReader<R> {
+    type Item = Result<(OutPoint, Coin)>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.remaining == 0 {
+            return None;
+        }
+        let item = self.read_coin();
+        // A decode error leaves the stream position unknown, so stop there.
+        self.remaining = if item.is_ok() { self.remaining - 1 } else { 0 };
+        Some(item)
+    }
+}
+
+/// Writes a UTXO snapshot. Coins must arrive grouped by txid, as they do
+/// when iterating Core's chainstate, and must total the announced count.
+#[derive(Debug)]
+pub struct SnapshotWriter<W> {
+    writer: W,
+    expected: u64,
+    written: u64,
+    group: Option<(Txid, Vec<(u32, Coin)>)>,
+}
+
+impl<W: io::Write> SnapshotWriter<W> {
+    pub fn new(mut writer: W, metadata: &SnapshotMetadata) -> io::Result<Self> {
+        metadata.consensus_encode(&mut writer)?;
+        Ok(Self {
+            writer,
+            expected: metadata.coins_count,
+            written: 0,
+            group: None,
+        })
+    }
+
+    pub fn write_coin(&mut self, outpoint: &OutPoint, coin: &Coin) -> io::Result<()> {
+        match &mut self.group {
+            Some((txid, coins)) if *txid == outpoint.txid => {
+                coins.push((outpoint.vout, coin.clone()));
+            }
+            _ => {
+                self.flush_group()?;
+                self.group = Some((outpoint.txid.clone(), vec![(outpoint.vout, coin.clone())]));
+            }
+        }
+        self.written += 1;
+        Ok(())
+    }
+
+    /// Flushes the last txid group, failing if the number of coins written
+    /// differs from the metadata's `coins_count`.
+    pub fn finish(mut self) -> Result<W> {
+        self.flush_group()?;
+        if self.written != self.expected {
+            return Err(BitcoinError::InvalidFormat);
+        }
+        Ok(self.writer)
+    }
+
+    fn flush_group(&mut self) -> io::Result<()> {
+        let Some((txid, coins)) = self.group.take() else {
+            return Ok(());
+        };
+        txid.consensus_encode(&mut self.writer)?;
+        CompactSize::new(coins.len() as u64).consensus_encode(&mut self.writer)?;
+        for (vout, coin) in &coins {
+            CompactSize::new(*vout as u64).consensus_encode(&mut self.writer)?;
+            coin.consensus_encode(&mut self.writer)?;
+        }
+        Ok(())
+    }
+}
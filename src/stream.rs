@@ -0,0 +1,188 @@
+use crate::{
+    BitcoinError, BitcoinTransaction, ConsensusDecode, DecodeLimits, DecodeOptions, Result,
+    SEGWIT_FLAG,
+};
+use alloc::vec::Vec;
+
+pub const DEFAULT_MAX_FRAME_SIZE: usize = 4_000_000;
+
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub enum ParseProgress {
+    Complete(BitcoinTransaction),
+    NeedMore(usize),
+}
+
+/// Splits transactions out of a byte stream that arrives in pieces. A frame
+/// longer than the maximum is an `OversizedFrame` error as soon as its
+/// length is known, so a peer can't make the parser buffer without bound.
+/// After an error the bad frame is dropped and parsing resumes after it;
+/// an oversized frame whose end isn't known yet takes the whole buffer
+/// with it.
+#[derive(Debug, Clone)]
+pub struct TxStreamParser {
+    buf: Vec<u8>,
+    max_frame_size: usize,
+    limits: DecodeLimits,
+}
+
+impl TxStreamParser {
+    pub fn new() -> Self {
+        Self::with_max_frame_size(DEFAULT_MAX_FRAME_SIZE)
+    }
+
+    pub fn with_max_frame_size(max_frame_size: usize) -> Self {
+        Self {
+            buf: Vec::new(),
+            max_frame_size,
+            limits: DecodeLimits::default(),
+        }
+    }
+
+    /// Decodes frames with `limits` instead of the defaults.
+    pub fn with_limits(mut self, limits: DecodeLimits) -> Self {
+        self.limits = limits;
+        self
+    }
+
+    pub fn max_frame_size(&self) -> usize {
+        self.max_frame_size
+    }
+
+    pub fn buffered(&self) -> usize {
+        self.buf.len()
+    }
+
+    pub fn push(&mut self, data: &[u8]) -> Result<ParseProgress> {
+        self.buf.extend_from_slice(data);
+        self.poll()
+    }
+
+    pub fn poll(&mut self) -> Result<ParseProgress> {
+        match frame_len(&self.buf) {
+            Err(needed) => {
+                let size = self.buf.len().saturating_add(needed);
+                if size > self.max_frame_size {
+                    self.buf.clear();
+                    return Err(self.oversized(size));
+                }
+                Ok(ParseProgress::NeedMore(needed))
+            }
+            Ok(len) => {
+                let frame: Vec<u8> = self.buf.drain(..len).collect();
+                if len > self.max_frame_size {
+                    return Err(self.oversized(len));
+                }
+                let options = DecodeOptions {
+                    limits: self.limits,
+                    allow_trailing: false,
+                    ..DecodeOptions::default()
+                };
+                let (tx, _) = BitcoinTransaction::from_bytes_with(&frame, &options)?;
+                Ok(ParseProgress::Complete(tx))
+            }
+        }
+    }
+
+    pub fn reset(&mut self) {
+        self.buf.clear();
+    }
+
+    fn oversized(&self, size: usize) -> BitcoinError {
+        BitcoinError::OversizedFrame {
+            size,
+            max: self.max_frame_size,
+        }
+    }
+}
+
+impl Default for TxStreamParser {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Walks the transaction framing in `buf` without decoding it, returning the
+/// full encoded length or the minimum number of bytes still missing.
+pub(crate) fn frame_len(buf: &[u8]) -> Result<usize, usize> {
+    let mut s = Scanner { buf, pos: 0 };
+    s.skip(4)?;
+    let mut inputs = s.compact_size()?;
+    let mut flag = 0;
+    if inputs == 0 {
+        flag = s.byte()?;
+        if flag != 0 {
+            inputs = s.compact_size()?;
+            s.skip_inputs(inputs)?;
+            s.skip_outputs()?;
+        }
+    } else {
+        s.skip_inputs(inputs)?;
+        s.skip_outputs()?;
+    }
+    if flag & SEGWIT_FLAG != 0 {
+        for _ in 0..inputs {
+            let items = s.compact_size()?;
+            for _ in 0..items {
+                s.skip_var_bytes()?;
+            }
+        }
+    }
+    s.skip(4)?;
+    Ok(s.pos)
+}
+
+struct Scanner<'a> {
+    buf: &'a [u8],
+    pos: usize,
+}
+
+impl Scanner<'_> {
+    fn skip(&mut self, n: u64) -> Result<(), usize> {
+        let available = (self.buf.len() - self.pos) as u64;
+        if n > available {
+            return Err(usize::try_from(n - available).unwrap_or(usize::MAX));
+        }
+        self.pos += n as usize;
+        Ok(())
+    }
+
+    fn byte(&mut self) -> Result<u8, usize> {
+        self.skip(1)?;
+        Ok(self.buf[self.pos - 1])
+    }
+
+    fn compact_size(&mut self) -> Result<u64, usize> {
+        let width = match self.byte()? {
+            n @ 0x00..=0xFC => return Ok(n as u64),
+            0xFD => 2,
+            0xFE => 4,
+            0xFF => 8,
+        };
+        self.skip(width)?;
+        let mut arr = [0u8; 8];
+        arr[..width as usize].copy_from_slice(&self.buf[self.pos - width as usize..self.pos]);
+        Ok(u64::from_le_bytes(arr))
+    }
+
+    fn skip_var_bytes(&mut self) -> Result<(), usize> {
+        let len = self.compact_size()?;
+        self.skip(len)
+    }
+
+    fn skip_inputs(&mut self, count: u64) -> Result<(), usize> {
+        for _ in 0..count {
+            self.skip(36)?;
+            self.skip_var_bytes()?;
+            self.skip(4)?;
+        }
+        Ok(())
+    }
+
+    fn skip_outputs(&mut self) -> Result<(), usize> {
+        for _ in 0..self.compact_size()? {
+            self.skip(8)?;
+            self.skip_var_bytes()?;
+        }
+        Ok(())
+    }
+}
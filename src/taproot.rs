@@ -0,0 +1,332 @@
+use crate::curve::Point;
+use crate::hashes::tagged_hash;
+use crate::{BitcoinError, CompactSize, ConsensusEncode, Result, Script, XOnlyPublicKey, fmt_hex};
+use alloc::vec;
+use alloc::vec::Vec;
+use core::fmt;
+
+/// The leaf version of BIP342 tapscript.
+pub const TAPSCRIPT_LEAF_VERSION: u8 = 0xc0;
+/// The deepest a leaf can be, bounded by the control block's size.
+pub const TAPROOT_CONTROL_MAX_NODE_COUNT: usize = 128;
+// Leaf versions share a byte with the output key's parity, and 0x50 would
+// look like an annex.
+const TAPROOT_LEAF_MASK: u8 = 0xfe;
+const ANNEX_TAG: u8 = 0x50;
+
+pub(crate) fn tap_leaf_hash(script: &[u8], leaf_version: u8) -> [u8; 32] {
+    let mut msg = Vec::with_capacity(script.len() + 10);
+    msg.push(leaf_version);
+    CompactSize::new(script.len() as u64).encode_into(&mut msg);
+    msg.extend_from_slice(script);
+    tagged_hash("TapLeaf", &msg)
+}
+
+/// Children are hashed in lexicographic order, so the result doesn't
+/// depend on which side of the tree each is on.
+pub(crate) fn tap_branch_hash(a: &[u8; 32], b: &[u8; 32]) -> [u8; 32] {
+    let (left, right) = if a <= b { (a, b) } else { (b, a) };
+    let mut msg = [0u8; 64];
+    msg[..32].copy_from_slice(left);
+    msg[32..].copy_from_slice(right);
+    tagged_hash("TapBranch", &msg)
+}
+
+/// The BIP341 hash of a script tree leaf. Like other tagged hashes it is
+/// displayed in byte order, not reversed like txids.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct TapLeafHash(pub [u8; 32]);
+
+impl TapLeafHash {
+    pub fn from_script(script: &Script, leaf_version: u8) -> TapLeafHash {
+        TapLeafHash(tap_leaf_hash(&script.bytes, leaf_version))
+    }
+}
+
+impl fmt::Display for TapLeafHash {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt_hex(f, self.0.iter(), false)
+    }
+}
+
+/// The hash of a script tree node: a leaf, or a branch over two nodes. The
+/// root's is the merkle root an output key commits to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct TapNodeHash(pub [u8; 32]);
+
+impl TapNodeHash {
+    /// The branch over `a` and `b`, which is the same whichever side
+    /// each is on.
+    pub fn from_node_hashes(a: TapNodeHash, b: TapNodeHash) -> TapNodeHash {
+        TapNodeHash(tap_branch_hash(&a.0, &b.0))
+    }
+
+    /// The root reached from `leaf` through the sibling hashes of
+    /// `merkle_branch`, innermost first, as in a control block.
+    pub fn from_merkle_branch(leaf: TapLeafHash, merkle_branch: &[[u8; 32]]) -> TapNodeHash {
+        merkle_branch.iter().fold(leaf.into(), |node, sibling| {
+            TapNodeHash::from_node_hashes(node, TapNodeHash(*sibling))
+        })
+    }
+}
+
+impl From<TapLeafHash> for TapNodeHash {
+    fn from(leaf: TapLeafHash) -> Self {
+        TapNodeHash(leaf.0)
+    }
+}
+
+impl fmt::Display for TapNodeHash {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt_hex(f, self.0.iter(), false)
+    }
+}
+
+/// The scalar added to `internal_key` to commit to an optional script tree
+/// root.
+pub(crate) fn tap_tweak_hash(internal_key: &[u8; 32], merkle_root: Option<&[u8; 32]>) -> [u8; 32] {
+    let mut msg = internal_key.to_vec();
+    if let Some(root) = merkle_root {
+        msg.extend_from_slice(root);
+    }
+    tagged_hash("TapTweak", &msg)
+}
+
+/// The output key committing to `internal_key` and an optional script tree
+/// root, with its y-parity. `None` if the internal key isn't a valid
+/// x-coordinate or, with negligible probability, the tweak is invalid.
+pub(crate) fn tweak_internal_key(
+    internal_key: &[u8; 32],
+    merkle_root: Option<&[u8; 32]>,
+) -> Option<([u8; 32], bool)> {
+    let point = Point::lift_x(internal_key)?;
+    let tweaked = point.add_tweak(&tap_tweak_hash(internal_key, merkle_root))?;
+    Some((tweaked.x_bytes(), tweaked.has_odd_y()))
+}
+
+/// What a script-path spend reveals besides the script: the leaf version,
+/// the output key's parity, the internal key and the hashes on the path
+/// from the leaf to the merkle root.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct ControlBlock {
+    pub leaf_version: u8,
+    pub output_key_parity: bool,
+    pub internal_key: XOnlyPublicKey,
+    pub merkle_branch: Vec<[u8; 32]>,
+}
+
+impl ControlBlock {
+    /// Parses the last witness item of a script-path spend.
+    pub fn from_slice(bytes: &[u8]) -> Result<ControlBlock> {
+        let invalid = BitcoinError::InvalidControlBlock;
+        if bytes.len() < 33
+            || !(bytes.len() - 33).is_multiple_of(32)
+            || (bytes.len() - 33) / 32 > TAPROOT_CONTROL_MAX_NODE_COUNT
+        {
+            return Err(invalid("wrong size"));
+        }
+        let internal_key = XOnlyPublicKey::from_slice(&bytes[1..33])
+            .map_err(|_| invalid("internal key is not on the curve"))?;
+        Ok(ControlBlock {
+            leaf_version: bytes[0] & TAPROOT_LEAF_MASK,
+            output_key_parity: bytes[0] & 1 == 1,
+            internal_key,
+            merkle_branch: bytes[33..]
+                .chunks_exact(32)
+                .map(|hash| hash.try_into().unwrap())
+                .collect(),
+        })
+    }
+
+    /// Whether `output_key` commits to `leaf_script` through this control
+    /// block, as BIP341 checks for script-path spends.
+    pub fn verify(&self, output_key: &XOnlyPublicKey, leaf_script: &Script) -> bool {
+        let leaf = TapLeafHash::from_script(leaf_script, self.leaf_version);
+        let root = TapNodeHash::from_merkle_branch(leaf, &self.merkle_branch);
+        tweak_internal_key(&self.internal_key.serialize(), Some(&root.0)).is_some_and(
+            |(key, parity)| key == output_key.serialize() && parity == self.output_key_parity,
+        )
+    }
+
+    pub fn serialize(&self) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(33 + 32 * self.merkle_branch.len());
+        bytes.push(self.leaf_version | self.output_key_parity as u8);
+        bytes.extend_from_slice(&self.internal_key.serialize());
+        for hash in &self.merkle_branch {
+            bytes.extend_from_slice(hash);
+        }
+        bytes
+    }
+}
+
+#[derive(Debug, Clone)]
+struct LeafInfo {
+    script: Script,
+    leaf_version: u8,
+    merkle_branch: Vec<[u8; 32]>,
+}
+
+#[derive(Debug, Clone)]
+struct NodeInfo {
+    hash: [u8; 32],
+    leaves: Vec<LeafInfo>,
+}
+
+impl NodeInfo {
+    fn combine(mut left: NodeInfo, mut right: NodeInfo) -> NodeInfo {
+        for leaf in &mut left.leaves {
+            leaf.merkle_branch.push(right.hash);
+        }
+        for leaf in &mut right.leaves {
+            leaf.merkle_branch.push(left.hash);
+        }
+        left.leaves.append(&mut right.leaves);
+        NodeInfo {
+            hash: tap_branch_hash(&left.hash, &right.hash),
+            leaves: left.leaves,
+        }
+    }
+}
+
+/// Assembles a script tree from leaves given in depth-first order with
+/// their depths, as Core's `TaprootBuilder`: `{A,{B,C}}` is A at depth 1,
+/// then B and C at depth 2.
+#[derive(Debug, Clone, Default)]
+pub struct TaprootBuilder {
+    // The pending left sibling at each depth of the path being built.
+    branch: Vec<Option<NodeInfo>>,
+}
+
+impl TaprootBuilder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds a tapscript leaf.
+    pub fn add_leaf(self, depth: usize, script: Script) -> Result<Self> {
+        self.add_leaf_with_version(depth, script, TAPSCRIPT_LEAF_VERSION)
+    }
+
+    pub fn add_leaf_with_version(
+        mut self,
+        mut depth: usize,
+        script: Script,
+        leaf_version: u8,
+    ) -> Result<Self> {
+        if leaf_version & TAPROOT_LEAF_MASK != leaf_version || leaf_version == ANNEX_TAG {
+            return Err(BitcoinError::InvalidTapTree("invalid leaf version"));
+        }
+        if depth > TAPROOT_CONTROL_MAX_NODE_COUNT {
+            return Err(BitcoinError::InvalidTapTree("leaf is too deep"));
+        }
+        // A leaf cannot go above a subtree still waiting for its sibling.
+        if depth + 1 < self.branch.len() {
+            return Err(BitcoinError::InvalidTapTree(
+                "leaves are not in depth-first order",
+            ));
+        }
+        let mut node = NodeInfo {
+            hash: tap_leaf_hash(&script.bytes, leaf_version),
+            leaves: vec![LeafInfo {
+                script,
+                leaf_version,
+                merkle_branch: Vec::new(),
+            }],
+        };
+        while let Some(Some(left)) = self.branch.get_mut(depth).map(Option::take) {
+            node = NodeInfo::combine(left, node);
+            self.branch.pop();
+            if depth == 0 {
+                return Err(BitcoinError::InvalidTapTree("tree is already complete"));
+            }
+            depth -= 1;
+        }
+        if self.branch.len() <= depth {
+            self.branch.resize(depth + 1, None);
+        }
+        self.branch[depth] = Some(node);
+        Ok(self)
+    }
+
+    /// Whether the leaves so far form a whole tree, or there are none.
+    pub fn is_complete(&self) -> bool {
+        matches!(&self.branch[..], [] | [Some(_)])
+    }
+
+    /// Commits `internal_key` to the tree, which must be complete. Without
+    /// leaves the output can only be spent with the key.
+    pub fn finalize(mut self, internal_key: &XOnlyPublicKey) -> Result<TaprootSpendInfo> {
+        if !self.is_complete() {
+            return Err(BitcoinError::InvalidTapTree("tree is incomplete"));
+        }
+        let root = self.branch.pop().flatten();
+        let merkle_root = root.as_ref().map(|root| root.hash);
+        let (output_key, output_key_parity) = internal_key.tap_tweak(merkle_root.as_ref());
+        Ok(TaprootSpendInfo {
+            internal_key: *internal_key,
+            merkle_root,
+            output_key,
+            output_key_parity,
+            leaves: root.map_or(Vec::new(), |root| root.leaves),
+        })
+    }
+}
+
+/// A finalized script tree: the output key and what each leaf's spend
+/// needs.
+#[derive(Debug, Clone)]
+pub struct TaprootSpendInfo {
+    internal_key: XOnlyPublicKey,
+    merkle_root: Option<[u8; 32]>,
+    output_key: XOnlyPublicKey,
+    output_key_parity: bool,
+    leaves: Vec<LeafInfo>,
+}
+
+impl TaprootSpendInfo {
+    pub fn internal_key(&self) -> XOnlyPublicKey {
+        self.internal_key
+    }
+
+    pub fn merkle_root(&self) -> Option<[u8; 32]> {
+        self.merkle_root
+    }
+
+    pub fn output_key(&self) -> XOnlyPublicKey {
+        self.output_key
+    }
+
+    pub fn output_key_parity(&self) -> bool {
+        self.output_key_parity
+    }
+
+    pub fn script_pubkey(&self) -> Script {
+        Script::new_p2tr(&self.output_key.serialize())
+    }
+
+    /// Each leaf's script with its control block, in the order added.
+    pub fn control_blocks(&self) -> impl Iterator<Item = (&Script, ControlBlock)> {
+        self.leaves
+            .iter()
+            .map(|leaf| (&leaf.script, self.leaf_control_block(leaf)))
+    }
+
+    /// The control block for spending with `script`, if it is a leaf. A
+    /// script in several leaves gets the shallowest one's.
+    pub fn control_block(&self, script: &Script, leaf_version: u8) -> Option<ControlBlock> {
+        self.leaves
+            .iter()
+            .filter(|leaf| leaf.script == *script && leaf.leaf_version == leaf_version)
+            .min_by_key(|leaf| leaf.merkle_branch.len())
+            .map(|leaf| self.leaf_control_block(leaf))
+    }
+
+    fn leaf_control_block(&self, leaf: &LeafInfo) -> ControlBlock {
+        ControlBlock {
+            leaf_version: leaf.leaf_version,
+            output_key_parity: self.output_key_parity,
+            internal_key: self.internal_key,
+            merkle_branch: leaf.merkle_branch.clone(),
+        }
+    }
+}
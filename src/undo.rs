@@ -0,0 +1,127 @@
+use crate::compress::{
+    decode_txout, encode_txout, read_varint, txout_size, varint_size, write_varint,
+};
+use crate::{
+    BitcoinError, CompactSize, ConsensusDecode, ConsensusEncode, Result, TransactionOutput,
+    check_count, io, read_compact_size,
+};
+use alloc::vec;
+use alloc::vec::Vec;
+use serde::{Deserialize, Serialize};
+
+/// An unspent (or, in undo data, just-spent) output with the height of the
+/// block that created it.
+#[derive(Debug, PartialEq, Eq, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
+pub struct Coin {
+    pub output: TransactionOutput,
+    pub height: u32,
+    pub is_coinbase: bool,
+}
+
+impl Coin {
+    fn code(&self) -> u64 {
+        (self.height as u64) << 1 | self.is_coinbase as u64
+    }
+
+    fn from_code(code: u64, output: TransactionOutput) -> Result<Self> {
+        let height = u32::try_from(code >> 1).map_err(|_| BitcoinError::InvalidFormat)?;
+        Ok(Coin {
+            output,
+            height,
+            is_coinbase: code & 1 == 1,
+        })
+    }
+}
+
+impl ConsensusEncode for Coin {
+    fn consensus_encode<W: io::Write + ?Sized>(&self, w: &mut W) -> io::Result<usize> {
+        Ok(write_varint(w, self.code())? + encode_txout(w, &self.output)?)
+    }
+
+    fn serialized_size(&self) -> usize {
+        varint_size(self.code()) + txout_size(&self.output)
+    }
+}
+
+impl ConsensusDecode for Coin {
+    fn consensus_decode<R: io::Read + ?Sized>(r: &mut R) -> Result<Self> {
+        let code = read_varint(r, "Coin")?;
+        Coin::from_code(code, decode_txout(r)?)
+    }
+}
+
+/// The coins spent by one transaction's inputs, in input order.
+#[derive(Debug, PartialEq, Eq, Clone, Default, Serialize, Deserialize)]
+#[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
+pub struct TxUndo {
+    pub prevouts: Vec<Coin>,
+}
+
+impl ConsensusEncode for TxUndo {
+    fn consensus_encode<W: io::Write + ?Sized>(&self, w: &mut W) -> io::Result<usize> {
+        let mut len = CompactSize::new(self.prevouts.len() as u64).consensus_encode(w)?;
+        for coin in &self.prevouts {
+            len += write_varint(w, coin.code())?;
+            // Undo records keep a placeholder for the long-gone tx version.
+            if coin.height > 0 {
+                len += write_varint(w, 0)?;
+            }
+            len += encode_txout(w, &coin.output)?;
+        }
+        Ok(len)
+    }
+
+    fn serialized_size(&self) -> usize {
+        CompactSize::new(self.prevouts.len() as u64).serialized_size()
+            + self
+                .prevouts
+                .iter()
+                .map(|coin| coin.serialized_size() + (coin.height > 0) as usize)
+                .sum::<usize>()
+    }
+}
+
+impl ConsensusDecode for TxUndo {
+    fn consensus_decode<R: io::Read + ?Sized>(r: &mut R) -> Result<Self> {
+        let count = read_compact_size(r, "TxUndo", false)?;
+        let count = check_count("TxUndo", count, usize::MAX)?;
+        let mut prevouts = vec![];
+        for _ in 0..count {
+            let code = read_varint(r, "TxUndo")?;
+            if code >> 1 > 0 {
+                read_varint(r, "TxUndo")?;
+            }
+            prevouts.push(Coin::from_code(code, decode_txout(r)?)?);
+        }
+        Ok(TxUndo { prevouts })
+    }
+}
+
+/// Undo data for a block as stored in `rev*.dat`: one [`TxUndo`] per
+/// non-coinbase transaction.
+///
+/// [`UndoFileReader`](crate::UndoFileReader) reads them from those files.
+#[derive(Debug, PartialEq, Eq, Clone, Default, Serialize, Deserialize)]
+#[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
+pub struct BlockUndo {
+    pub txdata: Vec<TxUndo>,
+}
+
+impl ConsensusEncode for BlockUndo {
+    fn consensus_encode<W: io::Write + ?Sized>(&self, w: &mut W) -> io::Result<usize> {
+        self.txdata.consensus_encode(w)
+    }
+
+    fn serialized_size(&self) -> usize {
+        self.txdata.serialized_size()
+    }
+}
+
+impl ConsensusDecode for BlockUndo {
+    fn consensus_decode<R: io::Read + ?Sized>(r: &mut R) -> Result<Self> {
+        Ok(BlockUndo {
+            txdata: Vec::consensus_decode(r)?,
+        })
+    }
+}
@@ -0,0 +1,183 @@
+use crate::{
+    BitcoinError, BitcoinTransaction, Decoder, OutPoint, Result, SEGWIT_FLAG, Script,
+    TransactionInput, TransactionOutput, Witness, check_count, check_script_len,
+};
+use alloc::vec;
+use alloc::vec::Vec;
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub struct ScriptRef<'a> {
+    pub bytes: &'a [u8],
+}
+
+impl<'a> ScriptRef<'a> {
+    pub fn new(bytes: &'a [u8]) -> Self {
+        Self { bytes }
+    }
+
+    pub fn decode(d: &mut Decoder<'a>) -> Result<Self> {
+        let len = check_script_len(d.read_compact_size()?.value, d.limits())?;
+        Ok(Self::new(d.read_slice(len as usize)?))
+    }
+
+    pub fn to_script(&self) -> Script {
+        Script::new(self.bytes.to_vec())
+    }
+}
+
+#[derive(Debug, PartialEq, Eq, Clone, Default)]
+pub struct WitnessRef<'a> {
+    pub items: Vec<&'a [u8]>,
+}
+
+impl<'a> WitnessRef<'a> {
+    pub fn decode(d: &mut Decoder<'a>) -> Result<Self> {
+        let max_items = d.limits().max_witness_items;
+        let max_item_len = d.limits().max_witness_item_len;
+        let count = check_count("Witness", d.read_compact_size()?.value, max_items)?;
+        let mut items = vec![];
+        for _ in 0..count {
+            let len = check_count("Witness", d.read_compact_size()?.value, max_item_len)?;
+            items.push(d.read_slice(len as usize)?);
+        }
+        Ok(Self { items })
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.items.is_empty()
+    }
+
+    pub fn to_witness(&self) -> Witness {
+        Witness::new(self.items.iter().map(|item| item.to_vec()).collect())
+    }
+}
+
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct TxInputRef<'a> {
+    pub previous_output: OutPoint,
+    pub script_sig: ScriptRef<'a>,
+    pub sequence: u32,
+    pub witness: WitnessRef<'a>,
+}
+
+impl<'a> TxInputRef<'a> {
+    pub fn decode(d: &mut Decoder<'a>) -> Result<Self> {
+        Ok(Self {
+            previous_output: d.read_outpoint()?,
+            script_sig: ScriptRef::decode(d)?,
+            sequence: d.read_u32()?,
+            witness: WitnessRef::default(),
+        })
+    }
+
+    pub fn to_input(&self) -> TransactionInput {
+        let mut input = TransactionInput::new(
+            self.previous_output.clone(),
+            self.script_sig.to_script(),
+            self.sequence,
+        );
+        input.witness = self.witness.to_witness();
+        input
+    }
+}
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub struct TxOutputRef<'a> {
+    pub value: u64,
+    pub script_pubkey: ScriptRef<'a>,
+}
+
+impl<'a> TxOutputRef<'a> {
+    pub fn decode(d: &mut Decoder<'a>) -> Result<Self> {
+        Ok(Self {
+            value: d.read_u64()?,
+            script_pubkey: ScriptRef::decode(d)?,
+        })
+    }
+
+    pub fn to_output(&self) -> TransactionOutput {
+        TransactionOutput::new(self.value, self.script_pubkey.to_script())
+    }
+}
+
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct TransactionRef<'a> {
+    pub version: u32,
+    pub inputs: Vec<TxInputRef<'a>>,
+    pub outputs: Vec<TxOutputRef<'a>>,
+    pub lock_time: u32,
+}
+
+impl<'a> TransactionRef<'a> {
+    pub fn from_bytes(bytes: &'a [u8]) -> Result<(Self, usize)> {
+        let mut d = Decoder::new(bytes);
+        let tx = Self::decode(&mut d)?;
+        Ok((tx, d.position()))
+    }
+
+    pub fn decode(d: &mut Decoder<'a>) -> Result<Self> {
+        let version = d.read_u32()?;
+        let mut inputs = decode_inputs(d)?;
+
+        let mut flag = 0;
+        let mut outputs = vec![];
+        if inputs.is_empty() {
+            flag = d.read_u8()?;
+            if flag != 0 {
+                inputs = decode_inputs(d)?;
+                outputs = decode_outputs(d)?;
+            }
+        } else {
+            outputs = decode_outputs(d)?;
+        }
+
+        if flag & SEGWIT_FLAG != 0 && d.options().allow_segwit {
+            flag ^= SEGWIT_FLAG;
+            for input in inputs.iter_mut() {
+                input.witness = WitnessRef::decode(d)?;
+            }
+            if inputs.iter().all(|input| input.witness.is_empty()) {
+                return Err(BitcoinError::InvalidFormat);
+            }
+        }
+        if flag != 0 {
+            return Err(BitcoinError::InvalidSegwitMarker { flag });
+        }
+
+        Ok(Self {
+            version,
+            inputs,
+            outputs,
+            lock_time: d.read_u32()?,
+        })
+    }
+
+    pub fn to_transaction(&self) -> BitcoinTransaction {
+        BitcoinTransaction::new(
+            self.version,
+            self.inputs.iter().map(TxInputRef::to_input).collect(),
+            self.outputs.iter().map(TxOutputRef::to_output).collect(),
+            self.lock_time,
+        )
+    }
+}
+
+fn decode_inputs<'a>(d: &mut Decoder<'a>) -> Result<Vec<TxInputRef<'a>>> {
+    let max = d.limits().max_inputs;
+    let count = check_count("TransactionInput", d.read_compact_size()?.value, max)?;
+    let mut inputs = vec![];
+    for _ in 0..count {
+        inputs.push(TxInputRef::decode(d)?);
+    }
+    Ok(inputs)
+}
+
+fn decode_outputs<'a>(d: &mut Decoder<'a>) -> Result<Vec<TxOutputRef<'a>>> {
+    let max = d.limits().max_outputs;
+    let count = check_count("TransactionOutput", d.read_compact_size()?.value, max)?;
+    let mut outputs = vec![];
+    for _ in 0..count {
+        outputs.push(TxOutputRef::decode(d)?);
+    }
+    Ok(outputs)
+}
@@ -34,6 +34,481 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_compact_size_canonical() {
+        assert_eq!(
+            CompactSize::from_bytes_canonical(&[0xFD, 0xFD, 0x00]),
+            Ok((CompactSize::new(253), 3))
+        );
+        assert_eq!(
+            CompactSize::from_bytes(&[0xFD, 0x01, 0x00]),
+            Ok((CompactSize::new(1), 3))
+        );
+        assert_eq!(
+            CompactSize::from_bytes_canonical(&[0xFD, 0x01, 0x00]),
+            Err(BitcoinError::NonCanonicalCompactSize {
+                value: 1,
+                encoded_len: 3,
+            })
+        );
+        assert_eq!(
+            CompactSize::from_bytes_canonical(&[0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0, 0, 0, 0]),
+            Err(BitcoinError::NonCanonicalCompactSize {
+                value: 0xFFFFFFFF,
+                encoded_len: 9,
+            })
+        );
+    }
+
+    #[test]
+    fn test_decode_exact_rejects_trailing_bytes() {
+        assert_eq!(
+            CompactSize::decode_exact(&[0xFD, 0x00, 0x01]),
+            Ok(CompactSize::new(256))
+        );
+        assert_eq!(
+            CompactSize::decode_exact(&[0x01, 0x00]),
+            Err(BitcoinError::TrailingBytes)
+        );
+
+        let outpoint = OutPoint::new(dummy_txid(9), 1);
+        let mut bytes = outpoint.to_bytes();
+        assert_eq!(OutPoint::decode_exact(&bytes), Ok(outpoint));
+        bytes.push(0);
+        assert_eq!(
+            OutPoint::decode_exact(&bytes),
+            Err(BitcoinError::TrailingBytes)
+        );
+
+        let script = Script::new(vec![0x51]);
+        assert_eq!(
+            Script::decode_exact(&[0x01, 0x51, 0x51]),
+            Err(BitcoinError::TrailingBytes)
+        );
+        assert_eq!(Script::decode_exact(&[0x01, 0x51]), Ok(script));
+
+        let input = TransactionInput::new(OutPoint::new(dummy_txid(9), 0), Script::new(vec![]), 0);
+        let mut bytes = input.to_bytes();
+        bytes.push(0xFF);
+        assert_eq!(
+            TransactionInput::decode_exact(&bytes),
+            Err(BitcoinError::TrailingBytes)
+        );
+
+        let tx = BitcoinTransaction::new(1, vec![input], vec![], 0);
+        let mut bytes = tx.to_bytes();
+        assert_eq!(BitcoinTransaction::decode_exact(&bytes), Ok(tx));
+        bytes.extend_from_slice(&[0, 0]);
+        assert_eq!(
+            BitcoinTransaction::decode_exact(&bytes),
+            Err(BitcoinError::TrailingBytes)
+        );
+    }
+
+    fn roundtrip<T: ConsensusEncode + ConsensusDecode + PartialEq + std::fmt::Debug>(value: T) {
+        let bytes = value.to_bytes();
+        assert_eq!(T::decode_exact(&bytes).unwrap(), value);
+    }
+
+    #[test]
+    fn test_generic_consensus_roundtrip() {
+        roundtrip(CompactSize::new(70_000));
+        roundtrip(OutPoint::new(dummy_txid(10), 4));
+        roundtrip(Script::new(vec![0xAC; 3]));
+        roundtrip(Witness::new(vec![vec![1], vec![2, 3]]));
+        roundtrip(TransactionOutput::new(7, Script::new(vec![0x51])));
+
+        let outputs = vec![
+            TransactionOutput::new(1, Script::new(vec![])),
+            TransactionOutput::new(2, Script::new(vec![0x51])),
+        ];
+        let bytes = outputs.to_bytes();
+        assert_eq!(bytes[0], 0x02);
+        assert_eq!(bytes.len(), 1 + 9 + 10);
+        roundtrip(outputs);
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn test_consensus_encode_to_writer() {
+        let bytes = hex::decode(GENESIS_COINBASE_HEX).unwrap();
+        let (tx, _) = BitcoinTransaction::from_bytes(&bytes).unwrap();
+
+        let mut sink = std::io::Cursor::new(Vec::new());
+        let written = tx.consensus_encode(&mut sink).unwrap();
+        assert_eq!(written, bytes.len());
+        assert_eq!(sink.into_inner(), bytes);
+
+        let mut short = [0u8; 10];
+        let err = tx.consensus_encode(&mut &mut short[..]).unwrap_err();
+        assert_eq!(err.kind(), std::io::ErrorKind::WriteZero);
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn test_consensus_decode_from_reader() {
+        let bytes = hex::decode(GENESIS_COINBASE_HEX).unwrap();
+        let mut stream = bytes.clone();
+        stream.extend_from_slice(&bytes);
+
+        let mut reader = std::io::BufReader::new(std::io::Cursor::new(stream));
+        let first = BitcoinTransaction::consensus_decode(&mut reader).unwrap();
+        let second = BitcoinTransaction::consensus_decode(&mut reader).unwrap();
+        assert_eq!(first, second);
+        assert_eq!(first.to_bytes(), bytes);
+        assert!(matches!(
+            BitcoinTransaction::consensus_decode(&mut reader),
+            Err(BitcoinError::InsufficientBytes { .. })
+        ));
+
+        let mut truncated = &bytes[..bytes.len() - 1];
+        assert!(matches!(
+            BitcoinTransaction::consensus_decode(&mut truncated),
+            Err(BitcoinError::InsufficientBytes {
+                needed: 4,
+                available: 3,
+                ..
+            })
+        ));
+    }
+
+    #[test]
+    fn test_serialized_size_matches_encoding() {
+        for value in [0u64, 252, 253, 0xFFFF, 0x10000, 0xFFFFFFFF, 0x100000000] {
+            let cs = CompactSize::new(value);
+            assert_eq!(cs.serialized_size(), cs.to_bytes().len());
+        }
+
+        let script = Script::new(vec![0x51; 300]);
+        assert_eq!(script.serialized_size(), 303);
+
+        let mut input = TransactionInput::new(OutPoint::new(dummy_txid(13), 0), script, 0);
+        input.witness.push(vec![0xAA; 72]);
+        input.witness.push(vec![0x02; 33]);
+        assert_eq!(
+            input.witness.serialized_size(),
+            input.witness.to_bytes().len()
+        );
+
+        let output = TransactionOutput::new(1, Script::new(vec![0x00, 0x14]));
+        let tx = BitcoinTransaction::new(2, vec![input], vec![output], 0);
+        assert_eq!(tx.serialized_size(), tx.to_bytes().len());
+        assert_eq!(tx.base_size(), tx.to_bytes_legacy().len());
+    }
+
+    #[test]
+    fn test_encode_into_appends_to_buffer() {
+        let outpoint = OutPoint::new(dummy_txid(14), 3);
+        let script = Script::new(vec![0x51, 0x52]);
+
+        let mut buf = vec![0xEE];
+        assert_eq!(outpoint.encode_into(&mut buf), 36);
+        assert_eq!(script.encode_into(&mut buf), 3);
+        assert_eq!(buf.len(), 1 + 36 + 3);
+        assert_eq!(buf[0], 0xEE);
+        assert_eq!(&buf[1..37], outpoint.to_bytes().as_slice());
+        assert_eq!(&buf[37..], &[0x02, 0x51, 0x52]);
+
+        let tx = BitcoinTransaction::new(
+            1,
+            vec![TransactionInput::new(outpoint, script, 0)],
+            vec![],
+            0,
+        );
+        let mut buf = Vec::new();
+        let written = tx.encode_into(&mut buf);
+        assert_eq!(written, tx.serialized_size());
+    }
+
+    #[test]
+    fn test_decoder_tracks_position() {
+        let outpoint = OutPoint::new(dummy_txid(15), 9);
+        let script = Script::new(vec![0x76, 0xA9]);
+        let mut bytes = CompactSize::new(2).to_bytes();
+        outpoint.encode_into(&mut bytes);
+        script.encode_into(&mut bytes);
+        bytes.extend_from_slice(&[0xAA, 0xBB]);
+
+        let mut decoder = Decoder::new(&bytes);
+        assert_eq!(decoder.read_compact_size().unwrap().value, 2);
+        assert_eq!(decoder.position(), 1);
+        assert_eq!(decoder.read_outpoint().unwrap(), outpoint);
+        assert_eq!(decoder.position(), 37);
+        assert_eq!(decoder.read_script().unwrap(), script);
+        assert_eq!(decoder.remaining(), &[0xAA, 0xBB]);
+        assert_eq!(decoder.clone().finish(), Err(BitcoinError::TrailingBytes));
+        assert_eq!(decoder.read_slice(2).unwrap(), &[0xAA, 0xBB]);
+        assert!(decoder.is_empty());
+        assert!(matches!(
+            decoder.read_u8(),
+            Err(BitcoinError::InsufficientBytes { offset: 42, .. })
+        ));
+        assert_eq!(decoder.finish(), Ok(()));
+    }
+
+    #[test]
+    fn test_stream_parser_reports_missing_bytes() {
+        let bytes = hex::decode(GENESIS_COINBASE_HEX).unwrap();
+        let (tx, _) = BitcoinTransaction::from_bytes(&bytes).unwrap();
+        let mut parser = TxStreamParser::new();
+
+        assert_eq!(
+            parser.push(&bytes[..2]).unwrap(),
+            ParseProgress::NeedMore(2)
+        );
+        // version + input count + outpoint, missing the scriptSig length
+        assert_eq!(
+            parser.push(&bytes[2..41]).unwrap(),
+            ParseProgress::NeedMore(1)
+        );
+        // scriptSig length 0x4d known, none of its bytes present yet
+        assert_eq!(
+            parser.push(&bytes[41..42]).unwrap(),
+            ParseProgress::NeedMore(0x4d)
+        );
+
+        let mut second = bytes.clone();
+        second[0] = 2;
+        let mut rest = bytes[42..].to_vec();
+        rest.extend_from_slice(&second[..10]);
+        assert_eq!(parser.push(&rest).unwrap(), ParseProgress::Complete(tx));
+        assert_eq!(parser.buffered(), 10);
+
+        let progress = parser.push(&second[10..]).unwrap();
+        match progress {
+            ParseProgress::Complete(next) => assert_eq!(next.version, 2),
+            other => panic!("unexpected {:?}", other),
+        }
+        assert_eq!(parser.buffered(), 0);
+        assert_eq!(parser.poll().unwrap(), ParseProgress::NeedMore(4));
+    }
+
+    #[test]
+    fn test_stream_parser_limits() {
+        let bytes = hex::decode(GENESIS_COINBASE_HEX).unwrap();
+        let (tx, _) = BitcoinTransaction::from_bytes(&bytes).unwrap();
+
+        // A scriptSig claiming 16 MB is refused before any of it arrives.
+        let mut parser = TxStreamParser::new();
+        let mut huge = bytes[..41].to_vec();
+        huge.extend_from_slice(&[0xFE, 0x00, 0x00, 0x00, 0x01]);
+        assert!(matches!(
+            parser.push(&huge),
+            Err(BitcoinError::OversizedFrame {
+                max: DEFAULT_MAX_FRAME_SIZE,
+                ..
+            })
+        ));
+        assert_eq!(parser.buffered(), 0);
+
+        let mut parser = TxStreamParser::with_max_frame_size(bytes.len() - 1);
+        assert_eq!(parser.max_frame_size(), bytes.len() - 1);
+        let mut stream = bytes.clone();
+        stream.extend_from_slice(&bytes[..10]);
+        assert_eq!(
+            parser.push(&stream),
+            Err(BitcoinError::OversizedFrame {
+                size: bytes.len(),
+                max: bytes.len() - 1
+            })
+        );
+        assert_eq!(parser.buffered(), 10);
+
+        // A frame that fails to decode is dropped, and the next one parses.
+        let limits = DecodeLimits {
+            max_outputs: 0,
+            ..DecodeLimits::default()
+        };
+        let mut parser = TxStreamParser::new().with_limits(limits);
+        let mut no_outputs = tx.clone();
+        no_outputs.outputs.clear();
+        let mut stream = bytes.clone();
+        stream.extend_from_slice(&no_outputs.to_bytes());
+        assert!(matches!(
+            parser.push(&stream),
+            Err(BitcoinError::SizeLimitExceeded { .. })
+        ));
+        assert_eq!(parser.poll().unwrap(), ParseProgress::Complete(no_outputs));
+        assert_eq!(parser.buffered(), 0);
+    }
+
+    #[test]
+    fn test_stream_parser_segwit_chunks() {
+        let mut input =
+            TransactionInput::new(OutPoint::new(dummy_txid(16), 0), Script::new(vec![]), 0);
+        input.witness.push(vec![0x11; 20]);
+        let tx = BitcoinTransaction::new(2, vec![input], vec![], 7);
+        let bytes = tx.to_bytes();
+
+        let mut parser = TxStreamParser::new();
+        for chunk in bytes[..bytes.len() - 1].chunks(3) {
+            assert!(matches!(
+                parser.push(chunk).unwrap(),
+                ParseProgress::NeedMore(_)
+            ));
+        }
+        assert_eq!(
+            parser.push(&bytes[bytes.len() - 1..]).unwrap(),
+            ParseProgress::Complete(tx)
+        );
+    }
+
+    #[cfg(feature = "async")]
+    #[tokio::test]
+    async fn test_consensus_decode_async() {
+        let bytes = hex::decode(GENESIS_COINBASE_HEX).unwrap();
+        let (tx, _) = BitcoinTransaction::from_bytes(&bytes).unwrap();
+
+        let mut input =
+            TransactionInput::new(OutPoint::new(dummy_txid(17), 0), Script::new(vec![]), 0);
+        input.witness.push(vec![0x22; 64]);
+        let segwit = BitcoinTransaction::new(2, vec![input], vec![], 0);
+
+        let mut stream = bytes.clone();
+        segwit.encode_into(&mut stream);
+        let (mut client, mut server) = tokio::io::duplex(16);
+        let writer = tokio::spawn(async move {
+            tokio::io::AsyncWriteExt::write_all(&mut client, &stream)
+                .await
+                .unwrap();
+        });
+
+        let first = BitcoinTransaction::consensus_decode_async(&mut server)
+            .await
+            .unwrap();
+        let second = BitcoinTransaction::consensus_decode_async(&mut server)
+            .await
+            .unwrap();
+        writer.await.unwrap();
+        assert_eq!(first, tx);
+        assert_eq!(second, segwit);
+        assert!(matches!(
+            BitcoinTransaction::consensus_decode_async(&mut server).await,
+            Err(BitcoinError::InsufficientBytes { available: 0, .. })
+        ));
+    }
+
+    #[cfg(feature = "codec")]
+    #[test]
+    fn test_tx_codec_framing() {
+        use bytes::BytesMut;
+        use tokio_util::codec::{Decoder as _, Encoder as _};
+
+        let bytes = hex::decode(GENESIS_COINBASE_HEX).unwrap();
+        let (tx, _) = BitcoinTransaction::from_bytes(&bytes).unwrap();
+        let mut codec = TxCodec::new();
+
+        let mut buf = BytesMut::new();
+        codec.encode(&tx, &mut buf).unwrap();
+        codec.encode(tx.clone(), &mut buf).unwrap();
+        assert_eq!(buf.len(), bytes.len() * 2);
+
+        let mut partial = buf.split_to(bytes.len() + 5);
+        assert_eq!(codec.decode(&mut partial).unwrap(), Some(tx.clone()));
+        assert_eq!(codec.decode(&mut partial).unwrap(), None);
+        partial.unsplit(buf);
+        assert_eq!(codec.decode(&mut partial).unwrap(), Some(tx.clone()));
+        assert!(partial.is_empty());
+
+        let mut small = TxCodec::with_max_frame_size(100);
+        let mut buf = BytesMut::from(&bytes[..50]);
+        assert_eq!(
+            small.decode(&mut buf),
+            Err(BitcoinError::OversizedFrame {
+                size: 42 + 0x4d,
+                max: 100
+            })
+        );
+        assert!(small.encode(&tx, &mut BytesMut::new()).is_err());
+    }
+
+    #[test]
+    fn test_borrowed_transaction_view() {
+        let bytes = hex::decode(GENESIS_COINBASE_HEX).unwrap();
+        let (view, consumed) = TransactionRef::from_bytes(&bytes).unwrap();
+        assert_eq!(consumed, bytes.len());
+
+        let script_sig = view.inputs[0].script_sig.bytes;
+        assert_eq!(script_sig.len(), 0x4d);
+        assert_eq!(script_sig.as_ptr(), bytes[42..].as_ptr());
+        assert_eq!(view.outputs[0].value, 5_000_000_000);
+
+        let (owned, _) = BitcoinTransaction::from_bytes(&bytes).unwrap();
+        assert_eq!(view.to_transaction(), owned);
+
+        let mut input =
+            TransactionInput::new(OutPoint::new(dummy_txid(19), 2), Script::new(vec![]), 0);
+        input.witness.push(vec![0x33; 5]);
+        let segwit = BitcoinTransaction::new(2, vec![input], vec![], 0);
+        let bytes = segwit.to_bytes();
+        let (view, _) = TransactionRef::from_bytes(&bytes).unwrap();
+        assert_eq!(view.inputs[0].witness.items, vec![&[0x33; 5][..]]);
+        assert_eq!(view.to_transaction(), segwit);
+    }
+
+    #[test]
+    fn test_error_display_and_boxing() {
+        assert_eq!(
+            BitcoinError::InsufficientBytes {
+                context: "Script",
+                offset: 7,
+                needed: 3,
+                available: 1
+            }
+            .to_string(),
+            "unexpected end of input decoding Script at offset 7: needed 3 bytes, 1 available"
+        );
+        assert_eq!(
+            BitcoinError::OversizedFrame { size: 10, max: 5 }.to_string(),
+            "frame of 10 bytes exceeds maximum of 5"
+        );
+
+        fn parse(bytes: &[u8]) -> Result<OutPoint, Box<dyn std::error::Error>> {
+            Ok(OutPoint::decode_exact(bytes)?)
+        }
+        let err = parse(&[0u8; 3]).unwrap_err();
+        assert!(err.to_string().starts_with("unexpected end of input"));
+    }
+
+    #[test]
+    fn test_error_reports_offset_and_context() {
+        let bytes = hex::decode(GENESIS_COINBASE_HEX).unwrap();
+
+        // Cut inside the coinbase scriptSig, which starts at offset 42.
+        assert_eq!(
+            BitcoinTransaction::from_bytes(&bytes[..50]),
+            Err(BitcoinError::InsufficientBytes {
+                context: "Script",
+                offset: 42,
+                needed: 0x4d,
+                available: 8,
+            })
+        );
+
+        // Cut inside the outpoint's vout.
+        assert_eq!(
+            BitcoinTransaction::from_bytes(&bytes[..39]),
+            Err(BitcoinError::InsufficientBytes {
+                context: "OutPoint",
+                offset: 37,
+                needed: 4,
+                available: 2,
+            })
+        );
+
+        // Cut inside the lock time.
+        let len = bytes.len();
+        assert_eq!(
+            BitcoinTransaction::from_bytes(&bytes[..len - 3]),
+            Err(BitcoinError::InsufficientBytes {
+                context: "BitcoinTransaction",
+                offset: len - 4,
+                needed: 4,
+                available: 1,
+            })
+        );
+    }
+
     #[test]
     fn test_outpoint_roundtrip() {
         let txid = dummy_txid(0xCC);
@@ -66,6 +541,19 @@ mod tests {
         assert_eq!(consumed, bytes.len());
     }
 
+    #[test]
+    fn test_tx_output_roundtrip() {
+        let output = TransactionOutput::new(
+            0x0102030405060708,
+            Script::new(vec![0x00, 0x14, 0xAA, 0xBB]),
+        );
+        let bytes = output.to_bytes();
+        assert_eq!(&bytes[..8], &0x0102030405060708u64.to_le_bytes());
+        let (parsed, consumed) = TransactionOutput::from_bytes(&bytes).unwrap();
+        assert_eq!(parsed, output);
+        assert_eq!(consumed, bytes.len());
+    }
+
     #[test]
     fn test_bitcoin_tx_roundtrip() {
         let inputs = vec![TransactionInput::new(
@@ -73,13 +561,5029 @@ mod tests {
             Script::new(vec![0x01, 0x02]),
             0xFFFFFFFF,
         )];
-        let tx = BitcoinTransaction::new(2, inputs.clone(), 1000);
+        let outputs = vec![TransactionOutput::new(
+            50_000,
+            Script::new(vec![0x76, 0xA9, 0x14, 0x88, 0xAC]),
+        )];
+        let tx = BitcoinTransaction::new(2, inputs.clone(), outputs, 1000);
+        let bytes = tx.to_bytes();
+        let (parsed, consumed) = BitcoinTransaction::from_bytes(&bytes).unwrap();
+        assert_eq!(parsed, tx);
+        assert_eq!(consumed, bytes.len());
+    }
+
+    #[test]
+    fn test_witness_roundtrip() {
+        let mut witness = Witness::default();
+        witness.push(vec![0xAA; 3]);
+        witness.push(vec![]);
+        witness.push(vec![0xBB; 300]);
+        assert_eq!(witness.len(), 3);
+
+        let bytes = witness.to_bytes();
+        assert_eq!(&bytes[..5], &[0x03, 0x03, 0xAA, 0xAA, 0xAA]);
+        assert_eq!(&bytes[5..9], &[0x00, 0xFD, 0x2C, 0x01]);
+        let (parsed, consumed) = Witness::from_bytes(&bytes).unwrap();
+        assert_eq!(parsed, witness);
+        assert_eq!(consumed, bytes.len());
+
+        let items: Vec<&[u8]> = parsed.iter().collect();
+        assert_eq!(items[1], &[] as &[u8]);
+        assert_eq!(parsed.last().unwrap().len(), 300);
+    }
+
+    #[test]
+    fn test_segwit_tx_roundtrip() {
+        let mut input = TransactionInput::new(
+            OutPoint::new(dummy_txid(2), 1),
+            Script::new(vec![]),
+            0xFFFFFFFD,
+        );
+        input.witness = Witness::new(vec![vec![0x30, 0x44, 0x01], vec![0x02; 33]]);
+        let outputs = vec![TransactionOutput::new(
+            10_000,
+            Script::new(vec![0x00, 0x14, 0x11, 0x22]),
+        )];
+        let tx = BitcoinTransaction::new(2, vec![input], outputs, 0);
+        assert!(tx.has_witness());
+
+        let bytes = tx.to_bytes();
+        assert_eq!(&bytes[4..6], &[0x00, 0x01]);
+        let (parsed, consumed) = BitcoinTransaction::from_bytes(&bytes).unwrap();
+        assert_eq!(parsed, tx);
+        assert_eq!(consumed, bytes.len());
+    }
+
+    #[test]
+    fn test_empty_legacy_tx_is_not_segwit() {
+        let tx = BitcoinTransaction::new(1, vec![], vec![], 0);
         let bytes = tx.to_bytes();
+        assert_eq!(bytes, vec![1, 0, 0, 0, 0x00, 0x00, 0, 0, 0, 0]);
         let (parsed, consumed) = BitcoinTransaction::from_bytes(&bytes).unwrap();
         assert_eq!(parsed, tx);
         assert_eq!(consumed, bytes.len());
     }
 
+    #[test]
+    fn test_segwit_flag_without_witness_rejected() {
+        let mut tx = BitcoinTransaction::new(
+            2,
+            vec![TransactionInput::new(
+                OutPoint::new(dummy_txid(3), 0),
+                Script::new(vec![]),
+                0xFFFFFFFF,
+            )],
+            vec![],
+            0,
+        );
+        tx.inputs[0].witness.push(vec![0x01]);
+        let mut bytes = tx.to_bytes();
+        // Replace the single one-byte witness stack with an empty one.
+        let witness_start = bytes.len() - 4 - 3;
+        bytes.splice(witness_start..witness_start + 3, [0x00]);
+        assert_eq!(
+            BitcoinTransaction::from_bytes(&bytes),
+            Err(BitcoinError::InvalidFormat)
+        );
+    }
+
+    #[test]
+    fn test_decode_error_variants() {
+        let unknown_flag = [2, 0, 0, 0, 0x00, 0x02, 0x00, 0x00, 0, 0, 0, 0];
+        assert_eq!(
+            BitcoinTransaction::from_bytes(&unknown_flag),
+            Err(BitcoinError::InvalidSegwitMarker { flag: 0x02 })
+        );
+        assert_eq!(
+            TransactionRef::from_bytes(&unknown_flag),
+            Err(BitcoinError::InvalidSegwitMarker { flag: 0x02 })
+        );
+
+        assert_eq!(
+            Script::from_bytes(&[0xFE, 0xFF, 0xFF, 0xFF, 0xFF]),
+            Err(BitcoinError::OversizedScript {
+                len: 0xFFFFFFFF,
+                max: DecodeLimits::default().max_script_len as u64,
+            })
+        );
+
+        let mut huge_count = vec![1, 0, 0, 0, 0xFF];
+        huge_count.extend_from_slice(&[0xFF; 8]);
+        assert_eq!(
+            BitcoinTransaction::from_bytes(&huge_count),
+            Err(BitcoinError::ValueOverflow {
+                context: "TransactionInput",
+                value: u64::MAX,
+                max: MAX_SIZE,
+            })
+        );
+
+        assert_eq!(Script::from_hex("0151"), Ok(Script::new(vec![0x51])));
+        assert_eq!(
+            Script::from_hex("051"),
+            Err(BitcoinError::InvalidHex(hex::FromHexError::OddLength))
+        );
+    }
+
+    #[test]
+    fn test_decode_limits() {
+        let input = TransactionInput::new(
+            OutPoint::new(dummy_txid(5), 0),
+            Script::new(vec![0x51; 10]),
+            0xFFFFFFFF,
+        );
+        let output = TransactionOutput::new(1, Script::new(vec![0x51]));
+        let tx = BitcoinTransaction::new(2, vec![input; 3], vec![output; 2], 0);
+        let bytes = tx.to_bytes();
+        assert!(BitcoinTransaction::from_bytes(&bytes).is_ok());
+
+        let limits = DecodeLimits {
+            max_inputs: 2,
+            ..DecodeLimits::default()
+        };
+        assert_eq!(
+            BitcoinTransaction::from_bytes_with_limits(&bytes, &limits),
+            Err(BitcoinError::SizeLimitExceeded {
+                context: "TransactionInput",
+                size: 3,
+                max: 2,
+            })
+        );
+
+        let limits = DecodeLimits {
+            max_script_len: 5,
+            ..DecodeLimits::default()
+        };
+        assert_eq!(
+            BitcoinTransaction::from_bytes_with_limits(&bytes, &limits),
+            Err(BitcoinError::OversizedScript { len: 10, max: 5 })
+        );
+        assert_eq!(
+            TransactionRef::decode(&mut Decoder::with_limits(&bytes, limits)),
+            Err(BitcoinError::OversizedScript { len: 10, max: 5 })
+        );
+
+        // A 0xFF count prefix must fail fast instead of looping or allocating.
+        let mut hostile = vec![1, 0, 0, 0, 0xFE];
+        hostile.extend_from_slice(&0x0100_0000u32.to_le_bytes());
+        assert!(matches!(
+            BitcoinTransaction::from_bytes(&hostile),
+            Err(BitcoinError::SizeLimitExceeded {
+                context: "TransactionInput",
+                ..
+            })
+        ));
+    }
+
+    #[test]
+    fn test_decode_options() {
+        let mut input = TransactionInput::new(
+            OutPoint::new(dummy_txid(6), 1),
+            Script::new(vec![]),
+            0xFFFFFFFD,
+        );
+        input.witness.push(vec![0x30; 71]);
+        let output = TransactionOutput::new(1000, Script::new(vec![0x51]));
+        let tx = BitcoinTransaction::new(2, vec![input], vec![output], 0);
+        let mut bytes = tx.to_bytes();
+
+        let no_segwit = DecodeOptions {
+            allow_segwit: false,
+            ..DecodeOptions::default()
+        };
+        assert_eq!(
+            BitcoinTransaction::from_bytes_with(&bytes, &no_segwit),
+            Err(BitcoinError::InvalidSegwitMarker { flag: 0x01 })
+        );
+        assert_eq!(
+            TransactionRef::decode(&mut Decoder::with_options(&bytes, no_segwit)),
+            Err(BitcoinError::InvalidSegwitMarker { flag: 0x01 })
+        );
+
+        bytes.push(0x00);
+        assert_eq!(
+            BitcoinTransaction::from_bytes_with(&bytes, &DecodeOptions::default()),
+            Ok((tx.clone(), bytes.len() - 1))
+        );
+        assert_eq!(
+            BitcoinTransaction::from_bytes_with(&bytes, &DecodeOptions::strict()),
+            Err(BitcoinError::TrailingBytes)
+        );
+        bytes.pop();
+
+        // Re-encode the one-byte output count as FD 01 00.
+        let count_pos = 4 + 2 + 1 + 36 + 1 + 4;
+        assert_eq!(bytes[count_pos], 0x01);
+        bytes.splice(count_pos..count_pos + 1, [0xFD, 0x01, 0x00]);
+        assert_eq!(BitcoinTransaction::from_bytes(&bytes).unwrap().0, tx);
+        assert_eq!(
+            BitcoinTransaction::from_bytes_with(&bytes, &DecodeOptions::strict()),
+            Err(BitcoinError::NonCanonicalCompactSize {
+                value: 1,
+                encoded_len: 3,
+            })
+        );
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn test_error_source_chain() {
+        use std::error::Error;
+
+        let err = Script::from_hex("0g").unwrap_err();
+        let source = err.source().expect("hex errors keep their source");
+        assert_eq!(
+            source.downcast_ref::<hex::FromHexError>(),
+            Some(&hex::FromHexError::InvalidHexCharacter { c: 'g', index: 1 })
+        );
+        assert!(BitcoinError::TrailingBytes.source().is_none());
+
+        let json = format!("\"{}\"", "ab".repeat(31));
+        let err = serde_json::from_str::<Txid>(&json).unwrap_err();
+        assert!(
+            err.to_string()
+                .starts_with("invalid length 62, expected 64 hex digits")
+        );
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn test_hash_deserialize_errors() {
+        use serde::Deserialize;
+        use serde::de::value::StrDeserializer;
+        use serde::de::{Error, Expected, Unexpected};
+        use std::fmt;
+
+        // Records which of serde's error kinds a deserializer reported.
+        #[derive(Debug, PartialEq)]
+        enum DeError {
+            InvalidLength(usize),
+            InvalidValue(String),
+            Custom(String),
+        }
+        impl fmt::Display for DeError {
+            fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                write!(f, "{:?}", self)
+            }
+        }
+        impl std::error::Error for DeError {}
+        impl Error for DeError {
+            fn custom<T: fmt::Display>(msg: T) -> Self {
+                DeError::Custom(msg.to_string())
+            }
+            fn invalid_length(len: usize, _: &dyn Expected) -> Self {
+                DeError::InvalidLength(len)
+            }
+            fn invalid_value(unexpected: Unexpected, _: &dyn Expected) -> Self {
+                DeError::InvalidValue(unexpected.to_string())
+            }
+        }
+        let deserialize = |s: &str| Txid::deserialize(StrDeserializer::<DeError>::new(s));
+
+        let hex = "4a5e1e4baab89f3a32518a88c31bc87f618f76673e2cc77ab2127b7afdeda33b";
+        assert_eq!(deserialize(hex).unwrap().to_string(), hex);
+        assert_eq!(deserialize(&hex[1..]), Err(DeError::InvalidLength(63)));
+        assert_eq!(deserialize(&hex[2..]), Err(DeError::InvalidLength(62)));
+        let bad = format!("{}x", &hex[1..]);
+        assert_eq!(
+            deserialize(&bad),
+            Err(DeError::InvalidValue("character `x`".to_string()))
+        );
+        assert_eq!(
+            BlockHash::deserialize(StrDeserializer::<DeError>::new("zz")),
+            Err(DeError::InvalidLength(2))
+        );
+    }
+
+    #[test]
+    fn test_fuzz_helpers_accept_known_vectors() {
+        let genesis = hex::decode(GENESIS_COINBASE_HEX).unwrap();
+        fuzz_decode_transaction(&genesis);
+        fuzz_decode_transaction(&genesis[..60]);
+        fuzz_decode_script(&[0x02, 0x51, 0x52, 0xFF]);
+        fuzz_decode_compact_size(&[0xFD, 0x01, 0x00]);
+        fuzz_decode_compact_size(&[]);
+    }
+
+    #[cfg(feature = "arbitrary")]
+    #[test]
+    fn test_arbitrary_transactions_round_trip() {
+        use arbitrary::{Arbitrary, Unstructured};
+
+        let seed: Vec<u8> = (0..4096u32)
+            .map(|i| (i.wrapping_mul(2654435761) >> 13) as u8)
+            .collect();
+        let mut u = Unstructured::new(&seed);
+        for _ in 0..16 {
+            let Ok(tx) = BitcoinTransaction::arbitrary(&mut u) else {
+                break;
+            };
+            let bytes = tx.to_bytes();
+            fuzz_decode_transaction(&bytes);
+            // Coinbase script limits and the zero-input segwit ambiguity make
+            // some generated transactions legitimately undecodable.
+            let ambiguous = tx.inputs.is_empty() && !tx.outputs.is_empty();
+            if !tx.is_coinbase() && !ambiguous {
+                assert_eq!(BitcoinTransaction::decode_exact(&bytes), Ok(tx));
+            }
+        }
+        let size = CompactSize::arbitrary(&mut u).unwrap_or(CompactSize::new(0));
+        fuzz_decode_compact_size(&size.to_bytes());
+    }
+
+    #[test]
+    fn test_hash_functions() {
+        assert_eq!(
+            hex::encode(hashes::sha256(b"abc")),
+            "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad"
+        );
+        assert_eq!(
+            hex::encode(hashes::ripemd160(b"")),
+            "9c1185a5c5e9fc54612808977ee8f548b2258d31"
+        );
+        assert_eq!(
+            hex::encode(hashes::ripemd160(b"abc")),
+            "8eb208f7e05d987a9b044a8e98c6b087f15a0bfc"
+        );
+        assert_eq!(
+            hex::encode(hashes::ripemd160(
+                b"12345678901234567890123456789012345678901234567890123456789012345678901234567890"
+            )),
+            "9b752e45573d4b39f4dbd3323cab82bf63326bfb"
+        );
+        assert_eq!(
+            hex::encode(hashes::sha512(b"abc")),
+            "ddaf35a193617abacc417349ae20413112e6fa4e89a97ea20a9eeee64b55d39a2192992a274fc1a836ba3c23a3feebbd454d4423643ce80e2a9ac94fa54ca49f"
+        );
+        // Long enough that the length needs a block of its own.
+        assert_eq!(
+            hex::encode(hashes::sha512(&[b'a'; 112])),
+            "c01d080efd492776a1c43bd23dd99d0a2e626d481e16782e75d54c2503b5dc32bd05f0f1ba33e568b88fd2d970929b719ecbb152f58f130a407c8830604b70ca"
+        );
+        // RFC 4231 test cases 2 and 6.
+        assert_eq!(
+            hex::encode(hashes::hmac_sha512(
+                b"Jefe",
+                b"what do ya want for nothing?"
+            )),
+            "164b7a7bfcf819e2e395fbe73b56e0a387bd64222e831fd610270cd7ea2505549758bf75c05a994a6d034f65f8f0e6fdcaeab1a34d4a6b4b636e070a38bce737"
+        );
+        assert_eq!(
+            hex::encode(hashes::hmac_sha512(
+                &[0xaa; 131],
+                b"Test Using Larger Than Block-Size Key - Hash Key First"
+            )),
+            "80b24263c7c1a3ebb71493c1dd7be8b49b46d1f41b4aeec1121b013783f8f3526b56d037e05f2598bd0fd2215d6a1e5295e64f73f63f0aec8b915a985d786598"
+        );
+        assert_eq!(
+            hex::encode(hashes::pbkdf2_hmac_sha512(b"password", b"salt", 1)),
+            "867f70cf1ade02cff3752599a3a53dc4af34c7a669815ae5d513554e1c8cf252c02d470a285a0501bad999bfe943c08f050235d7d68b1da55e63f73b60a57fce"
+        );
+        assert_eq!(
+            hex::encode(hashes::pbkdf2_hmac_sha512(b"password", b"salt", 2)),
+            "e1d9c16aa681708a45f5c7c4e215ceb66e011a2e9f0040713f18aefdb866d53cf76cab2868a39b9f7840edce4fef5a82be67335c77a6068e04112754f27ccf4e"
+        );
+
+        // Compressed generator point G hashes to the well-known P2PKH payload.
+        let g = hex::decode("0279be667ef9dcbbac55a06295ce870b07029bfcdb2dce28d959f2815b16f81798")
+            .unwrap();
+        assert_eq!(
+            hex::encode(hashes::hash160(&g)),
+            "751e76e8199196d454941c45d1b3a323f1433bd6"
+        );
+
+        let tag = hashes::sha256(b"TapLeaf");
+        let mut preimage = [tag, tag].concat();
+        preimage.extend_from_slice(b"msg");
+        assert_eq!(
+            hashes::tagged_hash("TapLeaf", b"msg"),
+            hashes::sha256(&preimage)
+        );
+    }
+
+    #[test]
+    fn test_txid_display_and_from_str_use_rpc_byte_order() {
+        let bytes = hex::decode(GENESIS_COINBASE_HEX).unwrap();
+        let (tx, _) = BitcoinTransaction::from_bytes(&bytes).unwrap();
+        let rpc = "4a5e1e4baab89f3a32518a88c31bc87f618f76673e2cc77ab2127b7afdeda33b";
+        assert_eq!(tx.txid().to_string(), rpc);
+        assert_eq!(rpc.parse::<Txid>(), Ok(tx.txid()));
+        assert_eq!(tx.txid().0[0], 0x3b);
+
+        assert_eq!(
+            serde_json::to_string(&tx.txid()).unwrap(),
+            format!("\"{}\"", rpc)
+        );
+        let parsed: Txid = serde_json::from_str(&format!("\"{}\"", rpc)).unwrap();
+        assert_eq!(parsed, tx.txid());
+
+        assert!(matches!(
+            "zz".parse::<Txid>(),
+            Err(BitcoinError::InvalidHex(_))
+        ));
+    }
+
+    #[test]
+    fn test_hash_newtypes() {
+        let script = Script::new(vec![0x51]);
+        let hash = script.script_hash();
+        let mut expected = hashes::sha256(&[0x51]);
+        assert_eq!(hash.0, expected);
+        expected.reverse();
+        assert_eq!(hash.to_string(), hex::encode(expected));
+        assert_eq!(hash.to_string().parse::<ScriptHash>(), Ok(hash.clone()));
+
+        let genesis = "000000000019d6689c085ae165831e934ff763ae46a2a6c172b3f1b60a8ce26f";
+        let block_hash: BlockHash = genesis.parse().unwrap();
+        assert_eq!(block_hash.0[31], 0x00);
+        assert_eq!(block_hash.0[0], 0x6f);
+        assert_eq!(
+            BlockHash::decode_exact(&block_hash.to_bytes()),
+            Ok(block_hash.clone())
+        );
+        assert_eq!(
+            serde_json::to_string(&block_hash).unwrap(),
+            format!("\"{}\"", genesis)
+        );
+
+        let wtxid = Wtxid([7; 32]);
+        assert_eq!(wtxid.to_string().parse::<Wtxid>(), Ok(wtxid));
+        assert!(matches!(
+            "00".parse::<BlockHash>(),
+            Err(BitcoinError::InvalidHex(
+                hex::FromHexError::InvalidStringLength
+            ))
+        ));
+    }
+
+    #[test]
+    fn test_hex_formatting() {
+        let bytes = hex::decode(GENESIS_COINBASE_HEX).unwrap();
+        let (tx, _) = BitcoinTransaction::from_bytes(&bytes).unwrap();
+        assert_eq!(format!("{:x}", tx), GENESIS_COINBASE_HEX);
+        assert_eq!(format!("{:X}", tx), GENESIS_COINBASE_HEX.to_uppercase());
+        assert_eq!(format!("{:x}", tx.txid()), tx.txid().to_string());
+        assert_eq!(
+            format!("{:X}", tx.txid()),
+            "4A5E1E4BAAB89F3A32518A88C31BC87F618F76673E2CC77AB2127B7AFDEDA33B"
+        );
+
+        let script = Script::new(vec![0x76, 0xa9, 0x14]);
+        assert_eq!(format!("{:x}", script), "76a914");
+        assert_eq!(format!("{:X}", script), "76A914");
+
+        let witness = Witness::new(vec![vec![0xab], vec![]]);
+        assert_eq!(format!("{:x}", witness), "0201ab00");
+    }
+
+    #[test]
+    fn test_serde_binary_and_human_readable_forms() {
+        let txid = Txid([0x11; 32]);
+        let encoded = bincode::serialize(&txid).unwrap();
+        assert_eq!(encoded, vec![0x11; 32]);
+        assert_eq!(bincode::deserialize::<Txid>(&encoded).unwrap(), txid);
+
+        let script = Script::new(vec![0x76, 0xa9]);
+        let encoded = bincode::serialize(&script).unwrap();
+        assert_eq!(encoded, [&2u64.to_le_bytes()[..], &[0x76, 0xa9]].concat());
+        assert_eq!(bincode::deserialize::<Script>(&encoded).unwrap(), script);
+        assert_eq!(serde_json::to_string(&script).unwrap(), "\"76a9\"");
+        assert_eq!(serde_json::from_str::<Script>("\"76a9\"").unwrap(), script);
+
+        let bytes = hex::decode(GENESIS_COINBASE_HEX).unwrap();
+        let (tx, _) = BitcoinTransaction::from_bytes(&bytes).unwrap();
+        let encoded = bincode::serialize(&tx).unwrap();
+        assert!(encoded.len() < serde_json::to_vec(&tx).unwrap().len());
+        assert_eq!(
+            bincode::deserialize::<BitcoinTransaction>(&encoded).unwrap(),
+            tx
+        );
+    }
+
+    #[test]
+    fn test_merkle_root() {
+        use rust_week_3_exercises::merkle::merkle_root;
+
+        assert_eq!(merkle_root(&[]), MerkleRoot([0; 32]));
+
+        let bytes = hex::decode(GENESIS_COINBASE_HEX).unwrap();
+        let (tx, _) = BitcoinTransaction::from_bytes(&bytes).unwrap();
+        assert_eq!(merkle_root(&[tx.txid()]).0, tx.txid().0);
+
+        // Block 100000.
+        let txids: Vec<Txid> = [
+            "8c14f0db3df150123e6f3dbbf30f8b955a8249b62ac1d1ff16284aefa3d06d87",
+            "fff2525b8931402dd09222c50775608f75787bd2b87e56995a7bdd30f79702c4",
+            "6359f0868171b1d194cbee1af2f16ea598ae8fad666d9b012c8ed2b79a236ec4",
+            "e9a66845e05d5abc0ad04ec80f774a7e585c6e8db975962d069a522137b80c1d",
+        ]
+        .iter()
+        .map(|s| s.parse().unwrap())
+        .collect();
+        assert_eq!(
+            merkle_root(&txids).to_string(),
+            "f3e94742aca4b5ef85488dc37c06c3282295ffec960994b2c0d5ac2a25a95766"
+        );
+
+        let odd = &txids[..3];
+        let padded = [odd, &txids[2..3]].concat();
+        assert_eq!(merkle_root(odd), merkle_root(&padded));
+    }
+
+    #[test]
+    fn test_merkle_proofs() {
+        use rust_week_3_exercises::merkle::{MerkleProof, merkle_root};
+
+        let txids: Vec<Txid> = (0..5u8).map(|i| Txid(dummy_txid(i))).collect();
+        let root = merkle_root(&txids);
+        for (i, txid) in txids.iter().enumerate() {
+            let proof = MerkleProof::generate(&txids, i).unwrap();
+            assert_eq!(proof.path.len(), 3);
+            assert!(proof.verify(&root, txid));
+            assert!(!proof.verify(&root, &txids[(i + 1) % txids.len()]));
+        }
+        assert_eq!(MerkleProof::generate(&txids, 5), None);
+
+        let mut proof = MerkleProof::generate(&txids, 2).unwrap();
+        proof.index = 3;
+        assert!(!proof.verify(&root, &txids[2]));
+        proof.index = 2 + 8;
+        assert!(!proof.verify(&root, &txids[2]));
+
+        let single = MerkleProof::generate(&txids[..1], 0).unwrap();
+        assert!(single.path.is_empty());
+        assert!(single.verify(&merkle_root(&txids[..1]), &txids[0]));
+    }
+
+    #[test]
+    fn test_partial_merkle_tree() {
+        use rust_week_3_exercises::merkle::{PartialMerkleTree, merkle_root};
+
+        let txids: Vec<Txid> = (0..7u8).map(|i| Txid(dummy_txid(i))).collect();
+        let flags = [false, true, false, false, false, true, false];
+        let tree = PartialMerkleTree::from_txids(&txids, &flags).unwrap();
+
+        let bytes = tree.to_bytes();
+        assert_eq!(bytes.len(), tree.serialized_size());
+        let decoded = PartialMerkleTree::decode_exact(&bytes).unwrap();
+        assert_eq!(decoded.hashes, tree.hashes);
+        assert_eq!(decoded.bits.len(), tree.bits.len().div_ceil(8) * 8);
+
+        let (mut matches, mut indexes) = (vec![], vec![]);
+        let root = decoded.extract_matches(&mut matches, &mut indexes).unwrap();
+        assert_eq!(root, merkle_root(&txids));
+        assert_eq!(matches, vec![txids[1].clone(), txids[5].clone()]);
+        assert_eq!(indexes, vec![1, 5]);
+
+        let mut truncated = tree.clone();
+        truncated.hashes.pop();
+        assert!(matches!(
+            truncated.extract_matches(&mut vec![], &mut vec![]),
+            Err(BitcoinError::InvalidMerkleTree(_))
+        ));
+        let mut empty = tree.clone();
+        empty.num_transactions = 0;
+        assert!(empty.extract_matches(&mut vec![], &mut vec![]).is_err());
+
+        let duplicated = [txids[0].clone(), txids[0].clone()];
+        let tree = PartialMerkleTree::from_txids(&duplicated, &[true, false]).unwrap();
+        assert_eq!(
+            tree.extract_matches(&mut vec![], &mut vec![]),
+            Err(BitcoinError::InvalidMerkleTree("duplicate sibling hashes"))
+        );
+
+        assert_eq!(
+            PartialMerkleTree::from_txids(&[], &[]),
+            Err(BitcoinError::InvalidMerkleTree("no transactions"))
+        );
+        assert!(matches!(
+            PartialMerkleTree::from_txids(&txids, &flags[..6]),
+            Err(BitcoinError::InvalidMerkleTree(_))
+        ));
+    }
+
+    #[test]
+    fn test_block_header_round_trip() {
+        let bytes = hex::decode(GENESIS_HEADER_HEX).unwrap();
+        let header = BlockHeader::decode_exact(&bytes).unwrap();
+        assert_eq!(header.version, 1);
+        assert_eq!(header.prev_blockhash, BlockHash([0; 32]));
+        assert_eq!(
+            header.merkle_root.to_string(),
+            "4a5e1e4baab89f3a32518a88c31bc87f618f76673e2cc77ab2127b7afdeda33b"
+        );
+        assert_eq!(header.time, 1231006505);
+        assert_eq!(header.bits, 0x1d00ffff);
+        assert_eq!(header.nonce, 2083236893);
+        assert_eq!(
+            header.block_hash().to_string(),
+            "000000000019d6689c085ae165831e934ff763ae46a2a6c172b3f1b60a8ce26f"
+        );
+        assert_eq!(header.to_bytes(), bytes);
+        assert_eq!(header.serialized_size(), BlockHeader::SIZE);
+        assert!(matches!(
+            BlockHeader::decode_exact(&bytes[..79]),
+            Err(BitcoinError::InsufficientBytes { .. })
+        ));
+
+        let json = serde_json::to_string(&header).unwrap();
+        assert!(json.contains("\"merkle_root\":\"4a5e1e4b"));
+        assert_eq!(serde_json::from_str::<BlockHeader>(&json).unwrap(), header);
+    }
+
+    #[test]
+    fn test_block_decode() {
+        let hex = format!("{}01{}", GENESIS_HEADER_HEX, GENESIS_COINBASE_HEX);
+        let block = Block::from_hex(&hex).unwrap();
+        assert_eq!(block.txdata.len(), 1);
+        assert!(block.check_merkle_root());
+        assert_eq!(
+            block.block_hash().to_string(),
+            "000000000019d6689c085ae165831e934ff763ae46a2a6c172b3f1b60a8ce26f"
+        );
+        assert_eq!(block.total_size(), 285);
+        assert_eq!(block.base_size(), 285);
+        assert_eq!(block.weight(), 1140);
+        assert_eq!(hex::encode(block.to_bytes()), hex);
+
+        let mut tampered = block.clone();
+        tampered.txdata[0].lock_time = 1;
+        assert!(!tampered.check_merkle_root());
+
+        let mut segwit = block.clone();
+        segwit.txdata[0].inputs[0].witness = Witness::new(vec![vec![0u8; 32]]);
+        assert_eq!(segwit.total_size(), 285 + 2 + 34);
+        assert_eq!(segwit.weight(), 285 * 3 + 285 + 36);
+        assert_eq!(Block::decode_exact(&segwit.to_bytes()).unwrap(), segwit);
+        assert!(segwit.check_merkle_root());
+    }
+
+    #[test]
+    fn test_proof_of_work() {
+        let target = |bits| CompactTarget(bits).to_target();
+        let mut expected = [0u8; 32];
+        expected[4..6].copy_from_slice(&[0xff, 0xff]);
+        assert_eq!(target(0x1d00ffff), Ok(Target(expected)));
+
+        let mut expected = [0u8; 32];
+        expected[28..].copy_from_slice(&[0x12, 0x34, 0x56, 0x00]);
+        assert_eq!(target(0x04123456), Ok(Target(expected)));
+        let mut expected = [0u8; 32];
+        expected[31] = 0x12;
+        assert_eq!(target(0x01123456), Ok(Target(expected)));
+
+        for bits in [0x00000000, 0x01003456, 0x04923456, 0xff123456, 0x21010000] {
+            assert_eq!(target(bits), Err(BitcoinError::InvalidTarget { bits }));
+        }
+
+        let mut header =
+            BlockHeader::decode_exact(&hex::decode(GENESIS_HEADER_HEX).unwrap()).unwrap();
+        let params = Params::MAINNET;
+        assert_eq!(header.validate_pow(&params), Ok(header.block_hash()));
+        header.nonce += 1;
+        assert_eq!(
+            header.validate_pow(&params),
+            Err(BitcoinError::InsufficientProofOfWork)
+        );
+
+        // Regtest's easiest target is well formed but above mainnet's limit.
+        header.bits = 0x207fffff;
+        while header.validate_pow(&Params::REGTEST).is_err() {
+            header.nonce += 1;
+        }
+        assert_eq!(
+            header.validate_pow(&params),
+            Err(BitcoinError::InvalidTarget { bits: 0x207fffff })
+        );
+    }
+
+    #[test]
+    fn test_opcodes() {
+        for byte in 0..=255u8 {
+            assert_eq!(Opcode::from(byte).to_u8(), byte);
+        }
+        assert_eq!(Opcode::from(0x76), Opcode::OP_DUP);
+        assert_eq!(Opcode::from(0xac).to_string(), "OP_CHECKSIG");
+        assert_eq!(Opcode::from(0x14), Opcode::OP_PUSHBYTES_20);
+        assert_eq!(Opcode::from(0xba), Opcode::OP_CHECKSIGADD);
+        assert_eq!(Opcode::from(0xbb).name(), "OP_RETURN_187");
+        assert_eq!(Opcode::from(0xff), Opcode::OP_INVALIDOPCODE);
+        assert_eq!(Opcode::OP_NOP2, Opcode::OP_CHECKLOCKTIMEVERIFY);
+        assert_eq!(u8::from(Opcode::OP_TRUE), 0x51);
+
+        assert!(Opcode::OP_PUSHDATA4.is_push_data());
+        assert!(!Opcode::OP_1NEGATE.is_push_data());
+        assert!(Opcode::OP_16.is_push() && Opcode::OP_RESERVED.is_push());
+        assert!(!Opcode::OP_NOP.is_push());
+        assert!(Opcode::OP_CAT.is_disabled() && !Opcode::OP_ADD.is_disabled());
+
+        for n in -1..=16 {
+            assert_eq!(Opcode::from_small_int(n).unwrap().small_int(), Some(n));
+        }
+        assert_eq!(Opcode::from_small_int(16), Some(Opcode::OP_16));
+        assert_eq!(Opcode::from_small_int(17), None);
+        assert_eq!(Opcode::OP_RESERVED.small_int(), None);
+    }
+
+    #[test]
+    fn test_script_instructions() {
+        let genesis = BitcoinTransaction::from_hex(GENESIS_COINBASE_HEX).unwrap();
+        let p2pk: Vec<Instruction> = genesis.outputs[0]
+            .script_pubkey
+            .instructions()
+            .collect::<Result<_>>()
+            .unwrap();
+        assert_eq!(p2pk.len(), 2);
+        assert_eq!(p2pk[0].push_bytes().map(<[u8]>::len), Some(65));
+        assert_eq!(p2pk[1], Instruction::Op(Opcode::OP_CHECKSIG));
+
+        let script =
+            Script::new(hex::decode("004c004c0201024d0300aabbcc4e01000000dd76ff").unwrap());
+        let instructions: Vec<Instruction> = script.instructions().collect::<Result<_>>().unwrap();
+        assert_eq!(
+            instructions,
+            vec![
+                Instruction::PushBytes(&[]),
+                Instruction::PushBytes(&[]),
+                Instruction::PushBytes(&[0x01, 0x02]),
+                Instruction::PushBytes(&[0xaa, 0xbb, 0xcc]),
+                Instruction::PushBytes(&[0xdd]),
+                Instruction::Op(Opcode::OP_DUP),
+                Instruction::Op(Opcode::OP_INVALIDOPCODE),
+            ]
+        );
+        assert_eq!(instructions[5].opcode(), Some(Opcode::OP_DUP));
+
+        let truncated = Script::new(vec![0x76, 0x4d, 0x05, 0x00, 0xaa]);
+        let mut iter = truncated.instructions();
+        assert_eq!(iter.next(), Some(Ok(Instruction::Op(Opcode::OP_DUP))));
+        assert_eq!(
+            iter.next(),
+            Some(Err(BitcoinError::InsufficientBytes {
+                context: "script push",
+                offset: 1,
+                needed: 5,
+                available: 1,
+            }))
+        );
+        assert_eq!(iter.next(), None);
+        assert!(
+            Script::new(vec![0x4c])
+                .instructions()
+                .next()
+                .unwrap()
+                .is_err()
+        );
+    }
+
+    #[test]
+    fn test_script_builder() {
+        use rust_week_3_exercises::script::encode_script_num;
+
+        let hash = [0xab; 20];
+        let p2pkh = ScriptBuilder::new()
+            .push_opcode(Opcode::OP_DUP)
+            .push_opcode(Opcode::OP_HASH160)
+            .push_slice(&hash)
+            .push_opcode(Opcode::OP_EQUALVERIFY)
+            .push_opcode(Opcode::OP_CHECKSIG)
+            .into_script();
+        assert_eq!(
+            hex::encode(&p2pkh.bytes),
+            format!("76a914{}88ac", "ab".repeat(20))
+        );
+
+        let push =
+            |data: &[u8]| hex::encode(Script::builder().push_slice(data).into_script().bytes);
+        assert_eq!(push(&[]), "00");
+        assert_eq!(push(&[0x05]), "55");
+        assert_eq!(push(&[0x81]), "4f");
+        assert_eq!(push(&[0x00]), "0100");
+        assert_eq!(push(&[0x11]), "0111");
+        assert_eq!(push(&[0xaa; 75])[..2], *"4b");
+        assert_eq!(push(&[0xaa; 76])[..4], *"4c4c");
+        assert_eq!(push(&[0xaa; 256])[..6], *"4d0001");
+        assert_eq!(push(&[0xaa; 65536])[..10], *"4e00000100");
+
+        let int = |n| hex::encode(Script::builder().push_int(n).into_script().bytes);
+        assert_eq!(int(0), "00");
+        assert_eq!(int(-1), "4f");
+        assert_eq!(int(16), "60");
+        assert_eq!(int(17), "0111");
+        assert_eq!(int(-2), "0182");
+        assert_eq!(int(128), "028000");
+        assert_eq!(int(-128), "028080");
+        assert_eq!(int(500_000), "0320a107");
+
+        assert_eq!(encode_script_num(0), Vec::<u8>::new());
+        assert_eq!(encode_script_num(255), vec![0xff, 0x00]);
+        assert_eq!(encode_script_num(i64::MIN).len(), 9);
+    }
+
+    #[test]
+    fn test_script_asm() {
+        let p2pkh =
+            Script::new(hex::decode("76a91462e907b15cbf27d5425399ebf6f0fb50ebb88f1888ac").unwrap());
+        let asm =
+            "OP_DUP OP_HASH160 62e907b15cbf27d5425399ebf6f0fb50ebb88f18 OP_EQUALVERIFY OP_CHECKSIG";
+        assert_eq!(p2pkh.asm(), asm);
+        assert_eq!(format!("{:#}", p2pkh), asm);
+        assert_eq!(
+            p2pkh.to_string(),
+            "76a91462e907b15cbf27d5425399ebf6f0fb50ebb88f1888ac"
+        );
+
+        let numbers = Script::builder()
+            .push_int(0)
+            .push_int(-1)
+            .push_int(16)
+            .push_int(1000)
+            .push_int(-1000)
+            .push_slice(&[0xaa; 5])
+            .push_opcode(Opcode::OP_CHECKLOCKTIMEVERIFY)
+            .push_opcode(Opcode::from(0xc0))
+            .into_script();
+        assert_eq!(
+            numbers.asm(),
+            "0 -1 16 1000 -1000 aaaaaaaaaa OP_CHECKLOCKTIMEVERIFY OP_UNKNOWN"
+        );
+
+        assert_eq!(Script::new(vec![0x51, 0x4c]).asm(), "1 [error]");
+        assert_eq!(Script::new(vec![]).asm(), "");
+    }
+
+    #[test]
+    fn test_script_from_asm() {
+        let asm =
+            "OP_DUP OP_HASH160 62e907b15cbf27d5425399ebf6f0fb50ebb88f18 OP_EQUALVERIFY OP_CHECKSIG";
+        let script = Script::from_asm(asm).unwrap();
+        assert_eq!(
+            hex::encode(&script.bytes),
+            "76a91462e907b15cbf27d5425399ebf6f0fb50ebb88f1888ac"
+        );
+        assert_eq!(script.asm(), asm);
+
+        let numbers = "0 -1 16 1000 -1000 aaaaaaaaaa OP_CHECKLOCKTIMEVERIFY";
+        assert_eq!(Script::from_asm(numbers).unwrap().asm(), numbers);
+
+        assert_eq!(
+            Script::from_asm("DUP  OP_TRUE\n0x5193 NOP2").unwrap().bytes,
+            vec![0x76, 0x51, 0x51, 0x93, 0xb1]
+        );
+        assert_eq!(Script::from_asm("").unwrap(), Script::new(vec![]));
+        assert_eq!(
+            Script::from_asm("OP_DUP OP_BOGUS"),
+            Err(BitcoinError::InvalidAsm("OP_BOGUS".to_string()))
+        );
+        assert!(Script::from_asm("abc").is_err());
+        assert!(Script::from_asm("0xabc").is_err());
+
+        // Hex pushes made only of decimal digits must stay data pushes.
+        let p2wsh = Script::new_p2wsh(&ScriptHash([0; 32]));
+        assert_eq!(p2wsh.asm(), format!("0 {}", "0".repeat(64)));
+        assert_eq!(Script::from_asm(&p2wsh.asm()).unwrap(), p2wsh);
+        let push = Script::new(hex::decode("050000000001").unwrap());
+        assert_eq!(push.asm(), "0000000001");
+        assert_eq!(Script::from_asm(&push.asm()).unwrap(), push);
+        assert_eq!(
+            Script::from_asm("4294967295").unwrap().bytes,
+            hex::decode("05ffffffff00").unwrap()
+        );
+        assert_eq!(
+            Script::from_asm("4294967296").unwrap().bytes,
+            hex::decode("054294967296").unwrap()
+        );
+    }
+
+    #[test]
+    fn test_script_classification() {
+        let classify = |asm: &str| Script::from_asm(asm).unwrap().classify();
+        let h20 = "ab".repeat(20);
+        let h32 = "cd".repeat(32);
+        let key = format!("02{}", "11".repeat(32));
+
+        assert_eq!(
+            classify(&format!(
+                "OP_DUP OP_HASH160 {} OP_EQUALVERIFY OP_CHECKSIG",
+                h20
+            )),
+            ScriptType::P2pkh
+        );
+        assert_eq!(
+            classify(&format!("OP_HASH160 {} OP_EQUAL", h20)),
+            ScriptType::P2sh
+        );
+        assert_eq!(classify(&format!("0 {}", h20)), ScriptType::P2wpkh);
+        assert_eq!(classify(&format!("0 {}", h32)), ScriptType::P2wsh);
+        assert_eq!(classify(&format!("1 {}", h32)), ScriptType::P2tr);
+        assert_eq!(classify("1 4e73"), ScriptType::WitnessUnknown);
+        assert_eq!(
+            classify(&format!("0 {}", "ab".repeat(21))),
+            ScriptType::NonStandard
+        );
+        assert_eq!(classify(&format!("{} OP_CHECKSIG", key)), ScriptType::P2pk);
+        assert_eq!(classify("OP_RETURN 68656c6c6f 5"), ScriptType::OpReturn);
+        assert_eq!(classify("OP_RETURN"), ScriptType::OpReturn);
+        assert_eq!(classify("OP_RETURN OP_DUP"), ScriptType::NonStandard);
+        assert_eq!(
+            classify(&format!("1 {} {} 2 OP_CHECKMULTISIG", key, key)),
+            ScriptType::Multisig
+        );
+        assert_eq!(
+            classify(&format!("3 {} {} 2 OP_CHECKMULTISIG", key, key)),
+            ScriptType::NonStandard
+        );
+        assert_eq!(classify("OP_ADD"), ScriptType::NonStandard);
+
+        let genesis = BitcoinTransaction::from_hex(GENESIS_COINBASE_HEX).unwrap();
+        assert!(genesis.outputs[0].script_pubkey.is_p2pk());
+        let multisig = Script::from_asm(&format!("1 {} {} 2 OP_CHECKMULTISIG", key, key)).unwrap();
+        assert_eq!(multisig.multisig_params(), Some((1, 2)));
+        let p2tr = Script::from_asm(&format!("1 {}", h32)).unwrap();
+        assert!(p2tr.is_p2tr() && !p2tr.is_p2wsh() && p2tr.is_witness_program());
+        assert_eq!(
+            p2tr.witness_program().map(|(v, p)| (v, p.len())),
+            Some((1, 32))
+        );
+    }
+
+    #[test]
+    fn test_script_templates() {
+        let hash = [0xab; 20];
+        let key = [0xcd; 32];
+        let p2pkh = Script::new_p2pkh(&hash);
+        assert_eq!(
+            hex::encode(&p2pkh.bytes),
+            format!("76a914{}88ac", "ab".repeat(20))
+        );
+        assert_eq!(p2pkh.classify(), ScriptType::P2pkh);
+        assert_eq!(Script::new_p2sh(&hash).classify(), ScriptType::P2sh);
+        assert_eq!(
+            hex::encode(&Script::new_p2wpkh(&hash).bytes),
+            format!("0014{}", "ab".repeat(20))
+        );
+        assert_eq!(
+            Script::new_p2wsh(&ScriptHash(key)).classify(),
+            ScriptType::P2wsh
+        );
+        assert_eq!(
+            hex::encode(&Script::new_p2tr(&key).bytes),
+            format!("5120{}", "cd".repeat(32))
+        );
+        assert_eq!(
+            hex::encode(&Script::new_witness_program(16, &[0x01, 0x02]).bytes),
+            "60020102"
+        );
+        assert_eq!(
+            hex::encode(&Script::new_op_return(b"hello").bytes),
+            "6a0568656c6c6f"
+        );
+        assert_eq!(Script::new_op_return(&[]).bytes, vec![0x6a, 0x00]);
+
+        let redeem = Script::from_asm("OP_TRUE").unwrap();
+        assert_eq!(
+            redeem.to_p2sh(),
+            Script::new_p2sh(&hashes::hash160(&redeem.bytes))
+        );
+        assert_eq!(
+            hex::encode(&redeem.to_p2wsh().bytes),
+            "00204ae81572f06e1b88fd5ced7a1a000945432e83e1551e6f721ee9c00b8cc33260"
+        );
+    }
+
+    #[test]
+    fn test_op_return_data() {
+        let script = Script::from_asm("OP_RETURN 68656c6c6f 0 aabbccddee").unwrap();
+        assert_eq!(
+            script.op_return_data(),
+            Some(vec![&b"hello"[..], &[], &[0xaa, 0xbb, 0xcc, 0xdd, 0xee]])
+        );
+        assert_eq!(
+            Script::new_op_return(&[0x42; 80]).op_return_data(),
+            Some(vec![&[0x42; 80][..]])
+        );
+        assert_eq!(
+            Script::from_asm("OP_RETURN").unwrap().op_return_data(),
+            Some(vec![])
+        );
+
+        assert_eq!(
+            Script::from_asm("OP_RETURN 5").unwrap().op_return_data(),
+            None
+        );
+        assert_eq!(
+            Script::from_asm("OP_RETURN OP_DUP")
+                .unwrap()
+                .op_return_data(),
+            None
+        );
+        assert_eq!(Script::new(vec![0x6a, 0x05, 0x01]).op_return_data(), None);
+        assert_eq!(Script::new_p2pkh(&[0; 20]).op_return_data(), None);
+        assert_eq!(Script::new(vec![]).op_return_data(), None);
+    }
+
+    #[test]
+    fn test_sigop_counting() {
+        let key = format!("02{}", "11".repeat(32));
+        let multisig = |m, n| {
+            let keys = vec![key.as_str(); n].join(" ");
+            Script::from_asm(&format!("{} {} {} OP_CHECKMULTISIG", m, keys, n)).unwrap()
+        };
+        assert_eq!(multisig(2, 3).count_sigops(true), 3);
+        assert_eq!(multisig(2, 3).count_sigops(false), 20);
+        assert_eq!(
+            Script::from_asm("OP_CHECKMULTISIG")
+                .unwrap()
+                .count_sigops(true),
+            20
+        );
+        assert_eq!(
+            Script::from_asm("OP_CHECKSIG OP_CHECKSIGVERIFY")
+                .unwrap()
+                .count_sigops(false),
+            2
+        );
+        // Counting stops at an unparsable push.
+        assert_eq!(Script::new(vec![0xac, 0x4c]).count_sigops(false), 1);
+
+        let redeem = multisig(2, 3);
+        let wsh_script = Script::from_asm(&format!("{} OP_CHECKSIG", key)).unwrap();
+        let nested = Script::new_p2wpkh(&[0x22; 20]);
+        let input = |i, script_sig: Script, witness: Vec<Vec<u8>>| {
+            let mut input =
+                TransactionInput::new(OutPoint::new(dummy_txid(i), 0), script_sig, 0xffffffff);
+            input.witness = Witness::new(witness);
+            input
+        };
+        let sig = vec![0x30; 71];
+        let tx = BitcoinTransaction::new(
+            2,
+            vec![
+                input(
+                    1,
+                    Script::builder()
+                        .push_int(0)
+                        .push_slice(&sig)
+                        .push_slice(&sig)
+                        .push_slice(&redeem.bytes)
+                        .into_script(),
+                    vec![],
+                ),
+                input(2, Script::new(vec![]), vec![sig.clone(), vec![0x02; 33]]),
+                input(
+                    3,
+                    Script::new(vec![]),
+                    vec![sig.clone(), wsh_script.bytes.clone()],
+                ),
+                input(
+                    4,
+                    Script::builder().push_slice(&nested.bytes).into_script(),
+                    vec![sig.clone(), vec![0x02; 33]],
+                ),
+            ],
+            vec![
+                TransactionOutput::new(1000, Script::new_p2pkh(&[0x33; 20])),
+                TransactionOutput::new(1000, multisig(1, 2)),
+            ],
+            0,
+        );
+        let prevouts = vec![
+            TransactionOutput::new(1000, redeem.to_p2sh()),
+            TransactionOutput::new(1000, Script::new_p2wpkh(&[0x44; 20])),
+            TransactionOutput::new(1000, wsh_script.to_p2wsh()),
+            TransactionOutput::new(1000, nested.to_p2sh()),
+        ];
+        assert_eq!(tx.legacy_sigop_count(), 21);
+        assert_eq!(tx.sigop_cost(&prevouts), Ok(84 + 12 + 3));
+        assert_eq!(
+            tx.sigop_cost(&prevouts[..3]),
+            Err(BitcoinError::PrevoutsMismatch {
+                inputs: 4,
+                prevouts: 3
+            })
+        );
+    }
+
+    #[test]
+    fn test_push_only_and_minimal_pushes() {
+        let script_sig = Script::from_asm(&format!("0 {} 16 -1", "ab".repeat(80))).unwrap();
+        assert!(script_sig.is_push_only());
+        assert_eq!(script_sig.check_minimal_pushes(), Ok(()));
+
+        let script = Script::new(hex::decode("0051ac").unwrap());
+        assert!(!script.is_push_only());
+        assert_eq!(
+            script.check_push_only(),
+            Err(BitcoinError::NotPushOnly {
+                offset: 2,
+                opcode: Opcode::OP_CHECKSIG
+            })
+        );
+        assert!(!Script::new(vec![0x02, 0xaa]).is_push_only());
+
+        let cases = [
+            ("4c00", Opcode::OP_PUSHDATA1, Opcode::OP_0),
+            ("0105", Opcode::OP_PUSHBYTES_1, Opcode::OP_5),
+            ("0181", Opcode::OP_PUSHBYTES_1, Opcode::OP_1NEGATE),
+            ("4c01ff", Opcode::OP_PUSHDATA1, Opcode::OP_PUSHBYTES_1),
+            ("4d0100ff", Opcode::OP_PUSHDATA2, Opcode::OP_PUSHBYTES_1),
+        ];
+        for (hex, used, expected) in cases {
+            let script = Script::new(hex::decode(format!("00{}", hex)).unwrap());
+            assert_eq!(
+                script.check_minimal_pushes(),
+                Err(BitcoinError::NonMinimalPush {
+                    offset: 1,
+                    used,
+                    expected
+                })
+            );
+        }
+    }
+
+    #[test]
+    fn test_script_interpreter() {
+        use script::interpreter::{
+            Interpreter, NoSignatureChecker, ScriptError, ScriptLimits, SigVersion,
+            SignatureChecker, VerifyFlags, verify_script,
+        };
+
+        const SIG: [u8; 9] = [0x30, 0x06, 0x02, 0x01, 0x01, 0x02, 0x01, 0x01, 0x01];
+        struct TestChecker {
+            pubkey: Vec<u8>,
+        }
+        impl SignatureChecker for TestChecker {
+            fn check_ecdsa_signature(
+                &self,
+                sig: &[u8],
+                pubkey: &[u8],
+                _script_code: &Script,
+                _sig_version: SigVersion,
+            ) -> bool {
+                sig == SIG && pubkey == self.pubkey
+            }
+
+            fn check_lock_time(&self, lock_time: i64) -> bool {
+                lock_time <= 500
+            }
+        }
+
+        let asm = |s: &str| Script::from_asm(s).unwrap();
+        let run = |script_sig: &str, script_pubkey: &str, flags| {
+            verify_script(
+                &asm(script_sig),
+                &asm(script_pubkey),
+                &Witness::default(),
+                flags,
+                NoSignatureChecker,
+            )
+        };
+        let fails = |e| Err(BitcoinError::Script(e));
+        assert_eq!(run("2 3", "OP_ADD 5 OP_EQUAL", VerifyFlags::NONE), Ok(()));
+        assert_eq!(
+            run("1", "OP_IF 2 OP_ELSE OP_RETURN OP_ENDIF", VerifyFlags::NONE),
+            Ok(())
+        );
+        assert_eq!(
+            run("0", "OP_IF 1 OP_ENDIF", VerifyFlags::NONE),
+            fails(ScriptError::EvalFalse)
+        );
+        assert_eq!(
+            run("1", "OP_IF 1", VerifyFlags::NONE),
+            fails(ScriptError::UnbalancedConditional)
+        );
+        assert_eq!(
+            run("1", "0 OP_IF OP_CAT OP_ENDIF", VerifyFlags::NONE),
+            fails(ScriptError::DisabledOpcode)
+        );
+        assert_eq!(
+            run("", "OP_DROP", VerifyFlags::NONE),
+            fails(ScriptError::InvalidStackOperation)
+        );
+        assert_eq!(
+            run(
+                "0x03 0x616263",
+                "OP_SHA1 a9993e364706816aba3e25717850c26c9cd0d89d OP_EQUAL",
+                VerifyFlags::NONE
+            ),
+            Ok(())
+        );
+        assert_eq!(
+            run("0x4c01 0x07", "OP_DROP 1", VerifyFlags::MINIMALDATA),
+            fails(ScriptError::MinimalData)
+        );
+        assert_eq!(run("0x02 0x0100", "OP_1ADD", VerifyFlags::NONE), Ok(()));
+        assert_eq!(
+            run("0x02 0x0100", "OP_1ADD", VerifyFlags::MINIMALDATA),
+            fails(ScriptError::InvalidNumber)
+        );
+        assert_eq!(run("1 OP_DUP", "OP_DROP", VerifyFlags::NONE), Ok(()));
+        assert_eq!(
+            run("1 OP_DUP", "OP_DROP", VerifyFlags::SIGPUSHONLY),
+            fails(ScriptError::SigPushOnly)
+        );
+
+        let interpreter =
+            Interpreter::new(NoSignatureChecker, VerifyFlags::NONE).with_limits(ScriptLimits {
+                max_ops: 2,
+                ..ScriptLimits::default()
+            });
+        let mut stack = vec![];
+        assert_eq!(
+            interpreter.eval(&mut stack, &asm("1 OP_DUP OP_DUP OP_DUP"), SigVersion::Base),
+            fails(ScriptError::OpCount)
+        );
+
+        let pubkey = vec![0x02; 33];
+        let checker = TestChecker {
+            pubkey: pubkey.clone(),
+        };
+        let pubkey_hash = hashes::hash160(&pubkey);
+        let push_sig = Script::builder()
+            .push_slice(&SIG)
+            .push_slice(&pubkey)
+            .into_script();
+        let flags = VerifyFlags::STANDARD;
+        let p2pkh = Script::new_p2pkh(&pubkey_hash);
+        assert_eq!(
+            verify_script(&push_sig, &p2pkh, &Witness::default(), flags, &checker),
+            Ok(())
+        );
+
+        let witness = Witness::new(vec![SIG.to_vec(), pubkey.clone()]);
+        let p2wpkh = Script::new_p2wpkh(&pubkey_hash);
+        let empty = Script::new(vec![]);
+        assert_eq!(
+            verify_script(&empty, &p2wpkh, &witness, flags, &checker),
+            Ok(())
+        );
+        assert_eq!(
+            verify_script(&push_sig, &p2pkh, &witness, flags, &checker),
+            fails(ScriptError::WitnessUnexpected)
+        );
+        let nested = Script::builder().push_slice(&p2wpkh.bytes).into_script();
+        assert_eq!(
+            verify_script(&nested, &p2wpkh.to_p2sh(), &witness, flags, &checker),
+            Ok(())
+        );
+
+        let multisig = asm(&format!(
+            "1 {} {} 2 OP_CHECKMULTISIG",
+            "03".repeat(33),
+            hex::encode(&pubkey)
+        ));
+        let spend = |dummy: &[u8]| {
+            Script::builder()
+                .push_slice(dummy)
+                .push_slice(&SIG)
+                .push_slice(&multisig.bytes)
+                .into_script()
+        };
+        let p2sh = multisig.to_p2sh();
+        assert_eq!(
+            verify_script(&spend(&[]), &p2sh, &Witness::default(), flags, &checker),
+            Ok(())
+        );
+        assert_eq!(
+            verify_script(&spend(&[1]), &p2sh, &Witness::default(), flags, &checker),
+            fails(ScriptError::SigNullDummy)
+        );
+
+        let cltv = asm("500 OP_CHECKLOCKTIMEVERIFY OP_DROP 1");
+        let cltv_late = asm("501 OP_CHECKLOCKTIMEVERIFY OP_DROP 1");
+        assert_eq!(
+            verify_script(&empty, &cltv, &Witness::default(), flags, &checker),
+            Ok(())
+        );
+        assert_eq!(
+            verify_script(&empty, &cltv_late, &Witness::default(), flags, &checker),
+            fails(ScriptError::UnsatisfiedLockTime)
+        );
+        assert_eq!(
+            verify_script(
+                &empty,
+                &cltv_late,
+                &Witness::default(),
+                VerifyFlags::NONE,
+                &checker
+            ),
+            Ok(())
+        );
+    }
+
+    #[test]
+    fn test_dust_threshold() {
+        let program = [0x11; 32];
+        let cases = [
+            (Script::new_p2pkh(&[0x11; 20]), 546),
+            (Script::new_p2sh(&[0x11; 20]), 540),
+            (Script::new_p2wpkh(&[0x11; 20]), 294),
+            (Script::new_witness_program(0, &program), 330),
+            (Script::new_p2tr(&program), 330),
+            (Script::new_op_return(b"data"), 0),
+        ];
+        for (script, threshold) in cases {
+            let output = TransactionOutput::new(threshold, script);
+            assert_eq!(output.dust_threshold(policy::DUST_RELAY_TX_FEE), threshold);
+            assert!(!output.is_dust(policy::DUST_RELAY_TX_FEE));
+            let below = TransactionOutput::new(threshold.saturating_sub(1), output.script_pubkey);
+            assert_eq!(below.is_dust(policy::DUST_RELAY_TX_FEE), threshold > 0);
+        }
+        // 1 sat/vB: a P2WPKH output and spend take 98 vbytes.
+        let output = TransactionOutput::new(97, Script::new_p2wpkh(&[0x11; 20]));
+        assert_eq!(output.dust_threshold(1000), 98);
+        assert!(output.is_dust(1000));
+        assert!(!output.is_dust(0));
+    }
+
+    #[test]
+    fn test_script_limits() {
+        use script::interpreter::ScriptError;
+
+        let limit = |e| Err(BitcoinError::Script(e));
+        let push = |len| Script::builder().push_slice(&vec![0xaa; len]).into_script();
+        assert_eq!(
+            push(script::MAX_SCRIPT_ELEMENT_SIZE).validate_limits(),
+            Ok(())
+        );
+        assert_eq!(push(521).validate_limits(), limit(ScriptError::PushSize));
+        assert_eq!(
+            Script::new(vec![0x61; 10_001]).validate_limits(),
+            limit(ScriptError::ScriptSize)
+        );
+        assert_eq!(Script::new(vec![0x61; 201]).validate_limits(), Ok(()));
+        assert_eq!(
+            Script::new(vec![0x61; 202]).validate_limits(),
+            limit(ScriptError::OpCount)
+        );
+
+        // Each CHECKMULTISIG also counts its keys.
+        let multisig = Script::from_asm("0 0 20 OP_CHECKMULTISIG").unwrap();
+        let mut bytes = multisig.bytes.repeat(9);
+        assert_eq!(Script::new(bytes.clone()).validate_limits(), Ok(()));
+        bytes.extend_from_slice(&multisig.bytes);
+        assert_eq!(
+            Script::new(bytes).validate_limits(),
+            limit(ScriptError::OpCount)
+        );
+
+        assert_eq!(Script::new(vec![0x51; 1000]).validate_limits(), Ok(()));
+        assert_eq!(
+            Script::new(vec![0x51; 1001]).validate_limits(),
+            limit(ScriptError::StackSize)
+        );
+        let tight = script::ScriptLimits {
+            max_stack_size: 2,
+            ..script::ScriptLimits::default()
+        };
+        assert!(
+            Script::new(vec![0x51; 3])
+                .validate_limits_with(&tight)
+                .is_err()
+        );
+        assert!(Script::new(vec![0x4c]).validate_limits().is_err());
+    }
+
+    #[test]
+    fn test_standardness() {
+        use policy::{NonStandardReason, PolicyOptions, check_standard_tx};
+
+        let script_sig = Script::builder()
+            .push_slice(&[0x30; 72])
+            .push_slice(&[0x02; 33])
+            .into_script();
+        let tx = |outputs: Vec<TransactionOutput>| {
+            BitcoinTransaction::new(
+                2,
+                vec![TransactionInput::new(
+                    OutPoint::new(dummy_txid(1), 0),
+                    script_sig.clone(),
+                    0xffffffff,
+                )],
+                outputs,
+                0,
+            )
+        };
+        let out = |value, script| TransactionOutput::new(value, script);
+        let p2wpkh = Script::new_p2wpkh(&[0x11; 20]);
+        let p2pkh = Script::new_p2pkh(&[0x11; 20]);
+        let defaults = PolicyOptions::default();
+        let check = |tx: &BitcoinTransaction| check_standard_tx(tx, &defaults);
+        let reject = |reason| Err(BitcoinError::NonStandard(reason));
+
+        let standard = tx(vec![out(294, p2wpkh.clone()), out(546, p2pkh.clone())]);
+        assert!(standard.is_standard());
+        let mut v4 = standard.clone();
+        v4.version = 4;
+        assert_eq!(check(&v4), reject(NonStandardReason::Version(4)));
+        assert_eq!(
+            check(&tx(vec![out(293, p2wpkh.clone())])),
+            reject(NonStandardReason::Dust(0))
+        );
+        assert_eq!(
+            check(&tx(vec![out(1000, p2wpkh.clone()), out(545, p2pkh)])),
+            reject(NonStandardReason::Dust(1))
+        );
+
+        let data = |len| out(0, Script::new_op_return(&vec![0xaa; len]));
+        assert_eq!(check(&tx(vec![data(80)])), Ok(()));
+        assert_eq!(
+            check(&tx(vec![data(81)])),
+            reject(NonStandardReason::DataCarrierSize(0))
+        );
+        assert_eq!(
+            check(&tx(vec![data(1), data(1)])),
+            reject(NonStandardReason::MultiOpReturn)
+        );
+        let no_data = PolicyOptions {
+            max_datacarrier_bytes: None,
+            ..defaults
+        };
+        assert!(check_standard_tx(&tx(vec![data(1)]), &no_data).is_err());
+
+        let multisig = |n: usize| {
+            let keys = vec![format!("02{}", "22".repeat(32)); n].join(" ");
+            let asm = format!("1 {} {} OP_CHECKMULTISIG", keys, n);
+            out(1000, Script::from_asm(&asm).unwrap())
+        };
+        assert_eq!(check(&tx(vec![multisig(3)])), Ok(()));
+        assert_eq!(
+            check(&tx(vec![multisig(4)])),
+            reject(NonStandardReason::ScriptPubKey(0))
+        );
+        let no_bare_multisig = PolicyOptions {
+            permit_bare_multisig: false,
+            ..defaults
+        };
+        assert_eq!(
+            check_standard_tx(&tx(vec![multisig(2)]), &no_bare_multisig),
+            reject(NonStandardReason::BareMultisig(0))
+        );
+        assert_eq!(
+            check(&tx(vec![out(1000, Script::new(vec![0x51]))])),
+            reject(NonStandardReason::ScriptPubKey(0))
+        );
+
+        let mut not_push_only = standard.clone();
+        not_push_only.inputs[0].script_sig = Script::from_asm("1 OP_DUP").unwrap();
+        let err = check(&not_push_only).unwrap_err();
+        assert_eq!(
+            err,
+            BitcoinError::NonStandard(NonStandardReason::ScriptSigNotPushOnly(0))
+        );
+        assert_eq!(
+            err.to_string(),
+            "non-standard transaction: scriptsig-not-pushonly at input 0"
+        );
+    }
+
+    #[test]
+    fn test_timelock_scripts() {
+        let key = format!("02{}", "33".repeat(32));
+        let checksig = Script::from_asm(&format!("{} OP_CHECKSIG", key)).unwrap();
+
+        let cltv = Script::new_cltv(840_000, &checksig);
+        assert_eq!(
+            cltv.asm(),
+            format!("840000 OP_CHECKLOCKTIMEVERIFY OP_DROP {} OP_CHECKSIG", key)
+        );
+        assert_eq!(
+            cltv.parse_cltv(),
+            Some((840_000, checksig.bytes.as_slice()))
+        );
+        assert_eq!(cltv.parse_csv(), None);
+
+        // Five-byte numbers cover the whole u32 range.
+        let late = Script::new_cltv(u32::MAX, &checksig);
+        assert_eq!(hex::encode(&late.bytes[..6]), "05ffffffff00");
+        assert_eq!(late.parse_cltv().map(|(n, _)| n), Some(u32::MAX));
+
+        let csv = Script::new_csv(144, &checksig);
+        assert_eq!(hex::encode(&csv.bytes[..5]), "029000b275");
+        assert_eq!(csv.parse_csv(), Some((144, checksig.bytes.as_slice())));
+        let small = Script::new_csv(16, &Script::new(vec![]));
+        assert_eq!(small.bytes, vec![0x60, 0xb2, 0x75]);
+        assert_eq!(small.parse_csv(), Some((16, &[][..])));
+
+        for asm in [
+            "0x02 0x1000 OP_CHECKSEQUENCEVERIFY OP_DROP",
+            "-1 OP_CHECKSEQUENCEVERIFY OP_DROP",
+            "144 OP_CHECKSEQUENCEVERIFY",
+            "144 OP_CHECKLOCKTIMEVERIFY OP_DROP",
+        ] {
+            assert_eq!(Script::from_asm(asm).unwrap().parse_csv(), None, "{}", asm);
+        }
+    }
+
+    #[test]
+    fn test_htlc_scripts() {
+        use script::{OfferedHtlc, ReceivedHtlc};
+
+        // BOLT 3 commitment test vectors: HTLC 2 (offered) and HTLC 0
+        // (received, expiry 500).
+        let offered_hex = "76a91414011f7254d96b819c76986c277d115efce6f7b58763ac67210394854aa6eab5b2a8122cc726e9dded053a2184d88256816826d6231c068d4a5b7c820120876475527c21030d417a46946384f88d5f3337267c5e579765875dc4daca813e21734b140639e752ae67a914b43e1b38138a41b37f7cd9a1d274bc63e3a9b5d188ac6868";
+        let received_hex = "76a91414011f7254d96b819c76986c277d115efce6f7b58763ac67210394854aa6eab5b2a8122cc726e9dded053a2184d88256816826d6231c068d4a5b7c8201208763a914b8bcb07f6344b42ab04250c86a6e8b75d3fdbbc688527c21030d417a46946384f88d5f3337267c5e579765875dc4daca813e21734b140639e752ae677502f401b175ac6868";
+        let local: [u8; 33] =
+            hex::decode("030d417a46946384f88d5f3337267c5e579765875dc4daca813e21734b140639e7")
+                .unwrap()
+                .try_into()
+                .unwrap();
+        let remote: [u8; 33] =
+            hex::decode("0394854aa6eab5b2a8122cc726e9dded053a2184d88256816826d6231c068d4a5b")
+                .unwrap()
+                .try_into()
+                .unwrap();
+
+        let offered_script = Script::new(hex::decode(offered_hex).unwrap());
+        let offered = OfferedHtlc::from_script(&offered_script).unwrap();
+        assert_eq!(offered.local_htlc_pubkey, local);
+        assert_eq!(offered.remote_htlc_pubkey, remote);
+        // RIPEMD160 of the payment hash, itself the SHA256 of the preimage.
+        assert_eq!(offered.payment_hash160, hashes::hash160(&[0x02; 32]));
+        assert!(!offered.anchors);
+        assert_eq!(offered.to_script(), offered_script);
+
+        let received_script = Script::new(hex::decode(received_hex).unwrap());
+        let received = ReceivedHtlc::from_script(&received_script).unwrap();
+        assert_eq!(received.cltv_expiry, 500);
+        assert_eq!(received.payment_hash160, hashes::hash160(&[0x00; 32]));
+        assert_eq!(
+            received.revocation_pubkey_hash,
+            offered.revocation_pubkey_hash
+        );
+        assert_eq!(received.to_script(), received_script);
+        assert_eq!(OfferedHtlc::from_script(&received_script), None);
+        assert_eq!(ReceivedHtlc::from_script(&offered_script), None);
+
+        let revocation = [0x02; 33];
+        let payment_hash = hashes::sha256(&[0x07; 32]);
+        let htlc = ReceivedHtlc {
+            anchors: true,
+            ..ReceivedHtlc::new(&revocation, local, remote, &payment_hash, 840_000)
+        };
+        let script = htlc.to_script();
+        assert!(
+            script
+                .asm()
+                .ends_with("OP_CHECKSIG OP_ENDIF 1 OP_CHECKSEQUENCEVERIFY OP_DROP OP_ENDIF")
+        );
+        assert_eq!(ReceivedHtlc::from_script(&script), Some(htlc));
+        // Expiries up to 16 are encoded as `OP_1`..`OP_16`, not data pushes.
+        for expiry in [0, 1, 10, 16, 17] {
+            let htlc = ReceivedHtlc::new(&revocation, local, remote, &payment_hash, expiry);
+            assert_eq!(
+                ReceivedHtlc::from_script(&htlc.to_script()),
+                Some(htlc),
+                "{}",
+                expiry
+            );
+        }
+        let htlc = OfferedHtlc {
+            anchors: true,
+            ..OfferedHtlc::new(&revocation, local, remote, &payment_hash)
+        };
+        assert_eq!(OfferedHtlc::from_script(&htlc.to_script()), Some(htlc));
+    }
+
+    #[test]
+    fn test_descriptors() {
+        use descriptor::Descriptor;
+
+        // BIP 381-386 test vectors.
+        let vectors = [
+            (
+                "pkh(02c6047f9441ed7d6d3045406e95c07cd85c778e4b8cef3ca7abac09b95c709ee5)",
+                "76a91406afd46bcdfd22ef94ac122aa11f241244a37ecc88ac",
+            ),
+            (
+                "wpkh(02f9308a019258c31049344f85f89d5229b531c845836f99b08601f113bce036f9)",
+                "00147dd65592d0ab2fe0d0257d571abf032cd9db93dc",
+            ),
+            (
+                "sh(wpkh(03fff97bd5755eeea420453a14355235d382f6472f8568a18b2f057a1460297556))",
+                "a914cc6ffbc0bf31af759451068f90ba7a0272b6b33287",
+            ),
+            (
+                "sh(multi(2,022f01e5e15cca351daff3843fb70f3c2f0a1bdd05e5af888a67784ef3e10a2a01,03acd484e2f0c7f65309ad178a9f559abde09796974c57e714c35f110dfc27ccbe))",
+                "a914a6a8b030a38762f4c1f5cbe387b61a3c5da5cd2687",
+            ),
+            (
+                "wsh(multi(2,03a0434d9e47f3c86235477c7b1ae6ae5d3442d49b1943c2b752a68e2a47e247c7,03774ae7f858a9411e5ef4246b70c65aac5649980be5c17891bbec17895da008cb,03d01115d548e7561b15c38f004d734633687cf4419620095bc5b0f47070afe85a))",
+                "0020773d709598b76c4e3b575c08aad40658963f9322affc0f8c28d1d9a68d0c944a",
+            ),
+            (
+                "sh(wsh(multi(1,03f28773c2d975288bc7d1d205c3748651b075fbc6610e58cddeeddf8f19405aa8,03499fdf9e895e719cfd64e67f07d38e3226aa7b63678949e6e49b241a60e823e4,02d7924d4f7d43ea965a465ae3095ff41131e5946f3c85f79e44adbcf8e27e080e)))",
+                "a914aec509e284f909f769bb7dda299a717c87cc97ac87",
+            ),
+            (
+                "tr(a34b99f22c790c4e36b2b3c2c35a36db06226e41c692fc82b8b56ac1c540c5bd)",
+                "512077aab6e066f8a7419c5ab714c12c67d25007ed55a43cadcacb4d7a970a093f11",
+            ),
+            (
+                "tr(a34b99f22c790c4e36b2b3c2c35a36db06226e41c692fc82b8b56ac1c540c5bd,pk(669b8afcec803a0d323e9a17f3ea8e68e8abe5a278020a929adbec52421adbd0))",
+                "512017cf18db381d836d8923b1bdb246cfcd818da1a9f0e6e7907f187f0b2f937754",
+            ),
+        ];
+        for (desc, spk) in vectors {
+            let parsed: Descriptor = desc.parse().unwrap();
+            assert_eq!(hex::encode(&parsed.script_pubkey().bytes), spk, "{}", desc);
+            assert_eq!(parsed.to_string(), desc);
+        }
+
+        let with_origin: Descriptor =
+            "wpkh([d34db33f/84h/0h/0h]02f9308a019258c31049344f85f89d5229b531c845836f99b08601f113bce036f9)"
+                .parse()
+                .unwrap();
+        assert_eq!(
+            with_origin.to_string(),
+            "wpkh([d34db33f/84'/0'/0']02f9308a019258c31049344f85f89d5229b531c845836f99b08601f113bce036f9)"
+        );
+
+        let uncompressed = "04a34b99f22c790c4e36b2b3c2c35a36db06226e41c692fc82b8b56ac1c540c5bda47213adca5f0578ddb89388f63fdaa61c558c55fc6e745d2b6d1157a54159fa";
+        assert!(
+            format!("pkh({})", uncompressed)
+                .parse::<Descriptor>()
+                .is_ok()
+        );
+        for invalid in [
+            format!("wpkh({})", uncompressed),
+            format!("wsh(pkh({}))", uncompressed),
+            "wsh(wpkh(02f9308a019258c31049344f85f89d5229b531c845836f99b08601f113bce036f9))".into(),
+            "sh(sh(pkh(02f9308a019258c31049344f85f89d5229b531c845836f99b08601f113bce036f9)))"
+                .into(),
+            "multi(3,02f9308a019258c31049344f85f89d5229b531c845836f99b08601f113bce036f9)".into(),
+            "pkh(a34b99f22c790c4e36b2b3c2c35a36db06226e41c692fc82b8b56ac1c540c5bd)".into(),
+            "pkh(02f9308a019258c31049344f85f89d5229b531c845836f99b08601f113bce036f9))".into(),
+            "foo(02f9308a019258c31049344f85f89d5229b531c845836f99b08601f113bce036f9)".into(),
+        ] {
+            assert!(
+                matches!(
+                    invalid.parse::<Descriptor>(),
+                    Err(BitcoinError::InvalidDescriptor(_))
+                ),
+                "{}",
+                invalid
+            );
+        }
+    }
+
+    #[test]
+    fn test_descriptor_checksum() {
+        use descriptor::{Descriptor, add_checksum, descriptor_checksum, verify_checksum};
+
+        // BIP 380 test vector.
+        assert_eq!(descriptor_checksum("raw(deadbeef)").unwrap(), "89f8spxm");
+        assert_eq!(
+            verify_checksum("raw(deadbeef)#89f8spxm").unwrap(),
+            "raw(deadbeef)"
+        );
+        for bad in [
+            "raw(deadbeef)#",
+            "raw(deadbeef)#89f8spxmx",
+            "raw(deedbeef)#89f8spxm",
+            "raw(deadbeef)##9f8spxm",
+            "raw(deadbeef)",
+        ] {
+            assert!(verify_checksum(bad).is_err(), "{}", bad);
+        }
+        assert!(descriptor_checksum("raw(dead\u{e9}beef)").is_err());
+
+        let desc = "wpkh(02f9308a019258c31049344f85f89d5229b531c845836f99b08601f113bce036f9)";
+        let parsed: Descriptor = desc.parse().unwrap();
+        let with_checksum = parsed.to_string_with_checksum();
+        assert_eq!(with_checksum, add_checksum(desc).unwrap());
+        assert_eq!(with_checksum.parse::<Descriptor>().unwrap(), parsed);
+        let mut corrupted = with_checksum.clone();
+        corrupted.pop();
+        corrupted.push(if with_checksum.ends_with('q') {
+            'p'
+        } else {
+            'q'
+        });
+        assert!(corrupted.parse::<Descriptor>().is_err());
+    }
+
+    #[test]
+    fn test_ranged_descriptor_derivation() {
+        use bip32::Xpub;
+        use descriptor::{Descriptor, DescriptorPublicKey, SinglePubKey};
+
+        // BIP32 test vector 1: m/0H/1 is derivable from the xpub at m/0H.
+        let xpub: Xpub = "xpub68Gmy5EdvgibQVfPdqkBBCHxA5htiqg55crXYuXoQRKfDBFA1WEjWgP6LHhwBZeNK1VTsfTFUHCdrfp1bgwQ9xv5ski8PX9rL2dZXvgGDnw".parse().unwrap();
+        let child = xpub.ckd_pub(1).unwrap();
+        let child_str = "xpub6ASuArnXKPbfEwhqN6e3mwBcDTgzisQN1wXN9BJcM47sSikHjJf3UFHKkNAWbWMiGj7Wf5uMash7SyYq527Hqck2AxYysAA7xmALppuCkwQ";
+        assert_eq!(child.to_string(), child_str);
+        assert_eq!(child.depth, 2);
+        assert_eq!(child.parent_fingerprint, xpub.fingerprint());
+        assert!(xpub.ckd_pub(1 << 31).is_err());
+        let master: Xpub = "xpub661MyMwAqRbcFtXgS5sYJABqqG9YLmC4Q1Rdap9gSE8NqtwybGhePY2gZ29ESFjqJoCu1Rupje8YtGqsefD265TMg7usUDFdp6W1EGMcet8".parse().unwrap();
+        assert_eq!(hex::encode(master.fingerprint()), "3442193e");
+
+        let desc = format!("wpkh([d34db33f/84'/0'/0']{}/0/*)", child_str);
+        let parsed: Descriptor = desc.parse().unwrap();
+        assert!(parsed.is_ranged());
+        assert_eq!(parsed.to_string(), desc);
+        let scripts: Vec<_> = parsed
+            .derive_scripts(0..3)
+            .map(|item| item.map(|(index, spk)| (index, hex::encode(&spk.bytes))))
+            .collect::<Result<_>>()
+            .unwrap();
+        assert_eq!(
+            scripts,
+            [
+                (
+                    0,
+                    "0014757c05317fcb85e910c5f3e6cd9dc4d06b5d8321".to_string()
+                ),
+                (
+                    1,
+                    "0014162bee13a8ea6b261db5d0950daffaf128548474".to_string()
+                ),
+                (
+                    2,
+                    "00148afa158c9e1fd88067d999593c1ba608fba1e7cf".to_string()
+                ),
+            ]
+        );
+
+        let derived = parsed.at_derivation_index(1).unwrap();
+        assert!(!derived.is_ranged());
+        let key = derived.keys()[0];
+        assert_eq!(
+            key.origin.as_ref().unwrap().to_string(),
+            "d34db33f/84'/0'/0'/0/1"
+        );
+        assert_eq!(
+            key.key,
+            DescriptorPublicKey::Single(SinglePubKey::Compressed(
+                hex::decode("02943c52f94ef55ae574df5dd0a66326f4ad18227e725438c44dd26edf3ebbb290")
+                    .unwrap()
+                    .try_into()
+                    .unwrap()
+            ))
+        );
+
+        // A fixed path needs no index, and a key with no origin takes the
+        // xpub's own fingerprint.
+        let tr: Descriptor = format!("tr({}/7)", master).parse().unwrap();
+        assert!(!tr.is_ranged());
+        assert_eq!(
+            hex::encode(&tr.script_pubkey().bytes),
+            "5120d1fd57b3ee450099762e228afedf0e74ae6088a1206020e7b354c154690fdafe"
+        );
+        let tr_key = tr.at_derivation_index(0).unwrap();
+        assert_eq!(
+            tr_key.keys()[0].origin.as_ref().unwrap().to_string(),
+            "3442193e/7"
+        );
+
+        for invalid in [
+            format!("wpkh({}/0'/*)", master),
+            format!("wpkh({}/*')", master),
+            format!("wpkh({}/*/0)", master),
+            format!("wpkh({}/0/*)", &child_str[..child_str.len() - 1]),
+        ] {
+            assert!(invalid.parse::<Descriptor>().is_err(), "{}", invalid);
+        }
+    }
+
+    #[test]
+    fn test_address_parse_and_display() {
+        fn bytes<const N: usize>(hex_str: &str) -> [u8; N] {
+            hex::decode(hex_str).unwrap().try_into().unwrap()
+        }
+
+        let vectors = [
+            (
+                "1BvBMSEYstWetqTFn5Au4m4GFg7xJaNVN2",
+                Address::P2pkh {
+                    network: Network::Mainnet,
+                    pubkey_hash: bytes("77bff20c60e522dfaa3350c39b030a5d004e839a"),
+                },
+            ),
+            (
+                "3J98t1WpEZ73CNmQviecrnyiWrnqRhWNLy",
+                Address::P2sh {
+                    network: Network::Mainnet,
+                    script_hash: bytes("b472a266d0bd89c13706a4132ccfb16f7c3b9fcb"),
+                },
+            ),
+            (
+                "mipcBbFg9gMiCh81Kj8tqqdgoZub1ZJRfn",
+                Address::P2pkh {
+                    network: Network::Testnet,
+                    pubkey_hash: bytes("243f1394f44554f4ce3fd68649c19adc483ce924"),
+                },
+            ),
+            (
+                "2MzQwSSnBHWHqSAqtTVQ6v47XtaisrJa1Vc",
+                Address::P2sh {
+                    network: Network::Testnet,
+                    script_hash: bytes("4e9f39ca4688ff102128ea4ccda34105324305b0"),
+                },
+            ),
+            // BIP173 and BIP350 test vectors.
+            (
+                "bc1qw508d6qejxtdg4y5r3zarvary0c5xw7kv8f3t4",
+                Address::P2wpkh {
+                    network: Network::Mainnet,
+                    pubkey_hash: bytes("751e76e8199196d454941c45d1b3a323f1433bd6"),
+                },
+            ),
+            (
+                "tb1qrp33g0q5c5txsp9arysrx4k6zdkfs4nce4xj0gdcccefvpysxf3q0sl5k7",
+                Address::P2wsh {
+                    network: Network::Testnet,
+                    script_hash: bytes(
+                        "1863143c14c5166804bd19203356da136c985678cd4d27a1b8c6329604903262",
+                    ),
+                },
+            ),
+            (
+                "bc1p0xlxvlhemja6c4dqv22uapctqupfhlxm9h8z3k2e72q4k9hcz7vqzk5jj0",
+                Address::P2tr {
+                    network: Network::Mainnet,
+                    output_key: bytes(
+                        "79be667ef9dcbbac55a06295ce870b07029bfcdb2dce28d959f2815b16f81798",
+                    ),
+                },
+            ),
+        ];
+        for (s, address) in vectors {
+            assert_eq!(s.parse::<Address>().unwrap(), address, "{}", s);
+            assert_eq!(address.to_string(), s);
+        }
+
+        // Uppercase bech32 is accepted and displayed lowercase.
+        let upper: Address = "BC1QW508D6QEJXTDG4Y5R3ZARVARY0C5XW7KV8F3T4"
+            .parse()
+            .unwrap();
+        assert_eq!(
+            upper.to_string(),
+            "bc1qw508d6qejxtdg4y5r3zarvary0c5xw7kv8f3t4"
+        );
+        let regtest = Address::P2wpkh {
+            network: Network::Regtest,
+            pubkey_hash: [0; 20],
+        };
+        assert!(regtest.to_string().starts_with("bcrt1q"));
+        assert_eq!(regtest.to_string().parse::<Address>().unwrap(), regtest);
+
+        // Anything without a segwit prefix is read as base58.
+        for invalid in [
+            "1BvBMSEYstWetqTFn5Au4m4GFg7xJaNVN3",
+            "tc1qw508d6qejxtdg4y5r3zarvary0c5xw7kg3g4ty",
+            "",
+        ] {
+            assert!(
+                matches!(invalid.parse::<Address>(), Err(BitcoinError::Base58(_))),
+                "{}",
+                invalid
+            );
+        }
+        assert!(matches!(
+            "bc1qW508d6qejxtdg4y5r3zarvary0c5xw7kv8f3t4".parse::<Address>(),
+            Err(BitcoinError::Bech32(bech32::Bech32Error::MixedCase))
+        ));
+        for invalid in [
+            // A valid witness version 2 program, which no variant covers.
+            "bc1zw508d6qejxtdg4y5r3zarvaryvaxxpcs",
+            // Litecoin P2PKH.
+            "LKDxGDJq5fF4FohAB8zJH24mDDNHDNtqsE",
+        ] {
+            assert!(
+                matches!(
+                    invalid.parse::<Address>(),
+                    Err(BitcoinError::InvalidAddress(_))
+                ),
+                "{}",
+                invalid
+            );
+        }
+    }
+
+    #[test]
+    fn test_address_script_pubkey() {
+        for (address, network, script_hex) in [
+            (
+                "1BvBMSEYstWetqTFn5Au4m4GFg7xJaNVN2",
+                Network::Mainnet,
+                "76a91477bff20c60e522dfaa3350c39b030a5d004e839a88ac",
+            ),
+            (
+                "3J98t1WpEZ73CNmQviecrnyiWrnqRhWNLy",
+                Network::Mainnet,
+                "a914b472a266d0bd89c13706a4132ccfb16f7c3b9fcb87",
+            ),
+            (
+                "bc1qw508d6qejxtdg4y5r3zarvary0c5xw7kv8f3t4",
+                Network::Mainnet,
+                "0014751e76e8199196d454941c45d1b3a323f1433bd6",
+            ),
+            (
+                "tb1qrp33g0q5c5txsp9arysrx4k6zdkfs4nce4xj0gdcccefvpysxf3q0sl5k7",
+                Network::Testnet,
+                "00201863143c14c5166804bd19203356da136c985678cd4d27a1b8c6329604903262",
+            ),
+            (
+                "bc1p0xlxvlhemja6c4dqv22uapctqupfhlxm9h8z3k2e72q4k9hcz7vqzk5jj0",
+                Network::Mainnet,
+                "512079be667ef9dcbbac55a06295ce870b07029bfcdb2dce28d959f2815b16f81798",
+            ),
+        ] {
+            let parsed: Address = address.parse().unwrap();
+            let script = parsed.script_pubkey();
+            assert_eq!(hex::encode(&script.bytes), script_hex);
+            let from_script = Address::from_script(&script, network).unwrap();
+            assert_eq!(from_script, parsed);
+            assert_eq!(from_script.to_string(), address);
+        }
+
+        // The same output shown for another network.
+        let script =
+            Script::new(hex::decode("0014751e76e8199196d454941c45d1b3a323f1433bd6").unwrap());
+        assert_eq!(
+            Address::from_script(&script, Network::Regtest)
+                .unwrap()
+                .to_string(),
+            "bcrt1qw508d6qejxtdg4y5r3zarvary0c5xw7kygt080"
+        );
+
+        let g = hex::decode("0279be667ef9dcbbac55a06295ce870b07029bfcdb2dce28d959f2815b16f81798")
+            .unwrap();
+        for script in [
+            Script::new_op_return(b"hello"),
+            ScriptBuilder::new()
+                .push_slice(&g)
+                .push_opcode(Opcode::OP_CHECKSIG)
+                .into_script(),
+            Script::new_witness_program(2, &[0; 16]),
+            Script::new(Vec::new()),
+        ] {
+            assert!(matches!(
+                Address::from_script(&script, Network::Mainnet),
+                Err(BitcoinError::InvalidAddress(_))
+            ));
+        }
+    }
+
+    #[test]
+    fn test_address_parse_checked() {
+        let mainnet = "bc1qw508d6qejxtdg4y5r3zarvary0c5xw7kv8f3t4";
+        let testnet = "tb1qrp33g0q5c5txsp9arysrx4k6zdkfs4nce4xj0gdcccefvpysxf3q0sl5k7";
+        let regtest = "bcrt1qw508d6qejxtdg4y5r3zarvary0c5xw7kygt080";
+        let testnet_p2pkh = "mipcBbFg9gMiCh81Kj8tqqdgoZub1ZJRfn";
+
+        assert_eq!(
+            Address::parse_checked(mainnet, Network::Mainnet).unwrap(),
+            mainnet.parse().unwrap()
+        );
+        assert_eq!(
+            Address::parse_checked(mainnet, Network::Testnet),
+            Err(BitcoinError::WrongNetwork {
+                expected: Network::Testnet,
+                found: Network::Mainnet,
+            })
+        );
+        assert_eq!(
+            Address::parse_checked(testnet, Network::Mainnet),
+            Err(BitcoinError::WrongNetwork {
+                expected: Network::Mainnet,
+                found: Network::Testnet,
+            })
+        );
+
+        // Test networks share "tb", and regtest has its own HRP.
+        for network in [Network::Testnet, Network::Testnet4, Network::Signet] {
+            let address = Address::parse_checked(testnet, network).unwrap();
+            assert_eq!(address.network(), network);
+            assert_eq!(address.to_string(), testnet);
+        }
+        assert!(matches!(
+            Address::parse_checked(testnet, Network::Regtest),
+            Err(BitcoinError::WrongNetwork { .. })
+        ));
+        assert_eq!(
+            Address::parse_checked(regtest, Network::Regtest)
+                .unwrap()
+                .network(),
+            Network::Regtest
+        );
+        assert!(Address::parse_checked(regtest, Network::Testnet).is_err());
+
+        // Base58 prefixes are shared by every non-mainnet network.
+        for network in [
+            Network::Testnet,
+            Network::Testnet4,
+            Network::Signet,
+            Network::Regtest,
+        ] {
+            let address = Address::parse_checked(testnet_p2pkh, network).unwrap();
+            assert_eq!(address.network(), network);
+            assert_eq!(address.to_string(), testnet_p2pkh);
+        }
+        assert!(matches!(
+            Address::parse_checked(testnet_p2pkh, Network::Mainnet),
+            Err(BitcoinError::WrongNetwork {
+                expected: Network::Mainnet,
+                found: Network::Testnet,
+            })
+        ));
+        assert!(matches!(
+            Address::parse_checked("bc1qinvalid", Network::Mainnet),
+            Err(BitcoinError::Bech32(_))
+        ));
+    }
+
+    #[test]
+    fn test_private_key_wif() {
+        let secret =
+            hex::decode("0c28fca386c7a227600b2fe50b7cae11ec86d3bf1fbe471be89827e19d72aa1d")
+                .unwrap();
+
+        let uncompressed: PrivateKey = "5HueCGU8rMjxEXxiPuD5BDku4MkFqeZyd4dZ1jvhTVqvbTLvyTJ"
+            .parse()
+            .unwrap();
+        assert_eq!(uncompressed.network, Network::Mainnet);
+        assert!(!uncompressed.compressed);
+        assert_eq!(uncompressed.secret_bytes().as_slice(), secret);
+        assert_eq!(
+            uncompressed.to_wif(),
+            "5HueCGU8rMjxEXxiPuD5BDku4MkFqeZyd4dZ1jvhTVqvbTLvyTJ"
+        );
+
+        let compressed =
+            PrivateKey::from_wif("KwdMAjGmerYanjeui5SHS7JkmpZvVipYvB2LJGU1ZxJwYvP98617").unwrap();
+        assert!(compressed.compressed);
+        assert_eq!(compressed.secret_bytes(), uncompressed.secret_bytes());
+        assert_eq!(
+            compressed.to_string(),
+            "KwdMAjGmerYanjeui5SHS7JkmpZvVipYvB2LJGU1ZxJwYvP98617"
+        );
+
+        let mut one = [0u8; 32];
+        one[31] = 1;
+        let testnet = PrivateKey::new(one, Network::Signet, true).unwrap();
+        assert_eq!(
+            testnet.to_wif(),
+            "cMahea7zqjxrtgAbB7LSGbcQUr1uX1ojuat9jZodMN87JcbXMTcA"
+        );
+        assert_eq!(
+            PrivateKey::from_wif(&testnet.to_wif()).unwrap().network,
+            Network::Testnet
+        );
+        assert!(format!("{:?}", testnet).contains("redacted"));
+        #[cfg(feature = "secp256k1")]
+        assert_eq!(
+            testnet.public_key().to_string(),
+            "0279be667ef9dcbbac55a06295ce870b07029bfcdb2dce28d959f2815b16f81798"
+        );
+
+        assert!(matches!(
+            PrivateKey::new([0; 32], Network::Mainnet, true),
+            Err(BitcoinError::InvalidKey(_))
+        ));
+        assert!(matches!(
+            PrivateKey::new([0xff; 32], Network::Mainnet, true),
+            Err(BitcoinError::InvalidKey(_))
+        ));
+        let zero_wif = base58::encode_check(&[[0x80].as_slice(), &[0; 32], &[1]].concat());
+        let bad_flag = base58::encode_check(&[[0x80].as_slice(), &one, &[2]].concat());
+        let bad_version = base58::encode_check(&[[0x81].as_slice(), &one, &[1]].concat());
+        let short = base58::encode_check(&[[0x80].as_slice(), &one[..31]].concat());
+        for invalid in [zero_wif, bad_flag, bad_version, short] {
+            assert!(matches!(
+                invalid.parse::<PrivateKey>(),
+                Err(BitcoinError::InvalidKey(_))
+            ));
+        }
+        assert!(matches!(
+            "KwdMAjGmerYanjeui5SHS7JkmpZvVipYvB2LJGU1ZxJwYvP98618".parse::<PrivateKey>(),
+            Err(BitcoinError::Base58(_))
+        ));
+    }
+
+    #[test]
+    fn test_public_key() {
+        let compressed_hex = "0279be667ef9dcbbac55a06295ce870b07029bfcdb2dce28d959f2815b16f81798";
+        let uncompressed_hex = "0479be667ef9dcbbac55a06295ce870b07029bfcdb2dce28d959f2815b16f81798483ada7726a3c4655da4fbfc0e1108a8fd17b448a68554199c47d08ffb10d4b8";
+
+        let compressed: PublicKey = compressed_hex.parse().unwrap();
+        assert!(compressed.compressed);
+        assert_eq!(compressed.to_string(), compressed_hex);
+        assert_eq!(
+            hex::encode(compressed.pubkey_hash()),
+            "751e76e8199196d454941c45d1b3a323f1433bd6"
+        );
+        assert_eq!(compressed.wpubkey_hash(), Ok(compressed.pubkey_hash()));
+
+        let uncompressed = PublicKey::from_slice(&hex::decode(uncompressed_hex).unwrap()).unwrap();
+        assert!(!uncompressed.compressed);
+        assert_ne!(uncompressed, compressed);
+        assert_eq!(uncompressed.serialize(), compressed.serialize());
+        assert_eq!(
+            compressed.serialize_uncompressed(),
+            uncompressed.serialize_uncompressed()
+        );
+        assert_eq!(hex::encode(uncompressed.to_bytes()), uncompressed_hex);
+        assert_eq!(
+            hex::encode(uncompressed.pubkey_hash()),
+            "91b24bf9f5288532960ac687abb035127b1d28a5"
+        );
+        assert!(matches!(
+            uncompressed.wpubkey_hash(),
+            Err(BitcoinError::InvalidKey(_))
+        ));
+
+        assert_eq!(Script::new_p2pk(&compressed).classify(), ScriptType::P2pk);
+        assert_eq!(
+            Script::new_p2wpkh(&compressed.wpubkey_hash().unwrap()),
+            "bc1qw508d6qejxtdg4y5r3zarvary0c5xw7kv8f3t4"
+                .parse::<Address>()
+                .unwrap()
+                .script_pubkey()
+        );
+
+        let json = serde_json::to_string(&compressed).unwrap();
+        assert_eq!(json, format!("\"{}\"", compressed_hex));
+        assert_eq!(
+            serde_json::from_str::<PublicKey>(&json).unwrap(),
+            compressed
+        );
+
+        for invalid in [
+            // Wrong prefix, hybrid encoding, off-curve x, and bad lengths.
+            "0579be667ef9dcbbac55a06295ce870b07029bfcdb2dce28d959f2815b16f81798",
+            "0679be667ef9dcbbac55a06295ce870b07029bfcdb2dce28d959f2815b16f81798483ada7726a3c4655da4fbfc0e1108a8fd17b448a68554199c47d08ffb10d4b8",
+            "020000000000000000000000000000000000000000000000000000000000000005",
+            "0279be667ef9dcbbac55a06295ce870b07029bfcdb2dce28d959f2815b16f817",
+            "",
+        ] {
+            assert!(
+                matches!(
+                    invalid.parse::<PublicKey>(),
+                    Err(BitcoinError::InvalidKey(_))
+                ),
+                "{}",
+                invalid
+            );
+        }
+    }
+
+    #[test]
+    fn test_x_only_public_key() {
+        // BIP341 wallet test vector with no script tree.
+        let internal: XOnlyPublicKey =
+            "d6889cb081036e0faefa3a35157ad71086b123b2b144b649798b494c300a961d"
+                .parse()
+                .unwrap();
+        assert_eq!(
+            internal.to_string(),
+            "d6889cb081036e0faefa3a35157ad71086b123b2b144b649798b494c300a961d"
+        );
+        let (output_key, _) = internal.tap_tweak(None);
+        assert_eq!(
+            output_key.to_string(),
+            "53a1f6e454df1aa2776a2814a721372d6258050de330b3c6d10ee8f4e0dda343"
+        );
+        assert_eq!(
+            hex::encode(&Script::new_p2tr_with_internal_key(&internal, None).bytes),
+            "512053a1f6e454df1aa2776a2814a721372d6258050de330b3c6d10ee8f4e0dda343"
+        );
+
+        // Both parities of a point map to the same x-only key.
+        let even: PublicKey = "02c6047f9441ed7d6d3045406e95c07cd85c778e4b8cef3ca7abac09b95c709ee5"
+            .parse()
+            .unwrap();
+        let odd: PublicKey = "03c6047f9441ed7d6d3045406e95c07cd85c778e4b8cef3ca7abac09b95c709ee5"
+            .parse()
+            .unwrap();
+        assert_eq!(XOnlyPublicKey::from(even), XOnlyPublicKey::from(odd));
+        assert_eq!(
+            XOnlyPublicKey::from(even).serialize(),
+            even.serialize()[1..]
+        );
+
+        let json = serde_json::to_string(&internal).unwrap();
+        assert_eq!(
+            serde_json::from_str::<XOnlyPublicKey>(&json).unwrap(),
+            internal
+        );
+
+        assert!(matches!(
+            XOnlyPublicKey::from_slice(&even.serialize()),
+            Err(BitcoinError::InvalidKey(_))
+        ));
+        assert!(matches!(
+            "0000000000000000000000000000000000000000000000000000000000000005"
+                .parse::<XOnlyPublicKey>(),
+            Err(BitcoinError::InvalidKey(_))
+        ));
+    }
+
+    #[cfg(feature = "secp256k1")]
+    #[test]
+    fn test_bip32_derivation() {
+        use bip32::{ChildNumber, DerivationPath, HARDENED, Xpriv, Xpub};
+
+        // BIP32 test vector 1.
+        let seed = hex::decode("000102030405060708090a0b0c0d0e0f").unwrap();
+        let master = Xpriv::new_master(Network::Mainnet, &seed).unwrap();
+        assert_eq!(
+            master.to_string(),
+            "xprv9s21ZrQH143K3QTDL4LXw2F7HEK3wJUD2nW2nRk4stbPy6cq3jPPqjiChkVvvNKmPGJxWUtg6LnF5kejMRNNU3TGtRBeJgk33yuGBxrMPHi"
+        );
+        assert_eq!(
+            master.to_xpub().to_string(),
+            "xpub661MyMwAqRbcFtXgS5sYJABqqG9YLmC4Q1Rdap9gSE8NqtwybGhePY2gZ29ESFjqJoCu1Rupje8YtGqsefD265TMg7usUDFdp6W1EGMcet8"
+        );
+        assert_eq!(hex::encode(master.fingerprint()), "3442193e");
+
+        let path: DerivationPath = "m/0'/1/2h".parse().unwrap();
+        let expected = [
+            (
+                "xprv9uHRZZhk6KAJC1avXpDAp4MDc3sQKNxDiPvvkX8Br5ngLNv1TxvUxt4cV1rGL5hj6KCesnDYUhd7oWgT11eZG7XnxHrnYeSvkzY7d2bhkJ7",
+                "xpub68Gmy5EdvgibQVfPdqkBBCHxA5htiqg55crXYuXoQRKfDBFA1WEjWgP6LHhwBZeNK1VTsfTFUHCdrfp1bgwQ9xv5ski8PX9rL2dZXvgGDnw",
+            ),
+            (
+                "xprv9wTYmMFdV23N2TdNG573QoEsfRrWKQgWeibmLntzniatZvR9BmLnvSxqu53Kw1UmYPxLgboyZQaXwTCg8MSY3H2EU4pWcQDnRnrVA1xe8fs",
+                "xpub6ASuArnXKPbfEwhqN6e3mwBcDTgzisQN1wXN9BJcM47sSikHjJf3UFHKkNAWbWMiGj7Wf5uMash7SyYq527Hqck2AxYysAA7xmALppuCkwQ",
+            ),
+            (
+                "xprv9z4pot5VBttmtdRTWfWQmoH1taj2axGVzFqSb8C9xaxKymcFzXBDptWmT7FwuEzG3ryjH4ktypQSAewRiNMjANTtpgP4mLTj34bhnZX7UiM",
+                "xpub6D4BDPcP2GT577Vvch3R8wDkScZWzQzMMUm3PWbmWvVJrZwQY4VUNgqFJPMM3No2dFDFGTsxxpG5uJh7n7epu4trkrX7x7DogT5Uv6fcLW5",
+            ),
+        ];
+        for (depth, (xprv, xpub)) in expected.iter().enumerate() {
+            let derived = master.derive_priv(&path.as_ref()[..=depth]).unwrap();
+            assert_eq!(derived.to_string(), *xprv);
+            assert_eq!(derived.to_xpub().to_string(), *xpub);
+            assert_eq!(xprv.parse::<Xpriv>().unwrap(), derived);
+        }
+
+        // Public derivation agrees with private derivation for normal
+        // children.
+        let account = master.ckd_priv(HARDENED).unwrap();
+        let path: DerivationPath = "1/7".parse().unwrap();
+        assert_eq!(
+            account.to_xpub().derive_pub(&path).unwrap(),
+            account.derive_priv(&path).unwrap().to_xpub()
+        );
+        assert!(
+            account
+                .to_xpub()
+                .derive_pub(path.child(ChildNumber::Hardened { index: 0 }))
+                .is_err()
+        );
+
+        // BIP32 test vector 3, whose private key keeps its leading zeros.
+        let seed = hex::decode("4b381541583be4423346c643850da4b320e46a87ae3d2a4e6da11eba819cd4acba45d239319ac14f863b8d5ab5a0d0c64d2e8a1e7d1457df2e5a3c51c73235be").unwrap();
+        let master = Xpriv::new_master(Network::Mainnet, &seed).unwrap();
+        assert_eq!(
+            master.ckd_priv(HARDENED).unwrap().to_string(),
+            "xprv9uPDJpEQgRQfDcW7BkF7eTya6RPxXeJCqCJGHuCJ4GiRVLzkTXBAJMu2qaMWPrS7AANYqdq6vcBcBUdJCVVFceUvJFjaPdGZ2y9WACViL4L"
+        );
+
+        let testnet = Xpriv::new_master(Network::Signet, &[7; 32]).unwrap();
+        assert!(testnet.to_string().starts_with("tprv"));
+        assert!(testnet.to_xpub().to_string().starts_with("tpub"));
+        assert_eq!(
+            testnet.private_key().public_key().serialize(),
+            testnet.to_xpub().public_key
+        );
+        assert!(format!("{:?}", testnet).contains("redacted"));
+
+        assert!(Xpriv::new_master(Network::Mainnet, &[0; 15]).is_err());
+        let mut encoded = master.encode();
+        encoded[45] = 1;
+        assert!(Xpriv::decode(&encoded).is_err());
+        let mut encoded = master.encode();
+        encoded[46..].fill(0);
+        assert!(Xpriv::decode(&encoded).is_err());
+        let mut encoded = master.encode();
+        encoded[5] = 1;
+        assert!(Xpriv::decode(&encoded).is_err());
+        // An xpub is not an xprv, and vice versa.
+        assert!(Xpriv::decode(&master.to_xpub().encode()).is_err());
+        assert!(Xpub::decode(&master.encode()).is_err());
+    }
+
+    #[cfg(feature = "secp256k1")]
+    #[test]
+    fn test_ecdsa_signing() {
+        use script::interpreter::{SigVersion, SignatureChecker, VerifyFlags, verify_script};
+
+        let mut secret = [0u8; 32];
+        secret[31] = 1;
+        let key = PrivateKey::new(secret, Network::Mainnet, true).unwrap();
+        let pubkey = key.public_key();
+        let msg = hashes::sha256(b"Satoshi Nakamoto");
+
+        // RFC 6979 nonces make the signature deterministic.
+        let sig = ecdsa::sign(&key, &msg);
+        assert_eq!(
+            hex::encode(&sig),
+            "3045022100934b1ea10a4b3c1757e2b0c017d0b6143ce3c9a7e6a4a49860d7a6ab210ee3d802202442ce9d2b916064108014783e923ec36b49743e2ffa1c4496f01a512aafd9e5"
+        );
+        assert!(ecdsa::verify(&pubkey, &msg, &sig));
+        assert!(!ecdsa::verify(&pubkey, &hashes::sha256(b"other"), &sig));
+        assert!(!ecdsa::verify(&pubkey, &msg, &sig[1..]));
+        let other = PrivateKey::new([7; 32], Network::Mainnet, true).unwrap();
+        assert!(!ecdsa::verify(&other.public_key(), &msg, &sig));
+
+        // A checker that treats `sighash` as the digest of every input
+        // stands in for a real transaction.
+        struct FixedSighash {
+            sighash: [u8; 32],
+        }
+        impl SignatureChecker for FixedSighash {
+            fn check_ecdsa_signature(
+                &self,
+                sig: &[u8],
+                pubkey: &[u8],
+                _script_code: &Script,
+                _sig_version: SigVersion,
+            ) -> bool {
+                let Some((_, der)) = sig.split_last() else {
+                    return false;
+                };
+                PublicKey::from_slice(pubkey)
+                    .is_ok_and(|pubkey| ecdsa::verify(&pubkey, &self.sighash, der))
+            }
+        }
+        let checker = FixedSighash { sighash: msg };
+        let sig = ecdsa::sign_with_sighash_type(&key, &msg, 0x01);
+        assert_eq!(sig.last(), Some(&0x01));
+
+        let script_pubkey = Script::new_p2pkh(&pubkey.pubkey_hash());
+        let script_sig = Script::new_p2pkh_script_sig(&sig, &pubkey);
+        assert!(
+            verify_script(
+                &script_sig,
+                &script_pubkey,
+                &Witness::default(),
+                VerifyFlags::STANDARD,
+                &checker
+            )
+            .is_ok()
+        );
+
+        let script_pubkey = Script::new_p2wpkh(&pubkey.wpubkey_hash().unwrap());
+        let witness = Witness::new_p2wpkh(&sig, &pubkey);
+        assert_eq!(witness.len(), 2);
+        assert!(
+            verify_script(
+                &Script::new(vec![]),
+                &script_pubkey,
+                &witness,
+                VerifyFlags::STANDARD,
+                &checker
+            )
+            .is_ok()
+        );
+        let forged =
+            Witness::new_p2wpkh(&ecdsa::sign_with_sighash_type(&other, &msg, 0x01), &pubkey);
+        assert!(
+            verify_script(
+                &Script::new(vec![]),
+                &script_pubkey,
+                &forged,
+                VerifyFlags::STANDARD,
+                &checker
+            )
+            .is_err()
+        );
+    }
+
+    #[test]
+    fn test_ecdsa_signature_der() {
+        use ecdsa::EcdsaSignature;
+
+        let der = hex::decode("3045022100934b1ea10a4b3c1757e2b0c017d0b6143ce3c9a7e6a4a49860d7a6ab210ee3d802202442ce9d2b916064108014783e923ec36b49743e2ffa1c4496f01a512aafd9e5").unwrap();
+        let mut pushed = der.clone();
+        pushed.push(0x81);
+        let sig = EcdsaSignature::from_slice(&pushed, true).unwrap();
+        assert_eq!(
+            hex::encode(sig.r),
+            "934b1ea10a4b3c1757e2b0c017d0b6143ce3c9a7e6a4a49860d7a6ab210ee3d8"
+        );
+        assert_eq!(
+            hex::encode(sig.s),
+            "2442ce9d2b916064108014783e923ec36b49743e2ffa1c4496f01a512aafd9e5"
+        );
+        assert_eq!(sig.sighash_type, 0x81);
+        assert_eq!(sig.serialize_der(), der);
+        assert_eq!(sig.to_vec(), pushed);
+
+        // Small integers are minimally encoded.
+        let mut small = EcdsaSignature {
+            r: [0; 32],
+            s: [0; 32],
+            sighash_type: 0x01,
+        };
+        small.r[31] = 1;
+        small.s[31] = 0x80;
+        assert_eq!(
+            hex::encode(small.to_vec()),
+            "300702010102020080".to_owned() + "01"
+        );
+        assert_eq!(
+            EcdsaSignature::from_slice(&small.to_vec(), true).unwrap(),
+            small
+        );
+
+        // Padded R and a long-form sequence length only pass lax parsing,
+        // and re-serialize canonically.
+        let mut lax = vec![0x30, 0x81, 0x46, 0x02, 0x22, 0x00];
+        lax.extend_from_slice(&der[4..]);
+        lax.push(0x01);
+        assert!(matches!(
+            EcdsaSignature::from_slice(&lax, true),
+            Err(BitcoinError::InvalidSignature(_))
+        ));
+        let sig = EcdsaSignature::from_slice(&lax, false).unwrap();
+        assert_eq!(sig.serialize_der(), der);
+        assert_eq!(sig.sighash_type, 0x01);
+
+        let mut long_int = vec![0x30, 0x45, 0x02, 0x81, 0x21];
+        long_int.extend_from_slice(&der[4..]);
+        long_int.push(0x01);
+        assert_eq!(
+            EcdsaSignature::from_slice(&long_int, false)
+                .unwrap()
+                .serialize_der(),
+            der
+        );
+
+        let mut overflow = vec![0x30, 0x46, 0x02, 0x22, 0x01];
+        overflow.extend_from_slice(&der[5..]);
+        overflow.push(0x01);
+        let invalid = [
+            vec![],
+            vec![0x01],
+            vec![0x31, 0x06, 0x02, 0x01, 0x01, 0x02, 0x01, 0x01, 0x01],
+            vec![0x30, 0x06, 0x02, 0x01, 0x01, 0x03, 0x01, 0x01, 0x01],
+            der[..20].to_vec(),
+            overflow,
+        ];
+        for bytes in invalid {
+            assert!(
+                EcdsaSignature::from_slice(&bytes, false).is_err(),
+                "{}",
+                hex::encode(&bytes)
+            );
+        }
+    }
+
+    #[test]
+    fn test_malleability() {
+        use ecdsa::EcdsaSignature;
+        use policy::{Malleability, malleability_vectors};
+
+        let low = hex::decode("3045022100934b1ea10a4b3c1757e2b0c017d0b6143ce3c9a7e6a4a49860d7a6ab210ee3d802202442ce9d2b916064108014783e923ec36b49743e2ffa1c4496f01a512aafd9e501").unwrap();
+        let high = hex::decode("3046022100934b1ea10a4b3c1757e2b0c017d0b6143ce3c9a7e6a4a49860d7a6ab210ee3d8022100dbbd3162d46e9f9bef7feb87c16dc13b4f6568a87f4e83f728e2443ba586675c01").unwrap();
+        let mut sig = EcdsaSignature::from_slice(&high, true).unwrap();
+        assert!(!sig.is_low_s());
+        sig.normalize_s();
+        assert!(sig.is_low_s());
+        assert_eq!(sig.to_vec(), low);
+        sig.normalize_s();
+        assert_eq!(sig.to_vec(), low);
+
+        let mut lax = vec![0x30, 0x81, 0x45];
+        lax.extend_from_slice(&low[2..]);
+        let p2pkh = |sig: &[u8]| Script::builder().push_slice(sig).push_slice(&[0x02; 33]);
+        let tx = |script_sigs: Vec<Script>| {
+            BitcoinTransaction::new(
+                2,
+                script_sigs
+                    .into_iter()
+                    .enumerate()
+                    .map(|(i, script_sig)| {
+                        TransactionInput::new(
+                            OutPoint::new(dummy_txid(1), i as u32),
+                            script_sig,
+                            0xffffffff,
+                        )
+                    })
+                    .collect(),
+                vec![TransactionOutput::new(
+                    1000,
+                    Script::new_p2wpkh(&[0x11; 20]),
+                )],
+                0,
+            )
+        };
+
+        let clean = tx(vec![p2pkh(&low).into_script(), Script::new(vec![])]);
+        assert!(malleability_vectors(&clean).is_empty());
+        assert!(!clean.has_malleable_txid());
+
+        let malleable = tx(vec![
+            p2pkh(&high).into_script(),
+            p2pkh(&lax).into_script(),
+            p2pkh(&low).push_opcode(Opcode::OP_DUP).into_script(),
+            Script::new(vec![0x4c, 0x01, 0x07]),
+        ]);
+        assert!(malleable.has_malleable_txid());
+        assert_eq!(
+            malleability_vectors(&malleable),
+            [
+                Malleability::HighS(0),
+                Malleability::NonDerSignature(1),
+                Malleability::ScriptSigNotPushOnly(2),
+                Malleability::NonMinimalPush(3),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_sighash_type() {
+        use sighash::SighashType;
+
+        for (byte, name) in [
+            (0x00, "DEFAULT"),
+            (0x01, "ALL"),
+            (0x02, "NONE"),
+            (0x03, "SINGLE"),
+            (0x81, "ALL|ANYONECANPAY"),
+            (0x82, "NONE|ANYONECANPAY"),
+            (0x83, "SINGLE|ANYONECANPAY"),
+        ] {
+            let sighash_type = SighashType::from_u8(byte, true).unwrap();
+            assert_eq!(sighash_type.to_u8(), byte);
+            assert_eq!(sighash_type.is_anyone_can_pay(), byte & 0x80 != 0);
+            assert_eq!(sighash_type.to_string(), name);
+            assert_eq!(name.parse::<SighashType>(), Ok(sighash_type));
+        }
+
+        // Undefined bytes only parse leniently, as consensus reads them, and
+        // keep their value.
+        for byte in [0x04, 0x80, 0x42, 0xe3, 0x20] {
+            assert!(matches!(
+                SighashType::from_u8(byte, true),
+                Err(BitcoinError::InvalidSighash(_))
+            ));
+            let sighash_type = SighashType::from_u8(byte, false).unwrap();
+            assert_eq!(sighash_type, SighashType::Undefined(byte));
+            assert_eq!(sighash_type.to_u8(), byte);
+            assert_eq!(sighash_type.is_anyone_can_pay(), byte & 0x80 != 0);
+        }
+        assert_eq!(SighashType::Undefined(0x04).to_string(), "0x04");
+        for name in [
+            "all",
+            "DEFAULT|ANYONECANPAY",
+            "ALL|",
+            "ALL|ANYONECANPAY|NONE",
+            "",
+        ] {
+            assert!(name.parse::<SighashType>().is_err(), "{}", name);
+        }
+    }
+
+    #[test]
+    fn test_legacy_sighash() {
+        use sighash::{SighashType, legacy_signature_hash};
+
+        // The unsigned transaction from BIP143's native P2WPKH example,
+        // whose first input spends a P2PK output.
+        let bytes = hex::decode("0100000002fff7f7881a8099afa6940d42d1e7f6362bec38171ea3edf433541db4e4ad969f0000000000eeffffffef51e1b804cc89d182d279655c3aa89e815b1b309fe287d9b2b55d57b90ec68a0100000000ffffffff02202cb206000000001976a9148280b37df378db99f66f85c95a783a76ac7a6d5988ac9093510d000000001976a9143bde42dbee7e4dbe6a21b2d50ce2f0167faa815988ac11000000").unwrap();
+        let (tx, _) = BitcoinTransaction::from_bytes(&bytes).unwrap();
+        let script_pubkey = Script::new(
+            hex::decode("2103c9f4836b9a4f77fc0d81f7bcb01b7f1b35916864b9476c241ce9fc198bd25432ac")
+                .unwrap(),
+        );
+        let sighash = legacy_signature_hash(&tx, 0, &script_pubkey, SighashType::All).unwrap();
+        assert_eq!(
+            hex::encode(sighash),
+            "63cec688ee06a91e913875356dd4dea2f8e0f2a2659885372da2a37e32c7532e"
+        );
+        #[cfg(feature = "secp256k1")]
+        {
+            // The signature in BIP143's signed transaction.
+            let pubkey = PublicKey::from_slice(&script_pubkey.bytes[1..34]).unwrap();
+            let sig = hex::decode("30450221008b9d1dc26ba6a9cb62127b02742fa9d754cd3bebf337f7a55d114c8e5cdd30be022040529b194ba3f9281a99f2b1c0a19c0489bc22ede944ccf4ecbab4cc618ef3ed").unwrap();
+            assert!(ecdsa::verify(&pubkey, &sighash, &sig));
+        }
+
+        // OP_CODESEPARATORs left in the script code are not signed.
+        let mut separated = vec![Opcode::OP_CODESEPARATOR.to_u8()];
+        separated.extend_from_slice(&script_pubkey.bytes);
+        separated.push(Opcode::OP_CODESEPARATOR.to_u8());
+        assert_eq!(
+            legacy_signature_hash(&tx, 0, &Script::new(separated), SighashType::All).unwrap(),
+            sighash
+        );
+
+        // NONE and SINGLE leave other inputs' sequences and the unsigned
+        // outputs free to change; ANYONECANPAY leaves out other inputs.
+        let mut changed = tx.clone();
+        changed.inputs[1].sequence = 7;
+        changed.outputs[1].value += 1;
+        for sighash_type in [SighashType::None, SighashType::Single] {
+            assert_eq!(
+                legacy_signature_hash(&changed, 0, &script_pubkey, sighash_type).unwrap(),
+                legacy_signature_hash(&tx, 0, &script_pubkey, sighash_type).unwrap()
+            );
+        }
+        assert_ne!(
+            legacy_signature_hash(&changed, 0, &script_pubkey, SighashType::All).unwrap(),
+            sighash
+        );
+        let mut fewer_inputs = tx.clone();
+        fewer_inputs.inputs.truncate(1);
+        let anyone_can_pay = SighashType::AllPlusAnyoneCanPay;
+        assert_eq!(
+            legacy_signature_hash(&fewer_inputs, 0, &script_pubkey, anyone_can_pay).unwrap(),
+            legacy_signature_hash(&tx, 0, &script_pubkey, anyone_can_pay).unwrap()
+        );
+
+        // SIGHASH_SINGLE without a matching output signs the number one.
+        let mut one = [0; 32];
+        one[0] = 1;
+        let mut single_output = tx.clone();
+        single_output.outputs.truncate(1);
+        assert_eq!(
+            legacy_signature_hash(&single_output, 1, &script_pubkey, SighashType::Single).unwrap(),
+            one
+        );
+
+        // An undefined byte signs like the type it reads as, but commits to
+        // the byte itself.
+        let odd = SighashType::Undefined(0x42);
+        assert_eq!(
+            legacy_signature_hash(&changed, 0, &script_pubkey, odd).unwrap(),
+            legacy_signature_hash(&tx, 0, &script_pubkey, odd).unwrap()
+        );
+        assert_ne!(
+            legacy_signature_hash(&tx, 0, &script_pubkey, odd).unwrap(),
+            legacy_signature_hash(&tx, 0, &script_pubkey, SighashType::None).unwrap()
+        );
+        assert_eq!(
+            legacy_signature_hash(&tx, 2, &script_pubkey, SighashType::All),
+            Err(BitcoinError::InputIndexOutOfRange {
+                index: 2,
+                inputs: 2
+            })
+        );
+    }
+
+    #[test]
+    fn test_segwit_v0_sighash() {
+        use sighash::{SighashType, segwit_v0_signature_hash};
+
+        // BIP143's native P2WPKH example: input 1 spends 6 BTC.
+        let bytes = hex::decode("0100000002fff7f7881a8099afa6940d42d1e7f6362bec38171ea3edf433541db4e4ad969f0000000000eeffffffef51e1b804cc89d182d279655c3aa89e815b1b309fe287d9b2b55d57b90ec68a0100000000ffffffff02202cb206000000001976a9148280b37df378db99f66f85c95a783a76ac7a6d5988ac9093510d000000001976a9143bde42dbee7e4dbe6a21b2d50ce2f0167faa815988ac11000000").unwrap();
+        let (tx, _) = BitcoinTransaction::from_bytes(&bytes).unwrap();
+        let pubkey = PublicKey::from_slice(
+            &hex::decode("025476c2e83188368da1ff3e292e7acafcdb3566bb0ad253f62fc70f07aeee6357")
+                .unwrap(),
+        )
+        .unwrap();
+        let script_code = Script::new_p2pkh(&pubkey.pubkey_hash());
+        let sighash =
+            segwit_v0_signature_hash(&tx, 1, &script_code, 600_000_000, SighashType::All).unwrap();
+        assert_eq!(
+            hex::encode(sighash),
+            "c37af31116d1b27caf68aae9e3ac82f1477929014d5b917657d0eb49478cb670"
+        );
+        #[cfg(feature = "secp256k1")]
+        {
+            let sig = hex::decode("304402203609e17b84f6a7d30c80bfa610b5b4542f32a8a0d5447a12fb1366d7f01cc44a0220573a954c4518331561406f90300e8f3358f51928d43c212a8caed02de67eebee").unwrap();
+            assert!(ecdsa::verify(&pubkey, &sighash, &sig));
+        }
+
+        // The amount is signed, so a lie about it invalidates the signature.
+        assert_ne!(
+            segwit_v0_signature_hash(&tx, 1, &script_code, 600_000_001, SighashType::All).unwrap(),
+            sighash
+        );
+
+        let mut changed = tx.clone();
+        changed.inputs[0].sequence = 7;
+        changed.outputs[0].value += 1;
+        let hash = |tx: &BitcoinTransaction, sighash_type| {
+            segwit_v0_signature_hash(tx, 1, &script_code, 600_000_000, sighash_type).unwrap()
+        };
+        assert_eq!(
+            hash(&changed, SighashType::None),
+            hash(&tx, SighashType::None)
+        );
+        assert_eq!(
+            hash(&changed, SighashType::Single),
+            hash(&tx, SighashType::Single)
+        );
+        assert_ne!(hash(&changed, SighashType::All), sighash);
+        let mut other_input = tx.clone();
+        other_input.inputs[0].previous_output.vout = 9;
+        let anyone_can_pay = SighashType::AllPlusAnyoneCanPay;
+        assert_eq!(
+            hash(&other_input, anyone_can_pay),
+            hash(&tx, anyone_can_pay)
+        );
+
+        // SIGHASH_SINGLE without a matching output commits to no outputs.
+        let mut single_output = tx.clone();
+        single_output.outputs.truncate(1);
+        let mut changed_output = single_output.clone();
+        changed_output.outputs[0].value += 1;
+        assert_eq!(
+            hash(&single_output, SighashType::Single),
+            hash(&changed_output, SighashType::Single)
+        );
+        assert!(matches!(
+            segwit_v0_signature_hash(&tx, 2, &script_code, 0, SighashType::All),
+            Err(BitcoinError::InputIndexOutOfRange { .. })
+        ));
+    }
+
+    #[test]
+    fn test_taproot_sighash() {
+        use sighash::{NO_CODESEPARATOR, Prevouts, SighashType, taproot_signature_hash};
+
+        let tx = BitcoinTransaction::new(
+            2,
+            vec![
+                TransactionInput::new(
+                    OutPoint::new(dummy_txid(1), 0),
+                    Script::new(vec![]),
+                    0xffffffff,
+                ),
+                TransactionInput::new(
+                    OutPoint::new(dummy_txid(2), 1),
+                    Script::new(vec![]),
+                    0xfffffffd,
+                ),
+            ],
+            vec![
+                TransactionOutput::new(50_000, Script::new_p2tr(&[0x22; 32])),
+                TransactionOutput::new(20_000, Script::new_p2wpkh(&[0x11; 20])),
+            ],
+            0,
+        );
+        let prevouts = [
+            TransactionOutput::new(100_000, Script::new_p2tr(&[0xaa; 32])),
+            TransactionOutput::new(30_000, Script::new_p2tr(&[0xbb; 32])),
+        ];
+        let all = Prevouts::All(&prevouts);
+        let hash = |index, prevouts, annex, script_path, sighash_type| {
+            taproot_signature_hash(&tx, index, &prevouts, annex, script_path, sighash_type)
+        };
+
+        let key_path = hash(1, all, None, None, SighashType::Default).unwrap();
+        assert_eq!(
+            hex::encode(key_path),
+            "89988f8f088feb913c2a43208cf4286526b937485140773dcbe95d3ece0593ae"
+        );
+        // ALL signs the same data but commits to its own type byte.
+        assert_ne!(
+            hash(1, all, None, None, SighashType::All).unwrap(),
+            key_path
+        );
+
+        let annex = [0x50, 0x01, 0x02];
+        let leaf = Some(([0x33; 32], NO_CODESEPARATOR));
+        let anyone_can_pay = SighashType::SinglePlusAnyoneCanPay;
+        let script_path = hash(0, all, Some(&annex), leaf, anyone_can_pay).unwrap();
+        assert_eq!(
+            hex::encode(script_path),
+            "7a9dc74758c01b8193d9317dc9b7dc3c9f174284ae857be7a19a984077c75fbf"
+        );
+        // ANYONECANPAY only needs the signing input's prevout.
+        assert_eq!(
+            hash(
+                0,
+                Prevouts::One(0, &prevouts[0]),
+                Some(&annex),
+                leaf,
+                anyone_can_pay
+            )
+            .unwrap(),
+            script_path
+        );
+        assert_ne!(
+            hash(0, all, Some(&annex), Some(([0x33; 32], 0)), anyone_can_pay).unwrap(),
+            script_path
+        );
+
+        let invalid_sighash = |result| matches!(result, Err(BitcoinError::InvalidSighash(_)));
+        assert!(invalid_sighash(hash(
+            0,
+            all,
+            Some(&[0x51]),
+            None,
+            SighashType::Default
+        )));
+        assert!(invalid_sighash(hash(
+            1,
+            Prevouts::One(1, &prevouts[1]),
+            None,
+            None,
+            SighashType::None
+        )));
+        assert!(invalid_sighash(hash(
+            1,
+            Prevouts::One(0, &prevouts[0]),
+            None,
+            None,
+            SighashType::AllPlusAnyoneCanPay
+        )));
+        assert_eq!(
+            hash(
+                0,
+                Prevouts::All(&prevouts[..1]),
+                None,
+                None,
+                SighashType::Default
+            ),
+            Err(BitcoinError::PrevoutsMismatch {
+                inputs: 2,
+                prevouts: 1
+            })
+        );
+        let mut one_output = tx.clone();
+        one_output.outputs.truncate(1);
+        assert!(invalid_sighash(taproot_signature_hash(
+            &one_output,
+            1,
+            &all,
+            None,
+            None,
+            SighashType::Single
+        )));
+    }
+
+    #[test]
+    fn test_sighash_cache() {
+        use sighash::{
+            Prevouts, SighashCache, SighashType, legacy_signature_hash, segwit_v0_signature_hash,
+            taproot_signature_hash,
+        };
+
+        let tx = BitcoinTransaction::new(
+            2,
+            (0..4)
+                .map(|i| {
+                    TransactionInput::new(
+                        OutPoint::new(dummy_txid(i), u32::from(i)),
+                        Script::new(vec![]),
+                        0xfffffffd,
+                    )
+                })
+                .collect(),
+            (0..3)
+                .map(|i| TransactionOutput::new(10_000 * (i + 1), Script::new_p2wpkh(&[0x11; 20])))
+                .collect(),
+            0,
+        );
+        let prevouts: Vec<_> = (0..4)
+            .map(|i| TransactionOutput::new(50_000 + i, Script::new_p2tr(&[0x22; 32])))
+            .collect();
+        let script_code = Script::new_p2pkh(&[0x33; 20]);
+
+        // The cache gives every input the same hashes as computing each
+        // from scratch, whatever order the types are asked for in.
+        let cache = SighashCache::new(&tx);
+        for sighash_type in ["ALL", "NONE|ANYONECANPAY", "SINGLE", "DEFAULT"] {
+            let sighash_type: SighashType = sighash_type.parse().unwrap();
+            for i in 0..tx.inputs.len() {
+                assert_eq!(
+                    cache.legacy_signature_hash(i, &script_code, sighash_type),
+                    legacy_signature_hash(&tx, i, &script_code, sighash_type)
+                );
+                assert_eq!(
+                    cache.segwit_v0_signature_hash(i, &script_code, 1000, sighash_type),
+                    segwit_v0_signature_hash(&tx, i, &script_code, 1000, sighash_type)
+                );
+                let all = Prevouts::All(&prevouts);
+                assert_eq!(
+                    cache.taproot_signature_hash(i, &all, None, None, sighash_type),
+                    taproot_signature_hash(&tx, i, &all, None, None, sighash_type)
+                );
+            }
+        }
+        assert_eq!(cache.transaction(), &tx);
+
+        let owned = SighashCache::new(tx.clone());
+        assert_eq!(
+            owned.segwit_v0_signature_hash(1, &script_code, 1000, SighashType::All),
+            cache.segwit_v0_signature_hash(1, &script_code, 1000, SighashType::All)
+        );
+        assert_eq!(owned.into_transaction(), tx);
+    }
+
+    #[cfg(feature = "secp256k1")]
+    #[test]
+    fn test_sign_input() {
+        use script::interpreter::{
+            SignatureChecker, TransactionSignatureChecker, VerifyFlags, verify_script,
+        };
+        use sighash::{Prevouts, SighashCache, SighashType, taproot_signature_hash};
+
+        let key = PrivateKey::new([0x42; 32], Network::Mainnet, true).unwrap();
+        let pubkey = key.public_key();
+        let wpkh = Script::new_p2wpkh(&pubkey.wpubkey_hash().unwrap());
+        let internal_key = XOnlyPublicKey::from(pubkey);
+        let p2tr = Script::new_p2tr_with_internal_key(&internal_key, None);
+        let prevouts = [
+            TransactionOutput::new(10_000, Script::new_p2pk(&pubkey)),
+            TransactionOutput::new(20_000, Script::new_p2pkh(&pubkey.pubkey_hash())),
+            TransactionOutput::new(30_000, wpkh.clone()),
+            TransactionOutput::new(40_000, wpkh.to_p2sh()),
+            TransactionOutput::new(50_000, p2tr.clone()),
+        ];
+        let mut tx = BitcoinTransaction::new(
+            2,
+            (0..prevouts.len() as u8)
+                .map(|i| {
+                    TransactionInput::new(
+                        OutPoint::new(dummy_txid(i), 0),
+                        Script::new(vec![]),
+                        0xfffffffd,
+                    )
+                })
+                .collect(),
+            vec![TransactionOutput::new(140_000, wpkh.clone())],
+            0,
+        );
+        for (i, prevout) in prevouts.iter().enumerate() {
+            let sighash_type = match prevout.script_pubkey.classify() {
+                ScriptType::P2tr => SighashType::AllPlusAnyoneCanPay,
+                _ => SighashType::All,
+            };
+            tx.sign_input(i, prevout, &key, sighash_type).unwrap();
+        }
+
+        let cache = SighashCache::new(&tx);
+        for (index, prevout) in prevouts.iter().enumerate().take(4) {
+            let input = &tx.inputs[index];
+            let checker = TransactionSignatureChecker::new(&cache, index, prevout.value);
+            assert_eq!(
+                verify_script(
+                    &input.script_sig,
+                    &prevout.script_pubkey,
+                    &input.witness,
+                    VerifyFlags::STANDARD,
+                    &checker
+                ),
+                Ok(()),
+                "input {}",
+                index
+            );
+        }
+        let checker = TransactionSignatureChecker::new(&cache, 0, prevouts[0].value);
+        assert!(checker.check_lock_time(0));
+        assert!(!checker.check_lock_time(1));
+        assert!(!checker.check_lock_time(500_000_000));
+        // Relative lock times are disabled by the input's sequence.
+        assert!(!checker.check_sequence(0));
+        assert!(tx.inputs[2].script_sig.bytes.is_empty());
+        assert_eq!(
+            tx.inputs[3].script_sig,
+            Script::builder().push_slice(&wpkh.bytes).into_script()
+        );
+
+        let sig = schnorr::SchnorrSignature::from_slice(&tx.inputs[4].witness.items[0]).unwrap();
+        assert_eq!(sig.sighash_type, 0x81);
+        let sighash = taproot_signature_hash(
+            &tx,
+            4,
+            &Prevouts::All(&prevouts),
+            None,
+            None,
+            SighashType::AllPlusAnyoneCanPay,
+        )
+        .unwrap();
+        let (output_key, _) = internal_key.tap_tweak(None);
+        assert!(schnorr::verify(&output_key, &sighash, &sig.signature));
+
+        let other = PrivateKey::new([0x43; 32], Network::Mainnet, true).unwrap();
+        let cannot_sign = |result| matches!(result, Err(BitcoinError::CannotSign(_)));
+        for prevout in &prevouts {
+            assert!(cannot_sign(tx.sign_input(
+                0,
+                prevout,
+                &other,
+                SighashType::AllPlusAnyoneCanPay
+            )));
+        }
+        let op_return = TransactionOutput::new(0, Script::new_op_return(b"x"));
+        assert!(cannot_sign(tx.sign_input(
+            0,
+            &op_return,
+            &key,
+            SighashType::All
+        )));
+        assert!(matches!(
+            tx.sign_input(1, &prevouts[1], &key, SighashType::Default),
+            Err(BitcoinError::InvalidSighash(_))
+        ));
+        assert!(matches!(
+            tx.sign_input(4, &prevouts[4], &key, SighashType::Default),
+            Err(BitcoinError::InvalidSighash(_))
+        ));
+        assert!(matches!(
+            tx.sign_input(5, &prevouts[1], &key, SighashType::All),
+            Err(BitcoinError::InputIndexOutOfRange { .. })
+        ));
+    }
+
+    #[test]
+    fn test_signed_message() {
+        assert_eq!(
+            hex::encode(message::signed_message_hash("")),
+            "80e795d4a4caadd7047af389d9f7f220562feb6196032e2131e10563352c4bcc"
+        );
+
+        #[cfg(feature = "secp256k1")]
+        {
+            use message::{recover_pubkey, sign_message, verify_message};
+
+            // From Core's signmessage functional test.
+            let key = PrivateKey::from_wif("cUeKHd5orzT3mz8P9pxyREHfsWtVfgsfDjiZZBcjUBAaGk1BTj7N")
+                .unwrap();
+            let address: Address = "mpLQjfK79b7CCV4VMJWEWAj5Mpx8Up5zxB".parse().unwrap();
+            let message = "This is just a test message";
+            let signature = sign_message(&key, message);
+            assert_eq!(
+                signature,
+                "INbVnW4e6PeRmsv2Qgu8NuopvrVjkcxob+sX8OcZG0SALhWybUjzMLPdAsXI46YZGb0KQTRii+wWIQzRpG/U+S0="
+            );
+            assert_eq!(recover_pubkey(&signature, message), Ok(key.public_key()));
+            assert_eq!(verify_message(&address, &signature, message), Ok(true));
+            assert_eq!(verify_message(&address, &signature, "other"), Ok(false));
+
+            // The header records whether the address uses the compressed key.
+            let uncompressed =
+                PrivateKey::new(key.secret_bytes(), Network::Testnet, false).unwrap();
+            let signature = sign_message(&uncompressed, message);
+            assert!(matches!(signature.as_bytes()[0], b'G' | b'H'));
+            assert_eq!(verify_message(&address, &signature, message), Ok(false));
+            let uncompressed_address = Address::P2pkh {
+                network: Network::Testnet,
+                pubkey_hash: uncompressed.public_key().pubkey_hash(),
+            };
+            assert_eq!(
+                verify_message(&uncompressed_address, &signature, message),
+                Ok(true)
+            );
+
+            let invalid_signature = |signature| {
+                matches!(
+                    verify_message(&address, signature, message),
+                    Err(BitcoinError::InvalidSignature(_))
+                )
+            };
+            assert!(invalid_signature("not base64!"));
+            assert!(invalid_signature("AAAA"));
+            assert!(invalid_signature(&("J".to_owned() + &signature[1..])));
+            let p2wpkh = Address::P2wpkh {
+                network: Network::Testnet,
+                pubkey_hash: key.public_key().pubkey_hash(),
+            };
+            assert!(matches!(
+                verify_message(&p2wpkh, &signature, message),
+                Err(BitcoinError::InvalidAddress(_))
+            ));
+        }
+    }
+
+    #[test]
+    fn test_bip322() {
+        // Vectors from BIP322.
+        assert_eq!(
+            hex::encode(bip322::message_hash("")),
+            "c90c269c4f8fcbe6880f72a721ddfbf1914268a794cbb21cfafee13770ae19f1"
+        );
+        assert_eq!(
+            hex::encode(bip322::message_hash("Hello World")),
+            "f0eb03b1a75ac6d9847f55c624a99169b5dccba2a31f5b23bea77ba270de0a7a"
+        );
+        let address: Address = "bc1q9vza2e8x573nczrlzms0wvx3gsqjx7vavgkx0l"
+            .parse()
+            .unwrap();
+        let to_spend = bip322::to_spend(&address.script_pubkey(), "");
+        assert_eq!(
+            to_spend.txid().to_string(),
+            "c5680aa69bb8d860bf82d4e9cd3504b55dde018de765a91bb566283c545a99a7"
+        );
+        assert_eq!(
+            bip322::to_sign(&to_spend).txid().to_string(),
+            "1e9654e951a5ba44c8604c4de6c67fd78a27e81dcadcfe1edf638ba3aaebaed6"
+        );
+        let to_spend = bip322::to_spend(&address.script_pubkey(), "Hello World");
+        assert_eq!(
+            to_spend.txid().to_string(),
+            "b79d196740ad5217771c1098fc4a4b51e0535c32236c71f1ea4d61a2d603352b"
+        );
+        assert_eq!(
+            bip322::to_sign(&to_spend).txid().to_string(),
+            "88737ae86f2077145f93cc4b153ae9a1cb8d56afa511988c149c5c8c9d93bddf"
+        );
+
+        #[cfg(feature = "secp256k1")]
+        {
+            use bip322::{sign_full, sign_simple, verify_full, verify_simple};
+
+            let proof = "AkcwRAIgZRfIY3p7/DoVTty6YZbWS71bc5Vct9p9Fia83eRmw2QCICK/ENGfwLtptFluMGs2KsqoNSk89pO7F29zJLUx9a/sASECx/EgAxlkQpQ9hYjgGu6EBCPMVPwVIVJqO4XCsMvViHI=";
+            assert_eq!(verify_simple(&address, "Hello World", proof), Ok(true));
+            assert_eq!(verify_simple(&address, "", proof), Ok(false));
+            let p2tr: Address = "bc1ppv609nr0vr25u07u95waq5lucwfm6tde4nydujnu8npg4q75mr5sxq8lt3"
+                .parse()
+                .unwrap();
+            let proof = "AUHd69PrJQEv+oKTfZ8l+WROBHuy9HKrbFCJu7U1iK2iiEy1vMU5EfMtjc+VSHM7aU0SDbak5IUZRVno2P5mjSafAQ==";
+            assert_eq!(verify_simple(&p2tr, "Hello World", proof), Ok(true));
+            assert_eq!(verify_simple(&p2tr, "", proof), Ok(false));
+
+            let key = PrivateKey::from_wif("L3VFeEujGtevx9w18HD1fhRbCH67Az2dpCymeRE1SoPK6XQtaN2k")
+                .unwrap();
+            let pubkey = key.public_key();
+            let wpkh = Script::new_p2wpkh(&pubkey.wpubkey_hash().unwrap());
+            let addresses = [
+                address,
+                p2tr,
+                Address::from_script(&wpkh.to_p2sh(), Network::Mainnet).unwrap(),
+                Address::P2pkh {
+                    network: Network::Mainnet,
+                    pubkey_hash: pubkey.pubkey_hash(),
+                },
+            ];
+            for (i, address) in addresses.iter().enumerate() {
+                let proof = sign_full(address, "Hello World", &key).unwrap();
+                assert_eq!(verify_full(address, "Hello World", &proof), Ok(true));
+                assert!(matches!(
+                    verify_full(address, "other", &proof),
+                    Err(BitcoinError::InvalidSignature(_))
+                ));
+                // Only native segwit proofs fit in a witness.
+                let simple = sign_simple(address, "Hello World", &key);
+                if i < 2 {
+                    assert_eq!(
+                        verify_simple(address, "Hello World", &simple.unwrap()),
+                        Ok(true)
+                    );
+                } else {
+                    assert!(matches!(simple, Err(BitcoinError::CannotSign(_))));
+                }
+            }
+            // A proof for another key's address is not valid.
+            let proof = sign_simple(&addresses[0], "Hello World", &key).unwrap();
+            let other = Address::P2wpkh {
+                network: Network::Mainnet,
+                pubkey_hash: [0; 20],
+            };
+            assert_eq!(verify_simple(&other, "Hello World", &proof), Ok(false));
+            assert!(verify_simple(&address, "Hello World", "not base64!").is_err());
+        }
+    }
+
+    #[cfg(feature = "musig2")]
+    #[test]
+    fn test_musig2() {
+        use musig2::{AggNonce, KeyAggContext, PubNonce, SecNonce, Session, nonce_gen, sort_keys};
+
+        let key = |s: &str| PublicKey::from_slice(&hex::decode(s).unwrap()).unwrap();
+        let bytes = |s: &str| hex::decode(s).unwrap();
+
+        // Vectors from BIP327.
+        let x = [
+            key("02F9308A019258C31049344F85F89D5229B531C845836F99B08601F113BCE036F9"),
+            key("03DFF1D77F2A671C5F36183726DB2341BE58FEAE1DA2DECED843240F7B502BA659"),
+            key("023590A94E768F8E1815C2F24B4D80A8E3149316C3518CE7B7AD338368D038CA66"),
+        ];
+        let aggregate = |keys: &[PublicKey]| {
+            KeyAggContext::new(keys)
+                .unwrap()
+                .x_only_public_key()
+                .to_string()
+                .to_uppercase()
+        };
+        assert_eq!(
+            aggregate(&x),
+            "90539EEDE565F5D054F32CC0C220126889ED1E5D193BAF15AEF344FE59D4610C"
+        );
+        assert_eq!(
+            aggregate(&[x[2], x[1], x[0]]),
+            "6204DE8B083426DC6EAF9502D27024D53FC826BF7D2012148A0575435DF54B2B"
+        );
+        assert_eq!(
+            aggregate(&[x[0], x[0], x[0]]),
+            "B436E3BAD62B8CD409969A224731C193D051162D8C5AE8B109306127DA3AA935"
+        );
+        assert_eq!(
+            aggregate(&[x[0], x[0], x[1], x[1]]),
+            "69BC22BFA5D106306E48A20679DE1D7389386124D07571D0D872686028C26A3E"
+        );
+        let mut sorted = [x[2], x[1], x[0]];
+        sort_keys(&mut sorted);
+        assert_eq!(sorted, [x[2], x[0], x[1]]);
+
+        let (secnonce, pubnonce) = nonce_gen(
+            &[0x0f; 32],
+            Some(&PrivateKey::new([0x02; 32], Network::Mainnet, true).unwrap()),
+            &key("024D4B6CD1361032CA9BD2AEB9D900AA4D45D9EAD80AC9423374C451A7254D0766"),
+            Some(&XOnlyPublicKey::from_slice(&[0x07; 32]).unwrap()),
+            Some(&[0x01; 32]),
+            Some(&[0x08; 32]),
+        )
+        .unwrap();
+        assert_eq!(format!("{:?}", secnonce), "SecNonce([redacted])");
+        assert_eq!(
+            hex::encode_upper(pubnonce.0),
+            "02F7BE7089E8376EB355272368766B17E88E7DB72047D05E56AA881EA52B3B35DF02C29C8046FDD0DED4C7E55869137200FBDBFE2EB654267B6D7013602CAED3115A"
+        );
+
+        let sk = PrivateKey::new(
+            bytes("7FB9E0E687ADA1EEBF7ECFE2F21E73EBDB51A7D450948DFE8D76D7F2D1007671")
+                .try_into()
+                .unwrap(),
+            Network::Mainnet,
+            true,
+        )
+        .unwrap();
+        let pubkeys = [
+            sk.public_key(),
+            x[0],
+            key("02DFF1D77F2A671C5F36183726DB2341BE58FEAE1DA2DECED843240F7B502BA661"),
+        ];
+        let pubnonces = [
+            "0337C87821AFD50A8644D820A8F3E02E499C931865C2360FB43D0A0D20DAFE07EA0287BF891D2A6DEAEBADC909352AA9405D1428C15F4B75F04DAE642A95C2548480",
+            "0279BE667EF9DCBBAC55A06295CE870B07029BFCDB2DCE28D959F2815B16F817980279BE667EF9DCBBAC55A06295CE870B07029BFCDB2DCE28D959F2815B16F81798",
+            "032DE2662628C90B03F5E720284EB52FF7D71F4284F627B68A853D78C78E1FFE9303E4C5524E83FFE1493B9077CF1CA6BEB2090C93D930321071AD40B2F44E599046",
+        ]
+        .map(|nonce| PubNonce(bytes(nonce).try_into().unwrap()));
+        let aggnonce = AggNonce::new(&pubnonces).unwrap();
+        assert_eq!(
+            hex::encode_upper(aggnonce.0),
+            "028465FCF0BBDBCF443AABCCE533D42B4B5A10966AC09A49655E8C42DAAB8FCD61037496A3CC86926D452CAFCFD55D25972CA1675D549310DE296BFF42F72EEEA8C9"
+        );
+        let key_agg = KeyAggContext::new(&pubkeys).unwrap();
+        let msg = bytes("F95466D086770E689964664219266FE5ED215C92AE20BAB5C9D79ADDDDF3C0CF");
+        let session = Session::new(&key_agg, &aggnonce, &msg).unwrap();
+        let mut secnonce = bytes(
+            "508B81A611F100A6B2B6B29656590898AF488BCF2E1F55CF22E5CFB84421FE61FA27FD49B1D50085B481285E1CA205D55C82CC1B31FF5CD54A489829355901F7",
+        );
+        secnonce.extend_from_slice(&sk.public_key().serialize());
+        let psig = session
+            .partial_sign(SecNonce::from_bytes(secnonce.try_into().unwrap()), &sk)
+            .unwrap();
+        assert_eq!(
+            hex::encode_upper(psig.0),
+            "012ABBCB52B3016AC03AD82395A1A415C48B93DEF78718E62A7A90052FE224FB"
+        );
+        assert!(session.verify_partial(&psig, &pubnonces[0], &pubkeys[0]));
+        assert!(!session.verify_partial(&psig, &pubnonces[1], &pubkeys[1]));
+        let invalid = PubNonce([0x04; 66]);
+        assert_eq!(
+            AggNonce::new(&[pubnonces[0], invalid]),
+            Err(BitcoinError::InvalidContribution {
+                signer: Some(1),
+                reason: "invalid public nonce"
+            })
+        );
+
+        // Three signers spending a taproot output whose internal key is
+        // their aggregate key.
+        let keys: Vec<PrivateKey> = (1..=3u8)
+            .map(|i| PrivateKey::new([i; 32], Network::Mainnet, true).unwrap())
+            .collect();
+        let pubkeys: Vec<PublicKey> = keys.iter().map(PrivateKey::public_key).collect();
+        let internal_key = KeyAggContext::new(&pubkeys).unwrap();
+        let merkle_root = [0x11; 32];
+        let key_agg = internal_key.clone().tap_tweak(Some(&merkle_root)).unwrap();
+        let (output_key, _) = internal_key
+            .x_only_public_key()
+            .tap_tweak(Some(&merkle_root));
+        assert_eq!(key_agg.x_only_public_key(), output_key);
+
+        let msg = [0x42; 32];
+        let (secnonces, pubnonces): (Vec<SecNonce>, Vec<PubNonce>) = keys
+            .iter()
+            .zip(0u8..)
+            .map(|(key, i)| {
+                let aggpk = key_agg.x_only_public_key();
+                nonce_gen(
+                    &[i; 32],
+                    Some(key),
+                    &key.public_key(),
+                    Some(&aggpk),
+                    Some(&msg),
+                    None,
+                )
+                .unwrap()
+            })
+            .unzip();
+        let aggnonce = AggNonce::new(&pubnonces).unwrap();
+        let session = Session::new(&key_agg, &aggnonce, &msg).unwrap();
+        let psigs: Vec<_> = secnonces
+            .into_iter()
+            .zip(&keys)
+            .map(|(secnonce, key)| session.partial_sign(secnonce, key).unwrap())
+            .collect();
+        for (i, psig) in psigs.iter().enumerate() {
+            assert!(session.verify_partial(psig, &pubnonces[i], &pubkeys[i]));
+        }
+        let signature = session.aggregate(&psigs).unwrap();
+        assert!(schnorr::verify(&output_key, &msg, &signature));
+        assert!(!schnorr::verify(&output_key, &[0x43; 32], &signature));
+        let signature = session.aggregate(&psigs[..2]).unwrap();
+        assert!(!schnorr::verify(&output_key, &msg, &signature));
+    }
+
+    #[cfg(feature = "secp256k1")]
+    #[test]
+    fn test_verify_transaction_signatures() {
+        use script::interpreter::{ScriptError, verify_transaction_signatures};
+        use sighash::SighashType;
+
+        let key = PrivateKey::new([0x42; 32], Network::Mainnet, true).unwrap();
+        let pubkey = key.public_key();
+        let internal_key = XOnlyPublicKey::from(pubkey);
+        let prevouts = [
+            TransactionOutput::new(10_000, Script::new_p2pkh(&pubkey.pubkey_hash())),
+            TransactionOutput::new(20_000, Script::new_p2wpkh(&pubkey.wpubkey_hash().unwrap())),
+            TransactionOutput::new(
+                30_000,
+                Script::new_p2tr_with_internal_key(&internal_key, None),
+            ),
+            TransactionOutput::new(
+                40_000,
+                Script::new_p2tr_with_internal_key(&internal_key, None),
+            ),
+        ];
+        let mut tx = BitcoinTransaction::new(
+            2,
+            (0..prevouts.len() as u8)
+                .map(|i| {
+                    TransactionInput::new(OutPoint::new(dummy_txid(i), 0), Script::new(vec![]), 0)
+                })
+                .collect(),
+            vec![TransactionOutput::new(
+                90_000,
+                Script::new_p2pkh(&pubkey.pubkey_hash()),
+            )],
+            0,
+        );
+        for (i, prevout) in prevouts.iter().enumerate() {
+            let sighash_type = match prevout.script_pubkey.classify() {
+                ScriptType::P2tr => SighashType::AllPlusAnyoneCanPay,
+                _ => SighashType::All,
+            };
+            tx.sign_input(i, prevout, &key, sighash_type).unwrap();
+        }
+        assert_eq!(verify_transaction_signatures(&tx, &prevouts), Ok(()));
+        assert!(matches!(
+            verify_transaction_signatures(&tx, &prevouts[..3]),
+            Err(BitcoinError::PrevoutsMismatch { .. })
+        ));
+
+        // Spending a different amount changes what segwit signatures commit to.
+        let mut wrong_amounts = prevouts.clone();
+        wrong_amounts[1].value += 1;
+        wrong_amounts[3].value += 1;
+        assert_eq!(
+            verify_transaction_signatures(&tx, &wrong_amounts),
+            Err(BitcoinError::InputScript {
+                index: 1,
+                error: ScriptError::EvalFalse
+            })
+        );
+        wrong_amounts[1].value -= 1;
+        assert_eq!(
+            verify_transaction_signatures(&tx, &wrong_amounts),
+            Err(BitcoinError::InputScript {
+                index: 3,
+                error: ScriptError::SchnorrSig
+            })
+        );
+
+        let mut tampered = tx.clone();
+        tampered.inputs[2].witness.items[0][64] = 0x00;
+        assert_eq!(
+            verify_transaction_signatures(&tampered, &prevouts),
+            Err(BitcoinError::InputScript {
+                index: 2,
+                error: ScriptError::SchnorrSigHashType
+            })
+        );
+        tampered.inputs[2].witness.items[0].pop();
+        tampered.inputs[2].witness.items[0].pop();
+        assert_eq!(
+            verify_transaction_signatures(&tampered, &prevouts),
+            Err(BitcoinError::InputScript {
+                index: 2,
+                error: ScriptError::SchnorrSigSize
+            })
+        );
+    }
+
+    #[cfg(feature = "secp256k1")]
+    #[test]
+    fn test_hybrid_pubkey_signatures() {
+        use script::interpreter::{
+            ScriptError, TransactionSignatureChecker, VerifyFlags, verify_script,
+        };
+        use sighash::{SighashCache, SighashType};
+
+        let key = PrivateKey::new([0x42; 32], Network::Mainnet, false).unwrap();
+        let uncompressed = key.public_key().serialize_uncompressed();
+        let odd_y = uncompressed[64] & 1;
+        let hybrid = |prefix: u8| [&[prefix], &uncompressed[1..]].concat();
+        let tx = BitcoinTransaction::new(
+            1,
+            vec![TransactionInput::new(
+                OutPoint::new(dummy_txid(0), 0),
+                Script::new(vec![]),
+                0,
+            )],
+            vec![TransactionOutput::new(900, Script::new(vec![0x51]))],
+            0,
+        );
+        let verify = |pubkey: &[u8], flags| {
+            let script_pubkey = Script::builder()
+                .push_slice(pubkey)
+                .push_opcode(Opcode::OP_CHECKSIG)
+                .into_script();
+            let sighash =
+                sighash::legacy_signature_hash(&tx, 0, &script_pubkey, SighashType::All).unwrap();
+            let sig = ecdsa::sign_with_sighash_type(&key, &sighash, 0x01);
+            let script_sig = Script::builder().push_slice(&sig).into_script();
+            let cache = SighashCache::new(&tx);
+            verify_script(
+                &script_sig,
+                &script_pubkey,
+                &Witness::default(),
+                flags,
+                TransactionSignatureChecker::new(&cache, 0, 1000),
+            )
+        };
+
+        // Consensus takes hybrid keys whose prefix matches the parity of y.
+        assert_eq!(
+            verify(&hybrid(0x06 | odd_y), VerifyFlags::CONSENSUS),
+            Ok(())
+        );
+        assert_eq!(
+            verify(&hybrid(0x07 ^ odd_y), VerifyFlags::CONSENSUS),
+            Err(BitcoinError::Script(ScriptError::EvalFalse))
+        );
+        assert_eq!(
+            verify(&hybrid(0x06 | odd_y), VerifyFlags::STANDARD),
+            Err(BitcoinError::Script(ScriptError::PubkeyType))
+        );
+    }
+
+    #[cfg(feature = "secp256k1")]
+    #[test]
+    fn test_undefined_sighash_signatures() {
+        use script::interpreter::{
+            ScriptError, TransactionSignatureChecker, VerifyFlags, verify_script,
+            verify_transaction_signatures,
+        };
+        use sighash::{SighashCache, SighashType};
+
+        let key = PrivateKey::new([0x42; 32], Network::Mainnet, true).unwrap();
+        let pubkey = key.public_key();
+        let prevouts = [
+            TransactionOutput::new(10_000, Script::new_p2pkh(&pubkey.pubkey_hash())),
+            TransactionOutput::new(20_000, Script::new_p2wpkh(&pubkey.wpubkey_hash().unwrap())),
+        ];
+        let mut tx = BitcoinTransaction::new(
+            2,
+            (0..2)
+                .map(|i| {
+                    TransactionInput::new(OutPoint::new(dummy_txid(i), 0), Script::new(vec![]), 0)
+                })
+                .collect(),
+            vec![TransactionOutput::new(
+                25_000,
+                Script::new_p2pkh(&pubkey.pubkey_hash()),
+            )],
+            0,
+        );
+        // Consensus accepts signatures with undefined hash type bytes.
+        for (i, byte) in [(0, 0x04), (1, 0x80)] {
+            tx.sign_input(i, &prevouts[i], &key, SighashType::Undefined(byte))
+                .unwrap();
+        }
+        assert_eq!(verify_transaction_signatures(&tx, &prevouts), Ok(()));
+
+        // STRICTENC, part of the standard flags, does not.
+        let cache = SighashCache::new(&tx);
+        let input = &tx.inputs[0];
+        assert_eq!(
+            verify_script(
+                &input.script_sig,
+                &prevouts[0].script_pubkey,
+                &input.witness,
+                VerifyFlags::STANDARD,
+                TransactionSignatureChecker::new(&cache, 0, prevouts[0].value),
+            ),
+            Err(BitcoinError::Script(ScriptError::SigHashType))
+        );
+    }
+
+    #[test]
+    fn test_taproot_builder() {
+        use descriptor::{Descriptor, TapTree};
+
+        let [internal_key, a, b, c] = [
+            "a34b99f22c790c4e36b2b3c2c35a36db06226e41c692fc82b8b56ac1c540c5bd",
+            "669b8afcec803a0d323e9a17f3ea8e68e8abe5a278020a929adbec52421adbd0",
+            "f9308a019258c31049344f85f89d5229b531c845836f99b08601f113bce036f9",
+            "2f01e5e15cca351daff3843fb70f3c2f0a1bdd05e5af888a67784ef3e10a2a01",
+        ];
+        let leaf = |key: &str| {
+            Script::builder()
+                .push_slice(&hex::decode(key).unwrap())
+                .push_opcode(Opcode::OP_CHECKSIG)
+                .into_script()
+        };
+        let internal: XOnlyPublicKey = internal_key.parse().unwrap();
+        let spend_info = TaprootBuilder::new()
+            .add_leaf(1, leaf(a))
+            .unwrap()
+            .add_leaf(2, leaf(b))
+            .unwrap()
+            .add_leaf(2, leaf(c))
+            .unwrap()
+            .finalize(&internal)
+            .unwrap();
+        let desc: Descriptor =
+            format!("tr({},{{pk({}),{{pk({}),pk({})}}}})", internal_key, a, b, c)
+                .parse()
+                .unwrap();
+        assert_eq!(spend_info.script_pubkey(), desc.script_pubkey());
+        let Descriptor::Tr {
+            tree: Some(tree), ..
+        } = &desc
+        else {
+            unreachable!()
+        };
+        assert_eq!(spend_info.merkle_root(), Some(tree.merkle_root()));
+        let TapTree::Branch(left, right) = tree else {
+            unreachable!()
+        };
+
+        let blocks: Vec<_> = spend_info.control_blocks().collect();
+        assert_eq!(blocks.len(), 3);
+        assert_eq!(*blocks[0].0, leaf(a));
+        assert_eq!(blocks[0].1.merkle_branch, [right.merkle_root()]);
+        let TapTree::Branch(b_leaf, c_leaf) = &**right else {
+            unreachable!()
+        };
+        assert_eq!(
+            blocks[1].1.merkle_branch,
+            [c_leaf.merkle_root(), left.merkle_root()]
+        );
+        assert_eq!(
+            blocks[2].1.merkle_branch,
+            [b_leaf.merkle_root(), left.merkle_root()]
+        );
+        let serialized = blocks[2].1.serialize();
+        assert_eq!(serialized.len(), 33 + 64);
+        assert_eq!(serialized[0], 0xc0 | spend_info.output_key_parity() as u8);
+        assert_eq!(serialized[1..33], internal.serialize());
+        assert_eq!(
+            spend_info.control_block(&leaf(c), 0xc0),
+            Some(blocks[2].1.clone())
+        );
+        assert_eq!(spend_info.control_block(&leaf(c), 0xc2), None);
+        assert_eq!(
+            spend_info.output_key(),
+            internal.tap_tweak(spend_info.merkle_root().as_ref()).0
+        );
+
+        // Without leaves, only the key can spend.
+        let key_only = TaprootBuilder::new().finalize(&internal).unwrap();
+        assert_eq!(key_only.merkle_root(), None);
+        assert_eq!(
+            key_only.script_pubkey(),
+            Script::new_p2tr_with_internal_key(&internal, None)
+        );
+
+        let invalid =
+            |result: Result<TaprootBuilder>| matches!(result, Err(BitcoinError::InvalidTapTree(_)));
+        let deep = TaprootBuilder::new().add_leaf(2, leaf(a)).unwrap();
+        assert!(!deep.is_complete());
+        assert!(matches!(
+            deep.clone().finalize(&internal),
+            Err(BitcoinError::InvalidTapTree(_))
+        ));
+        assert!(invalid(deep.add_leaf(1, leaf(b))));
+        let whole = TaprootBuilder::new().add_leaf(0, leaf(a)).unwrap();
+        assert!(whole.is_complete());
+        assert!(invalid(whole.add_leaf(0, leaf(b))));
+        assert!(invalid(TaprootBuilder::new().add_leaf(129, leaf(a))));
+        assert!(invalid(TaprootBuilder::new().add_leaf_with_version(
+            0,
+            leaf(a),
+            0xc1
+        )));
+        assert!(invalid(TaprootBuilder::new().add_leaf_with_version(
+            0,
+            leaf(a),
+            0x50
+        )));
+    }
+
+    #[test]
+    fn test_control_block() {
+        let internal: XOnlyPublicKey =
+            "a34b99f22c790c4e36b2b3c2c35a36db06226e41c692fc82b8b56ac1c540c5bd"
+                .parse()
+                .unwrap();
+        let leaves: Vec<Script> = (1..=3)
+            .map(|n| Script::builder().push_int(n).into_script())
+            .collect();
+        let spend_info = leaves
+            .iter()
+            .zip([1, 2, 2])
+            .try_fold(TaprootBuilder::new(), |builder, (leaf, depth)| {
+                builder.add_leaf(depth, leaf.clone())
+            })
+            .unwrap()
+            .finalize(&internal)
+            .unwrap();
+        let output_key = spend_info.output_key();
+        for (leaf, control_block) in spend_info.control_blocks() {
+            let parsed = ControlBlock::from_slice(&control_block.serialize()).unwrap();
+            assert_eq!(parsed, control_block);
+            assert!(parsed.verify(&output_key, leaf));
+            assert!(!parsed.verify(&internal, leaf));
+            assert!(!parsed.verify(&output_key, &leaves[0].to_p2sh()));
+            let mut flipped = parsed.clone();
+            flipped.output_key_parity ^= true;
+            assert!(!flipped.verify(&output_key, leaf));
+        }
+        let control_block = spend_info.control_block(&leaves[1], 0xc0).unwrap();
+        let mut other_version = control_block.clone();
+        other_version.leaf_version = 0xc2;
+        assert!(!other_version.verify(&output_key, &leaves[1]));
+
+        let invalid = |bytes: &[u8]| {
+            matches!(
+                ControlBlock::from_slice(bytes),
+                Err(BitcoinError::InvalidControlBlock(_))
+            )
+        };
+        let bytes = control_block.serialize();
+        assert!(invalid(&bytes[..32]));
+        assert!(invalid(&bytes[..34]));
+        assert!(invalid(&[&bytes[..33], &[0; 32 * 129]].concat()));
+        assert!(!invalid(&[&bytes[..33], &[0; 32 * 128]].concat()));
+        let mut off_curve = bytes.clone();
+        off_curve[1..33].copy_from_slice(&[0xff; 32]);
+        assert!(invalid(&off_curve));
+
+        #[cfg(feature = "secp256k1")]
+        {
+            use script::interpreter::{ScriptError, verify_transaction_signatures};
+
+            let prevouts = [TransactionOutput::new(1000, spend_info.script_pubkey())];
+            let mut tx = BitcoinTransaction::new(
+                2,
+                vec![TransactionInput::new(
+                    OutPoint::new(dummy_txid(0), 0),
+                    Script::new(vec![]),
+                    0,
+                )],
+                vec![],
+                0,
+            );
+            let mut spend = |control_block: Vec<u8>| {
+                tx.inputs[0].witness = Witness::new(vec![leaves[1].bytes.clone(), control_block]);
+                verify_transaction_signatures(&tx, &prevouts)
+            };
+            // The commitment checks out, but the tapscript can't be run.
+            assert_eq!(
+                spend(bytes.clone()),
+                Err(BitcoinError::UnsupportedInput {
+                    index: 0,
+                    reason: "tapscript is not supported"
+                })
+            );
+            let mut wrong_parity = bytes.clone();
+            wrong_parity[0] ^= 1;
+            assert_eq!(
+                spend(wrong_parity),
+                Err(BitcoinError::InputScript {
+                    index: 0,
+                    error: ScriptError::WitnessProgramMismatch
+                })
+            );
+            assert_eq!(
+                spend(bytes[..34].to_vec()),
+                Err(BitcoinError::InputScript {
+                    index: 0,
+                    error: ScriptError::TaprootWrongControlSize
+                })
+            );
+        }
+    }
+
+    #[test]
+    fn test_tap_hashes() {
+        let internal: XOnlyPublicKey =
+            "a34b99f22c790c4e36b2b3c2c35a36db06226e41c692fc82b8b56ac1c540c5bd"
+                .parse()
+                .unwrap();
+        let leaves: Vec<Script> = (1..=3)
+            .map(|n| Script::builder().push_int(n).into_script())
+            .collect();
+        let spend_info = TaprootBuilder::new()
+            .add_leaf(1, leaves[0].clone())
+            .unwrap()
+            .add_leaf(2, leaves[1].clone())
+            .unwrap()
+            .add_leaf(2, leaves[2].clone())
+            .unwrap()
+            .finalize(&internal)
+            .unwrap();
+
+        let [a, b, c] = [0, 1, 2].map(|i| {
+            TapNodeHash::from(TapLeafHash::from_script(&leaves[i], TAPSCRIPT_LEAF_VERSION))
+        });
+        let bc = TapNodeHash::from_node_hashes(b, c);
+        assert_eq!(bc, TapNodeHash::from_node_hashes(c, b));
+        let root = TapNodeHash::from_node_hashes(a, bc);
+        assert_eq!(spend_info.merkle_root(), Some(root.0));
+        assert_ne!(
+            TapLeafHash::from_script(&leaves[0], 0xc2),
+            TapLeafHash::from_script(&leaves[0], TAPSCRIPT_LEAF_VERSION)
+        );
+
+        let control_block = spend_info
+            .control_block(&leaves[1], TAPSCRIPT_LEAF_VERSION)
+            .unwrap();
+        let leaf = TapLeafHash::from_script(&leaves[1], TAPSCRIPT_LEAF_VERSION);
+        assert_eq!(
+            TapNodeHash::from_merkle_branch(leaf, &control_block.merkle_branch),
+            root
+        );
+        assert_eq!(TapNodeHash::from_merkle_branch(leaf, &[]), b);
+        assert_eq!(root.to_string(), hex::encode(root.0));
+        assert_eq!(leaf.to_string(), hex::encode(leaf.0));
+    }
+
+    #[test]
+    fn test_schnorr_signature_encoding() {
+        use schnorr::{SIGHASH_DEFAULT, SchnorrSignature};
+
+        let sig = SchnorrSignature::from_slice(&[7; 64]).unwrap();
+        assert_eq!(sig.sighash_type, SIGHASH_DEFAULT);
+        assert_eq!(sig.to_vec(), [7; 64]);
+        assert_eq!(Witness::new_p2tr_key_spend(&sig).items, [vec![7; 64]]);
+
+        let mut bytes = vec![7; 65];
+        bytes[64] = 0x83;
+        let sig = SchnorrSignature::from_slice(&bytes).unwrap();
+        assert_eq!(sig.sighash_type, 0x83);
+        assert_eq!(sig.signature, [7; 64]);
+        assert_eq!(sig.to_vec(), bytes);
+
+        bytes[64] = SIGHASH_DEFAULT;
+        assert!(matches!(
+            SchnorrSignature::from_slice(&bytes),
+            Err(BitcoinError::InvalidSignature(_))
+        ));
+        assert!(SchnorrSignature::from_slice(&[7; 63]).is_err());
+        assert!(SchnorrSignature::from_slice(&[7; 66]).is_err());
+    }
+
+    #[cfg(feature = "secp256k1")]
+    #[test]
+    fn test_schnorr_signing() {
+        // Test vectors 0 and 1 from BIP340.
+        let vectors = [
+            (
+                "0000000000000000000000000000000000000000000000000000000000000003",
+                "f9308a019258c31049344f85f89d5229b531c845836f99b08601f113bce036f9",
+                [0; 32],
+                "0000000000000000000000000000000000000000000000000000000000000000",
+                "e907831f80848d1069a5371b402410364bdf1c5f8307b0084c55f1ce2dca821525f66a4a85ea8b71e482a74f382d2ce5ebeee8fdb2172f477df4900d310536c0",
+            ),
+            (
+                "b7e151628aed2a6abf7158809cf4f3c762e7160f38b4da56a784d9045190cfef",
+                "dff1d77f2a671c5f36183726db2341be58feae1da2deced843240f7b502ba659",
+                {
+                    let mut aux = [0; 32];
+                    aux[31] = 1;
+                    aux
+                },
+                "243f6a8885a308d313198a2e03707344a4093822299f31d0082efa98ec4e6c89",
+                "6896bd60eeae296db48a229ff71dfe071bde413e6d43f917dc8dcf8c78de33418906d11ac976abccb20b091292bff4ea897efcb639ea871cfa95f6de339e4b0a",
+            ),
+        ];
+        for (secret, pubkey, aux, msg, expected) in vectors {
+            let key = PrivateKey::new(
+                hex::decode(secret).unwrap().try_into().unwrap(),
+                Network::Mainnet,
+                true,
+            )
+            .unwrap();
+            let pubkey: XOnlyPublicKey = pubkey.parse().unwrap();
+            assert_eq!(XOnlyPublicKey::from(key.public_key()), pubkey);
+            let msg: [u8; 32] = hex::decode(msg).unwrap().try_into().unwrap();
+            let sig = schnorr::sign(&key, &msg, &aux);
+            assert_eq!(hex::encode(sig), expected);
+            assert!(schnorr::verify(&pubkey, &msg, &sig));
+            let mut tampered = sig;
+            tampered[63] ^= 1;
+            assert!(!schnorr::verify(&pubkey, &msg, &tampered));
+        }
+
+        // A tweaked key signs for the output key of its public key.
+        let key = PrivateKey::new([0x42; 32], Network::Mainnet, true).unwrap();
+        let internal_key = XOnlyPublicKey::from(key.public_key());
+        let root = [9; 32];
+        for merkle_root in [None, Some(&root)] {
+            let (output_key, _) = internal_key.tap_tweak(merkle_root);
+            let tweaked = key.tap_tweak(merkle_root);
+            assert_eq!(XOnlyPublicKey::from(tweaked.public_key()), output_key);
+            let msg = hashes::sha256(b"key path");
+            let sig = schnorr::sign(&tweaked, &msg, &[0; 32]);
+            assert!(schnorr::verify(&output_key, &msg, &sig));
+            assert!(!schnorr::verify(&internal_key, &msg, &sig));
+        }
+    }
+
+    #[cfg(feature = "secp256k1")]
+    #[test]
+    fn test_bip85() {
+        use bip32::{DerivationPath, Xpriv};
+
+        // Test vectors from BIP85.
+        let root: Xpriv = "xprv9s21ZrQH143K2LBWUUQRFXhucrQqBpKdRRxNVq2zBqsx8HVqFk2uYo8kmbaLLHRdqtQpUm98uKfu3vca1LqdGhUtyoFnCNkfmXRyPXLjbKb".parse().unwrap();
+        let path: DerivationPath = "0'/0'".parse().unwrap();
+        assert_eq!(
+            hex::encode(bip85::derive_entropy(&root, &path).unwrap()),
+            "efecfbccffea313214232d29e71563d941229afb4338c21f9517c41aaa0d16f00b83d2a09ef747e7a64e8e2bd5a14869e693da66ce94ac2da570ab7ee48618f7"
+        );
+        assert_eq!(
+            bip85::wif(&root, 0).unwrap().to_wif(),
+            "Kzyv4uF39d4Jrw2W7UryTHwZr1zQVNk4dAFyqE6BuMrMh1Za7uhp"
+        );
+        assert_eq!(
+            hex::encode(bip85::hex(&root, 64, 0).unwrap()),
+            "492db4698cf3b73a5a24998aa3e9d7fa96275d85724a91e71aa2d645442f878555d078fd1f1f67e368976f04137b1f7a0d19232136ca50c44614af72b5582a5c"
+        );
+        assert_eq!(bip85::hex(&root, 16, 0).unwrap().len(), 16);
+        assert_ne!(
+            bip85::hex(&root, 16, 1).unwrap(),
+            bip85::hex(&root, 16, 0).unwrap()
+        );
+
+        assert!(matches!(
+            bip85::hex(&root, 65, 0),
+            Err(BitcoinError::InvalidBip85Request(_))
+        ));
+        assert!(matches!(
+            bip85::derive_entropy(&root, &"0'/0".parse().unwrap()),
+            Err(BitcoinError::InvalidBip85Request(_))
+        ));
+        assert!(bip85::wif(&root, 1 << 31).is_err());
+
+        #[cfg(feature = "bip39")]
+        {
+            let expected = [
+                (
+                    12,
+                    "girl mad pet galaxy egg matter matrix prison refuse sense ordinary nose",
+                ),
+                (
+                    18,
+                    "near account window bike charge season chef number sketch tomorrow excuse sniff circle vital hockey outdoor supply token",
+                ),
+                (
+                    24,
+                    "puppy ocean match cereal symbol another shed magic wrap hammer bulb intact gadget divorce twin tonight reason outdoor destroy simple truth cigar social volcano",
+                ),
+            ];
+            for (word_count, phrase) in expected {
+                let mnemonic = bip85::mnemonic(&root, word_count, 0).unwrap();
+                assert_eq!(mnemonic.to_string(), phrase);
+            }
+            assert!(bip85::mnemonic(&root, 15, 0).is_err());
+        }
+    }
+
+    #[test]
+    fn test_account_paths() {
+        use account::{Account, Purpose};
+        use bip32::KeyApplication;
+
+        let account = Account::bip84(Network::Mainnet, 0).unwrap();
+        assert_eq!(account.path().to_string(), "m/84'/0'/0'");
+        assert_eq!(
+            account.address_path(true, 5).unwrap().to_string(),
+            "m/84'/0'/0'/1/5"
+        );
+        assert_eq!(
+            Account::bip44(Network::Testnet, 3)
+                .unwrap()
+                .path()
+                .to_string(),
+            "m/44'/1'/3'"
+        );
+        assert!(Account::bip86(Network::Mainnet, 1 << 31).is_err());
+        assert!(account.address_path(false, 1 << 31).is_err());
+        assert_eq!(Purpose::from_number(49), Some(Purpose::Bip49));
+        assert_eq!(Purpose::from_number(45), None);
+        assert_eq!(
+            Purpose::Bip84.key_application(),
+            Some(KeyApplication::Segwit)
+        );
+
+        #[cfg(feature = "secp256k1")]
+        {
+            use bip32::Xpriv;
+
+            // The first receive address of each purpose for the "abandon ...
+            // about" mnemonic.
+            let seed = hex::decode("5eb00bbddcf069084889a8ab9155568165f5c453ccb85e70811aaed6f6da5fc19a5ac40b389cd370d086206dec8aa6c43daea6690f20ad3d8d48b2d2ce9e38e4").unwrap();
+            let master = Xpriv::new_master(Network::Mainnet, &seed).unwrap();
+            let expected = [
+                (Purpose::Bip44, "1LqBGSKuX5yYUonjxT5qGfpUsXKYYWeabA"),
+                (Purpose::Bip49, "37VucYSaXLCAsxYyAPfbSi9eh4iEcbShgf"),
+                (Purpose::Bip84, "bc1qcr8te4kr609gcawutmrza0j4xv80jy8z306fyu"),
+                (
+                    Purpose::Bip86,
+                    "bc1p5cyxnuxmeuwuvkwfem96lqzszd02n6xdcjrs20cac6yqjjwudpxqkedrcr",
+                ),
+            ];
+            for (purpose, address) in expected {
+                let account = Account::new(purpose, Network::Mainnet, 0).unwrap();
+                let descriptor = account.descriptor(&master, false).unwrap();
+                let script = descriptor.at_derivation_index(0).unwrap().script_pubkey();
+                assert_eq!(
+                    Address::from_script(&script, Network::Mainnet)
+                        .unwrap()
+                        .to_string(),
+                    address
+                );
+            }
+            assert_eq!(
+                account.descriptor(&master, true).unwrap().to_string(),
+                "wpkh([73c5da0a/84'/0'/0']xpub6CatWdiZiodmUeTDp8LT5or8nmbKNcuyvz7WyksVFkKB4RHwCD3XyuvPEbvqAQY3rAPshWcMLoP2fMFMKHPJ4ZeZXYVUhLv1VMrjPC7PW6V/1/*)"
+            );
+        }
+    }
+
+    #[cfg(feature = "secp256k1")]
+    #[test]
+    fn test_slip132() {
+        use bip32::{KeyApplication, Xpriv, Xpub};
+
+        // BIP84 and BIP49 account keys for the "abandon ... about" mnemonic.
+        let zpub = "zpub6rFR7y4Q2AijBEqTUquhVz398htDFrtymD9xYYfG1m4wAcvPhXNfE3EfH1r1ADqtfSdVCToUG868RvUUkgDKf31mGDtKsAYz2oz2AGutZYs";
+        let (xpub, application) = Xpub::from_slip132_str(zpub).unwrap();
+        assert_eq!(application, KeyApplication::Segwit);
+        assert_eq!(xpub.network, Network::Mainnet);
+        assert_eq!(
+            xpub.to_string(),
+            "xpub6CatWdiZiodmUeTDp8LT5or8nmbKNcuyvz7WyksVFkKB4RHwCD3XyuvPEbvqAQY3rAPshWcMLoP2fMFMKHPJ4ZeZXYVUhLv1VMrjPC7PW6V"
+        );
+        assert_eq!(xpub.to_slip132_string(KeyApplication::Segwit), zpub);
+        // Plain xpubs go through the same path.
+        assert_eq!(
+            Xpub::from_slip132_str(&xpub.to_string()).unwrap(),
+            (xpub, KeyApplication::Legacy)
+        );
+        // Only plain prefixes parse as an `Xpub` directly.
+        assert!(zpub.parse::<Xpub>().is_err());
+
+        let zprv = "zprvAdG4iTXWBoARxkkzNpNh8r6Qag3irQB8PzEMkAFeTRXxHpbF9z4QgEvBRmfvqWvGp42t42nvgGpNgYSJA9iefm1yYNZKEm7z6qUWCroSQnE";
+        let (xprv, application) = Xpriv::from_slip132_str(zprv).unwrap();
+        assert_eq!(application, KeyApplication::Segwit);
+        assert_eq!(xprv.to_xpub(), xpub);
+        assert_eq!(xprv.to_slip132_string(KeyApplication::Segwit), zprv);
+        // Public and private prefixes are not interchangeable.
+        assert!(Xpub::from_slip132_str(zprv).is_err());
+        assert!(Xpriv::from_slip132_str(zpub).is_err());
+
+        let (xpub, application) = Xpub::from_slip132_str("ypub6Ww3ibxVfGzLrAH1PNcjyAWenMTbbAosGNB6VvmSEgytSER9azLDWCxoJwW7Ke7icmizBMXrzBx9979FfaHxHcrArf3zbeJJJUZPf663zsP").unwrap();
+        assert_eq!(application, KeyApplication::NestedSegwit);
+        assert!(xpub.to_string().starts_with("xpub"));
+
+        let testnet = Xpriv::new_master(Network::Testnet, &[1; 32]).unwrap();
+        let prefixes = [
+            (KeyApplication::Legacy, "tpub", "tprv"),
+            (KeyApplication::NestedSegwit, "upub", "uprv"),
+            (KeyApplication::Segwit, "vpub", "vprv"),
+            (KeyApplication::NestedSegwitMultisig, "Upub", "Uprv"),
+            (KeyApplication::SegwitMultisig, "Vpub", "Vprv"),
+        ];
+        for (application, public, private) in prefixes {
+            let s = testnet.to_xpub().to_slip132_string(application);
+            assert!(s.starts_with(public), "{}", s);
+            assert_eq!(
+                Xpub::from_slip132_str(&s).unwrap(),
+                (testnet.to_xpub(), application)
+            );
+            let s = testnet.to_slip132_string(application);
+            assert!(s.starts_with(private), "{}", s);
+            assert_eq!(Xpriv::from_slip132_str(&s).unwrap(), (testnet, application));
+        }
+        let mainnet = Xpriv::new_master(Network::Mainnet, &[1; 32]).unwrap();
+        assert!(
+            mainnet
+                .to_xpub()
+                .to_slip132_string(KeyApplication::SegwitMultisig)
+                .starts_with("Zpub")
+        );
+        assert!(
+            mainnet
+                .to_slip132_string(KeyApplication::NestedSegwitMultisig)
+                .starts_with("Yprv")
+        );
+    }
+
+    #[test]
+    fn test_derivation_path() {
+        use bip32::{ChildNumber, DerivationPath, HARDENED};
+
+        let path: DerivationPath = "m/84'/0'/0'/0/5".parse().unwrap();
+        assert_eq!(path.len(), 5);
+        assert_eq!(path.to_string(), "m/84'/0'/0'/0/5");
+        assert_eq!("m/84h/0h/0h/0/5".parse::<DerivationPath>().unwrap(), path);
+        assert_eq!("84'/0'/0'/0/5".parse::<DerivationPath>().unwrap(), path);
+        let raw: Vec<u32> = path.iter().map(|&child| child.into()).collect();
+        assert_eq!(raw, [84 | HARDENED, HARDENED, HARDENED, 0, 5]);
+        assert_eq!(
+            path.iter().copied().collect::<DerivationPath>(),
+            raw.into_iter().map(ChildNumber::from).collect()
+        );
+
+        let child = ChildNumber::from_hardened_idx(84).unwrap();
+        assert!(child.is_hardened());
+        assert_eq!(child.index(), 84);
+        assert_eq!(child.to_string(), "84'");
+        assert_eq!(ChildNumber::from(84 | HARDENED), child);
+        assert!(ChildNumber::from_normal_idx(HARDENED).is_err());
+
+        let master: DerivationPath = "m".parse().unwrap();
+        assert!(master.is_master());
+        assert_eq!(master.to_string(), "m");
+        assert_eq!(
+            master
+                .child(child)
+                .extend([ChildNumber::Normal { index: 1 }])
+                .to_string(),
+            "m/84'/1"
+        );
+
+        for bad in [
+            "",
+            "m/",
+            "m//1",
+            "m/x",
+            "m/1''",
+            "m/-1",
+            "m/+1",
+            "m/2147483648",
+            "n/1",
+        ] {
+            assert!(
+                matches!(
+                    bad.parse::<DerivationPath>(),
+                    Err(BitcoinError::InvalidDerivationPath(_))
+                ),
+                "{}",
+                bad
+            );
+        }
+
+        let json = serde_json::to_string(&path).unwrap();
+        assert_eq!(json, r#""m/84'/0'/0'/0/5""#);
+        assert_eq!(serde_json::from_str::<DerivationPath>(&json).unwrap(), path);
+    }
+
+    #[cfg(feature = "bip39")]
+    #[test]
+    fn test_bip39_mnemonic() {
+        use bip32::Xpriv;
+        use bip39::{Bip39Error, Mnemonic};
+
+        // Test vectors from BIP39, with the passphrase "TREZOR".
+        let mnemonic = Mnemonic::from_entropy(&[0; 16]).unwrap();
+        assert_eq!(
+            mnemonic.to_string(),
+            "abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon about"
+        );
+        let seed = mnemonic.to_seed("TREZOR");
+        assert_eq!(
+            hex::encode(seed),
+            "c55257c360c07c72029aebc1b53c05ed0362ada38ead3e3e9efa3708e53495531f09a6987599d18264c1e1c92f2cf141630c7a3c4ab7c81b2f001698e7463b04"
+        );
+        assert_eq!(
+            Xpriv::new_master(Network::Mainnet, &seed)
+                .unwrap()
+                .to_string(),
+            "xprv9s21ZrQH143K3h3fDYiay8mocZ3afhfULfb5GX8kCBdno77K4HiA15Tg23wpbeF1pLfs1c5SPmYHrEpTuuRhxMwvKDwqdKiGJS9XFKzUsAF"
+        );
+
+        let vectors = [
+            (
+                "7f7f7f7f7f7f7f7f7f7f7f7f7f7f7f7f",
+                "legal winner thank year wave sausage worth useful legal winner thank yellow",
+            ),
+            (
+                "ffffffffffffffffffffffffffffffffffffffffffffffff",
+                "zoo zoo zoo zoo zoo zoo zoo zoo zoo zoo zoo zoo zoo zoo zoo zoo zoo when",
+            ),
+            (
+                "9f6a2878b2520799a44ef18bc7df394e7061a224d2c33cd015b157d746869863",
+                "panda eyebrow bullet gorilla call smoke muffin taste mesh discover soft ostrich alcohol speed nation flash devote level hobby quick inner drive ghost inside",
+            ),
+            (
+                "f30f8c1da665478f49b001d94c5fc452",
+                "vessel ladder alter error federal sibling chat ability sun glass valve picture",
+            ),
+        ];
+        for (entropy, phrase) in vectors {
+            let entropy = hex::decode(entropy).unwrap();
+            let mnemonic = Mnemonic::from_entropy(&entropy).unwrap();
+            assert_eq!(mnemonic.to_string(), phrase);
+            let parsed: Mnemonic = phrase.parse().unwrap();
+            assert_eq!(parsed.entropy(), entropy);
+            assert_eq!(parsed.word_count(), phrase.split(' ').count());
+        }
+        let mnemonic: Mnemonic =
+            "legal winner thank year wave sausage worth useful legal winner thank yellow"
+                .parse()
+                .unwrap();
+        assert_eq!(
+            hex::encode(mnemonic.to_seed("")),
+            "878386efb78845b3355bd15ea4d39ef97d179cb712b77d5c12b6be415fffeffe5f377ba02bf3f8544ab800b955e51fbff09828f682052a20faa6addbbddfb096"
+        );
+        // Extra whitespace between words is not part of the seed.
+        let spaced: Mnemonic =
+            "  legal winner\tthank year wave sausage worth useful legal winner thank   yellow\n"
+                .parse()
+                .unwrap();
+        assert_eq!(spaced, mnemonic);
+        assert!(!format!("{:?}", mnemonic).contains("legal"));
+
+        let generated = Mnemonic::generate(24, |bytes| bytes.fill(0x80)).unwrap();
+        assert_eq!(generated.entropy(), [0x80; 32]);
+        assert!(generated.to_string().ends_with("acoustic bless"));
+
+        assert!(matches!(
+            Mnemonic::from_entropy(&[0; 15]),
+            Err(BitcoinError::Bip39(Bip39Error::BadEntropyLength(15)))
+        ));
+        assert!(matches!(
+            Mnemonic::generate(13, |_| {}),
+            Err(BitcoinError::Bip39(Bip39Error::BadWordCount(13)))
+        ));
+        assert!(matches!(
+            "abandon abandon abandon".parse::<Mnemonic>(),
+            Err(BitcoinError::Bip39(Bip39Error::BadWordCount(3)))
+        ));
+        assert_eq!(
+            "abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon"
+                .parse::<Mnemonic>()
+                .unwrap_err(),
+            BitcoinError::Bip39(Bip39Error::InvalidChecksum)
+        );
+        assert_eq!(
+            "abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon Abandon about"
+                .parse::<Mnemonic>()
+                .unwrap_err(),
+            BitcoinError::Bip39(Bip39Error::UnknownWord {
+                word: "Abandon".into(),
+                position: 10
+            })
+        );
+    }
+
+    #[test]
+    fn test_bip21_uri() {
+        // BIP21 examples, with a valid address substituted.
+        let address: Address = "1BvBMSEYstWetqTFn5Au4m4GFg7xJaNVN2".parse().unwrap();
+        let uri: Bip21Uri = "bitcoin:1BvBMSEYstWetqTFn5Au4m4GFg7xJaNVN2?amount=50&label=Luke-Jr&message=Donation%20for%20project%20xyz"
+            .parse()
+            .unwrap();
+        assert_eq!(uri.address, address);
+        assert_eq!(uri.amount, Some(50 * 100_000_000));
+        assert_eq!(uri.label.as_deref(), Some("Luke-Jr"));
+        assert_eq!(uri.message.as_deref(), Some("Donation for project xyz"));
+        assert_eq!(
+            uri.to_string(),
+            "bitcoin:1BvBMSEYstWetqTFn5Au4m4GFg7xJaNVN2?amount=50&label=Luke-Jr&message=Donation%20for%20project%20xyz"
+        );
+
+        let uri: Bip21Uri = "BITCOIN:1BvBMSEYstWetqTFn5Au4m4GFg7xJaNVN2?amount=20.3&somethingyoudontunderstand=50&somethingelseyoudontget=999"
+            .parse()
+            .unwrap();
+        assert_eq!(uri.amount, Some(2_030_000_000));
+        assert_eq!(
+            uri.extras,
+            [
+                ("somethingyoudontunderstand".to_string(), "50".to_string()),
+                ("somethingelseyoudontget".to_string(), "999".to_string()),
+            ]
+        );
+        assert!(uri.to_string().ends_with("&somethingelseyoudontget=999"));
+
+        let built = Bip21Uri::new(
+            "bc1qw508d6qejxtdg4y5r3zarvary0c5xw7kv8f3t4"
+                .parse()
+                .unwrap(),
+        )
+        .with_amount(12_345)
+        .with_label("Café & bar")
+        .with_lightning("lnbc1")
+        .with_param("pj", "https://example.com/?a=1");
+        let s = built.to_string();
+        assert_eq!(
+            s,
+            "bitcoin:bc1qw508d6qejxtdg4y5r3zarvary0c5xw7kv8f3t4?amount=0.00012345&label=Caf%C3%A9%20%26%20bar&lightning=lnbc1&pj=https%3A%2F%2Fexample.com%2F%3Fa%3D1"
+        );
+        assert_eq!(s.parse::<Bip21Uri>().unwrap(), built);
+
+        for invalid in [
+            "bitcoin:1BvBMSEYstWetqTFn5Au4m4GFg7xJaNVN2?req-somethingyoudontunderstand=50",
+            "litecoin:1BvBMSEYstWetqTFn5Au4m4GFg7xJaNVN2",
+            "bitcoin:1BvBMSEYstWetqTFn5Au4m4GFg7xJaNVN2?amount=1.123456789",
+            "bitcoin:1BvBMSEYstWetqTFn5Au4m4GFg7xJaNVN2?amount=-1",
+            "bitcoin:1BvBMSEYstWetqTFn5Au4m4GFg7xJaNVN2?amount=1e3",
+            "bitcoin:1BvBMSEYstWetqTFn5Au4m4GFg7xJaNVN2?amount=1&amount=2",
+            "bitcoin:1BvBMSEYstWetqTFn5Au4m4GFg7xJaNVN2?label=a&label=b",
+            "bitcoin:1BvBMSEYstWetqTFn5Au4m4GFg7xJaNVN2?label=%4",
+            "bitcoin:1BvBMSEYstWetqTFn5Au4m4GFg7xJaNVN2?label=%ff",
+        ] {
+            assert!(
+                matches!(
+                    invalid.parse::<Bip21Uri>(),
+                    Err(BitcoinError::InvalidUri(_))
+                ),
+                "{}",
+                invalid
+            );
+        }
+        assert!(matches!(
+            "bitcoin:1BvBMSEYstWetqTFn5Au4m4GFg7xJaNVN3".parse::<Bip21Uri>(),
+            Err(BitcoinError::Base58(_))
+        ));
+    }
+
+    #[test]
+    fn test_bech32() {
+        use bech32::{Bech32Error, Variant};
+
+        // BIP173 and BIP350 valid strings.
+        for (s, variant) in [
+            ("A12UEL5L", Variant::Bech32),
+            ("a12uel5l", Variant::Bech32),
+            (
+                "an83characterlonghumanreadablepartthatcontainsthenumber1andtheexcludedcharactersbio1tt5tgs",
+                Variant::Bech32,
+            ),
+            (
+                "abcdef1qpzry9x8gf2tvdw0s3jn54khce6mua7lmqqqxw",
+                Variant::Bech32,
+            ),
+            ("?1ezyfcl", Variant::Bech32),
+            ("A1LQFN3A", Variant::Bech32m),
+            (
+                "abcdef1l7aum6echk45nj3s0wdvt2fg8x9yrzpqzd3ryx",
+                Variant::Bech32m,
+            ),
+            ("?1v759aa", Variant::Bech32m),
+        ] {
+            let (hrp, data, found) = bech32::decode(s).unwrap();
+            assert_eq!(found, variant, "{}", s);
+            assert_eq!(
+                bech32::encode(&hrp, &data, variant).unwrap(),
+                s.to_ascii_lowercase()
+            );
+        }
+        let (_, data, _) = bech32::decode("abcdef1qpzry9x8gf2tvdw0s3jn54khce6mua7lmqqqxw").unwrap();
+        assert_eq!(data, (0..32).collect::<Vec<u8>>());
+
+        for (s, error) in [
+            ("pzry9x0s0muk", Bech32Error::MissingSeparator),
+            ("1pzry9x0s0muk", Bech32Error::EmptyHrp),
+            (
+                "x1b4n0q5v",
+                Bech32Error::InvalidCharacter {
+                    character: 'b',
+                    position: 2,
+                },
+            ),
+            ("li1dgmt3", Bech32Error::TooShort(5)),
+            ("A1G7SGD8", Bech32Error::InvalidChecksum),
+            (
+                "an84characterslonghumanreadablepartthatcontainsthenumber1andtheexcludedcharactersbio1569pvx",
+                Bech32Error::TooLong(91),
+            ),
+            ("a12UEL5L", Bech32Error::MixedCase),
+        ] {
+            assert_eq!(bech32::decode(s), Err(BitcoinError::Bech32(error)), "{}", s);
+        }
+
+        assert_eq!(
+            bech32::convert_bits(&[0xff], 8, 5, true).unwrap(),
+            [0x1f, 0x1c]
+        );
+        assert_eq!(
+            bech32::convert_bits(&[0x1f, 0x1f], 5, 8, false),
+            Err(BitcoinError::Bech32(Bech32Error::InvalidPadding))
+        );
+
+        let program = hex::decode("751e76e8199196d454941c45d1b3a323f1433bd6").unwrap();
+        let address = bech32::encode_segwit("bc", 0, &program).unwrap();
+        assert_eq!(address, "bc1qw508d6qejxtdg4y5r3zarvary0c5xw7kv8f3t4");
+        assert_eq!(
+            bech32::decode_segwit(&address).unwrap(),
+            ("bc".to_string(), 0, program)
+        );
+        // BIP350 invalid segwit addresses.
+        for (s, error) in [
+            (
+                "bc1qw508d6qejxtdg4y5r3zarvary0c5xw7kemeawh",
+                Bech32Error::WrongVariant {
+                    expected: Variant::Bech32,
+                    found: Variant::Bech32m,
+                },
+            ),
+            ("bc1pw5dgrnzv", Bech32Error::InvalidProgramLength(1)),
+            (
+                "BC1QR508D6QEJXTDG4Y5R3ZARVARYV98GJ9P",
+                Bech32Error::InvalidProgramLength(16),
+            ),
+            ("bc1gmk9yu", Bech32Error::InvalidProgramLength(0)),
+        ] {
+            assert_eq!(
+                bech32::decode_segwit(s),
+                Err(BitcoinError::Bech32(error)),
+                "{}",
+                s
+            );
+        }
+    }
+
+    #[test]
+    fn test_base58() {
+        use base58::Base58Error;
+
+        assert_eq!(base58::encode(b""), "");
+        assert_eq!(base58::encode(b"hello world"), "StV1DL6CwTryKyV");
+        assert_eq!(base58::encode(&[0, 0, 0x28, 0x7f, 0xb4, 0xcd]), "11233QC4");
+        assert_eq!(
+            base58::decode("11233QC4").unwrap(),
+            [0, 0, 0x28, 0x7f, 0xb4, 0xcd]
+        );
+
+        let payload = hex::decode("0077bff20c60e522dfaa3350c39b030a5d004e839a").unwrap();
+        assert_eq!(
+            base58::encode_check(&payload),
+            "1BvBMSEYstWetqTFn5Au4m4GFg7xJaNVN2"
+        );
+        assert_eq!(
+            base58::decode_check("1BvBMSEYstWetqTFn5Au4m4GFg7xJaNVN2").unwrap(),
+            payload
+        );
+
+        assert_eq!(
+            base58::decode_check("1BvBMSEYstWetqTFn5Au4m4GFg7xJaNVN3"),
+            Err(BitcoinError::Base58(Base58Error::InvalidChecksum {
+                expected: [0xf4, 0x15, 0x76, 0x6b],
+                found: [0xf4, 0x15, 0x76, 0x6c],
+            }))
+        );
+        assert_eq!(
+            base58::decode_check("1BvBMSEYstWetqTFn5Au4m4GFg7xJaNVN0"),
+            Err(BitcoinError::Base58(Base58Error::InvalidCharacter {
+                character: '0',
+                position: 33,
+            }))
+        );
+        assert_eq!(
+            base58::decode_check("1z"),
+            Err(BitcoinError::Base58(Base58Error::TooShort(2)))
+        );
+    }
+
+    #[test]
+    fn test_utxo_snapshot() {
+        let metadata = SnapshotMetadata {
+            network_magic: Network::Regtest.magic(),
+            base_blockhash: Network::Regtest.genesis_hash(),
+            coins_count: 3,
+        };
+        let coin = |value, height| Coin {
+            output: TransactionOutput::new(value, Script::new(vec![0x51])),
+            height,
+            is_coinbase: height == 1,
+        };
+        let utxos = [
+            (OutPoint::new(dummy_txid(1), 0), coin(5_000_000_000, 1)),
+            (OutPoint::new(dummy_txid(1), 7), coin(1_000, 2)),
+            (OutPoint::new(dummy_txid(2), 1), coin(0, 3)),
+        ];
+
+        let mut writer = SnapshotWriter::new(Vec::new(), &metadata).unwrap();
+        for (outpoint, coin) in &utxos {
+            writer.write_coin(outpoint, coin).unwrap();
+        }
+        let bytes = writer.finish().unwrap();
+        assert_eq!(hex::encode(&bytes[..11]), "7574786fff0200fabfb5da");
+        // One group for each txid.
+        assert_eq!(bytes[51 + 32], 2);
+
+        let mut reader = SnapshotReader::new(bytes.as_slice()).unwrap();
+        assert_eq!(reader.metadata(), &metadata);
+        let read: Vec<(OutPoint, Coin)> = reader.by_ref().collect::<Result<_>>().unwrap();
+        assert_eq!(read, utxos);
+        assert!(reader.next().is_none());
+
+        let mut short = SnapshotWriter::new(Vec::new(), &metadata).unwrap();
+        short.write_coin(&utxos[0].0, &utxos[0].1).unwrap();
+        assert_eq!(short.finish().unwrap_err(), BitcoinError::InvalidFormat);
+
+        let mut bad_version = bytes.clone();
+        bad_version[5] = 1;
+        assert!(matches!(
+            SnapshotReader::new(bad_version.as_slice()),
+            Err(BitcoinError::InvalidFormat)
+        ));
+        let truncated = &bytes[..bytes.len() - 1];
+        let results: Vec<_> = SnapshotReader::new(truncated).unwrap().collect();
+        assert_eq!(results.len(), 3);
+        assert!(results[2].is_err());
+    }
+
+    #[test]
+    fn test_undo_data() {
+        // Vectors from Bitcoin Core's compress_tests.
+        for (amount, compressed) in [
+            (0, 0x0),
+            (1, 0x1),
+            (1_000_000, 0x7),
+            (100_000_000, 0x9),
+            (50 * 100_000_000, 0x32),
+            (21_000_000 * 100_000_000, 0x1406f40),
+        ] {
+            assert_eq!(compress_amount(amount), compressed);
+            assert_eq!(decompress_amount(compressed), amount);
+        }
+
+        let p2pkh = Script::new([&[0x76, 0xa9, 0x14][..], &[0xab; 20], &[0x88, 0xac]].concat());
+        let coin = Coin {
+            output: TransactionOutput::new(50 * 100_000_000, p2pkh),
+            height: 0x91a,
+            is_coinbase: false,
+        };
+        let bytes = coin.to_bytes();
+        assert_eq!(hex::encode(&bytes[..4]), "a3343200");
+        assert_eq!(bytes.len(), coin.serialized_size());
+        assert_eq!(Coin::decode_exact(&bytes).unwrap(), coin);
+
+        // The genesis output pays to an uncompressed key, which is stored as
+        // its x-coordinate and recovered on decode.
+        let genesis = BitcoinTransaction::from_hex(GENESIS_COINBASE_HEX).unwrap();
+        let genesis_coin = Coin {
+            output: genesis.outputs[0].clone(),
+            height: 0,
+            is_coinbase: true,
+        };
+        assert_eq!(genesis_coin.serialized_size(), 1 + 1 + 33);
+        assert_eq!(
+            Coin::decode_exact(&genesis_coin.to_bytes()).unwrap(),
+            genesis_coin
+        );
+
+        let g = "79be667ef9dcbbac55a06295ce870b07029bfcdb2dce28d959f2815b16f81798";
+        let g_y = "483ada7726a3c4655da4fbfc0e1108a8fd17b448a68554199c47d08ffb10d4b8";
+        let decoded = Coin::decode_exact(&hex::decode(format!("000004{}", g)).unwrap()).unwrap();
+        assert_eq!(
+            hex::encode(&decoded.output.script_pubkey.bytes),
+            format!("4104{}{}ac", g, g_y)
+        );
+        let odd = Coin::decode_exact(&hex::decode(format!("000005{}", g)).unwrap()).unwrap();
+        assert_ne!(odd.output.script_pubkey, decoded.output.script_pubkey);
+        assert_eq!(Coin::decode_exact(&odd.to_bytes()).unwrap(), odd);
+        assert_eq!(
+            Coin::decode_exact(&hex::decode(format!("000004{}", "ff".repeat(32))).unwrap()),
+            Err(BitcoinError::InvalidFormat)
+        );
+
+        let raw = Coin {
+            output: TransactionOutput::new(1234, Script::new(vec![0x51, 0x52, 0x93])),
+            height: 7,
+            is_coinbase: true,
+        };
+        let undo = BlockUndo {
+            txdata: vec![
+                TxUndo {
+                    prevouts: vec![coin.clone(), genesis_coin.clone()],
+                },
+                TxUndo {
+                    prevouts: vec![raw.clone()],
+                },
+            ],
+        };
+        let bytes = undo.to_bytes();
+        assert_eq!(bytes.len(), undo.serialized_size());
+        assert_eq!(BlockUndo::decode_exact(&bytes).unwrap(), undo);
+        // Non-genesis-height coins carry a zero version placeholder.
+        let tx_undo = undo.txdata[1].to_bytes();
+        assert_eq!(hex::encode(&tx_undo[..3]), "010f00");
+    }
+
+    #[test]
+    fn test_block_file_reader() {
+        let block =
+            hex::decode(format!("{}01{}", GENESIS_HEADER_HEX, GENESIS_COINBASE_HEX)).unwrap();
+        let magic = Network::Mainnet.magic();
+        let record = |payload: &[u8]| {
+            let mut out = magic.to_vec();
+            out.extend_from_slice(&(payload.len() as u32).to_le_bytes());
+            out.extend_from_slice(payload);
+            out
+        };
+        let mut file = vec![0u8; 5];
+        file.extend(record(&block));
+        file.extend_from_slice(b"junk");
+        file.extend_from_slice(&magic);
+        file.extend_from_slice(&10u32.to_le_bytes());
+        file.extend(record(&block));
+        file.extend_from_slice(&[0u8; 64]);
+
+        let blocks: Vec<Block> = BlockFileReader::new(file.as_slice(), Network::Mainnet)
+            .collect::<Result<_>>()
+            .unwrap();
+        assert_eq!(blocks.len(), 2);
+        assert!(
+            blocks
+                .iter()
+                .all(|b| b.block_hash() == Network::Mainnet.genesis_hash())
+        );
+
+        let key = [0x13, 0x37, 0x00, 0xff, 0xa5, 0x5a, 0x01, 0x80];
+        let obfuscated: Vec<u8> = file
+            .iter()
+            .enumerate()
+            .map(|(i, b)| b ^ key[i % 8])
+            .collect();
+        assert_eq!(
+            BlockFileReader::new(obfuscated.as_slice(), Network::Mainnet).count(),
+            0
+        );
+        let mut reader =
+            BlockFileReader::new(obfuscated.as_slice(), Network::Mainnet).with_xor_key(key);
+        assert_eq!(reader.next_raw().unwrap(), Some(block.clone()));
+        assert_eq!(reader.position(), 5 + 8 + block.len() as u64);
+
+        let truncated = &record(&block)[..100];
+        let mut reader = BlockFileReader::new(truncated, Network::Mainnet);
+        assert!(matches!(
+            reader.next(),
+            Some(Err(BitcoinError::InsufficientBytes { .. }))
+        ));
+        assert!(reader.next().is_none());
+    }
+
+    #[test]
+    fn test_undo_file_reader() {
+        use hashes::sha256d;
+
+        let magic = Network::Mainnet.magic();
+        let genesis = Network::Mainnet.genesis_hash();
+        let record = |prev_block_hash: &BlockHash, undo: &BlockUndo| {
+            let data = undo.to_bytes();
+            let mut out = magic.to_vec();
+            out.extend_from_slice(&(data.len() as u32).to_le_bytes());
+            out.extend_from_slice(&data);
+            out.extend_from_slice(&sha256d(&[&prev_block_hash.0[..], &data].concat()));
+            out
+        };
+        // A block with only a coinbase has one byte of undo data.
+        let coinbase_only = BlockUndo::default();
+        let spending = BlockUndo {
+            txdata: vec![TxUndo {
+                prevouts: vec![Coin {
+                    output: TransactionOutput::new(5000, Script::new(vec![0x51])),
+                    height: 1,
+                    is_coinbase: true,
+                }],
+            }],
+        };
+        let mut file = vec![0u8; 3];
+        file.extend(record(&genesis, &coinbase_only));
+        file.extend(record(&BlockHash(dummy_txid(1)), &spending));
+        assert_eq!(coinbase_only.to_bytes(), [0x00]);
+
+        let mut reader = UndoFileReader::new(file.as_slice(), Network::Mainnet);
+        assert_eq!(reader.next_undo(&genesis), Ok(Some(coinbase_only)));
+        assert_eq!(reader.position(), 3 + 8 + 1 + 32);
+        assert_eq!(
+            reader.next_undo(&BlockHash(dummy_txid(1))),
+            Ok(Some(spending))
+        );
+        assert_eq!(reader.next_undo(&genesis), Ok(None));
+
+        // The checksum commits to the parent block's hash.
+        let mut reader = UndoFileReader::new(file.as_slice(), Network::Mainnet);
+        assert_eq!(
+            reader.next_undo(&BlockHash(dummy_txid(2))),
+            Err(BitcoinError::UndoChecksumMismatch)
+        );
+        let (data, _) = reader.next_raw().unwrap().unwrap();
+        assert_eq!(BlockUndo::decode_exact(&data).unwrap().txdata.len(), 1);
+
+        let key = [0x13, 0x37, 0x00, 0xff, 0xa5, 0x5a, 0x01, 0x80];
+        let obfuscated: Vec<u8> = file
+            .iter()
+            .enumerate()
+            .map(|(i, b)| b ^ key[i % 8])
+            .collect();
+        let mut reader =
+            UndoFileReader::new(obfuscated.as_slice(), Network::Mainnet).with_xor_key(key);
+        assert_eq!(reader.next_raw().unwrap().unwrap().0, [0x00]);
+
+        let truncated = &file[..file.len() - 1];
+        let mut reader = UndoFileReader::new(truncated, Network::Mainnet);
+        reader.next_raw().unwrap();
+        assert!(matches!(
+            reader.next_raw(),
+            Err(BitcoinError::InsufficientBytes { .. })
+        ));
+    }
+
+    #[test]
+    fn test_network_constants() {
+        let cases = [
+            (
+                Network::Mainnet,
+                "000000000019d6689c085ae165831e934ff763ae46a2a6c172b3f1b60a8ce26f",
+            ),
+            (
+                Network::Testnet,
+                "000000000933ea01ad0ee984209779baaec3ced90fa3f408719526f8d77f4943",
+            ),
+            (
+                Network::Testnet4,
+                "00000000da84f2bafbbc53dee25a72ae507ff4914b867c565be350b0da8bf043",
+            ),
+            (
+                Network::Signet,
+                "00000008819873e925422c1ff0f99f7cc9bbb232af63a077a480a3633bee1ef6",
+            ),
+            (
+                Network::Regtest,
+                "0f9188f13cb7b2c71f2a335e3a4fc328bf5beb436012afca590b1a11466e2206",
+            ),
+        ];
+        for (network, hash) in cases {
+            assert_eq!(network.genesis_hash().to_string(), hash);
+            let header = network.genesis_header();
+            assert!(header.validate_pow(&network.params()).is_ok());
+        }
+        assert_eq!(
+            hex::encode(Network::Mainnet.genesis_header().to_bytes()),
+            GENESIS_HEADER_HEX
+        );
+        assert_eq!(Network::Mainnet.magic(), [0xf9, 0xbe, 0xb4, 0xd9]);
+        assert_eq!(Network::Regtest.default_port(), 18444);
+        assert_eq!(
+            serde_json::to_string(&Network::Testnet4).unwrap(),
+            "\"testnet4\""
+        );
+
+        // BIP94 retargets from the first block's bits, ignoring a
+        // minimum-difficulty block at the end of the period.
+        let mut first = Network::Testnet4.genesis_header();
+        first.bits = 0x1c05a3f4;
+        let mut last = first.clone();
+        last.time = first.time + 1_209_600;
+        last.bits = 0x1d00ffff;
+        assert_eq!(
+            calculate_next_work_required(&first, &last, &Network::Testnet4.params()),
+            Ok(CompactTarget(0x1c05a3f4))
+        );
+        assert_eq!(
+            calculate_next_work_required(&first, &last, &Network::Testnet.params()),
+            Ok(CompactTarget(0x1d00ffff))
+        );
+    }
+
+    #[test]
+    fn test_median_time_past() {
+        let chain: Vec<BlockHeader> = [5, 1, 9, 3, 7, 2, 8, 100, 4, 6, 10, 11, 0]
+            .iter()
+            .map(|&time| BlockHeader {
+                version: 1,
+                prev_blockhash: BlockHash([0; 32]),
+                merkle_root: MerkleRoot([0; 32]),
+                time,
+                bits: 0x1d00ffff,
+                nonce: 0,
+            })
+            .collect();
+        // Only the last eleven count: 9 3 7 2 8 100 4 6 10 11 0.
+        assert_eq!(median_time_past(&chain), 7);
+        assert_eq!(median_time_past(&chain[..4]), 5);
+        assert_eq!(median_time_past(&chain[..1]), 5);
+        assert_eq!(median_time_past(&[]), 0);
+    }
+
+    #[test]
+    fn test_difficulty_adjustment() {
+        let header = |time, bits| BlockHeader {
+            version: 1,
+            prev_blockhash: BlockHash([0; 32]),
+            merkle_root: MerkleRoot([0; 32]),
+            time,
+            bits,
+            nonce: 0,
+        };
+        let params = Params::MAINNET;
+        assert_eq!(params.difficulty_adjustment_interval(), 2016);
+
+        // Vectors from Bitcoin Core's pow_tests.
+        let next = |first_time, last_time, bits| {
+            calculate_next_work_required(&header(first_time, 0), &header(last_time, bits), &params)
+                .unwrap()
+        };
+        assert_eq!(
+            next(1261130161, 1262152739, 0x1d00ffff),
+            CompactTarget(0x1d00d86a)
+        );
+        assert_eq!(
+            next(1231006505, 1233061996, 0x1d00ffff),
+            CompactTarget(0x1d00ffff)
+        );
+        assert_eq!(
+            next(1279008237, 1279297671, 0x1c05a3f4),
+            CompactTarget(0x1c0168fd)
+        );
+        assert_eq!(
+            next(1263163443, 1269211443, 0x1c387f6f),
+            CompactTarget(0x1d00e1fd)
+        );
+
+        let regtest =
+            calculate_next_work_required(&header(0, 0), &header(1, 0x207fffff), &Params::REGTEST);
+        assert_eq!(regtest, Ok(CompactTarget(0x207fffff)));
+        assert_eq!(
+            Params::REGTEST.pow_limit.to_compact_lossy(),
+            CompactTarget(0x207fffff)
+        );
+
+        let last = header(1_000_000, 0x1c05a3f4);
+        assert_eq!(min_difficulty_bits(&last, 1_001_201, &params), None);
+        assert_eq!(
+            min_difficulty_bits(&last, 1_001_200, &Params::TESTNET),
+            None
+        );
+        assert_eq!(
+            min_difficulty_bits(&last, 1_001_201, &Params::TESTNET),
+            Some(CompactTarget(0x1d00ffff))
+        );
+    }
+
+    #[test]
+    fn test_chainwork() {
+        let header = BlockHeader::decode_exact(&hex::decode(GENESIS_HEADER_HEX).unwrap()).unwrap();
+        let work = header.work().unwrap();
+        assert_eq!(work, U256::from_u64(0x1_0001_0001));
+        assert_eq!(
+            format!("{:x}", work),
+            "0000000000000000000000000000000000000000000000000000000100010001"
+        );
+        assert_eq!(Target([0xff; 32]).to_work(), U256::ONE);
+        assert_eq!(
+            Target(U256::ONE.to_be_bytes()).to_work(),
+            U256::from_be_bytes({
+                let mut half = [0u8; 32];
+                half[0] = 0x80;
+                half
+            })
+        );
+
+        let mut chainwork = U256::ZERO;
+        for _ in 0..3 {
+            chainwork = chainwork.saturating_add(work);
+        }
+        assert_eq!(chainwork, U256::from_u64(3 * 0x1_0001_0001));
+        assert!(chainwork > work);
+        assert_eq!(U256::MAX.saturating_add(U256::ONE), U256::MAX);
+        assert_eq!(U256::MAX.checked_add(U256::ONE), None);
+        assert_eq!(
+            U256::from_u64(100).div_rem(U256::from_u64(7)),
+            (U256::from_u64(14), U256::from_u64(2))
+        );
+    }
+
+    #[test]
+    fn test_hash_engine_streams() {
+        let data: Vec<u8> = (0..300u32).map(|i| (i * 7) as u8).collect();
+        for len in [0, 1, 55, 56, 63, 64, 65, 119, 120, 128, 300] {
+            for step in [1, 7, 64, 100] {
+                let mut engine = hashes::HashEngine::new();
+                for chunk in data[..len].chunks(step) {
+                    engine.input(chunk);
+                }
+                assert_eq!(engine.finalize(), hashes::sha256(&data[..len]));
+            }
+        }
+
+        let tx = BitcoinTransaction::from_hex(GENESIS_COINBASE_HEX).unwrap();
+        let mut engine = hashes::HashEngine::default();
+        tx.consensus_encode(&mut engine).unwrap();
+        assert_eq!(Txid(engine.finalize_double()), tx.txid());
+        assert_eq!(
+            tx.txid().to_string(),
+            "4a5e1e4baab89f3a32518a88c31bc87f618f76673e2cc77ab2127b7afdeda33b"
+        );
+    }
+
+    #[test]
+    fn test_bip152_short_ids() {
+        use rust_week_3_exercises::bip152::{ShortId, ShortIdKey};
+
+        // Reference vectors from the SipHash paper: key 00..0f.
+        let k0 = u64::from_le_bytes([0, 1, 2, 3, 4, 5, 6, 7]);
+        let k1 = u64::from_le_bytes([8, 9, 10, 11, 12, 13, 14, 15]);
+        assert_eq!(hashes::siphash24(k0, k1, &[]), 0x726fdb47dd0e0e31);
+        let msg: Vec<u8> = (0..15).collect();
+        assert_eq!(hashes::siphash24(k0, k1, &msg), 0xa129ca6149be45e5);
+
+        let header = [7u8; 80];
+        let key = ShortIdKey::new(&header, 42);
+        let hash = hashes::sha256(&[&header[..], &42u64.to_le_bytes()].concat());
+        assert_eq!(key.k0.to_le_bytes(), hash[..8]);
+        assert_eq!(key.k1.to_le_bytes(), hash[8..16]);
+
+        let wtxid = Wtxid(dummy_txid(3));
+        let id = key.short_id(&wtxid);
+        assert_eq!(
+            id.to_u64(),
+            hashes::siphash24(key.k0, key.k1, &wtxid.0) & 0xffff_ffff_ffff
+        );
+        assert_eq!(ShortId::from_u64(id.to_u64()), id);
+        assert_ne!(ShortIdKey::new(&header, 43).short_id(&wtxid), id);
+    }
+
+    #[test]
+    fn test_result_alias_composes_with_question_mark() {
+        fn parse_script(s: &str) -> Result<Script> {
+            let bytes = hex::decode(s)?;
+            Script::decode_exact(&bytes)
+        }
+        assert_eq!(parse_script("0151"), Ok(Script::new(vec![0x51])));
+        assert!(matches!(
+            parse_script("zz"),
+            Err(BitcoinError::InvalidHex(_))
+        ));
+        assert_eq!(parse_script("02"), Script::decode_exact(&[0x02]));
+    }
+
+    const GENESIS_HEADER_HEX: &str = "0100000000000000000000000000000000000000000000000000000000000000000000003ba3edfd7a7b12b27ac72c3e67768f617fc81bc3888a51323a9fb8aa4b1e5e4a29ab5f49ffff001d1dac2b7c";
+
+    const GENESIS_COINBASE_HEX: &str = "01000000010000000000000000000000000000000000000000000000000000000000000000ffffffff4d04ffff001d0104455468652054696d65732030332f4a616e2f32303039204368616e63656c6c6f72206f6e206272696e6b206f66207365636f6e64206261696c6f757420666f722062616e6b73ffffffff0100f2052a01000000434104678afdb0fe5548271967f1a67130b7105cd6a828e03909a67962e0ea1f61deb649f6bc3f4cef38c4f35504e51ec112de5c384df7ba0b8d578a4c702b6bf11d5fac00000000";
+
+    #[test]
+    fn test_txid_genesis_coinbase() {
+        let bytes = hex::decode(GENESIS_COINBASE_HEX).unwrap();
+        let (tx, consumed) = BitcoinTransaction::from_bytes(&bytes).unwrap();
+        assert_eq!(consumed, bytes.len());
+        let mut txid = tx.txid().0;
+        txid.reverse();
+        assert_eq!(
+            hex::encode(txid),
+            "4a5e1e4baab89f3a32518a88c31bc87f618f76673e2cc77ab2127b7afdeda33b"
+        );
+    }
+
+    #[test]
+    fn test_coinbase_detection_and_display() {
+        let bytes = hex::decode(GENESIS_COINBASE_HEX).unwrap();
+        let (tx, _) = BitcoinTransaction::from_bytes(&bytes).unwrap();
+        assert!(tx.is_coinbase());
+        assert!(tx.inputs[0].previous_output.is_null());
+
+        let output = format!("{}", tx);
+        assert!(output.contains("Coinbase: 04ffff001d"));
+        assert!(!output.contains("Previous Output Txid"));
+
+        let regular = BitcoinTransaction::new(
+            1,
+            vec![TransactionInput::new(
+                OutPoint::new(dummy_txid(1), 0),
+                Script::new(vec![]),
+                0xFFFFFFFF,
+            )],
+            vec![],
+            0,
+        );
+        assert!(!regular.is_coinbase());
+    }
+
+    #[test]
+    fn test_coinbase_script_sig_length_enforced() {
+        let coinbase = |len: usize| {
+            BitcoinTransaction::new(
+                1,
+                vec![TransactionInput::new(
+                    OutPoint::null(),
+                    Script::new(vec![0x51; len]),
+                    0xFFFFFFFF,
+                )],
+                vec![TransactionOutput::new(50, Script::new(vec![0x51]))],
+                0,
+            )
+            .to_bytes()
+        };
+        for (len, expected) in [
+            (2, Ok(())),
+            (100, Ok(())),
+            (1, Err(BitcoinError::BadCoinbaseLength { len: 1 })),
+            (101, Err(BitcoinError::BadCoinbaseLength { len: 101 })),
+        ] {
+            let bytes = coinbase(len);
+            let (tx, _) = BitcoinTransaction::from_bytes(&bytes).unwrap();
+            assert_eq!(tx.to_bytes(), bytes);
+            assert_eq!(tx.check_coinbase(), expected);
+        }
+    }
+
+    #[test]
+    fn test_txid_ignores_witness() {
+        let input = TransactionInput::new(
+            OutPoint::new(dummy_txid(4), 0),
+            Script::new(vec![]),
+            0xFFFFFFFF,
+        );
+        let mut tx = BitcoinTransaction::new(2, vec![input], vec![], 0);
+        let txid = tx.txid();
+        tx.inputs[0].witness.push(vec![0x01, 0x02]);
+        assert_eq!(tx.txid(), txid);
+        assert_ne!(tx.to_bytes(), tx.to_bytes_legacy());
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn test_cached_transaction_ids() {
+        let bytes = hex::decode(GENESIS_COINBASE_HEX).unwrap();
+        let (tx, _) = BitcoinTransaction::from_bytes(&bytes).unwrap();
+        let cached = CachedTransaction::from(tx.clone());
+
+        let first: *const Txid = cached.txid();
+        let second: *const Txid = cached.txid();
+        assert_eq!(first, second);
+        assert_eq!(*cached.txid(), tx.txid());
+        assert_eq!(*cached.wtxid(), tx.wtxid());
+        assert_eq!(cached.version, 1);
+        assert_eq!(cached.into_inner(), tx);
+    }
+
+    #[test]
+    fn test_wtxid_matches_txid_without_witness() {
+        let input = TransactionInput::new(
+            OutPoint::new(dummy_txid(5), 1),
+            Script::new(vec![0x51]),
+            0xFFFFFFFF,
+        );
+        let mut tx = BitcoinTransaction::new(1, vec![input], vec![], 0);
+        assert_eq!(tx.wtxid().0, tx.txid().0);
+
+        tx.inputs[0].witness.push(vec![0xAB; 72]);
+        assert_ne!(tx.wtxid().0, tx.txid().0);
+
+        let json = serde_json::to_string(&tx.wtxid()).unwrap();
+        let parsed: Wtxid = serde_json::from_str(&json).unwrap();
+        assert_eq!(parsed, tx.wtxid());
+    }
+
+    #[test]
+    fn test_tx_weight_and_vsize() {
+        let input = TransactionInput::new(
+            OutPoint::new(dummy_txid(6), 0),
+            Script::new(vec![]),
+            0xFFFFFFFF,
+        );
+        let output = TransactionOutput::new(1000, Script::new(vec![0x00, 0x14]));
+        let mut tx = BitcoinTransaction::new(2, vec![input], vec![output], 0);
+
+        // 4 + 1 + 41 + 1 + 11 + 4
+        assert_eq!(tx.base_size(), 62);
+        assert_eq!(tx.total_size(), 62);
+        assert_eq!(tx.weight(), 248);
+        assert_eq!(tx.vsize(), 62);
+
+        // marker + flag + item count + 1-byte length + 4 bytes of data
+        tx.inputs[0].witness.push(vec![0xEE; 4]);
+        assert_eq!(tx.base_size(), 62);
+        assert_eq!(tx.total_size(), 70);
+        assert_eq!(tx.weight(), 62 * 3 + 70);
+        assert_eq!(tx.vsize(), 64);
+    }
+
     #[test]
     fn test_bitcoin_tx_json_serialization() {
         let input = TransactionInput::new(
@@ -87,7 +5591,8 @@ mod tests {
             Script::new(vec![0xDE, 0xAD, 0xBE, 0xEF]),
             0xABCDEF01,
         );
-        let tx = BitcoinTransaction::new(1, vec![input], 999);
+        let output = TransactionOutput::new(1234, Script::new(vec![0x51]));
+        let tx = BitcoinTransaction::new(1, vec![input], vec![output], 999);
 
         let json = serde_json::to_string_pretty(&tx).unwrap();
         let parsed: BitcoinTransaction = serde_json::from_str(&json).unwrap();
@@ -104,9 +5609,11 @@ mod tests {
             Script::new(vec![0x01, 0x02, 0x03]),
             0xFFFFFFFF,
         );
-        let tx = BitcoinTransaction::new(1, vec![input], 0);
+        let tx_output = TransactionOutput::new(5000, Script::new(vec![0x6A]));
+        let tx = BitcoinTransaction::new(1, vec![input], vec![tx_output], 0);
         let output = format!("{}", tx);
         assert!(output.contains("Version: 1"));
+        assert!(output.contains("Output Value: 5000"));
         assert!(output.contains("Lock Time: 0"));
         assert!(output.contains("Previous Output Vout: 7"));
     }